@@ -0,0 +1,279 @@
+//! Screen-reader friendly text (REPORT_FORMAT=accessible, `/config format`): no emoji or
+//! arrows, every line starts with its label and ends with its unit, sections are headings, and
+//! the generation mix keeps the same order from one report to the next.
+
+use crate::clock::discord_time;
+use crate::alerts::{FaultChange, IndicatorChange};
+use crate::demand_response;
+use crate::history::{DailySummary, SummarySource};
+use crate::locale::{Labels, Locale, NumberFormat};
+use crate::regional::RegionBalance;
+use crate::render::{
+    format_hour_range, format_outage, format_peak_time, indicator_label, DiscordTextRenderer, FuelDisplay, Renderer, ReportSections,
+    DATA_SOURCE_URL,
+};
+use crate::analysis::CombinedPowerData;
+use crate::taipower_api::ReserveIndicator;
+
+#[derive(Default)]
+pub struct AccessibleRenderer {
+    pub locale: Locale,
+    pub numbers: NumberFormat,
+    pub sections: ReportSections,
+}
+
+impl From<&DiscordTextRenderer> for AccessibleRenderer {
+    fn from(text: &DiscordTextRenderer) -> Self {
+        AccessibleRenderer { locale: text.locale, numbers: text.numbers, sections: text.sections }
+    }
+}
+
+impl AccessibleRenderer {
+    /// Pause between clauses on one line
+    fn separator(&self) -> &'static str {
+        match self.locale {
+            Locale::ZhTw => "，",
+            Locale::En => ", ",
+        }
+    }
+
+    fn load(&self, wan_kw: f64) -> String {
+        self.locale.load_value(wan_kw, self.numbers)
+    }
+
+    /// e.g. "7.12%，綠燈"
+    fn rate_with_indicator(&self, rate: f64, indicator: ReserveIndicator) -> String {
+        format!("{}{}{}", self.numbers.percent(rate, 2), self.separator(), indicator_label(indicator, self.locale.labels()))
+    }
+
+    /// e.g. "下降 0.4 個百分點"
+    fn pp_change(&self, change: f64, l: &Labels) -> String {
+        let direction = if change >= 0.0 { l.rose } else { l.fell };
+        format!("{} {} {}", direction, self.numbers.decimal(change.abs(), 1), l.percentage_points)
+    }
+
+    /// e.g. "北部: 發電 9,100 MW，負載 13,500 MW，輸入 32.6%，輸入比例偏高"
+    fn region(&self, region: &RegionBalance, l: &Labels) -> String {
+        let share = region.import_share();
+        let mut line = format!(
+            "{}: {} {}{}{} {}{}{} {}",
+            self.locale.region_name(&region.area),
+            l.region_generation,
+            self.numbers.mw(region.generation, 0),
+            self.separator(),
+            l.region_load,
+            self.numbers.mw(region.load, 0),
+            self.separator(),
+            if share >= 0.0 { l.importing } else { l.exporting },
+            self.numbers.percent(share.abs(), 1),
+        );
+        if region.heavy_import {
+            line.push_str(self.separator());
+            line.push_str(l.heavy_import);
+        }
+        line
+    }
+
+    fn mix_lines<'a>(&self, mix: impl IntoIterator<Item = (&'a str, f64)>, lines: &mut Vec<String>) {
+        for (energy_type, generation) in FuelDisplay::from_env().apply_by_name(mix) {
+            lines.push(format!("{}: {}", self.locale.energy_type(&energy_type), self.numbers.mw(generation, 1)));
+        }
+    }
+}
+
+impl Renderer for AccessibleRenderer {
+    type Output = String;
+
+    fn report(&self, data: &CombinedPowerData) -> String {
+        let l = self.locale.labels();
+        let n = self.numbers;
+        let mut lines = vec![format!("## {}", l.report_title)];
+        if let Some(stress) = &data.stress {
+            lines.push(format!("{}: {:.0}/100", l.stress_index, stress.value));
+        }
+
+        if let Some(load_data) = &data.load_data {
+            if self.sections.supply {
+                lines.push(format!("### {}", l.supply_demand));
+                lines.push(format!("{}: {}", l.current_load, self.load(load_data.current_load)));
+                lines.push(format!("{}: {}", l.util_rate, n.percent(load_data.current_util_rate, 1)));
+                lines.push(format!("{}: {}", l.forecast_max_supply, self.load(load_data.forecast_max_supply_capacity)));
+                lines.push(format!("{}: {}", l.forecast_peak_demand, self.load(load_data.forecast_peak_demand_load)));
+                if let Some(own) = &data.own_forecast {
+                    lines.push(format!("{}: {}{}{}", l.own_forecast, self.load(own.day_peak), self.separator(), own.day_peak_time.format("%H:%M")));
+                    lines.push(format!("{}: {}", l.next_hour, self.load(own.next_hour)));
+                }
+                lines.push(format!("{}: {}", l.forecast_reserve_capacity, self.load(load_data.forecast_peak_reserve_capacity)));
+                lines.push(format!("{}: {}", l.forecast_reserve_rate,
+                    self.rate_with_indicator(load_data.forecast_peak_reserve_rate, load_data.forecast_peak_reserve_indicator)));
+                if let Some(mw) = data.demand_response_mw {
+                    let (capacity, rate) = demand_response::reserve_without(load_data, mw);
+                    lines.push(format!("{}: {}", l.demand_response, n.mw(mw, 1)));
+                    lines.push(format!("{}: {}{}{}", l.reserve_without_dr, n.percent(rate, 2), self.separator(), self.load(capacity)));
+                }
+                lines.push(format!("{}: {}", l.forecast_peak_hours, format_hour_range(load_data, l)));
+                if let Some(publish_time) = load_data.publish_time {
+                    lines.push(format!("{}: {}", l.data_updated, discord_time(publish_time)));
+                }
+            }
+
+            if self.sections.yesterday {
+                lines.push(format!("### {}", l.yesterday));
+                lines.push(format!("{}: {}", l.max_supply, self.load(load_data.yesterday_max_supply_capacity)));
+                lines.push(format!("{}: {}", l.peak_demand, self.load(load_data.yesterday_peak_demand_load)));
+                lines.push(format!("{}: {}", l.peak_reserve_capacity, self.load(load_data.yesterday_peak_reserve_capacity)));
+                lines.push(format!("{}: {}", l.peak_reserve_rate,
+                    self.rate_with_indicator(load_data.yesterday_peak_reserve_rate, load_data.yesterday_peak_reserve_indicator)));
+            }
+
+            if self.sections.realtime && load_data.real_hour_max_supply_capacity > 0.0 {
+                lines.push(format!("### {}", l.realtime_peak));
+                lines.push(format!("{}: {}", l.realtime_max_supply, self.load(load_data.real_hour_max_supply_capacity)));
+                lines.push(format!("{}: {}", l.peak_time, format_peak_time(load_data, l)));
+            }
+        }
+
+        if self.sections.regions && !data.regions.is_empty() {
+            lines.push(format!("### {}", l.regions));
+            lines.extend(data.regions.iter().map(|region| self.region(region, l)));
+        }
+
+        let analysis = &data.power_analysis;
+        if self.sections.generation {
+            lines.push(format!("### {}", l.generation));
+            lines.push(format!("{}: {}", l.updated, discord_time(analysis.update_time)));
+            lines.push(format!("{}: {}", l.total_generation, n.mw(analysis.total_generation, 1)));
+            lines.push(format!("{}: {}", l.installed_capacity, n.mw(analysis.estimated_max_generation, 1)));
+            lines.push(format!("{}: {}", l.generation_ratio, n.percent(analysis.generation_ratio(), 1)));
+            lines.push(format!("{}: {}", l.renewable_ratio, n.percent(analysis.renewable_ratio, 1)));
+            lines.push(format!("{}: {}", l.private_ratio, n.percent(analysis.private_ratio, 1)));
+            lines.push(format!("{}: {}{}{}", l.top_plant, analysis.top_plant.0, self.separator(), n.mw(analysis.top_plant.1, 1)));
+            lines.push(format!("{}: {}{}{}", l.top_unit, analysis.top_unit.0, self.separator(), n.mw(analysis.top_unit.1, 1)));
+
+            lines.push(format!("### {}", l.by_type));
+            self.mix_lines(analysis.generation_by_type.iter().map(|(t, g)| (t.as_str(), *g)), &mut lines);
+        }
+
+        if self.sections.units {
+            lines.push(format!("### {}", l.unit_status));
+            lines.push(format!("{}: {}{}", l.restrictions, analysis.environmental_restrictions, l.units_suffix));
+            lines.push(format!("{}: {}{}", l.maintenance, analysis.maintenance_count, l.units_suffix));
+            lines.push(format!("{}: {}{}", l.faults, analysis.fault_count, l.units_suffix));
+            if !data.outages.is_empty() {
+                lines.push(format!("### {}", l.outages));
+                lines.extend(data.outages.iter().map(|outage| format_outage(outage, l, n)));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{}: {} <{}>", l.source, l.source_name, DATA_SOURCE_URL));
+        lines.push(l.disclaimer.to_string());
+        lines.join("\n")
+    }
+
+    fn compact(&self, data: &CombinedPowerData) -> String {
+        let l = self.locale.labels();
+        let analysis = &data.power_analysis;
+        let mut parts = Vec::new();
+        match &data.load_data {
+            Some(load_data) => {
+                parts.push(format!("{}: {}", l.current_load, self.load(load_data.current_load)));
+                parts.push(format!("{}: {}", l.forecast_reserve_rate,
+                    self.rate_with_indicator(load_data.forecast_peak_reserve_rate, load_data.forecast_peak_reserve_indicator)));
+                if let Some(trend) = data.trend {
+                    parts.push(trend.label(l).to_string());
+                }
+            }
+            None => parts.push(format!("{}: {}", l.total_generation, self.numbers.mw(analysis.total_generation, 1))),
+        }
+        parts.push(format!("{}: {}", l.renewable_ratio, self.numbers.percent(analysis.renewable_ratio, 1)));
+        parts.push(format!("{}: {}", l.updated, analysis.update_time.format("%H:%M")));
+        parts.join(self.separator())
+    }
+
+    fn daily_summary(&self, summary: &DailySummary) -> String {
+        let l = self.locale.labels();
+        let mut lines = vec![format!("## {} {}", summary.date, l.summary_title)];
+
+        lines.push(match (summary.peak_load, &summary.peak_time) {
+            (Some(peak), Some(time)) => format!("{}: {}{}{}", l.peak_load, self.load(peak), self.separator(), time),
+            (Some(peak), None) => format!("{}: {}", l.peak_load, self.load(peak)),
+            _ => format!("{}: {}", l.peak_load, l.no_data),
+        });
+        if let (Some(min), Some(time)) = (summary.min_load, &summary.min_time) {
+            lines.push(format!("{}: {}{}{}", l.min_load, self.load(min), self.separator(), time));
+        }
+        lines.push(match (summary.min_reserve_rate, summary.peak_reserve_rate) {
+            (Some(rate), _) => format!("{}: {}", l.min_reserve_rate, self.numbers.percent(rate, 2)),
+            (None, Some(rate)) => format!("{}: {}", l.peak_reserve_rate, self.numbers.percent(rate, 2)),
+            (None, None) => format!("{}: {}", l.min_reserve_rate, l.no_data),
+        });
+        if let Some(ratio) = summary.average_renewable_ratio {
+            lines.push(format!("{}: {}", l.average_renewable, self.numbers.percent(ratio, 1)));
+        }
+        if let Some(plant) = &summary.top_plant {
+            lines.push(format!("{}: {}", l.top_plant, plant));
+        }
+        if let Some(events) = summary.fault_events {
+            lines.push(format!("{}: {}{}", l.fault_events, events, l.units_suffix));
+        }
+
+        if !summary.generation_mix.is_empty() {
+            lines.push(format!("### {}", l.average_mix));
+            self.mix_lines(summary.generation_mix.iter().map(|(t, g)| (t.as_str(), *g)), &mut lines);
+        }
+
+        if let (Some(faults), Some(maintenance), Some(restrictions)) = (
+            summary.max_fault_count,
+            summary.max_maintenance_count,
+            summary.max_environmental_restrictions,
+        ) {
+            lines.push(format!("### {}", l.most_affected));
+            lines.push(format!("{}: {}{}", l.restrictions, restrictions, l.units_suffix));
+            lines.push(format!("{}: {}{}", l.maintenance, maintenance, l.units_suffix));
+            lines.push(format!("{}: {}{}", l.faults, faults, l.units_suffix));
+        }
+
+        lines.push(String::new());
+        lines.push(match summary.source {
+            SummarySource::Local => format!("{}: {}{}{}{}", l.source, l.local_records, self.separator(), summary.sample_count, l.samples_suffix),
+            SummarySource::Archive => format!("{}: {}", l.source, l.archive_source),
+        });
+        lines.join("\n")
+    }
+
+    fn indicator_change(&self, change: &IndicatorChange) -> String {
+        let l = self.locale.labels();
+        format!(
+            "{}: {}{}{} {}\n{}: {}{}{}",
+            l.indicator_changed,
+            indicator_label(change.to, l),
+            self.separator(),
+            l.previously,
+            indicator_label(change.from, l),
+            l.forecast_reserve_rate,
+            self.numbers.percent(change.reserve_rate, 1),
+            self.separator(),
+            self.pp_change(change.reserve_rate_change, l),
+        )
+    }
+
+    fn fault_change(&self, change: &FaultChange) -> String {
+        let l = self.locale.labels();
+        let list = |units: &[(String, f64)]| {
+            units
+                .iter()
+                .map(|(unit, capacity)| format!("{}{}{}", unit, self.separator(), self.numbers.mw(*capacity, 0)))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        let mut lines = vec![l.fault_change.to_string()];
+        if !change.faulted.is_empty() {
+            lines.push(format!("{}: {}", l.newly_faulted, list(&change.faulted)));
+        }
+        if !change.recovered.is_empty() {
+            lines.push(format!("{}: {}", l.recovered, list(&change.recovered)));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,423 @@
+use chrono::{DateTime, FixedOffset, NaiveDateTime, NaiveTime};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::analysis::{classify_remark, RemarkClass};
+use crate::clock::discord_timestamp;
+use crate::forecast::HolidayCalendar;
+use crate::taipower_api::{LoadData, PowerUnit, ReserveIndicator};
+
+/// The reserve indicator moved between two consecutive samples
+#[derive(Debug, Clone)]
+pub struct IndicatorChange {
+    pub from: ReserveIndicator,
+    pub to: ReserveIndicator,
+    pub reserve_rate: f64,
+    /// Percentage-point change of the reserve rate since the previous sample
+    pub reserve_rate_change: f64,
+}
+
+/// Percentage-point change of the forecast peak reserve rate since `previous`
+pub fn reserve_rate_change(current: &LoadData, previous: Option<&LoadData>) -> Option<f64> {
+    previous.map(|previous| current.forecast_peak_reserve_rate - previous.forecast_peak_reserve_rate)
+}
+
+pub fn detect_indicator_change(current: &LoadData, previous: Option<&LoadData>) -> Option<IndicatorChange> {
+    let previous = previous?;
+    let (from, to) = (previous.forecast_peak_reserve_indicator, current.forecast_peak_reserve_indicator);
+
+    if from == to || from == ReserveIndicator::Unknown || to == ReserveIndicator::Unknown {
+        return None;
+    }
+
+    Some(IndicatorChange {
+        from,
+        to,
+        reserve_rate: current.forecast_peak_reserve_rate,
+        reserve_rate_change: current.forecast_peak_reserve_rate - previous.forecast_peak_reserve_rate,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DayType {
+    Workday,
+    OffDay,
+    Any,
+}
+
+/// One ALERT_SENSITIVITY entry: during `start`..`end` (Taipei time, may wrap past midnight) on
+/// matching days, escalations to `min_indicator` or worse ping
+#[derive(Debug, Clone)]
+struct SensitivityRule {
+    days: DayType,
+    start: NaiveTime,
+    end: NaiveTime,
+    min_indicator: ReserveIndicator,
+}
+
+impl SensitivityRule {
+    /// e.g. "workday 13:00-17:00 yellow" or "offday 22:00-07:00 red"
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split_whitespace();
+        let days = match parts.next()? {
+            "workday" => DayType::Workday,
+            "offday" => DayType::OffDay,
+            "any" | "*" => DayType::Any,
+            _ => return None,
+        };
+        let (start, end) = parts.next()?.split_once('-')?;
+        let min_indicator = match parts.next()? {
+            "yellow" => ReserveIndicator::Yellow,
+            "orange" => ReserveIndicator::Orange,
+            "red" => ReserveIndicator::Red,
+            "black" => ReserveIndicator::Black,
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(SensitivityRule {
+            days,
+            start: NaiveTime::parse_from_str(start, "%H:%M").ok()?,
+            end: NaiveTime::parse_from_str(end, "%H:%M").ok()?,
+            min_indicator,
+        })
+    }
+
+    fn matches(&self, at: NaiveDateTime, holidays: &HolidayCalendar) -> bool {
+        let day_matches = match self.days {
+            DayType::Workday => !holidays.is_off_day(at.date()),
+            DayType::OffDay => holidays.is_off_day(at.date()),
+            DayType::Any => true,
+        };
+        let time = at.time();
+        let in_window = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        day_matches && in_window
+    }
+}
+
+/// Decides which indicator changes deserve an immediate alert with a role ping: escalations to
+/// orange or worse, at most once per cooldown so a flapping indicator doesn't ping every cycle.
+/// ALERT_SENSITIVITY rules (`;`-separated, first match wins) move that threshold by time of day
+/// and working/non-working day, so tight supply on a workday afternoon pings sooner than at night
+pub struct ReserveAlertGate {
+    cooldown: Duration,
+    /// Taipei time of the last ping
+    last_alert: Option<NaiveDateTime>,
+    rules: Vec<SensitivityRule>,
+    holidays: HolidayCalendar,
+}
+
+/// ALERT_SENSITIVITY's rules that don't parse
+pub fn invalid_sensitivity_rules(value: &str) -> Vec<&str> {
+    value.split(';').map(str::trim).filter(|r| !r.is_empty() && SensitivityRule::parse(r).is_none()).collect()
+}
+
+impl ReserveAlertGate {
+    pub fn new(cooldown: Duration) -> Self {
+        ReserveAlertGate { cooldown, last_alert: None, rules: Vec::new(), holidays: HolidayCalendar::from_env() }
+    }
+
+    pub fn from_env() -> Self {
+        let minutes = crate::config::var("ALERT_COOLDOWN_MINUTES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(60);
+        let mut gate = ReserveAlertGate::new(Duration::from_secs(minutes * 60));
+        for rule in crate::config::var("ALERT_SENSITIVITY").unwrap_or_default().split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            match SensitivityRule::parse(rule) {
+                Some(rule) => gate.rules.push(rule),
+                None => warn!("Ignoring invalid ALERT_SENSITIVITY rule {:?}", rule),
+            }
+        }
+        gate
+    }
+
+    /// Pick up changed settings after `/config reload`, keeping the cooldown already running
+    pub fn reload(&mut self) {
+        *self = ReserveAlertGate { last_alert: self.last_alert, ..ReserveAlertGate::from_env() };
+    }
+
+    /// The mildest indicator that pings at `at`
+    fn threshold(&self, at: NaiveDateTime) -> ReserveIndicator {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(at, &self.holidays))
+            .map(|rule| rule.min_indicator)
+            .unwrap_or(ReserveIndicator::Orange)
+    }
+
+    /// `at` is the Taipei time of the change
+    pub fn allow(&mut self, change: &IndicatorChange, at: NaiveDateTime) -> bool {
+        if change.to < self.threshold(at) || change.to <= change.from {
+            return false;
+        }
+        if self.last_alert.is_some_and(|last| (at - last).to_std().is_ok_and(|elapsed| elapsed < self.cooldown)) {
+            return false;
+        }
+        self.last_alert = Some(at);
+        true
+    }
+}
+
+/// Units whose 備註 started or stopped saying 故障 between two fetches, as (unit, capacity MW)
+#[derive(Debug, Clone, Default)]
+pub struct FaultChange {
+    pub faulted: Vec<(String, f64)>,
+    pub recovered: Vec<(String, f64)>,
+}
+
+/// Remembers which units were faulted last cycle so only changes get announced
+#[derive(Default)]
+pub struct FaultWatch {
+    faulted: Option<HashMap<String, f64>>,
+}
+
+impl FaultWatch {
+    /// Fault alerts are on unless FAULT_ALERTS is off
+    pub fn enabled() -> bool {
+        !matches!(crate::config::var("FAULT_ALERTS").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+    }
+
+    /// The first fetch only records the current faults; a unit missing from a fetch is not taken as recovered
+    pub fn observe(&mut self, units: &[PowerUnit]) -> Option<FaultChange> {
+        let current: HashMap<String, f64> = units
+            .iter()
+            .filter(|u| !u.unit_name.contains("小計") && classify_remark(&u.remark) == RemarkClass::Fault)
+            .map(|u| (u.unit_name.clone(), u.capacity))
+            .collect();
+        let previous = self.faulted.replace(current.clone())?;
+
+        let mut change = FaultChange::default();
+        for (unit, capacity) in &current {
+            if !previous.contains_key(unit) {
+                change.faulted.push((unit.clone(), *capacity));
+            }
+        }
+        for (unit, capacity) in previous {
+            if !current.contains_key(&unit) && units.iter().any(|u| u.unit_name == unit) {
+                change.recovered.push((unit, capacity));
+            }
+        }
+        change.faulted.sort_by(|a, b| b.1.total_cmp(&a.1));
+        change.recovered.sort_by(|a, b| b.1.total_cmp(&a.1));
+        (!change.faulted.is_empty() || !change.recovered.is_empty()).then_some(change)
+    }
+}
+
+/// Re-arms once the index is this far back below the threshold, so hovering around it alerts once
+pub(crate) const STRESS_REARM_MARGIN: f64 = 10.0;
+
+/// Alerts when the grid stress index rises to STRESS_ALERT_THRESHOLD (0–100; unset disables)
+pub struct StressWatch {
+    threshold: Option<f64>,
+    armed: bool,
+}
+
+impl StressWatch {
+    pub fn new(threshold: Option<f64>) -> Self {
+        StressWatch { threshold, armed: true }
+    }
+
+    pub fn from_env() -> Self {
+        let threshold = crate::config::var("STRESS_ALERT_THRESHOLD").ok().and_then(|v| v.trim().parse().ok());
+        StressWatch::new(threshold)
+    }
+
+    /// True when `value` has just reached the threshold
+    pub fn observe(&mut self, value: f64) -> bool {
+        let Some(threshold) = self.threshold else {
+            return false;
+        };
+        if value >= threshold && self.armed {
+            self.armed = false;
+            return true;
+        }
+        if value < threshold - STRESS_REARM_MARGIN {
+            self.armed = true;
+        }
+        false
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FreezeEvent {
+    /// publish_time has not advanced for `cycles` consecutive fetches
+    Frozen { publish_time: DateTime<FixedOffset>, cycles: u32 },
+    Recovered { publish_time: DateTime<FixedOffset>, cycles: u32 },
+}
+
+/// Detects the load feed returning HTTP 200 with a publish_time that stopped advancing
+pub struct FreezeWatchdog {
+    threshold: u32,
+    last_publish_time: Option<DateTime<FixedOffset>>,
+    unchanged_cycles: u32,
+}
+
+impl FreezeWatchdog {
+    pub fn new(threshold: u32) -> Self {
+        FreezeWatchdog { threshold: threshold.max(1), last_publish_time: None, unchanged_cycles: 0 }
+    }
+
+    pub fn from_env() -> Self {
+        let threshold = crate::config::var("FREEZE_ALERT_CYCLES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(3);
+        FreezeWatchdog::new(threshold)
+    }
+
+    /// Feed each successfully fetched publish_time; reports once when frozen and once on recovery
+    pub fn observe(&mut self, publish_time: Option<DateTime<FixedOffset>>) -> Option<FreezeEvent> {
+        let publish_time = publish_time?;
+
+        if self.last_publish_time.is_some_and(|last| publish_time <= last) {
+            self.unchanged_cycles += 1;
+            return (self.unchanged_cycles == self.threshold).then_some(FreezeEvent::Frozen {
+                publish_time,
+                cycles: self.unchanged_cycles,
+            });
+        }
+
+        let was_frozen = self.unchanged_cycles >= self.threshold;
+        let cycles = self.unchanged_cycles;
+        self.last_publish_time = Some(publish_time);
+        self.unchanged_cycles = 0;
+        was_frozen.then_some(FreezeEvent::Recovered { publish_time, cycles })
+    }
+}
+
+/// Report channels skip snapshots they already have, so a stalled feed would otherwise go quiet;
+/// once the data is older than STALE_WARNING_MINUTES they get one warning per stalled snapshot
+pub struct StaleDataGate {
+    max_age: Option<chrono::Duration>,
+    warned_snapshot: Option<String>,
+}
+
+impl StaleDataGate {
+    pub fn from_env() -> Self {
+        let minutes = crate::config::var("STALE_WARNING_MINUTES")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .unwrap_or(60);
+        StaleDataGate { max_age: (minutes > 0).then(|| chrono::Duration::minutes(minutes)), warned_snapshot: None }
+    }
+
+    /// How old the data is, the first time `snapshot_id` is seen past the limit
+    pub fn check(&mut self, snapshot_id: &str, data_time: DateTime<FixedOffset>, now: DateTime<FixedOffset>) -> Option<chrono::Duration> {
+        let age = now - data_time;
+        if age < self.max_age? || self.warned_snapshot.as_deref() == Some(snapshot_id) {
+            return None;
+        }
+        self.warned_snapshot = Some(snapshot_id.to_string());
+        Some(age)
+    }
+}
+
+/// Posted to report channels instead of a repeat of the last report
+pub fn stale_data_warning(data_time: DateTime<FixedOffset>, age: chrono::Duration) -> String {
+    stale_data_text(&discord_timestamp(data_time, 'f'), age)
+}
+
+/// `stale_data_warning` for terminal output, with the Taipei time written out
+pub fn stale_data_warning_plain(data_time: DateTime<FixedOffset>, age: chrono::Duration) -> String {
+    stale_data_text(&data_time.format("%Y-%m-%d %H:%M").to_string(), age)
+}
+
+fn stale_data_text(data_time: &str, age: chrono::Duration) -> String {
+    format!("⚠️ **台電資料似乎已停止更新**: 最後更新於 {} (已 {} 分鐘)，恢復後將繼續發布", data_time, age.num_minutes())
+}
+
+/// A made-up condition for `/alerts test`, sent down the real alert path so admins can check
+/// their channel, role and push setup without waiting for the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestAlert {
+    ReserveCritical,
+    IndicatorChange,
+    Fault,
+    StressHigh,
+}
+
+/// Heads every test alert so nobody mistakes it for a real one
+pub const TEST_BANNER: &str = "🧪 **測試警報**: 以下訊息由 /alerts test 產生，並非真實的電網事件";
+
+impl TestAlert {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reserve_critical" => Some(TestAlert::ReserveCritical),
+            "indicator_change" => Some(TestAlert::IndicatorChange),
+            "fault" => Some(TestAlert::Fault),
+            "stress_high" => Some(TestAlert::StressHigh),
+            _ => None,
+        }
+    }
+
+    /// Yellow to orange pings the alert role; green to yellow doesn't
+    pub fn indicator_change(&self) -> IndicatorChange {
+        match self {
+            TestAlert::ReserveCritical => IndicatorChange {
+                from: ReserveIndicator::Yellow,
+                to: ReserveIndicator::Orange,
+                reserve_rate: 5.8,
+                reserve_rate_change: -1.4,
+            },
+            _ => IndicatorChange { from: ReserveIndicator::Green, to: ReserveIndicator::Yellow, reserve_rate: 9.2, reserve_rate_change: -1.1 },
+        }
+    }
+
+    pub fn fault_change() -> FaultChange {
+        FaultChange { faulted: vec![("測試機組#1".to_string(), 550.0)], recovered: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 7, 15).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn change(from: ReserveIndicator, to: ReserveIndicator) -> IndicatorChange {
+        IndicatorChange { from, to, reserve_rate: 5.0, reserve_rate_change: -1.0 }
+    }
+
+    #[test]
+    fn reserve_alerts_wait_out_the_cooldown() {
+        use ReserveIndicator::*;
+        let mut gate = ReserveAlertGate::new(Duration::from_secs(60 * 60));
+        // Below the default orange threshold, or not an escalation
+        assert!(!gate.allow(&change(Green, Yellow), at(13, 0)));
+        assert!(!gate.allow(&change(Red, Orange), at(13, 10)));
+
+        assert!(gate.allow(&change(Yellow, Orange), at(14, 0)));
+        assert!(!gate.allow(&change(Orange, Red), at(14, 30)));
+        assert!(!gate.allow(&change(Orange, Red), at(14, 59)));
+        assert!(gate.allow(&change(Orange, Red), at(15, 0)));
+        // The cooldown restarts from the last ping, not the first
+        assert!(!gate.allow(&change(Red, Black), at(15, 30)));
+    }
+
+    #[test]
+    fn freeze_is_reported_once_and_recovery_after_it() {
+        let published = |hour, minute| crate::clock::taipei_datetime(at(hour, minute));
+        let mut watchdog = FreezeWatchdog::new(3);
+        assert_eq!(watchdog.observe(published(14, 0)), None);
+        // A failed parse says nothing about whether the feed moved
+        assert_eq!(watchdog.observe(None), None);
+        assert_eq!(watchdog.observe(published(14, 0)), None);
+        assert_eq!(watchdog.observe(published(14, 0)), None);
+        assert_eq!(watchdog.observe(published(13, 50)), Some(FreezeEvent::Frozen { publish_time: published(13, 50).unwrap(), cycles: 3 }));
+        assert_eq!(watchdog.observe(published(14, 0)), None);
+        assert_eq!(watchdog.observe(published(14, 10)), Some(FreezeEvent::Recovered { publish_time: published(14, 10).unwrap(), cycles: 4 }));
+        // A stall shorter than the threshold recovers quietly
+        assert_eq!(watchdog.observe(published(14, 10)), None);
+        assert_eq!(watchdog.observe(published(14, 20)), None);
+    }
+}
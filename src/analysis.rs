@@ -0,0 +1,351 @@
+//! What a generation payload says once its units are added up: totals, mix and unit status, and
+//! the combined snapshot the rest of the bot reports on.
+
+use chrono::{DateTime, FixedOffset};
+use std::collections::HashMap;
+
+use crate::clock::{parse_taipei_datetime, taipei_now};
+use crate::taipower_api::{AlternativePowerData, LoadData, PowerData, PowerUnit, TaipowerError};
+use crate::{forecast, maintenance, regional, stress, trend};
+
+#[derive(Debug, Clone)]
+pub struct PowerAnalysis {
+    pub update_time: DateTime<FixedOffset>,
+    pub total_generation: f64,
+    pub estimated_max_generation: f64,
+    pub generation_by_type: HashMap<String, f64>,
+    /// Installed capacity (MW) per energy type
+    pub capacity_by_type: HashMap<String, f64>,
+    pub top_plant: (String, f64),
+    pub top_unit: (String, f64),
+    pub environmental_restrictions: i32,
+    pub maintenance_count: i32,
+    pub fault_count: i32,
+    pub renewable_ratio: f64,
+    pub private_ratio: f64,
+    pub units: Vec<PowerUnit>,
+    /// The endpoint that served it, when fetched live
+    pub source: Option<&'static str>,
+    /// False when the payload has no timestamp of its own and `update_time` is when it was read
+    pub timestamped: bool,
+}
+
+impl PowerAnalysis {
+    /// Current output as a percentage of installed capacity
+    pub fn generation_ratio(&self) -> f64 {
+        capacity_factor(self.total_generation, self.estimated_max_generation)
+    }
+}
+
+/// Output as a percentage of capacity. Pumped storage draws power while pumping, which would
+/// make this negative, so it's floored at zero; no capacity (or bad input) also gives zero
+pub fn capacity_factor(generation: f64, capacity: f64) -> f64 {
+    if capacity > 0.0 && generation.is_finite() && capacity.is_finite() {
+        (generation / capacity * 100.0).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// What a unit's 備註 says about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemarkClass {
+    /// 環保限制 / 運轉限制
+    Restricted,
+    /// 歲修 / 檢修
+    Maintenance,
+    Fault,
+    Normal,
+}
+
+pub fn classify_remark(remark: &str) -> RemarkClass {
+    if remark.contains("環保限制") || remark.contains("運轉限制") {
+        RemarkClass::Restricted
+    } else if remark.contains("歲修") || remark.contains("檢修") {
+        RemarkClass::Maintenance
+    } else if remark.contains("故障") {
+        RemarkClass::Fault
+    } else {
+        RemarkClass::Normal
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CombinedPowerData {
+    pub power_analysis: PowerAnalysis,
+    pub load_data: Option<LoadData>,
+    pub regions: Vec<regional::RegionBalance>,
+    /// °C at the configured weather location
+    pub temperature: Option<f64>,
+    pub own_forecast: Option<forecast::OwnForecast>,
+    /// Large units offline right now, checked against the maintenance schedule
+    pub outages: Vec<maintenance::Outage>,
+    /// MW of demand response (需量反應) activated today
+    pub demand_response_mw: Option<f64>,
+    /// 0–100 composite of reserve, load ramp and faults
+    pub stress: Option<stress::StressIndex>,
+    /// Direction of the last hour's load and reserve rate, from history
+    pub trend: Option<trend::Trend>,
+}
+
+/// The feeds a snapshot is built from, as named in its ID
+pub const GENERATION_FEED: &str = "taipower/genary";
+pub const LOAD_FEED: &str = "taipower/loadpara";
+
+/// See `CombinedPowerData::snapshot_id`; also used to backfill rows stored before it existed.
+/// `feeds` pairs each source with the timestamp it gave, if any
+pub fn snapshot_id(feeds: &[(&str, Option<DateTime<FixedOffset>>)]) -> String {
+    let key = feeds
+        .iter()
+        .map(|(source, time)| format!("{}@{}", source, time.map(|t| t.to_rfc3339()).unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join("|");
+    let hash = key.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+impl CombinedPowerData {
+    /// Stable across retries and restarts: derived only from the feeds' own timestamps, so the
+    /// same upstream data always gets the same ID (FNV-1a, hex). None when the generation payload
+    /// has no timestamp, as there's nothing to tell a repeat from new data by
+    pub fn snapshot_id(&self) -> Option<String> {
+        let analysis = &self.power_analysis;
+        let publish_time = self.load_data.as_ref().and_then(|l| l.publish_time);
+        analysis.timestamped.then(|| snapshot_id(&[(GENERATION_FEED, Some(analysis.update_time)), (LOAD_FEED, publish_time)]))
+    }
+
+    /// The newer of the two feeds' own timestamps: how fresh the snapshot is
+    pub fn data_time(&self) -> DateTime<FixedOffset> {
+        let publish_time = self.load_data.as_ref().and_then(|l| l.publish_time);
+        publish_time.map_or(self.power_analysis.update_time, |t| t.max(self.power_analysis.update_time))
+    }
+}
+
+pub fn analyze_power_data_from_standard(data: PowerData) -> Result<PowerAnalysis, TaipowerError> {
+    analyze_power_data(data.aa_data, parse_taipei_datetime(&data.date_time))
+}
+
+/// This layout carries no timestamp
+pub fn analyze_power_data_from_alternative(data: AlternativePowerData) -> Result<PowerAnalysis, TaipowerError> {
+    analyze_power_data(data.datas, None)
+}
+
+/// `date_time` is the payload's own timestamp; without one the data counts as read just now
+pub fn analyze_power_data(units: Vec<PowerUnit>, date_time: Option<DateTime<FixedOffset>>) -> Result<PowerAnalysis, TaipowerError> {
+    let mut total_generation = 0.0;
+    let mut estimated_max_generation = 0.0;
+    let mut generation_by_type: HashMap<String, f64> = HashMap::new();
+    let mut capacity_by_type: HashMap<String, f64> = HashMap::new();
+    let mut plant_generation: HashMap<String, f64> = HashMap::new();
+    let mut unit_generation: HashMap<String, f64> = HashMap::new();
+    let mut environmental_restrictions = 0;
+    let mut maintenance_count = 0;
+    let mut fault_count = 0;
+    let mut renewable_generation = 0.0;
+    let mut private_generation = 0.0;
+    
+    for unit in &units {
+        // Skip summary rows
+        if unit.unit_name == "小計" {
+            continue;
+        }
+        
+        // Parse capacity and generation
+        let capacity = unit.capacity;
+        let generation = unit.generation;
+        
+        // Add to total generation
+        total_generation += generation;
+        estimated_max_generation += capacity;
+        
+        // Group by energy type
+        let energy_type = clean_energy_type(&unit.unit_type);
+        *generation_by_type.entry(energy_type.clone()).or_insert(0.0) += generation;
+        *capacity_by_type.entry(energy_type.clone()).or_insert(0.0) += capacity;
+        
+        // Track renewable energy (風力, 太陽能, 水力, 其它再生能源)
+        if is_renewable(&energy_type) {
+            renewable_generation += generation;
+        }
+        
+        // Track private generation (民營電廠)
+        if unit.unit_type.contains("民營電廠") {
+            private_generation += generation;
+        }
+        
+        // Extract plant name for top plant calculation
+        if let Some(plant_name) = extract_plant_name(&unit.unit_name) {
+            *plant_generation.entry(plant_name).or_insert(0.0) += generation;
+        }
+        
+        // Track individual units
+        if generation > 0.0 && !unit.unit_name.contains("小計") {
+            unit_generation.insert(unit.unit_name.clone(), generation);
+        }
+        
+        // Count issues based on remarks
+        match classify_remark(&unit.remark) {
+            RemarkClass::Restricted => environmental_restrictions += 1,
+            RemarkClass::Maintenance => maintenance_count += 1,
+            RemarkClass::Fault => fault_count += 1,
+            RemarkClass::Normal => {}
+        }
+    }
+    
+    // Find top plant and unit
+    let top_plant = plant_generation
+        .into_iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or(("未知".to_string(), 0.0));
+    
+    let top_unit = unit_generation
+        .into_iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or(("未知".to_string(), 0.0));
+    
+    // Calculate ratios
+    let renewable_ratio = if total_generation > 0.0 {
+        (renewable_generation / total_generation) * 100.0
+    } else {
+        0.0
+    };
+    
+    let private_ratio = if total_generation > 0.0 {
+        (private_generation / total_generation) * 100.0
+    } else {
+        0.0
+    };
+    
+    Ok(PowerAnalysis {
+        update_time: date_time.unwrap_or_else(taipei_now),
+        total_generation,
+        estimated_max_generation,
+        generation_by_type,
+        capacity_by_type,
+        top_plant,
+        top_unit,
+        environmental_restrictions,
+        maintenance_count,
+        fault_count,
+        renewable_ratio,
+        private_ratio,
+        units,
+        source: None,
+        timestamped: date_time.is_some(),
+    })
+}
+
+/// (available MW, installed MW) per energy type. Units in planned maintenance are left out of
+/// both, so only unplanned outages (故障) count against availability
+pub fn availability_by_type(units: &[PowerUnit]) -> HashMap<String, (f64, f64)> {
+    let mut by_type: HashMap<String, (f64, f64)> = HashMap::new();
+    for unit in units.iter().filter(|u| !u.unit_name.contains("小計")) {
+        let class = classify_remark(&unit.remark);
+        if class == RemarkClass::Maintenance {
+            continue;
+        }
+        let (available, installed) = by_type.entry(clean_energy_type(&unit.unit_type)).or_insert((0.0, 0.0));
+        *installed += unit.capacity;
+        if class != RemarkClass::Fault {
+            *available += unit.capacity;
+        }
+    }
+    by_type
+}
+
+pub fn clean_energy_type(energy_type: &str) -> String {
+    // Simplify energy type names
+    if energy_type.contains("民營電廠") {
+        energy_type.replace("民營電廠-", "民營")
+    } else if energy_type.contains("其它再生能源") {
+        "其它再生能源".to_string()
+    } else {
+        energy_type.to_string()
+    }
+}
+
+pub fn is_renewable(energy_type: &str) -> bool {
+    matches!(energy_type, "風力" | "太陽能" | "水力" | "其它再生能源")
+}
+
+pub fn extract_plant_name(unit_name: &str) -> Option<String> {
+    // Extract plant name from unit name (e.g., "台中#1" -> "台中")
+    if let Some(pos) = unit_name.find('#') {
+        Some(unit_name[..pos].trim().to_string())
+    } else if unit_name.contains("小計") {
+        None
+    } else {
+        // For complex names, try to extract meaningful part
+        let parts: Vec<&str> = unit_name.split(&['(', '[', '#'][..]).collect();
+        Some(parts[0].trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn extract_plant_name_is_the_trimmed_prefix(name in "\\PC*") {
+            if let Some(plant) = extract_plant_name(&name) {
+                // Everything before the unit number, or before any bracket when there is none
+                let prefix = if name.contains('#') { name.split('#').next() } else { name.split(['(', '[']).next() };
+                prop_assert_eq!(plant, prefix.unwrap().trim());
+            } else {
+                prop_assert!(name.contains("小計") && !name.contains('#'));
+            }
+        }
+
+        #[test]
+        fn extract_plant_name_splits_units(plant in "[^#(\\[小]{1,8}", unit in 1u32..20) {
+            prop_assert_eq!(extract_plant_name(&format!("{}#{}", plant, unit)), Some(plant.trim().to_string()));
+        }
+
+        #[test]
+        fn classify_remark_finds_keywords(prefix in "\\PC{0,10}", suffix in "\\PC{0,10}") {
+            prop_assume!(!(prefix.clone() + &suffix).contains("限制"));
+            prop_assert_eq!(classify_remark(&format!("{}環保限制{}", prefix, suffix)), RemarkClass::Restricted);
+            prop_assert_eq!(classify_remark(&format!("{}歲修{}", prefix, suffix)), RemarkClass::Maintenance);
+        }
+
+        #[test]
+        fn classify_remark_never_panics(remark in "\\PC*") {
+            let _ = classify_remark(&remark);
+        }
+
+        #[test]
+        fn capacity_factor_is_never_negative(generation in any::<f64>(), capacity in any::<f64>()) {
+            let factor = capacity_factor(generation, capacity);
+            prop_assert!(factor >= 0.0 && !factor.is_nan());
+        }
+    }
+
+    fn combined(analysis: PowerAnalysis) -> CombinedPowerData {
+        CombinedPowerData {
+            power_analysis: analysis,
+            load_data: None,
+            regions: Vec::new(),
+            temperature: None,
+            own_forecast: None,
+            outages: Vec::new(),
+            demand_response_mw: None,
+            stress: None,
+            trend: None,
+        }
+    }
+
+    #[test]
+    fn snapshot_id_needs_a_feed_timestamp() {
+        let time = parse_taipei_datetime("2024-07-15 14:30").unwrap();
+        let timestamped = combined(analyze_power_data(Vec::new(), Some(time)).unwrap());
+        assert_eq!(timestamped.snapshot_id(), Some(snapshot_id(&[(GENERATION_FEED, Some(time)), (LOAD_FEED, None)])));
+        assert_eq!(timestamped.snapshot_id(), combined(analyze_power_data(Vec::new(), Some(time)).unwrap()).snapshot_id());
+        assert_ne!(snapshot_id(&[("other/feed", Some(time))]), snapshot_id(&[(GENERATION_FEED, Some(time))]));
+
+        let untimestamped = analyze_power_data(Vec::new(), None).unwrap();
+        assert!(!untimestamped.timestamped);
+        assert_eq!(combined(untimestamped).snapshot_id(), None);
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDate;
+use tracing::debug;
+
+use crate::history::{DailySummary, SummarySource};
+
+// 台電過去電力供需資訊 (daily peak supply/demand since 2019)
+const ARCHIVE_URL: &str = "https://service.taipower.com.tw/data/opendata/apply/file/d006002/001.csv";
+
+/// Look up a past day in Taipower's historical open-data archive.
+pub async fn fetch_archived_summary(date: NaiveDate) -> Result<Option<DailySummary>, Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http::client();
+    let url = crate::http::endpoint(ARCHIVE_URL);
+    debug!(endpoint = %url, "Fetching archive data");
+
+    let response = client.get(&url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()).into());
+    }
+
+    let text = response.text().await?;
+    parse_archive_csv(&text, date)
+}
+
+fn parse_archive_csv(text: &str, date: NaiveDate) -> Result<Option<DailySummary>, Box<dyn std::error::Error + Send + Sync>> {
+    // Quoted fields can hold thousands separators ("38,123"); rows may be ragged
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+    let columns = reader.headers()?.clone();
+    if columns.iter().all(str::is_empty) {
+        return Err("Empty archive file".into());
+    }
+
+    let find_column = |prefix: &str| columns.iter().position(|c| c.starts_with(prefix));
+    let date_col = find_column("日期").ok_or("Archive is missing 日期 column")?;
+    let peak_load_col = find_column("尖峰負載");
+    let reserve_rate_col = find_column("備轉容量率");
+
+    // The archive writes dates as 20240802
+    let wanted = date.format("%Y%m%d").to_string();
+
+    for record in reader.records() {
+        let fields = record?;
+        if fields.get(date_col).map(|d| d.replace(['-', '/'], "")) != Some(wanted.clone()) {
+            continue;
+        }
+
+        let value_at = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .and_then(|v| v.replace(',', "").parse::<f64>().ok())
+        };
+
+        return Ok(Some(DailySummary {
+            date,
+            source: SummarySource::Archive,
+            sample_count: 1,
+            // Archive values are MW; reports use 萬瓩
+            peak_load: value_at(peak_load_col).map(|mw| mw / 10.0),
+            peak_time: None,
+            min_load: None,
+            min_time: None,
+            min_reserve_rate: None,
+            peak_reserve_rate: value_at(reserve_rate_col),
+            average_renewable_ratio: None,
+            generation_mix: Vec::new(),
+            fault_events: None,
+            top_plant: None,
+            max_fault_count: None,
+            max_maintenance_count: None,
+            max_environmental_restrictions: None,
+        }));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_values_keep_their_thousands_separators() {
+        let text = "\u{feff}日期,淨尖峰供電能力(MW),尖峰負載(MW),備轉容量(MW),備轉容量率(%)\n\
+                    20240801,\"41,002\",\"38,123\",\"2,879\",7.55\n\
+                    20240802,\"40,500\",\"37,900\",\"2,600\",6.86\n";
+        let summary = parse_archive_csv(text, NaiveDate::from_ymd_opt(2024, 8, 2).unwrap()).unwrap().unwrap();
+        assert_eq!(summary.peak_load, Some(3790.0));
+        assert_eq!(summary.peak_reserve_rate, Some(6.86));
+        assert_eq!(summary.min_reserve_rate, None);
+        assert!(parse_archive_csv(text, NaiveDate::from_ymd_opt(2024, 8, 3).unwrap()).unwrap().is_none());
+        assert!(parse_archive_csv("", NaiveDate::from_ymd_opt(2024, 8, 3).unwrap()).is_err());
+    }
+}
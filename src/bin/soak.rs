@@ -0,0 +1,69 @@
+//! Soak test harness; see `taipower_discord::soak`. Needs `--features soak`.
+
+use clap::Parser;
+use std::net::TcpListener;
+
+use taipower_discord::logging;
+use taipower_discord::soak::{self, SoakOptions};
+
+#[derive(Parser)]
+#[command(about = "Run the update pipeline against a mock Taipower server for thousands of simulated cycles")]
+struct Cli {
+    /// Update cycles to run
+    #[arg(long, default_value_t = 5000)]
+    cycles: u32,
+    /// Cycles before the memory, task and file descriptor baselines are taken
+    #[arg(long, default_value_t = 500)]
+    warmup: u32,
+    /// Cycles between resource samples
+    #[arg(long, default_value_t = 250)]
+    sample_every: u32,
+    /// RSS growth after warmup that fails the run, in MB
+    #[arg(long, default_value_t = 16)]
+    max_rss_growth_mb: u64,
+    /// Growth in alive tasks or open descriptors after warmup that fails the run
+    #[arg(long, default_value_t = 4)]
+    max_task_growth: usize,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let options = SoakOptions {
+        cycles: cli.cycles,
+        warmup: cli.warmup,
+        sample_every: cli.sample_every.max(1),
+        max_rss_growth_kb: cli.max_rss_growth_mb * 1024,
+        max_task_growth: cli.max_task_growth,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the mock server");
+    let base_url = format!("http://{}", listener.local_addr().expect("Failed to read the mock server address"));
+    for (key, value) in soak::environment(&base_url) {
+        // SAFETY: no other thread exists yet; the runtime is started below
+        unsafe { std::env::set_var(key, value) };
+    }
+    logging::init();
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the Tokio runtime");
+    let report = match runtime.block_on(soak::run(&options, listener)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Soak test failed to run: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    println!("{} cycle(s), {} published", report.cycles, report.published);
+    for (event, count) in &report.events {
+        println!("  {}: {}", event, count);
+    }
+    if report.violations.is_empty() {
+        println!("No violations");
+    } else {
+        println!("{} violation(s):", report.violations.len());
+        for violation in report.violations.iter().take(50) {
+            println!("  {}", violation);
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,1359 @@
+//! The Discord side: the event handler and its update loop, delivery to report channels, and
+//! the single-shot `once` run.
+
+mod commands;
+mod pages;
+mod poll;
+mod presence;
+mod refresh;
+mod snapshot;
+mod ticker;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serenity::{
+    async_trait,
+    builder::{CreateAllowedMentions, CreateAttachment, CreateMessage, EditMessage, EditThread},
+    http::Http,
+    model::{
+        application::{Command, Interaction},
+        channel::{Channel, Message},
+        gateway::Ready,
+        guild::{Guild, UnavailableGuild},
+        id::{ChannelId, MessageId, UserId},
+    },
+    prelude::*,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::analysis::CombinedPowerData;
+use crate::clock::{self, taipei_now, Clock};
+use crate::config::{Config, ConfigKey};
+use crate::format::generation_mix_chart;
+use crate::history::{GuildConfig, History, SnapshotRow};
+use crate::latency::{self, Phase};
+use crate::render::{self, Cadence, ContentProfile, DiscordTextRenderer, Renderer, ReportFormat, ReportProfile};
+use crate::reporting;
+use crate::taipower_api::{fetch_and_analyze_power_data, fetch_load_data, LoadData, TaipowerError};
+use crate::validation::Violation;
+use crate::{
+    alerts, catchup, chart, config, crashloop, cycle, dashboard, demand_response, digest, forecast, leader, locale, maintenance, mentions, metrics,
+    push, records, regional, scheduler, stress, systemd, trend, weather, weekly,
+};
+
+struct Handler {
+    history: Arc<History>,
+    admin: AdminRoute,
+    chart_cache: Arc<chart::ChartCache>,
+    maintenance: Arc<maintenance::MaintenanceCalendar>,
+    dashboard: Option<Arc<dashboard::Dashboard>>,
+    /// The last cycle's data, which slash commands answer from
+    snapshots: Arc<snapshot::SnapshotCache>,
+    /// Pages of long listings, for their previous/next buttons
+    pager: pages::Pager,
+    /// The refresh button on scheduled reports
+    refresher: refresh::Refresher,
+    clock: Arc<dyn Clock>,
+    /// Flips to true on SIGTERM/Ctrl-C; the update loop stops before its next cycle
+    shutdown: watch::Receiver<bool>,
+    /// Held for the duration of each update cycle so shutdown can wait for it to finish
+    cycle_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Set with LEADER_ELECTION: only the lease holder posts
+    leadership: Option<Arc<leader::Leadership>>,
+    /// Cleared once the history database is open and the gateway connected
+    startup: Arc<crashloop::StartupGuard>,
+}
+
+/// The config in effect: what the client started with, or the last `/config reload`
+async fn current_config(ctx: &Context) -> Config {
+    shared_config(ctx).await.read().unwrap().clone()
+}
+
+/// The lock `/config reload` writes through
+async fn shared_config(ctx: &Context) -> Arc<std::sync::RwLock<Config>> {
+    ctx.data.read().await.get::<ConfigKey>().cloned().expect("the config is inserted when the client is built")
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        info!("{} is connected!", ready.user.name);
+        systemd::ready();
+        // The database opened and the bot is up; a Taipower outage keeping the first cycle from
+        // finishing is no reason to quarantine it on the next restart
+        self.startup.healthy();
+        
+        if let Err(why) = Command::set_global_commands(&ctx.http, commands::definitions()).await {
+            error!("Error registering slash commands: {:?}", why);
+        }
+        
+        let shared = shared_config(&ctx).await;
+        let config = shared.read().unwrap().clone();
+        let clock = self.clock.clone();
+        let mut shutdown = self.shutdown.clone();
+        let cycle_lock = self.cycle_lock.clone();
+        
+        if config.daily_digest
+            && let Some(time) = config.digest_time
+        {
+            tokio::spawn(post_digests(ctx.clone(), self.history.clone(), clock.clone(), shutdown.clone(), self.leadership.clone(), time));
+        }
+        
+        let mut schedule = scheduler::Scheduler::from_config(&config, clock.clone());
+        let mut updater = cycle::Updater::new(self.history.clone(), clock.clone(), shared);
+        let mut feeds = cycle::LiveFeeds { maintenance: Some(self.maintenance.clone()) };
+        let mut outlet = DiscordOutlet {
+            ctx,
+            history: self.history.clone(),
+            clock,
+            config,
+            admin: self.admin,
+            report_charts: chart::report_charts_enabled().then(|| self.chart_cache.clone()),
+            snapshots: self.snapshots.clone(),
+            dashboard: self.dashboard.clone(),
+            leadership: self.leadership.clone(),
+            presence: presence::Presence::default(),
+            ticker: ticker::Ticker::default(),
+            home: Vec::new(),
+            last_posted: HashMap::new(),
+            targets: Vec::new(),
+        };
+        tokio::spawn(latency::scope(async move {
+            outlet.home = home_channels(&outlet.ctx.http, &outlet.config.channels).await;
+            loop {
+                tokio::select! {
+                    _ = schedule.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                let _cycle = cycle_lock.lock().await;
+                if *shutdown.borrow() {
+                    break;
+                }
+                // A `/config reload` since the last cycle
+                let config = current_config(&outlet.ctx).await;
+                if config != outlet.config {
+                    if (config.interval, &config.schedules) != (outlet.config.interval, &outlet.config.schedules) {
+                        schedule.reload(&config);
+                    }
+                    updater.reload();
+                    outlet.home = home_channels(&outlet.ctx.http, &config.channels).await;
+                    outlet.config = config;
+                    info!("Settings reloaded; {} home channel(s)", outlet.home.len());
+                }
+                updater.cycle(&mut feeds, &mut outlet).await;
+            }
+        }));
+    }
+    
+    /// Removed from a guild (not just an outage): forget everything stored for it
+    async fn guild_delete(&self, _ctx: Context, incomplete: UnavailableGuild, _full: Option<Guild>) {
+        if incomplete.unavailable {
+            return;
+        }
+        match self.history.purge_guild(incomplete.id.get(), &[]) {
+            Ok(deleted) => info!("Removed from guild {}, purged {} rows", incomplete.id, deleted),
+            Err(why) => error!("Error purging data for guild {}: {:?}", incomplete.id, why),
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::Command(command) => commands::handle_command(&ctx, &command, self).await,
+            Interaction::Component(component) => commands::handle_component(&ctx, &component, self).await,
+            Interaction::Autocomplete(autocomplete) => commands::handle_autocomplete(&ctx, &autocomplete, self).await,
+            _ => {}
+        }
+    }
+}
+
+/// Where operational errors go: ADMIN_CHANNEL_ID, else a DM to OWNER_ID, else only the log
+#[derive(Clone, Copy, Default)]
+struct AdminRoute {
+    channel_id: Option<ChannelId>,
+    owner_id: Option<UserId>,
+}
+
+impl AdminRoute {
+    async fn send(&self, http: &Http, text: &str) {
+        let channel_id = match (self.channel_id, self.owner_id) {
+            (Some(channel_id), _) => channel_id,
+            (None, Some(owner_id)) => match owner_id.create_dm_channel(http).await {
+                Ok(dm) => dm.id,
+                Err(why) => {
+                    error!("Error opening DM with owner {}: {:?}", owner_id, why);
+                    return;
+                }
+            },
+            (None, None) => return,
+        };
+        if let Err(why) = channel_id.say(http, text).await {
+            error!("Error sending admin notice to {}: {:?}", channel_id, why);
+        }
+    }
+}
+
+/// A channel that gets the scheduled report and alerts: each CHANNEL_ID entry, and each guild's
+/// /config channel
+struct ReportTarget {
+    channel_id: ChannelId,
+    config: GuildConfig,
+    content: ContentProfile,
+}
+
+impl ReportTarget {
+    fn renderer(&self) -> DiscordTextRenderer {
+        DiscordTextRenderer { numbers: self.config.numbers, sections: self.content.sections(self.config.sections), ..Default::default() }
+    }
+
+    /// The guild's `/config format`, else REPORT_FORMAT
+    fn format(&self, default: ReportFormat) -> ReportFormat {
+        self.config.format.unwrap_or(default)
+    }
+
+    /// The guild's `/config alert-role`, else ALERT_ROLE_ID in a home channel
+    fn alert_role(&self, home_role: Option<u64>, channels: &[(ChannelId, ContentProfile)]) -> Option<u64> {
+        self.config.alert_role_id.or(home_role.filter(|_| channels.iter().any(|(id, _)| *id == self.channel_id)))
+    }
+
+    /// Each renderer/schedule pair the channel's mode asks for
+    fn cadences(&self) -> impl Iterator<Item = (&ReportTarget, Cadence)> {
+        let cadences = if self.content.receives_reports() { self.config.mode().cadences() } else { &[] };
+        cadences.iter().map(move |cadence| (self, *cadence))
+    }
+
+    /// Live status messages are edited every cycle; otherwise a minute of slack so a 10-minute
+    /// interval doesn't skip a cycle to timer jitter
+    fn due(&self, cadence: Cadence, posted: Option<DateTime<FixedOffset>>, now: DateTime<FixedOffset>) -> bool {
+        cadence.live
+            || posted.is_none_or(|posted| {
+                now - posted + chrono::Duration::minutes(1) >= chrono::Duration::minutes(self.config.interval_minutes as i64)
+            })
+    }
+}
+
+/// `home` is each CHANNEL_ID entry with the guild it belongs to
+fn report_targets(history: &History, home: &[(ChannelId, ContentProfile, Option<u64>)]) -> Vec<ReportTarget> {
+    let mut targets = Vec::new();
+    for &(channel_id, content, guild_id) in home {
+        let config = match guild_id.map(|id| history.guild_config(id)) {
+            Some(Ok(config)) => config,
+            Some(Err(why)) => {
+                error!("Error reading guild config: {:?}", why);
+                GuildConfig::new(guild_id.unwrap_or_default())
+            }
+            None => GuildConfig::new(0),
+        };
+        targets.push(ReportTarget { channel_id, config, content });
+    }
+
+    match history.report_guilds() {
+        Ok(configs) => {
+            for config in configs {
+                let Some(channel_id) = config.channel_id.map(ChannelId::new) else {
+                    continue;
+                };
+                if !targets.iter().any(|t| t.channel_id == channel_id) {
+                    targets.push(ReportTarget { channel_id, config, content: ContentProfile::Full });
+                }
+            }
+        }
+        Err(why) => error!("Error reading guild configs: {:?}", why),
+    }
+    targets
+}
+
+/// The update loop's announcements, posted to Discord: home and /config report channels, the
+/// admin route, followers, phone pushes and the bot's presence
+struct DiscordOutlet {
+    ctx: Context,
+    history: Arc<History>,
+    clock: Arc<dyn Clock>,
+    config: Config,
+    admin: AdminRoute,
+    report_charts: Option<Arc<chart::ChartCache>>,
+    snapshots: Arc<snapshot::SnapshotCache>,
+    dashboard: Option<Arc<dashboard::Dashboard>>,
+    leadership: Option<Arc<leader::Leadership>>,
+    presence: presence::Presence,
+    ticker: ticker::Ticker,
+    /// Each CHANNEL_ID entry with the guild it belongs to
+    home: Vec<(ChannelId, ContentProfile, Option<u64>)>,
+    last_posted: HashMap<(ChannelId, Cadence), DateTime<FixedOffset>>,
+    /// This cycle's report channels
+    targets: Vec<ReportTarget>,
+}
+
+impl DiscordOutlet {
+    fn report_channels(&self) -> impl Iterator<Item = &ReportTarget> {
+        self.targets.iter().filter(|t| t.content.receives_reports())
+    }
+}
+
+#[async_trait]
+impl cycle::Outlet for DiscordOutlet {
+    async fn begin(&mut self) -> bool {
+        let leading = self.leadership.as_ref().is_none_or(|l| l.heartbeat(&self.history));
+        self.targets = if leading { report_targets(&self.history, &self.home) } else { Vec::new() };
+        leading
+    }
+
+    async fn admin(&mut self, notice: &str) {
+        self.admin.send(&self.ctx.http, notice).await;
+    }
+
+    async fn digest(&mut self, date: NaiveDate) {
+        for target in self.report_channels() {
+            send_digest(&self.ctx.http, &self.history, target, date).await;
+        }
+    }
+
+    async fn weekly(&mut self, monday: NaiveDate) {
+        for target in self.report_channels() {
+            if let Some(message) = weekly::build(&self.history, monday, target.config.numbers)
+                && let Err(why) = send_to(&self.ctx.http, target.channel_id, message).await
+            {
+                error!("Error sending weekly report to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    fn has_fallback(&self) -> bool {
+        self.snapshots.fallback(self.clock.now()).is_some()
+    }
+
+    async fn outage(&mut self) -> bool {
+        let now = self.clock.now();
+        let cached = self.snapshots.fallback(now);
+        for target in self.report_channels() {
+            let sent = match &cached {
+                Some(cached) => {
+                    let message = cached_report(target, self.config.report_format, cached, now);
+                    send_to(&self.ctx.http, target.channel_id, message).await.map(|_| ())
+                }
+                None => target.channel_id.say(&self.ctx.http, reporting::OUTAGE_NOTICE).await.map(|_| ()),
+            };
+            if let Err(why) = sent {
+                error!("Error sending outage notice to {}: {:?}", target.channel_id, why);
+            }
+        }
+        cached.is_some()
+    }
+
+    async fn freeze(&mut self, event: &alerts::FreezeEvent) {
+        let alert_channel_id = self.admin.channel_id.or(self.config.channels.first().map(|(id, _)| *id));
+        send_freeze_alert(&self.ctx.http, &self.history, alert_channel_id, event).await;
+    }
+
+    async fn observed(&mut self, data: &CombinedPowerData, previous_load: Option<&LoadData>) {
+        if let Some(dashboard) = &self.dashboard {
+            dashboard.update(data, &self.history);
+        }
+        let now = self.clock.now();
+        self.snapshots.store(snapshot::Snapshot {
+            data: data.clone(),
+            previous_reserve_rate: previous_load.map(|previous| previous.forecast_peak_reserve_rate),
+            fetched_at: now,
+            load_fetched_at: data.load_data.is_some().then_some(now),
+        });
+    }
+
+    async fn indicator_change(&mut self, change: &alerts::IndicatorChange, ping: bool) {
+        push_indicator_change(&self.history, change).await;
+        for target in &self.targets {
+            let alert = if ping {
+                let role = target.alert_role(self.config.alert_role_id, &self.config.channels);
+                target.format(self.config.report_format).reserve_alert_message(change, &target.renderer(), role)
+            } else {
+                target.format(self.config.report_format).indicator_change_message(change, &target.renderer())
+            };
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, alert).await {
+                error!("Error sending indicator alert to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn load_update(&mut self, load: &LoadData, previous: Option<&LoadData>, trend: Option<trend::Trend>) {
+        self.presence.update(&self.ctx, render::presence_text(load, alerts::reserve_rate_change(load, previous), trend));
+        check_ping_ladders(&self.ctx.http, &self.history, &self.targets, load.forecast_peak_reserve_rate).await;
+        update_tickers(&self.ctx.http, &self.history, &mut self.ticker, load).await;
+        let now = self.clock.now().naive_local();
+        if poll::due(load, now, &self.config) {
+            post_supply_polls(&self.ctx.http, &self.history, &self.targets, now, self.config.digest_time).await;
+        }
+    }
+
+    async fn fault_change(&mut self, change: &alerts::FaultChange) {
+        for target in &self.targets {
+            let alert = target.format(self.config.report_format).fault_change_message(change, &target.renderer());
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, alert).await {
+                error!("Error sending fault alert to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn stress_alert(&mut self, stress: &stress::StressIndex) {
+        let alert = stress::alert_message(stress);
+        push::notify(&self.history, push::AlertType::StressHigh, "電網壓力指數升高", &alert.replace("**", "").replace("-# ", "")).await;
+        for target in self.report_channels() {
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, CreateMessage::new().content(&alert)).await {
+                error!("Error sending stress alert to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn catch_up(&mut self, last: &SnapshotRow, data: &CombinedPowerData) {
+        let now = self.clock.now().naive_local();
+        for target in self.report_channels() {
+            let summary = catchup::build(last, data, now, target.config.numbers);
+            let message = CreateMessage::new().content(summary).allowed_mentions(CreateAllowedMentions::new());
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, message).await {
+                error!("Error sending catch-up summary to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn stale_data(&mut self, data_time: DateTime<FixedOffset>, age: chrono::Duration) {
+        let warning = alerts::stale_data_warning(data_time, age);
+        for target in self.report_channels() {
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, CreateMessage::new().content(&warning)).await {
+                error!("Error sending stale data warning to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn record(&mut self, record: &records::RecordBreak) {
+        let announcement = records::announcement(record);
+        for target in self.report_channels() {
+            if let Err(why) = send_to(&self.ctx.http, target.channel_id, CreateMessage::new().content(&announcement)).await {
+                error!("Error sending renewable record to {}: {:?}", target.channel_id, why);
+            }
+        }
+    }
+
+    async fn report(&mut self, data: &CombinedPowerData, indicator_change: Option<&alerts::IndicatorChange>) {
+        let now = self.clock.now();
+        let due: Vec<(&ReportTarget, Cadence)> = self
+            .targets
+            .iter()
+            .flat_map(ReportTarget::cadences)
+            .filter(|(t, cadence)| t.due(*cadence, self.last_posted.get(&(t.channel_id, *cadence)).copied(), now))
+            .collect();
+        let charts = self.report_charts.as_deref();
+        for delivered in post_reports(&self.ctx.http, &self.history, self.config.report_format, charts, &due, data, now).await {
+            self.last_posted.insert(delivered, now);
+        }
+        relay_to_followers(&self.ctx.http, &self.history, data, indicator_change).await;
+    }
+}
+
+/// DIGEST_TIME's own schedule: every day at `time`, that day's digest to each report channel
+async fn post_digests(
+    ctx: Context,
+    history: Arc<History>,
+    clock: Arc<dyn Clock>,
+    mut shutdown: watch::Receiver<bool>,
+    leadership: Option<Arc<leader::Leadership>>,
+    time: NaiveTime,
+) {
+    let mut config = current_config(&ctx).await;
+    let mut home = home_channels(&ctx.http, &config.channels).await;
+    info!("Daily digest scheduled for {} every day", time.format("%H:%M"));
+
+    loop {
+        let now = clock.now().naive_local();
+        let next = digest::next_run(time, now);
+        tokio::select! {
+            _ = tokio::time::sleep((next - now).to_std().unwrap_or_default()) => {}
+            _ = shutdown.changed() => break,
+        }
+        if *shutdown.borrow() {
+            break;
+        }
+        if leadership.as_ref().is_some_and(|l| !l.is_leading()) {
+            continue;
+        }
+        let latest = current_config(&ctx).await;
+        if latest.channels != config.channels {
+            home = home_channels(&ctx.http, &latest.channels).await;
+            config = latest;
+        }
+        for target in report_targets(&history, &home).iter().filter(|t| t.content.receives_reports()) {
+            send_digest(&ctx.http, &history, target, next.date()).await;
+        }
+    }
+}
+
+/// The home channels with the guild each is in, for `report_targets`
+async fn home_channels(http: &Http, channels: &[(ChannelId, ContentProfile)]) -> Vec<(ChannelId, ContentProfile, Option<u64>)> {
+    let mut home = Vec::new();
+    for &(channel_id, content) in channels {
+        home.push((channel_id, content, channel_guild(http, channel_id).await));
+    }
+    home
+}
+
+/// The digest for `date`, with the answer to the day's supply poll if it was asked in this channel
+async fn send_digest(http: &Http, history: &History, target: &ReportTarget, date: NaiveDate) {
+    let outcome = poll::outcome(http, history, target.config.guild_id, target.channel_id, date, target.config.numbers).await;
+    if let Some(message) = digest::build(history, date, target.config.numbers, target.config.digest_csv, outcome)
+        && let Err(why) = send_to(http, target.channel_id, message).await
+    {
+        error!("Error sending daily digest to {}: {:?}", target.channel_id, why);
+    }
+}
+
+/// Claim `snapshot_id` for `channel_id`; false if it was already posted there. Database errors
+/// allow the post, since a rare duplicate beats a missed report, and so does data without an ID
+fn claim_delivery(history: &History, snapshot_id: Option<&str>, channel_id: ChannelId) -> bool {
+    let Some(snapshot_id) = snapshot_id else {
+        return true;
+    };
+    history.claim_delivery(snapshot_id, channel_id.get()).unwrap_or_else(|why| {
+        error!("Error recording delivery to {}: {:?}", channel_id, why);
+        true
+    })
+}
+
+/// Undo a claim after a failed send so the next attempt can retry
+fn release_delivery(history: &History, snapshot_id: Option<&str>, channel_id: ChannelId) {
+    if let Some(snapshot_id) = snapshot_id
+        && let Err(why) = history.release_delivery(snapshot_id, channel_id.get())
+    {
+        error!("Error releasing delivery to {}: {:?}", channel_id, why);
+    }
+}
+
+/// Send each due report that its channel hasn't had for this snapshot yet; returns what was delivered
+async fn post_reports(
+    http: &Http,
+    history: &History,
+    report_format: ReportFormat,
+    charts: Option<&chart::ChartCache>,
+    due: &[(&ReportTarget, Cadence)],
+    data: &CombinedPowerData,
+    now: DateTime<FixedOffset>,
+) -> Vec<(ChannelId, Cadence)> {
+    let mut delivered = Vec::new();
+    for &(target, cadence) in due {
+        // The full post keeps the bare ID; other cadences of the same snapshot are separate deliveries
+        let snapshot_id = data.snapshot_id().map(|id| match cadence {
+            Cadence { profile: ReportProfile::Full, live: false } => id,
+            Cadence { profile, live } => format!("{}/{}{}", id, profile.code(), if live { "-live" } else { "" }),
+        });
+        if !claim_delivery(history, snapshot_id.as_deref(), target.channel_id) {
+            continue;
+        }
+        let result = if cadence.live {
+            latency::measure_async(Phase::Post, update_live_status(http, history, report_format, target, cadence.profile, data, now)).await
+        } else {
+            let mention = history.mention_target(target.channel_id.get()).unwrap_or_else(|why| {
+                error!("Error reading mention policy: {:?}", why);
+                None
+            });
+            let text = target.renderer();
+            let message = latency::measure(Phase::Render, || {
+                let message = target.format(report_format).report_message(data, mention.as_ref(), &text).components(vec![refresh::button()]);
+                match charts.and_then(|cache| generation_mix_chart(cache, data, &text)) {
+                    Some(png) => message.add_file(CreateAttachment::bytes(png, "generation-mix.png")),
+                    None => message,
+                }
+            });
+            let sent = latency::measure_async(Phase::Post, send_to(http, target.channel_id, message)).await.map(|_| ());
+            if sent.is_ok() {
+                post_pending_notes(http, history, target).await;
+            }
+            sent
+        };
+        match result {
+            Ok(()) => delivered.push((target.channel_id, cadence)),
+            Err(why) => {
+                error!("Error sending message to {}: {:?}", target.channel_id, why);
+                release_delivery(history, snapshot_id.as_deref(), target.channel_id);
+                // The configured channel was deleted or the bot lost access; forget it
+                if is_gone(&why) && target.config.channel_id == Some(target.channel_id.get()) {
+                    let config = GuildConfig { channel_id: None, ..target.config.clone() };
+                    if let Err(e) = history.save_guild_config(&config) {
+                        error!("Error clearing channel for guild {}: {:?}", config.guild_id, e);
+                    }
+                }
+            }
+        }
+    }
+    delivered
+}
+
+/// The report of `cached` labelled with its age, posted in place of the outage notice
+fn cached_report(target: &ReportTarget, report_format: ReportFormat, cached: &snapshot::Snapshot, now: DateTime<FixedOffset>) -> CreateMessage {
+    let text = target.renderer();
+    let (report, embed, badge) = target.format(report_format).report_parts(&cached.data, &text);
+    let note = format!("🗄️ **快取資料 ({} 分鐘前)** 台電資料暫時無法取得，恢復後將自動繼續更新", (now - cached.fetched_at).num_minutes());
+    let mut message = CreateMessage::new()
+        .content(match report {
+            Some(report) => format!("{}\n{}", note, report),
+            None => note,
+        })
+        .allowed_mentions(CreateAllowedMentions::new());
+    if let Some(embed) = embed {
+        message = message.embed(embed);
+    }
+    if let Some(badge) = badge {
+        message = message.add_file(badge);
+    }
+    message
+}
+
+/// Rename each guild's `/config ticker` voice channel to the current reserve rate
+async fn update_tickers(http: &Http, history: &History, ticker: &mut ticker::Ticker, load_data: &LoadData) {
+    let configs = match history.ticker_guilds() {
+        Ok(configs) => configs,
+        Err(why) => {
+            error!("Error reading ticker channels: {:?}", why);
+            return;
+        }
+    };
+    for config in configs {
+        let Some(channel_id) = config.ticker_channel_id.map(ChannelId::new) else {
+            continue;
+        };
+        if let Err(why) = ticker.update(http, channel_id, &render::ticker_text(load_data, config.numbers)).await {
+            error!("Error renaming ticker channel {} (guild {}): {:?}", channel_id, config.guild_id, why);
+            // The channel was deleted or the bot lost access; stop renaming it
+            if is_gone(&why) {
+                let config = GuildConfig { ticker_channel_id: None, ..config };
+                if let Err(e) = history.save_guild_config(&config) {
+                    error!("Error clearing ticker channel for guild {}: {:?}", config.guild_id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Ask each `/config poll` guild whether today will see a supply warning, once a day, in its
+/// report channel
+async fn post_supply_polls(http: &Http, history: &History, targets: &[ReportTarget], now: NaiveDateTime, digest_time: Option<NaiveTime>) {
+    let mut seen = HashSet::new();
+    for target in targets.iter().filter(|t| t.config.supply_poll && t.config.guild_id != 0 && t.content.receives_reports()) {
+        let guild_id = target.config.guild_id;
+        if !seen.insert(guild_id) {
+            continue;
+        }
+        match history.supply_poll(guild_id, now.date()) {
+            Ok(None) => {}
+            Ok(Some(_)) => continue,
+            Err(why) => {
+                error!("Error reading supply poll for guild {}: {:?}", guild_id, why);
+                continue;
+            }
+        }
+        match send_to(http, target.channel_id, poll::message(now, digest_time)).await {
+            Ok(message) => {
+                if let Err(why) = history.record_supply_poll(guild_id, now.date(), target.channel_id.get(), message.id.get()) {
+                    error!("Error recording supply poll for guild {}: {:?}", guild_id, why);
+                }
+            }
+            Err(why) => error!("Error posting supply poll to {}: {:?}", target.channel_id, why),
+        }
+    }
+}
+
+/// Ping each guild's /ping-ladder rungs the reserve rate just fell below, and re-arm the ones it
+/// has climbed back above
+async fn check_ping_ladders(http: &Http, history: &History, targets: &[ReportTarget], rate: f64) {
+    let hysteresis = mentions::ladder_hysteresis_from_env();
+    let mut seen = HashSet::new();
+    for target in targets.iter().filter(|t| t.config.guild_id != 0 && t.content.receives_reports()) {
+        let guild_id = target.config.guild_id;
+        if !seen.insert(guild_id) {
+            continue;
+        }
+        let crossed = match mentions::advance_ladder(history, guild_id, rate, hysteresis) {
+            Ok(crossed) if !crossed.is_empty() => crossed,
+            Ok(_) => continue,
+            Err(why) => {
+                error!("Error updating ping ladder for guild {}: {:?}", guild_id, why);
+                continue;
+            }
+        };
+        match send_to(http, target.channel_id, mentions::ladder_message(rate, &crossed)).await {
+            Ok(_) => {
+                for rung in crossed {
+                    if let Err(why) = history.set_ladder_active(guild_id, rung.below, true) {
+                        error!("Error updating ping ladder for guild {}: {:?}", guild_id, why);
+                    }
+                }
+            }
+            Err(why) => error!("Error sending ping ladder alert to {}: {:?}", target.channel_id, why),
+        }
+    }
+}
+
+/// Follow the guild's next scheduled report with the /note annotations made since the last one
+async fn post_pending_notes(http: &Http, history: &History, target: &ReportTarget) {
+    if target.config.guild_id == 0 {
+        return;
+    }
+    let notes = match history.pending_notes(target.config.guild_id) {
+        Ok(notes) if !notes.is_empty() => notes,
+        Ok(_) => return,
+        Err(why) => {
+            error!("Error reading notes for guild {}: {:?}", target.config.guild_id, why);
+            return;
+        }
+    };
+    let mut content = String::from("📝 **管理員備註**");
+    for note in &notes {
+        let when = clock::taipei_datetime(note.created_at)
+            .map(|time| clock::discord_timestamp(time, 'f'))
+            .unwrap_or_else(|| note.created_at.format("%Y-%m-%d %H:%M").to_string());
+        content.push_str(&format!("\n• {} — <@{}> {}", note.text, note.author_id, when));
+    }
+    let message = CreateMessage::new().content(content).allowed_mentions(CreateAllowedMentions::new());
+    match send_to(http, target.channel_id, message).await {
+        Ok(_) => {
+            let ids: Vec<i64> = notes.iter().map(|note| note.id).collect();
+            if let Err(why) = history.mark_notes_posted(&ids) {
+                error!("Error marking notes posted: {:?}", why);
+            }
+        }
+        Err(why) => error!("Error sending notes to {}: {:?}", target.channel_id, why),
+    }
+}
+
+/// Edit the channel's pinned live-status message, or post and pin a new one if there is none
+/// (or it was deleted)
+async fn update_live_status(
+    http: &Http,
+    history: &History,
+    report_format: ReportFormat,
+    target: &ReportTarget,
+    profile: ReportProfile,
+    data: &CombinedPowerData,
+    now: DateTime<FixedOffset>,
+) -> serenity::Result<()> {
+    let channel_id = target.channel_id;
+    let (content, embed) = target.format(report_format).live_status(data, &target.renderer(), profile, now.timestamp());
+
+    let existing = history.live_message(channel_id.get()).unwrap_or_else(|why| {
+        error!("Error reading live status message for {}: {:?}", channel_id, why);
+        None
+    });
+    if let Some(message_id) = existing {
+        let mut edit = EditMessage::new().content(content.clone());
+        if let Some(embed) = &embed {
+            edit = edit.embed(embed.clone());
+        }
+        let mut result = channel_id.edit_message(http, MessageId::new(message_id), edit.clone()).await;
+        if let Err(why) = &result
+            && is_thread_blocked(why)
+            && reopen_thread(http, channel_id).await
+        {
+            result = channel_id.edit_message(http, MessageId::new(message_id), edit).await;
+        }
+        match result {
+            Ok(_) => return Ok(()),
+            Err(why) if is_gone(&why) => info!("Live status message in {} is gone, posting a new one", channel_id),
+            Err(why) => return Err(why),
+        }
+    }
+
+    let mut message = CreateMessage::new().content(content).allowed_mentions(CreateAllowedMentions::new());
+    if let Some(embed) = embed {
+        message = message.embed(embed);
+    }
+    let message = send_to(http, channel_id, message).await?;
+    // Without Manage Messages the status still works, it just isn't pinned
+    if let Err(why) = message.pin(http).await {
+        error!("Error pinning live status in {}: {:?}", channel_id, why);
+    }
+    if let Err(why) = history.set_live_message(channel_id.get(), message.id.get()) {
+        error!("Error saving live status message for {}: {:?}", channel_id, why);
+    }
+    Ok(())
+}
+
+/// HTTP 200 but stale data is a different failure from an outage, so it gets its own alert
+async fn send_freeze_alert(http: &Http, history: &History, alert_channel_id: Option<ChannelId>, event: &alerts::FreezeEvent) {
+    let message = match event {
+        alerts::FreezeEvent::Frozen { publish_time, cycles } => format!(
+            "🧊 **上游資料凍結**: 台電負載資料的更新時間已連續 {} 次停在 {}，數值可能已過時",
+            cycles,
+            clock::discord_timestamp(*publish_time, 'f')
+        ),
+        alerts::FreezeEvent::Recovered { publish_time, .. } => {
+            format!("✅ **上游資料恢復更新**: 最新資料時間 {}", clock::discord_timestamp(*publish_time, 'f'))
+        }
+    };
+
+    // Phones can't render Discord timestamps
+    if let alerts::FreezeEvent::Frozen { publish_time, cycles } = event {
+        let push_message = format!("台電負載資料的更新時間已連續 {} 次停在 {}，數值可能已過時", cycles, publish_time.format("%Y-%m-%d %H:%M"));
+        push::notify(history, push::AlertType::UpstreamFrozen, "上游資料凍結", &push_message).await;
+    }
+
+    if let Some(alert_channel_id) = alert_channel_id
+        && let Err(why) = alert_channel_id.say(http, &message).await
+    {
+        error!("Error sending freeze alert: {:?}", why);
+    }
+}
+
+/// Phone pushes for /push subscribers: every change, and separately when it turns orange or worse
+async fn push_indicator_change(history: &History, change: &alerts::IndicatorChange) {
+    let message = render::PlainRenderer.indicator_change(change);
+    push::notify(history, push::AlertType::IndicatorChange, "供電燈號變更", &message).await;
+    if change.to.is_critical() && !change.from.is_critical() {
+        let title = format!("⚠️ 供電吃緊: {}", render::indicator_label(change.to, &locale::ZH_TW));
+        push::notify(history, push::AlertType::ReserveCritical, &title, &message).await;
+    }
+}
+
+/// `/alerts test`: a made-up alert through the same channels, role ping and thread reopening as
+/// a real one, each headed by a TEST banner. Phone pushes only go to the admin's own
+/// subscriptions. Returns a line per delivery for the reply
+async fn send_test_alert(http: &Http, handler: &Handler, config: &Config, guild_id: u64, user_id: u64, kind: alerts::TestAlert) -> Vec<String> {
+    let mut home = Vec::new();
+    for &(channel_id, content) in &config.channels {
+        let guild = channel_guild(http, channel_id).await;
+        if guild == Some(guild_id) {
+            home.push((channel_id, content, guild));
+        }
+    }
+    let targets: Vec<ReportTarget> = report_targets(&handler.history, &home)
+        .into_iter()
+        .filter(|t| t.config.guild_id == guild_id)
+        // Stress alerts skip channels that don't get reports, like the real ones
+        .filter(|t| kind != alerts::TestAlert::StressHigh || t.content.receives_reports())
+        .collect();
+
+    let change = kind.indicator_change();
+    let stress = stress::StressIndex {
+        value: 85.0,
+        components: vec![(stress::StressComponent::Reserve, 90.0), (stress::StressComponent::Ramp, 70.0), (stress::StressComponent::Fault, 60.0)],
+    };
+    let mut lines = Vec::new();
+    for target in &targets {
+        let format = target.format(config.report_format);
+        let message = match kind {
+            alerts::TestAlert::ReserveCritical => {
+                format.reserve_alert_message(&change, &target.renderer(), target.alert_role(config.alert_role_id, &config.channels))
+            }
+            alerts::TestAlert::IndicatorChange => format.indicator_change_message(&change, &target.renderer()),
+            alerts::TestAlert::Fault => format.fault_change_message(&alerts::TestAlert::fault_change(), &target.renderer()),
+            alerts::TestAlert::StressHigh => CreateMessage::new().content(stress::alert_message(&stress)),
+        };
+        let banner = CreateMessage::new().content(alerts::TEST_BANNER).allowed_mentions(CreateAllowedMentions::new());
+        let result = match send_to(http, target.channel_id, banner).await {
+            Ok(_) => send_to(http, target.channel_id, message).await,
+            Err(why) => Err(why),
+        };
+        match result {
+            Ok(_) => lines.push(format!("✅ <#{}>", target.channel_id)),
+            Err(why) => {
+                error!("Error sending test alert to {}: {:?}", target.channel_id, why);
+                lines.push(format!("❌ <#{}>: {}", target.channel_id, why));
+            }
+        }
+    }
+
+    let push = match kind {
+        alerts::TestAlert::ReserveCritical => Some((
+            push::AlertType::ReserveCritical,
+            format!("⚠️ 供電吃緊: {}", render::indicator_label(change.to, &locale::ZH_TW)),
+            render::PlainRenderer.indicator_change(&change),
+        )),
+        alerts::TestAlert::IndicatorChange => {
+            Some((push::AlertType::IndicatorChange, "供電燈號變更".to_string(), render::PlainRenderer.indicator_change(&change)))
+        }
+        alerts::TestAlert::StressHigh => Some((
+            push::AlertType::StressHigh,
+            "電網壓力指數升高".to_string(),
+            stress::alert_message(&stress).replace("**", "").replace("-# ", ""),
+        )),
+        // Fault alerts have no push type
+        alerts::TestAlert::Fault => None,
+    };
+    if let Some((alert_type, title, message)) = push {
+        let sent = push::notify_user(&handler.history, user_id, alert_type, &format!("[測試] {}", title), &message).await;
+        if sent > 0 {
+            lines.push(format!("📱 已推播到你的 {} 個 {} 訂閱", sent, alert_type.code()));
+        }
+    }
+    lines
+}
+
+/// Post the update to every channel registered with /follow
+async fn relay_to_followers(http: &Http, history: &History, data: &CombinedPowerData, indicator_change: Option<&alerts::IndicatorChange>) {
+    let follows = match history.follows() {
+        Ok(follows) => follows,
+        Err(why) => {
+            error!("Error reading follows: {:?}", why);
+            return;
+        }
+    };
+
+    let snapshot_id = data.snapshot_id();
+    for follow in follows {
+        let channel = ChannelId::new(follow.channel_id);
+        if !claim_delivery(history, snapshot_id.as_deref(), channel) {
+            continue;
+        }
+        let renderer = DiscordTextRenderer {
+            locale: follow.locale,
+            numbers: history.number_format(Some(follow.guild_id)),
+            ..Default::default()
+        };
+
+        let mut result = Ok(());
+        if let Some(change) = indicator_change {
+            result = send_to(http, channel, CreateMessage::new().content(renderer.indicator_change(change))).await.map(|_| ());
+        }
+        if result.is_ok() {
+            let mention = history.mention_target(follow.channel_id).ok().flatten();
+            let message = mentions::apply(CreateMessage::new(), Some(follow.profile.render(&renderer, data)), mention.as_ref(), data);
+            result = send_to(http, channel, message).await.map(|_| ());
+        }
+
+        if let Err(why) = result {
+            error!("Error relaying to channel {} (guild {}): {:?}", follow.channel_id, follow.guild_id, why);
+            release_delivery(history, snapshot_id.as_deref(), channel);
+            // The channel was deleted or the bot lost access; stop relaying there
+            if is_gone(&why) && let Err(e) = history.remove_follow(follow.channel_id) {
+                error!("Error removing follow for channel {}: {:?}", follow.channel_id, e);
+            }
+        }
+    }
+}
+
+/// Guild the channel belongs to, for per-guild settings; None for DMs or if it can't be fetched
+async fn channel_guild(http: &Http, channel_id: ChannelId) -> Option<u64> {
+    match channel_id.to_channel(http).await {
+        Ok(channel) => channel.guild().map(|c| c.guild_id.get()),
+        Err(why) => {
+            error!("Error looking up channel {}: {:?}", channel_id, why);
+            None
+        }
+    }
+}
+
+/// Send to a channel or thread. Threads auto-archive after inactivity and the bot can be removed
+/// from them, so a thread that refuses the message is unarchived and re-joined, then retried once
+pub async fn send_to(http: &Http, channel_id: ChannelId, message: CreateMessage) -> serenity::Result<Message> {
+    let started = std::time::Instant::now();
+    let result = match channel_id.send_message(http, message.clone()).await {
+        Err(why) if is_thread_blocked(&why) && reopen_thread(http, channel_id).await => channel_id.send_message(http, message).await,
+        result => result,
+    };
+    metrics::discord_send(started.elapsed());
+    result
+}
+
+/// Archived thread (50083) or missing access (50001, e.g. removed from a private thread)
+fn is_thread_blocked(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(response))
+            if matches!(response.error.code, 50001 | 50083)
+    )
+}
+
+/// Unarchive and join `channel_id` if it is a thread; false if it isn't one or that failed
+async fn reopen_thread(http: &Http, channel_id: ChannelId) -> bool {
+    let metadata = match channel_id.to_channel(http).await {
+        Ok(Channel::Guild(channel)) => channel.thread_metadata,
+        Ok(_) => None,
+        Err(why) => {
+            error!("Error looking up channel {}: {:?}", channel_id, why);
+            None
+        }
+    };
+    let Some(metadata) = metadata else {
+        return false;
+    };
+    if metadata.archived
+        && let Err(why) = channel_id.edit_thread(http, EditThread::new().archived(false)).await
+    {
+        error!("Error unarchiving thread {}: {:?}", channel_id, why);
+        return false;
+    }
+    if let Err(why) = channel_id.join_thread(http).await {
+        error!("Error joining thread {}: {:?}", channel_id, why);
+        return false;
+    }
+    info!("Reopened thread {}", channel_id);
+    true
+}
+
+fn is_gone(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(response))
+            if response.status_code.as_u16() == 403 || response.status_code.as_u16() == 404
+    )
+}
+
+/// Process exit codes for `once`
+pub mod exit_code {
+    pub const CONFIG: i32 = 1;
+    pub const FETCH: i32 = 2;
+    pub const PARSE: i32 = 3;
+    pub const DELIVERY: i32 = 4;
+    pub const REJECTED: i32 = 5;
+    pub const STALE: i32 = 6;
+}
+
+/// Outcome of a `once` run, printed with --json
+#[derive(Default)]
+pub struct OnceOutcome {
+    pub exit_code: i32,
+    pub stage: Option<&'static str>,
+    pub error: Option<String>,
+    pub data: Option<CombinedPowerData>,
+    pub violations: Vec<Violation>,
+    pub posted: bool,
+}
+
+impl OnceOutcome {
+    fn failed(exit_code: i32, stage: &'static str, error: String) -> Self {
+        OnceOutcome { exit_code, stage: Some(stage), error: Some(error), ..Default::default() }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let analysis = self.data.as_ref().map(|d| &d.power_analysis);
+        let load = self.data.as_ref().and_then(|d| d.load_data.as_ref());
+        serde_json::json!({
+            "ok": self.exit_code == 0,
+            "exit_code": self.exit_code,
+            "failed_stage": self.stage,
+            "error": self.error,
+            "posted": self.posted,
+            "update_time": analysis.map(|a| a.update_time.to_rfc3339()),
+            "total_generation_mw": analysis.map(|a| a.total_generation),
+            "renewable_ratio": analysis.map(|a| a.renewable_ratio),
+            "current_load_wan_kw": load.map(|l| l.current_load),
+            "forecast_peak_reserve_rate": load.map(|l| l.forecast_peak_reserve_rate),
+            "forecast_peak_reserve_indicator": load.map(|l| l.forecast_peak_reserve_indicator.code()),
+            "violations": self.violations.iter().map(|v| v.describe()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Exit code and failed stage for `once`
+fn classify_error(error: &TaipowerError) -> (i32, &'static str) {
+    match error {
+        TaipowerError::Http(_) => (exit_code::FETCH, "fetch"),
+        TaipowerError::Decode(_) | TaipowerError::SchemaChanged(_) => (exit_code::PARSE, "parse"),
+        TaipowerError::StaleData { .. } => (exit_code::STALE, "stale"),
+        TaipowerError::Discord(_) => (exit_code::DELIVERY, "deliver"),
+    }
+}
+
+/// Fetch, record and post a single report (the `once` command)
+pub async fn run_once(dry_run: bool) -> OnceOutcome {
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(errors) => return OnceOutcome::failed(exit_code::CONFIG, "config", errors.join("; ")),
+    };
+    let (power, load) = tokio::join!(fetch_and_analyze_power_data(), fetch_load_data());
+    let power_analysis = match power {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            let (code, stage) = classify_error(&e);
+            return OnceOutcome::failed(code, stage, format!("generation: {}", e));
+        }
+    };
+
+    // Load data is optional for the report, but a broken payload still counts as a failure
+    let mut load_data = match load {
+        Ok(data) => Some(data),
+        Err(e) if e.is_parse_failure() => {
+            let (code, stage) = classify_error(&e);
+            return OnceOutcome::failed(code, stage, format!("load: {}", e));
+        }
+        Err(e) => {
+            error!("Error fetching load data: {:?}", e);
+            None
+        }
+    };
+
+    let power_violations = config.sanity_bounds.check_power(&power_analysis);
+    let load_violations = load_data.as_ref().map(|data| config.sanity_bounds.check_load(data)).unwrap_or_default();
+    let violations: Vec<Violation> = power_violations.iter().chain(&load_violations).copied().collect();
+    for violation in &violations {
+        warn!("Data quality violation: {}", violation.describe());
+    }
+    if power_violations.iter().any(|v| v.metric.blocks_publishing()) {
+        return OnceOutcome {
+            violations,
+            ..OnceOutcome::failed(exit_code::REJECTED, "sanity", "generation data failed sanity checks".to_string())
+        };
+    }
+    if !load_violations.is_empty() {
+        load_data = None;
+    }
+
+    let maintenance = maintenance::MaintenanceCalendar::from_env();
+    let today = taipei_now().date_naive();
+    let (shares, (), demand_response_mw) = tokio::join!(
+        async {
+            if load_data.is_some() { Some(regional::fetch_regional_shares().await) } else { None }
+        },
+        maintenance.refresh(),
+        demand_response::fetch_activated_mw(today),
+    );
+    let regions = match (&load_data, shares) {
+        (Some(load), Some(Ok(shares))) => regional::estimate(&shares, load.current_load, config.region_import_warn),
+        (_, Some(Err(e))) => {
+            error!("Error fetching regional data: {:?}", e);
+            Vec::new()
+        }
+        _ => Vec::new(),
+    };
+    let outages = maintenance.outages(&power_analysis.units, today);
+
+    let stress = stress::StressIndex::compute(&power_analysis, load_data.as_ref(), None, &config.stress_weights);
+    let data = CombinedPowerData {
+        power_analysis,
+        load_data,
+        regions,
+        temperature: None,
+        own_forecast: None,
+        outages,
+        demand_response_mw,
+        stress,
+        trend: None,
+    };
+    let mut outcome = OnceOutcome { data: Some(data), violations, ..Default::default() };
+    // Still posted, as the bot would, but a timer watching the exit code should hear about it
+    if let Some(data) = &outcome.data {
+        let data_time = data.data_time();
+        if let Some(snapshot_id) = data.snapshot_id()
+            && let Some(age) = alerts::StaleDataGate::from_env().check(&snapshot_id, data_time, taipei_now())
+        {
+            let error = TaipowerError::StaleData { data_time, age };
+            let (code, stage) = classify_error(&error);
+            outcome.exit_code = code;
+            outcome.stage = Some(stage);
+            outcome.error = Some(error.to_string());
+        }
+    }
+    if dry_run {
+        return outcome;
+    }
+
+    let Some(token) = config::discord_token() else {
+        return OnceOutcome {
+            data: outcome.data,
+            violations: outcome.violations,
+            ..OnceOutcome::failed(exit_code::CONFIG, "config", "DISCORD_TOKEN must be set".to_string())
+        };
+    };
+    let history_path = config::history_path();
+
+    let history = match History::open(&history_path) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            error!("Error opening history database: {:?}", e);
+            None
+        }
+    };
+    let temperatures = if config.own_forecast {
+        weather::fetch_hourly_temperatures().await.inspect_err(|e| error!("Error fetching weather: {:?}", e)).ok()
+    } else {
+        None
+    };
+    let data = outcome.data.as_mut().unwrap();
+    data.temperature = temperatures.as_ref().and_then(|t| t.at(taipei_now().naive_local()));
+
+    if let Some(history) = &history {
+        if let Err(why) = history.record(data) {
+            error!("Error recording history: {:?}", why);
+        }
+        data.trend = trend::current(history, taipei_now().naive_local());
+        if let Err(why) = history.record_units(&data.power_analysis.units, &config.unit_history) {
+            error!("Error recording unit history: {:?}", why);
+        }
+        if let Some(temperatures) = &temperatures {
+            match forecast::run(history, temperatures, taipei_now().naive_local()) {
+                Ok(own_forecast) => data.own_forecast = own_forecast,
+                Err(why) => error!("Error running demand forecast: {:?}", why),
+            }
+        }
+    }
+    let data = outcome.data.as_ref().unwrap();
+
+    let http = Http::new(&token);
+    let Some(history) = &history else {
+        // Without the database there are no guild settings; only CHANNEL_ID can be served
+        let reported: Vec<&(ChannelId, ContentProfile)> = config.channels.iter().filter(|(_, content)| content.receives_reports()).collect();
+        if reported.is_empty() {
+            return OnceOutcome::failed(exit_code::CONFIG, "config", "CHANNEL_ID must list a report channel when the history database is unavailable".to_string());
+        }
+        for (channel_id, content) in reported {
+            let text = DiscordTextRenderer { sections: content.sections(Default::default()), ..Default::default() };
+            match send_to(&http, *channel_id, config.report_format.report_message(data, None, &text)).await {
+                Ok(_) => outcome.posted = true,
+                Err(why) => outcome.error = Some(TaipowerError::from(why).to_string()),
+            }
+        }
+        if !outcome.posted {
+            outcome.exit_code = exit_code::DELIVERY;
+            outcome.stage = Some("deliver");
+        }
+        return outcome;
+    };
+
+    let mut home = Vec::new();
+    for &(channel_id, content) in &config.channels {
+        home.push((channel_id, content, channel_guild(&http, channel_id).await));
+    }
+    let targets = report_targets(history, &home);
+    if targets.is_empty() {
+        outcome.exit_code = exit_code::CONFIG;
+        outcome.stage = Some("config");
+        outcome.error = Some("no channel to post to; set CHANNEL_ID or use /config channel".to_string());
+        return outcome;
+    }
+    let due: Vec<(&ReportTarget, Cadence)> = targets.iter().flat_map(ReportTarget::cadences).collect();
+    let report_charts = chart::report_charts_enabled().then(chart::ChartCache::from_env);
+    if post_reports(&http, history, config.report_format, report_charts.as_ref(), &due, data, taipei_now()).await.is_empty() {
+        outcome.exit_code = exit_code::DELIVERY;
+        outcome.stage = Some("deliver");
+        outcome.error = Some("the report could not be delivered to any channel".to_string());
+        return outcome;
+    }
+    outcome.posted = true;
+
+    relay_to_followers(&http, history, data, None).await;
+    outcome
+}
+
+/// Connect to Discord and run the update loop until SIGTERM/Ctrl-C
+pub async fn run() {
+    let token = config::discord_token().expect("Expected a token in the environment");
+    let config = Config::from_env().unwrap_or_else(|errors| panic!("Invalid settings: {}", errors.join("; ")));
+    let history_path = config::history_path();
+    let admin = AdminRoute {
+        channel_id: config::discord_id("ADMIN_CHANNEL_ID").expect("Invalid admin channel ID").map(ChannelId::new),
+        owner_id: config::discord_id("OWNER_ID").expect("Invalid owner ID").map(UserId::new),
+    };
+    
+    let clock = clock::system();
+    let (startup, quarantine_notice) = crashloop::StartupGuard::begin(&history_path);
+    let startup = Arc::new(startup);
+    let history = Arc::new(History::open_with_clock(&history_path, clock.clone())
+        .expect("Error opening history database"));
+    
+    systemd::check_watchdog();
+    let dashboard_addr = dashboard::addr_from_env();
+    let dashboard = dashboard_addr.clone().map(|addr| {
+        let dashboard = Arc::new(dashboard::Dashboard::new());
+        tokio::spawn(dashboard::serve(addr, Some(dashboard.clone())));
+        dashboard
+    });
+    // The dashboard already serves /metrics on its own address
+    if let Some(addr) = metrics::addr_from_env().filter(|addr| Some(addr) != dashboard_addr.as_ref()) {
+        tokio::spawn(dashboard::serve(addr, None));
+    }
+    
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let cycle_lock = Arc::new(tokio::sync::Mutex::new(()));
+    let shutdown_history = history.clone();
+    let leadership = leader::Leadership::from_env().map(Arc::new);
+    let shutdown_leadership = leadership.clone();
+    
+    // Set gateway intents
+    // GUILDS delivers GuildDelete when the bot is removed from a server
+    let intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+    
+    // Create a new instance of the Client
+    let mut client = Client::builder(&token, intents)
+        .type_map_insert::<ConfigKey>(Arc::new(std::sync::RwLock::new(config)))
+        .event_handler(Handler {
+            history,
+            admin,
+            chart_cache: Arc::new(chart::ChartCache::from_env()),
+            maintenance: Arc::new(maintenance::MaintenanceCalendar::from_env()),
+            dashboard,
+            snapshots: Arc::new(snapshot::SnapshotCache::default()),
+            pager: pages::Pager::default(),
+            refresher: refresh::Refresher::default(),
+            clock,
+            shutdown: shutdown_rx,
+            cycle_lock: cycle_lock.clone(),
+            leadership,
+            startup: startup.clone(),
+        })
+        .await
+        .expect("Err creating client");
+    
+    if let Some(notice) = quarantine_notice {
+        admin.send(&client.http, &notice).await;
+    }
+    
+    let shard_manager = client.shard_manager.clone();
+    let http = client.http.clone();
+    let data = client.data.clone();
+    tokio::spawn(async move {
+        wait_for_shutdown_signal().await;
+        info!("Shutting down");
+        systemd::stopping();
+        let _ = shutdown_tx.send(true);
+        // Let a cycle in progress finish its history writes and posts
+        if tokio::time::timeout(SHUTDOWN_GRACE, cycle_lock.lock()).await.is_err() {
+            warn!("Update cycle still running after {}s, shutting down anyway", SHUTDOWN_GRACE.as_secs());
+        }
+        // Stopped on purpose, so this start didn't crash either
+        startup.healthy();
+        if let Err(why) = shutdown_history.flush() {
+            error!("Error flushing history database: {:?}", why);
+        }
+        // Decided before resigning: only the instance that was posting says it's restarting
+        let leading = shutdown_leadership.as_ref().is_none_or(|l| l.is_leading());
+        if let Some(leadership) = &shutdown_leadership {
+            leadership.resign(&shutdown_history);
+        }
+        if shutdown_notice_enabled() && leading {
+            let shared = data.read().await.get::<ConfigKey>().cloned().expect("the config is inserted when the client is built");
+            let channels = shared.read().unwrap().channels.clone();
+            for (channel_id, _) in channels.iter().filter(|(_, content)| content.receives_reports()) {
+                if let Err(why) = channel_id.say(&http, "🔄 機器人重新啟動中，稍後將恢復更新").await {
+                    error!("Error sending shutdown notice to {}: {:?}", channel_id, why);
+                }
+            }
+        }
+        shard_manager.shutdown_all().await;
+    });
+    
+    // Start bot; returns once the shards are shut down
+    if let Err(why) = client.start().await {
+        error!("Client error: {:?}", why);
+    }
+}
+
+/// How long shutdown waits for an update cycle in progress
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// Post a "restarting" notice to CHANNEL_ID on shutdown (SHUTDOWN_NOTICE, off by default)
+fn shutdown_notice_enabled() -> bool {
+    matches!(config::var("SHUTDOWN_NOTICE").as_deref().map(str::trim), Ok("on") | Ok("true") | Ok("1"))
+}
+
+/// Ctrl-C, or SIGTERM from a container runtime or systemd
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Error installing SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    if let Err(why) = tokio::signal::ctrl_c().await {
+        error!("Error waiting for Ctrl-C: {:?}", why);
+    }
+}
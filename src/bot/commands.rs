@@ -0,0 +1,2327 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serenity::{
+    builder::{
+        CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateAutocompleteResponse, CreateCommand,
+        CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditInteractionResponse,
+    },
+    model::application::{
+        CommandInteraction, CommandOptionType, ComponentInteraction, ComponentInteractionDataKind, ResolvedValue,
+    },
+    model::channel::ChannelType,
+    model::id::ChannelId,
+    model::permissions::Permissions,
+    prelude::*,
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::accessible::AccessibleRenderer;
+use crate::alerts;
+use crate::archive::fetch_archived_summary;
+use crate::chart::{self, CachedChart, ChartCache, Series};
+use crate::clock::{discord_time, discord_timestamp, taipei_datetime, taipei_now};
+use crate::config::{self, Config};
+use crate::history::{Follow, GuildConfig, History, SnapshotRow, DEFAULT_REPORT_INTERVAL_MINUTES};
+use crate::demand_response;
+use crate::export;
+use crate::incident;
+use crate::regional;
+use crate::reporting;
+use crate::locale::{Locale, NumberFormat, ZH_TW};
+use crate::maintenance::MaintenanceCalendar;
+use crate::mentions::{self, LadderMention, MentionPolicy, MentionTarget};
+use crate::push::{self, AlertType, PushService};
+use crate::embed::EmbedRenderer;
+use crate::render::{format_hour_range, format_pp_change, indicator_emoji, indicator_label, DiscordTextRenderer, FuelDisplay, PlainRenderer, Renderer, ReportFormat, ReportMode, ReportProfile, ReportSections};
+use crate::analysis::{capacity_factor, classify_remark, clean_energy_type, extract_plant_name, is_renewable, CombinedPowerData, RemarkClass};
+use crate::taipower_api::{fetch_and_analyze_power_data, fetch_load_data, PowerUnit, ReserveIndicator};
+use crate::stress::StressIndex;
+
+use super::pages;
+use super::snapshot::Snapshot;
+use super::Handler;
+
+pub fn definitions() -> Vec<CreateCommand> {
+    vec![
+        CreateCommand::new("power")
+            .description("立即查詢目前的電力資訊")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "format", "輸出格式 (預設 報告)")
+                    .add_string_choice("報告", "report")
+                    .add_string_choice("無障礙 (適合螢幕閱讀器)", "accessible")
+                    .add_string_choice("JSON (供其他機器人讀取)", "json"),
+            ),
+        CreateCommand::new("accessibility")
+            .description("設定 /power 是否以適合螢幕閱讀器的格式回覆你")
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "是否啟用").required(true)),
+        CreateCommand::new("on")
+            .description("查詢指定日期 (或時間) 的電力資訊")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "date", "日期，例如 2024-08-02")
+                    .required(true),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::String, "time", "時間，例如 14:00 (僅限本機紀錄)")),
+        CreateCommand::new("at")
+            .description("查詢最接近指定時間的本機紀錄，包含發電結構")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "time", "時間，例如 2024-07-02 14:30")
+                    .required(true),
+            ),
+        CreateCommand::new("plant")
+            .description("查詢單一電廠各機組的即時發電狀況")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "name", "電廠名稱，例如 台中")
+                    .required(true)
+                    .set_autocomplete(true),
+            ),
+        CreateCommand::new("type")
+            .description("列出單一能源類型所有機組的即時發電狀況")
+            .add_option(
+                TYPE_CHOICES
+                    .iter()
+                    .fold(CreateCommandOption::new(CommandOptionType::String, "energy", "能源類型").required(true), |option, energy| {
+                        option.add_string_choice(*energy, *energy)
+                    }),
+            ),
+        CreateCommand::new("faults").description("列出目前故障、歲修/檢修或環保/運轉限制中的機組"),
+        CreateCommand::new("region").description("查詢北、中、南、東各區域的負載、發電與供電餘裕"),
+        CreateCommand::new("reserve").description("查詢今日尖峰備轉容量率、供電燈號與其意義"),
+        CreateCommand::new("renewables").description("查詢太陽能與風力的裝置容量、即時發電、容量因數與占用電比例"),
+        CreateCommand::new("status").description("查詢機器人更新週期的耗時與資料新鮮度"),
+        CreateCommand::new("unit-history")
+            .description("查詢單一機組的歷史發電量")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "unit", "機組名稱，例如 台中#9")
+                    .required(true),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::String, "window", "時間範圍，例如 24h、7d (預設 7d)")),
+        CreateCommand::new("chart")
+            .description("繪製電力數據圖表")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "metric", "項目")
+                    .required(true)
+                    .add_string_choice("用電量", "load")
+                    .add_string_choice("備轉容量率", "reserve")
+                    .add_string_choice("再生能源占比", "renewable")
+                    .add_string_choice("總發電量", "generation"),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::String, "window", "時間範圍，例如 24h、7d (預設 24h)")),
+        CreateCommand::new("loadcurve")
+            .description("最近 24 小時的用電曲線")
+            .add_option(CreateCommandOption::new(CommandOptionType::Boolean, "yesterday", "疊加前一天的曲線 (預設開啟)")),
+        CreateCommand::new("transition")
+            .description("每月燃煤、燃氣、核能與再生能源發電占比的長期變化")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "months", "月數 (預設 12)")
+                    .min_int_value(2)
+                    .max_int_value(120),
+            ),
+        CreateCommand::new("availability")
+            .description("各能源類型每月的容量加權可用率 (不含計畫歲修)")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "months", "月數 (預設 6)")
+                    .min_int_value(1)
+                    .max_int_value(12),
+            ),
+        CreateCommand::new("peakhours")
+            .description("最近幾天的每日尖峰用電時段分布")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "days", "天數 (預設 7)")
+                    .min_int_value(1)
+                    .max_int_value(90),
+            ),
+        CreateCommand::new("incident")
+            .description("電網事件紀錄")
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "列出最近的事件"))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "export", "匯出事件的時間軸、紀錄與圖表")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "id", "事件編號")
+                            .required(true),
+                    ),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "response",
+                "最近一次備轉容量率下降後，抽蓄水力與儲能的出力變化",
+            )),
+        CreateCommand::new("note")
+            .description("在目前時間加上備註，附在下一次定時報告並標示於圖表")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "text", "備註內容 (例如: 台電記者會宣布限電準備)")
+                    .required(true)
+                    .max_length(200),
+            ),
+        CreateCommand::new("maintenance")
+            .description("機組歲修計畫")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "upcoming", "列出未來幾週預定歲修的大型機組")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "weeks", "週數 (預設 4)")
+                            .min_int_value(1)
+                            .max_int_value(52),
+                    ),
+            ),
+        CreateCommand::new("push")
+            .description("手機推播通知 (ntfy / Pushover / Bark)")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "add", "新增推播")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "service", "推播服務")
+                            .required(true)
+                            .add_string_choice("ntfy", "ntfy")
+                            .add_string_choice("Pushover", "pushover")
+                            .add_string_choice("Bark", "bark"),
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "endpoint", "ntfy/Bark 網址，或 Pushover 使用者金鑰")
+                            .required(true),
+                    )
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "alert", "通知類型")
+                            .required(true)
+                            .add_string_choice("供電吃緊 (橘燈以上)", "reserve_critical")
+                            .add_string_choice("供電燈號變更", "indicator_change")
+                            .add_string_choice("上游資料凍結", "upstream_frozen")
+                            .add_string_choice("電網壓力指數過高", "stress_high"),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "移除推播")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "id", "推播編號")
+                            .required(true),
+                    ),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "列出我的推播")),
+        CreateCommand::new("mentions")
+            .description("設定此頻道的定時電力資訊要提及誰")
+            .default_member_permissions(Permissions::MANAGE_CHANNELS)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "policy", "何時提及")
+                    .required(true)
+                    .add_string_choice("從不", "never")
+                    .add_string_choice("每次", "always")
+                    .add_string_choice("僅限供電吃緊日 (橘燈以上)", "critical"),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::Role, "role", "要提及的身分組"))
+            .add_option(CreateCommandOption::new(CommandOptionType::User, "user", "要提及的使用者")),
+        CreateCommand::new("ping-ladder")
+            .description("設定備轉容量率低於各門檻時要提及的對象")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "add", "新增或修改門檻")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Number, "below", "備轉容量率低於此值 (%) 時提及")
+                            .required(true)
+                            .min_number_value(0.0)
+                            .max_number_value(100.0),
+                    )
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Role, "role", "要提及的身分組"))
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "broadcast", "改為提及所有人")
+                            .add_string_choice("@here", "here")
+                            .add_string_choice("@everyone", "everyone"),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "移除門檻")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Number, "below", "門檻 (%)").required(true),
+                    ),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "list", "列出目前的門檻")),
+        CreateCommand::new("alerts")
+            .description("供電警報")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "test", "發送一則標示為測試的警報，確認頻道、身分組與推播設定")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "type", "警報類型")
+                            .required(true)
+                            .add_string_choice("供電吃緊 (橘燈，提及警報身分組)", "reserve_critical")
+                            .add_string_choice("供電燈號變更", "indicator_change")
+                            .add_string_choice("機組故障", "fault")
+                            .add_string_choice("電網壓力指數過高", "stress_high"),
+                    ),
+            ),
+        CreateCommand::new("follow")
+            .description("在此頻道轉發定時電力資訊")
+            .default_member_permissions(Permissions::MANAGE_CHANNELS)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "locale", "語言 (預設 中文)")
+                    .add_string_choice("中文", "zh-TW")
+                    .add_string_choice("English", "en"),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "profile", "內容 (預設 完整)")
+                    .add_string_choice("完整", "full")
+                    .add_string_choice("精簡", "compact"),
+            ),
+        CreateCommand::new("numbers")
+            .description("設定此伺服器的數字格式")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "style", "格式")
+                    .required(true)
+                    .add_string_choice("2981.4 萬瓩", "plain")
+                    .add_string_choice("2,981.4 萬瓩", "grouped")
+                    .add_string_choice("2.981,4 萬瓩", "decimal-comma")
+                    .add_string_choice("2.98 千萬瓩", "chinese"),
+            ),
+        CreateCommand::new("config")
+            .description("設定此伺服器的定時電力資訊")
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "channel", "設定發布頻道或討論串")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Channel, "channel", "頻道或討論串 (預設 此頻道)").channel_types(vec![
+                            ChannelType::Text,
+                            ChannelType::News,
+                            ChannelType::PublicThread,
+                            ChannelType::PrivateThread,
+                            ChannelType::NewsThread,
+                        ]),
+                    )
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::String, "thread_id", "討論串 ID (已封存的討論串無法從清單選取)")),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "stop", "停止在此伺服器發布定時電力資訊"))
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "mode", "設定發布方式")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "mode", "發布方式")
+                            .required(true)
+                            .add_string_choice("每次發送新訊息", "post")
+                            .add_string_choice("置頂一則訊息並持續更新", "live")
+                            .add_string_choice("置頂精簡狀態並持續更新，另定時發送完整報告", "dual"),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "format", "設定報告格式")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::String, "format", "報告格式")
+                            .required(true)
+                            .add_string_choice("嵌入訊息", "embed")
+                            .add_string_choice("文字", "text")
+                            .add_string_choice("純文字", "plain")
+                            .add_string_choice("無障礙 (適合螢幕閱讀器)", "accessible")
+                            .add_string_choice("依機器人預設", "default"),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "interval", "設定發布間隔")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Integer, "minutes", "分鐘 (10 的倍數)")
+                            .required(true)
+                            .min_int_value(10)
+                            .max_int_value(1440),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "sections", "設定報告內容")
+                    .add_sub_option(
+                        CreateCommandOption::new(
+                            CommandOptionType::String,
+                            "list",
+                            "以逗號分隔: supply, yesterday, realtime, regions, generation, units 或 all",
+                        )
+                        .required(true),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "alert-role", "設定供電吃緊 (橘燈、紅燈) 時要提及的身分組")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Role, "role", "身分組 (留空則不提及)")),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "digest-csv", "每日摘要是否附上當日紀錄的 CSV 檔")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "附上 CSV").required(true)),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "ticker", "每次更新時將語音頻道改名為目前的備轉容量率")
+                    .add_sub_option(
+                        CreateCommandOption::new(CommandOptionType::Channel, "channel", "語音頻道 (留空則停用)")
+                            .channel_types(vec![ChannelType::Voice, ChannelType::Stage]),
+                    ),
+            )
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::SubCommand, "poll", "預估橘燈以上的日子發起「會不會出現供電警戒」投票")
+                    .add_sub_option(CreateCommandOption::new(CommandOptionType::Boolean, "enabled", "發起投票").required(true)),
+            )
+            .add_option(CreateCommandOption::new(CommandOptionType::SubCommand, "show", "顯示本頻道目前生效的設定與其來源"))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "reload",
+                "重新讀取 config.toml，不需重新啟動 (限機器人擁有者或管理頻道)",
+            )),
+        CreateCommand::new("purge-data")
+            .description("刪除此伺服器在機器人中儲存的所有設定與資料")
+            .default_member_permissions(Permissions::ADMINISTRATOR)
+            .dm_permission(false)
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::Boolean, "confirm", "確認刪除 (無法復原)").required(true),
+            ),
+        CreateCommand::new("unfollow")
+            .description("停止在此頻道轉發電力資訊")
+            .default_member_permissions(Permissions::MANAGE_CHANNELS)
+            .dm_permission(false),
+    ]
+}
+
+pub async fn handle_command(ctx: &Context, command: &CommandInteraction, handler: &Handler) {
+    let history = handler.history.as_ref();
+    // Archive lookups and chart rendering can take longer than the 3 second interaction deadline.
+    // Push endpoints are secrets, so those replies are only shown to the user
+    let deferred = if matches!(command.data.name.as_str(), "push" | "accessibility") {
+        command.defer_ephemeral(&ctx.http).await
+    } else {
+        command.defer(&ctx.http).await
+    };
+    if let Err(why) = deferred {
+        error!("Error deferring /{}: {:?}", command.data.name, why);
+        return;
+    }
+    let settings = super::current_config(ctx).await;
+
+    // Chart replies carry their cache key so the uploaded attachment's URL can be remembered
+    let mut chart_key = None;
+    let response = match command.data.name.as_str() {
+        "power" => run_power(command, handler, &settings).await,
+        "on" => run_on(command, history).await,
+        "accessibility" => run_accessibility(command, history),
+        "at" => run_at(command, history),
+        "chart" => run_chart(command, history, &handler.chart_cache, &mut chart_key),
+        "plant" => run_plant(command, handler, &settings).await,
+        "type" => run_type(command, handler, &settings).await,
+        "faults" => run_faults(command, handler, &settings).await,
+        "region" => run_region(command, handler, &settings).await,
+        "reserve" => run_reserve(command, handler, &settings).await,
+        "renewables" => run_renewables(command, handler, &settings).await,
+        "status" => run_status(handler, &settings),
+        "unit-history" => run_unit_history(command, history, &handler.chart_cache, &mut chart_key),
+        "loadcurve" => run_loadcurve(command, history, &handler.chart_cache, &mut chart_key),
+        "transition" => run_transition(command, history, &handler.chart_cache, &mut chart_key),
+        "availability" => run_availability(command, history),
+        "peakhours" => run_peakhours(command, history),
+        "incident" => run_incident(command, history, handler.clock.now().naive_local()),
+        "note" => run_note(command, history),
+        "maintenance" => run_maintenance(command, &handler.maintenance, guild_numbers(command, history)).await,
+        "mentions" => run_mentions(command, history),
+        "ping-ladder" => run_ping_ladder(command, history),
+        "push" => run_push(command, history),
+        "alerts" => run_alerts(ctx, command, handler, &settings).await,
+        "follow" => run_follow(command, handler, &settings),
+        "unfollow" => run_unfollow(command, history),
+        "numbers" => run_numbers(command, history),
+        "config" => run_config(ctx, command, handler, &settings).await,
+        "purge-data" => run_purge_data(ctx, command, history).await,
+        other => {
+            warn!("Unknown command: {}", other);
+            EditInteractionResponse::new().content("❌ 未知的指令")
+        }
+    };
+
+    match command.edit_response(&ctx.http, response).await {
+        Ok(message) => {
+            if let (Some(key), Some(attachment)) = (chart_key, message.attachments.first()) {
+                handler.chart_cache.set_url(&key, attachment.url.clone());
+            }
+        }
+        Err(why) => error!("Error responding to /{}: {:?}", command.data.name, why),
+    }
+}
+
+/// Serve a chart from the cache, or render and cache it
+fn chart_response(
+    cache: &ChartCache,
+    key: &str,
+    content: String,
+    filename: &str,
+    render: impl FnOnce() -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>,
+) -> EditInteractionResponse {
+    let png = match cache.get(key) {
+        Some(CachedChart::Url(url)) => {
+            return EditInteractionResponse::new()
+                .content(content)
+                .embed(CreateEmbed::new().image(url))
+                .clear_attachments();
+        }
+        Some(CachedChart::Png(png)) => png,
+        None => match render() {
+            Ok(png) => {
+                cache.insert(key.to_string(), png.clone());
+                png
+            }
+            Err(e) => {
+                error!("Error rendering chart: {:?}", e);
+                return EditInteractionResponse::new().content(content);
+            }
+        },
+    };
+    // new_attachment replaces whatever was attached before, so the picker can swap charts
+    EditInteractionResponse::new()
+        .content(content)
+        .embeds(Vec::new())
+        .new_attachment(CreateAttachment::bytes(png, filename))
+}
+
+fn string_option(command: &CommandInteraction, name: &str) -> Option<String> {
+    command
+        .data
+        .options()
+        .into_iter()
+        .find_map(|opt| match opt.value {
+            ResolvedValue::String(value) if opt.name == name => Some(value.to_string()),
+            _ => None,
+        })
+}
+
+fn bool_option(command: &CommandInteraction, name: &str) -> Option<bool> {
+    command
+        .data
+        .options()
+        .into_iter()
+        .find_map(|opt| match opt.value {
+            ResolvedValue::Boolean(value) if opt.name == name => Some(value),
+            _ => None,
+        })
+}
+
+/// Number format chosen for the server the command was used in
+fn guild_numbers(command: &CommandInteraction, history: &History) -> NumberFormat {
+    history.number_format(command.guild_id.map(|id| id.get()))
+}
+
+fn integer_option(command: &CommandInteraction, name: &str) -> Option<i64> {
+    command
+        .data
+        .options()
+        .into_iter()
+        .find_map(|opt| match opt.value {
+            ResolvedValue::Integer(value) if opt.name == name => Some(value),
+            _ => None,
+        })
+}
+
+async fn run_power(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching power data for /power: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let data = &snapshot.data;
+    let note = staleness_note(&snapshot).map(|note| format!("\n{}", note)).unwrap_or_default();
+
+    // An explicit choice, else the user's /accessibility preference, else the guild's format
+    let format = match string_option(command, "format").as_deref() {
+        Some("json") => return json_response(data),
+        Some("accessible") => ReportFormat::Accessible,
+        _ => preferred_format(command, &handler.history).unwrap_or(settings.report_format),
+    };
+
+    let renderer = DiscordTextRenderer { numbers: guild_numbers(command, &handler.history), ..Default::default() };
+    match format {
+        ReportFormat::Text => EditInteractionResponse::new().content(renderer.report(data) + &note),
+        ReportFormat::Embed => {
+            let (embed, badge) = EmbedRenderer::from(&renderer).report_with_badge(data);
+            let response = with_staleness(EditInteractionResponse::new().embed(embed), &snapshot);
+            match badge {
+                Some(badge) => response.new_attachment(badge),
+                None => response,
+            }
+        }
+        ReportFormat::Plain => EditInteractionResponse::new().content(PlainRenderer.report(data) + &note),
+        ReportFormat::Accessible => EditInteractionResponse::new().content(AccessibleRenderer::from(&renderer).report(data) + &note),
+    }
+}
+
+/// The user's own format, then their guild's
+fn preferred_format(command: &CommandInteraction, history: &History) -> Option<ReportFormat> {
+    let user = history.user_report_format(command.user.id.get()).unwrap_or_else(|e| {
+        error!("Error reading settings for user {}: {:?}", command.user.id, e);
+        None
+    });
+    user.or_else(|| command.guild_id.and_then(|id| history.guild_config(id.get()).ok()).and_then(|config| config.format))
+}
+
+fn run_accessibility(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let enabled = bool_option(command, "enabled").unwrap_or(false);
+    let format = enabled.then_some(ReportFormat::Accessible);
+    match history.set_user_report_format(command.user.id.get(), format) {
+        Ok(()) if enabled => EditInteractionResponse::new().content("✅ /power 將以無表情符號、標籤在前的格式回覆你，適合螢幕閱讀器"),
+        Ok(()) => EditInteractionResponse::new().content("✅ /power 將恢復使用伺服器的報告格式"),
+        Err(e) => {
+            error!("Error saving settings for user {}: {:?}", command.user.id, e);
+            EditInteractionResponse::new().content("❌ 無法儲存設定")
+        }
+    }
+}
+
+/// The snapshot as a ```json block, or as an attached file when it won't fit in a message
+fn json_response(data: &CombinedPowerData) -> EditInteractionResponse {
+    let json = export::snapshot_json(data).to_string();
+    if json.chars().count() <= 1900 {
+        return EditInteractionResponse::new().content(format!("```json\n{}\n```", json));
+    }
+    EditInteractionResponse::new()
+        .content(format!("📎 schema `{}` v{}", export::SCHEMA, export::SCHEMA_VERSION))
+        .new_attachment(CreateAttachment::bytes(json.into_bytes(), "snapshot.json"))
+}
+
+async fn run_on(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let renderer = DiscordTextRenderer { numbers: guild_numbers(command, history), ..Default::default() };
+    let raw_date = string_option(command, "date").unwrap_or_default();
+
+    let Some(date) = parse_date(&raw_date) else {
+        return EditInteractionResponse::new().content(format!("❌ 無法解析日期: {} (格式: 2024-08-02)", raw_date));
+    };
+
+    if let Some(raw_time) = string_option(command, "time") {
+        let Ok(time) = NaiveTime::parse_from_str(raw_time.trim(), "%H:%M") else {
+            return EditInteractionResponse::new().content(format!("❌ 無法解析時間: {} (格式: 14:00)", raw_time));
+        };
+        return EditInteractionResponse::new().content(snapshot_at(history, date.and_time(time), renderer.numbers));
+    }
+
+    match history.daily_summary(date) {
+        Ok(Some(summary)) => return EditInteractionResponse::new().content(renderer.daily_summary(&summary)),
+        Ok(None) => {}
+        Err(e) => error!("Error reading history for {}: {:?}", date, e),
+    }
+
+    let content = match fetch_archived_summary(date).await {
+        Ok(Some(summary)) => renderer.daily_summary(&summary),
+        Ok(None) => format!("📭 查無 {} 的電力資料", date),
+        Err(e) => {
+            error!("Error fetching archive for {}: {:?}", date, e);
+            format!("❌ 本機無 {} 的紀錄，且無法取得台電歷史資料", date)
+        }
+    };
+    EditInteractionResponse::new().content(content)
+}
+
+/// "What was the load at 14:00?": the nearest local sample within half an hour
+fn snapshot_at(history: &History, at: NaiveDateTime, numbers: NumberFormat) -> String {
+    let sample = match history.snapshot_near(at, Duration::minutes(30)) {
+        Ok(Some(sample)) => sample,
+        Ok(None) => return format!("📭 本機沒有 {} 前後 30 分鐘內的紀錄", at.format("%Y-%m-%d %H:%M")),
+        Err(e) => {
+            error!("Error reading history for {}: {:?}", at, e);
+            return "❌ 無法讀取歷史紀錄".to_string();
+        }
+    };
+
+    let mut lines = vec![format!(
+        "🕑 **{} 的電力資訊** (紀錄時間 {})",
+        at.format("%Y-%m-%d %H:%M"),
+        sample.recorded_at.format("%H:%M")
+    )];
+    lines.extend(snapshot_figures(&sample, numbers));
+    lines.join("\n")
+}
+
+/// Load, reserve, generation and renewable share of a stored sample, one line each
+fn snapshot_figures(sample: &SnapshotRow, numbers: NumberFormat) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(load) = sample.current_load {
+        lines.push(format!("📊 **目前用電量**: {}", numbers.wan_kw(load)));
+    }
+    if let Some(rate) = sample.current_util_rate {
+        lines.push(format!("📈 **使用率**: {}", numbers.percent(rate, 1)));
+    }
+    if let Some(rate) = sample.reserve_rate {
+        let indicator = sample.indicator.as_deref().map(ReserveIndicator::from_code).unwrap_or(ReserveIndicator::Unknown);
+        lines.push(format!("🔋 **預估尖峰備轉容量率**: {} ({})", numbers.percent(rate, 2), indicator_label(indicator, &ZH_TW)));
+    }
+    lines.push(format!("⚡ **總發電量**: {}", numbers.mw(sample.total_generation, 1)));
+    lines.push(format!("🌱 **再生能源**: {}", numbers.percent(sample.renewable_ratio, 1)));
+    lines
+}
+
+/// `/at 2024-07-02 14:30`: the nearest stored sample however far off, saying how far
+fn run_at(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let numbers = guild_numbers(command, history);
+    let raw_time = string_option(command, "time").unwrap_or_default();
+    let Some(at) = parse_date_time(&raw_time) else {
+        return EditInteractionResponse::new().content(format!("❌ 無法解析時間: {} (格式: 2024-07-02 14:30)", raw_time));
+    };
+
+    let (sample, mix) = match history.nearest_snapshot(at) {
+        Ok(Some(found)) => found,
+        Ok(None) => return EditInteractionResponse::new().content("📭 本機尚無任何紀錄"),
+        Err(e) => {
+            error!("Error reading history for {}: {:?}", at, e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史紀錄");
+        }
+    };
+
+    let offset = sample.recorded_at - at;
+    let distance = match offset.num_minutes() {
+        0 => "與指定時間相同".to_string(),
+        m if m.abs() < 24 * 60 => format!("{} {} 分鐘", if m < 0 { "早" } else { "晚" }, m.abs()),
+        m => format!("{} {:.1} 天", if m < 0 { "早" } else { "晚" }, m.abs() as f64 / (24.0 * 60.0)),
+    };
+    let mut lines = vec![
+        format!("🕑 **{} 最接近的紀錄**", at.format("%Y-%m-%d %H:%M")),
+        format!("-# 紀錄時間 {} ({})", sample.recorded_at.format("%Y-%m-%d %H:%M"), distance),
+    ];
+    lines.extend(snapshot_figures(&sample, numbers));
+    if !mix.is_empty() {
+        lines.push("🏭 **發電結構**:".to_string());
+        for (energy_type, generation) in FuelDisplay::from_env().apply(mix.iter().map(|(t, g)| (t.as_str(), *g))) {
+            lines.push(format!("   • {}: {}", energy_type, numbers.mw(generation, 1)));
+        }
+    }
+    EditInteractionResponse::new().content(lines.join("\n"))
+}
+
+fn run_chart(command: &CommandInteraction, history: &History, cache: &ChartCache, chart_key: &mut Option<String>) -> EditInteractionResponse {
+    let metric = string_option(command, "metric").unwrap_or_default();
+    let raw_window = string_option(command, "window").unwrap_or_else(|| "24h".to_string());
+    let guild_id = command.guild_id.map(|id| id.get());
+    chart_reply(history, cache, &metric, &raw_window, guild_id, guild_numbers(command, history), chart_key)
+}
+
+/// The guild's /note annotations since `since`, as chart markers, and a cache key suffix naming them
+fn note_markers(history: &History, guild_id: Option<u64>, since: NaiveDateTime) -> (Vec<(NaiveDateTime, String)>, String) {
+    let Some(guild_id) = guild_id else {
+        return (Vec::new(), String::new());
+    };
+    let notes = history.notes_since(guild_id, since).unwrap_or_else(|why| {
+        error!("Error reading notes: {:?}", why);
+        Vec::new()
+    });
+    let key = notes.iter().map(|note| format!("#{}", note.id)).collect();
+    (notes.into_iter().map(|note| (note.created_at, note.text)).collect(), key)
+}
+
+fn chart_reply(
+    history: &History,
+    cache: &ChartCache,
+    metric: &str,
+    raw_window: &str,
+    guild_id: Option<u64>,
+    numbers: NumberFormat,
+    chart_key: &mut Option<String>,
+) -> EditInteractionResponse {
+    let Some(window) = parse_window(raw_window) else {
+        return EditInteractionResponse::new().content(format!("❌ 無法解析時間範圍: {} (例如 24h、7d)", raw_window));
+    };
+    let (column, label, unit) = match metric {
+        "load" => ("current_load", "目前用電量", "萬瓩"),
+        "reserve" => ("forecast_peak_reserve_rate", "預估尖峰備轉容量率", "%"),
+        "renewable" => ("renewable_ratio", "再生能源占比", "%"),
+        "generation" => ("total_generation", "總發電量", "MW"),
+        _ => return EditInteractionResponse::new().content(format!("❌ 未知的項目: {}", metric)),
+    };
+
+    let since = (taipei_now() - window).naive_local();
+    let points = match history.snapshot_series(column, since) {
+        Ok(points) => points,
+        Err(e) => {
+            error!("Error reading {} history: {:?}", column, e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史資料");
+        }
+    };
+    if points.len() < 2 {
+        return EditInteractionResponse::new().content(format!("📭 最近 {} 內沒有足夠的紀錄", raw_window));
+    }
+
+    let content = format!("📈 **{}** 最近 {} ({} 筆)", label, raw_window, points.len());
+    let series = [Series { label: format!("{} ({})", label, unit), points }];
+    let (markers, notes_key) = note_markers(history, guild_id, since);
+    let key = ChartCache::key(&format!("{}{}", metric, notes_key), raw_window, &series, numbers);
+    let response = chart_response(cache, &key, content, "chart.png", || {
+        chart::line_chart_with_markers(&format!("{} ({})", label, raw_window), unit, &series, &markers, numbers)
+    });
+    *chart_key = Some(key);
+    response.components(vec![window_picker(&format!("{}chart:{}", WINDOW_PICKER_PREFIX, metric), raw_window)])
+}
+
+/// The last cycle's data, or before the first cycle completes, one fetch shared by every
+/// command waiting on it
+async fn latest_snapshot(handler: &Handler, settings: &Config) -> Result<Arc<Snapshot>, Box<dyn std::error::Error + Send + Sync>> {
+    handler.snapshots.get_or_fetch(|| fetch_snapshot(handler, settings)).await
+}
+
+/// What an update cycle collects, minus the weather and history-based extras
+pub(super) async fn fetch_snapshot(handler: &Handler, settings: &Config) -> Result<Snapshot, Box<dyn std::error::Error + Send + Sync>> {
+    let (power, load) = tokio::join!(fetch_and_analyze_power_data(), fetch_load_data());
+    let power_analysis = power?;
+    if !settings.sanity_bounds.check_power(&power_analysis).is_empty() {
+        return Err("generation data failed sanity checks".into());
+    }
+    let load_data = load
+        .inspect_err(|e| error!("Error fetching load data for a command: {:?}", e))
+        .ok()
+        .filter(|data| settings.sanity_bounds.check_load(data).is_empty());
+    let regions = match &load_data {
+        Some(load) => match regional::fetch_regional_shares().await {
+            Ok(shares) => regional::estimate(&shares, load.current_load, settings.region_import_warn),
+            Err(e) => {
+                error!("Error fetching regional data for a command: {:?}", e);
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let today = taipei_now().date_naive();
+    let outages = handler.maintenance.outages(&power_analysis.units, today);
+    let demand_response_mw = demand_response::fetch_activated_mw(today).await;
+    let stress = StressIndex::compute(&power_analysis, load_data.as_ref(), None, &settings.stress_weights);
+    let fetched_at = taipei_now();
+    let load_fetched_at = load_data.is_some().then_some(fetched_at);
+    Ok(Snapshot {
+        data: CombinedPowerData {
+            power_analysis,
+            load_data,
+            regions,
+            temperature: None,
+            own_forecast: None,
+            outages,
+            demand_response_mw,
+            stress,
+            trend: None,
+        },
+        previous_reserve_rate: handler.history.latest_snapshot().ok().flatten().and_then(|sample| sample.reserve_rate),
+        fetched_at,
+        load_fetched_at,
+    })
+}
+
+/// Small print for replies from a snapshot the update loop hasn't refreshed in a while, or whose
+/// load figures were kept from an earlier cycle
+fn staleness_note(snapshot: &Snapshot) -> Option<String> {
+    if snapshot.is_stale(taipei_now()) {
+        return Some(format!("-# ⏳ 資料取得於 {}，更新可能延遲", discord_time(snapshot.fetched_at)));
+    }
+    let load_fetched_at = snapshot.load_fetched_at.filter(|_| snapshot.load_is_carried_over())?;
+    Some(format!("-# ⏳ 負載與備轉容量資料取得於 {}，最近一次更新失敗", discord_time(load_fetched_at)))
+}
+
+/// `response` with the staleness note as its message content, for embed replies
+fn with_staleness(response: EditInteractionResponse, snapshot: &Snapshot) -> EditInteractionResponse {
+    match staleness_note(snapshot) {
+        Some(note) => response.content(note),
+        None => response,
+    }
+}
+
+/// Plant names with their total capacity, largest first
+fn plant_names(units: &[PowerUnit]) -> Vec<(String, f64)> {
+    let mut plants: Vec<(String, f64)> = Vec::new();
+    for unit in units {
+        let Some(plant) = extract_plant_name(&unit.unit_name).filter(|p| !p.is_empty()) else {
+            continue;
+        };
+        match plants.iter_mut().find(|(name, _)| *name == plant) {
+            Some((_, capacity)) => *capacity += unit.capacity,
+            None => plants.push((plant, unit.capacity)),
+        }
+    }
+    plants.sort_by(|a, b| b.1.total_cmp(&a.1));
+    plants
+}
+
+/// Suggest plant names containing what has been typed so far
+pub async fn handle_autocomplete(ctx: &Context, autocomplete: &CommandInteraction, handler: &Handler) {
+    let Some(focused) = autocomplete.data.autocomplete() else {
+        return;
+    };
+    let typed = focused.value.trim().to_string();
+    let plants = handler.snapshots.latest().map(|snapshot| plant_names(&snapshot.data.power_analysis.units)).unwrap_or_default();
+    // Discord shows at most 25 choices
+    let response = plants
+        .into_iter()
+        .filter(|(name, _)| name.contains(&typed))
+        .take(25)
+        .fold(CreateAutocompleteResponse::new(), |response, (name, _)| response.add_string_choice(name.clone(), name));
+    if let Err(why) = autocomplete.create_response(&ctx.http, CreateInteractionResponse::Autocomplete(response)).await {
+        error!("Error answering autocomplete for /{}: {:?}", autocomplete.data.name, why);
+    }
+}
+
+/// Taipower's indicator tiers and what each means, mildest first
+const RESERVE_TIERS: [(ReserveIndicator, &str, &str); 5] = [
+    (ReserveIndicator::Green, "供電充裕", "備轉容量率 10% 以上"),
+    (ReserveIndicator::Yellow, "供電吃緊", "備轉容量率 6% ~ 10%"),
+    (ReserveIndicator::Orange, "供電警戒", "備轉容量率 6% 以下"),
+    (ReserveIndicator::Red, "限電警戒", "備轉容量 90 萬瓩以下"),
+    (ReserveIndicator::Black, "限電準備", "備轉容量 50 萬瓩以下"),
+];
+
+async fn run_reserve(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching data for /reserve: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let Some(load_data) = &snapshot.data.load_data else {
+        return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+    };
+    let previous_rate = snapshot.previous_reserve_rate;
+
+    let indicator = load_data.forecast_peak_reserve_indicator;
+    let rate = load_data.forecast_peak_reserve_rate;
+    // e.g. "▼0.4pp (上次 12.74%)"
+    let trend = previous_rate
+        .map(|previous| format!(" {} (上次 {})", format_pp_change(rate - previous), numbers.percent(previous, 2)))
+        .unwrap_or_default();
+
+    let mut lines = vec![
+        format!("{} **預估今日尖峰備轉容量率**: {}{}", indicator_emoji(indicator), numbers.percent(rate, 2), trend),
+        format!("🔋 **預估今日尖峰備轉容量**: {}", numbers.wan_kw(load_data.forecast_peak_reserve_capacity)),
+        format!("🕐 **預估尖峰用電時段**: {}", format_hour_range(load_data, &ZH_TW)),
+    ];
+    if let Some(publish_time) = load_data.publish_time {
+        lines.push(format!("📅 **資料更新時間**: {}", discord_time(publish_time)));
+    }
+    lines.push(String::new());
+    lines.push("**燈號說明**".to_string());
+    for (tier, meaning, threshold) in RESERVE_TIERS {
+        let line = format!("{} {} {}: {}", indicator_emoji(tier), indicator_label(tier, &ZH_TW), meaning, threshold);
+        // The current tier stands out; the rest are small print
+        lines.push(if tier == indicator { format!("**{}** ◀ 目前", line) } else { format!("-# {}", line) });
+    }
+    lines.extend(staleness_note(&snapshot));
+    EditInteractionResponse::new().content(lines.join("\n"))
+}
+
+/// Variable renewables covered by /renewables
+const VARIABLE_RENEWABLES: [(&str, &str); 2] = [("太陽能", "☀️"), ("風力", "🌬️")];
+
+async fn run_renewables(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching power data for /renewables: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let analysis = &snapshot.data.power_analysis;
+    // Island-wide load in MW; total generation stands in when the load feed is unavailable
+    let (demand, demand_label) = match &snapshot.data.load_data {
+        Some(load_data) => (load_data.current_load * 10.0, "占目前用電"),
+        None => (analysis.total_generation, "占總發電量"),
+    };
+    let share = |mw: f64| if demand > 0.0 { mw / demand * 100.0 } else { 0.0 };
+
+    let mut lines = vec![format!("🌱 **太陽能與風力發電** ({})", discord_timestamp(analysis.update_time, 'f'))];
+    let mut total = 0.0;
+    for (energy_type, emoji) in VARIABLE_RENEWABLES {
+        let capacity = analysis.capacity_by_type.get(energy_type).copied().unwrap_or(0.0);
+        let generation = analysis.generation_by_type.get(energy_type).copied().unwrap_or(0.0);
+        total += generation;
+        lines.push(String::new());
+        lines.push(format!("{} **{}**", emoji, energy_type));
+        lines.push(format!("   • 裝置容量: {}", numbers.mw(capacity, 1)));
+        lines.push(format!("   • 目前發電: {}", numbers.mw(generation, 1)));
+        lines.push(format!("   • 容量因數: {}", numbers.percent(capacity_factor(generation, capacity), 1)));
+        lines.push(format!("   • {}: {}", demand_label, numbers.percent(share(generation), 1)));
+    }
+    lines.push(String::new());
+    lines.push(format!("⚡ **合計**: {} ({} {})", numbers.mw(total, 1), demand_label, numbers.percent(share(total), 1)));
+    lines.extend(staleness_note(&snapshot));
+    EditInteractionResponse::new().content(lines.join("\n"))
+}
+
+async fn run_region(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching data for /region: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let Some(load_data) = &snapshot.data.load_data else {
+        return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+    };
+
+    let mut regions = snapshot.data.regions.clone();
+    if regions.is_empty() {
+        return EditInteractionResponse::new().content("📭 台電目前沒有提供區域資料");
+    }
+    // Tightest first
+    regions.sort_by(|a, b| b.import_share().total_cmp(&a.import_share()));
+
+    let mut content = String::from("🗺️ **各區域供需** (依全台負載比例估計)\n");
+    if let Some(publish_time) = load_data.publish_time {
+        content.push_str(&format!("📅 資料時間: {}\n", discord_time(publish_time)));
+    }
+    for region in &regions {
+        let margin = region.margin();
+        content.push_str(&format!(
+            "\n• **{}**{}\n   負載 {} / 發電 {} / 餘裕 {}{}",
+            region.area,
+            if region.heavy_import { " ⚠️" } else { "" },
+            numbers.mw(region.load, 0),
+            numbers.mw(region.generation, 0),
+            if margin < 0.0 { "-" } else { "+" },
+            numbers.mw(margin.abs(), 0),
+        ));
+        let share = region.import_share();
+        if share > 0.0 {
+            content.push_str(&format!(" (需自他區輸入 {})", numbers.percent(share, 1)));
+        } else {
+            content.push_str(&format!(" (可輸出 {})", numbers.percent(-share, 1)));
+        }
+    }
+    if let Some(tightest) = regions.first().filter(|r| r.margin() < 0.0) {
+        content.push_str(&format!("\n\n🔴 **最吃緊**: {} (缺口 {})", tightest.area, numbers.mw(-tightest.margin(), 0)));
+    }
+    if let Some(note) = staleness_note(&snapshot) {
+        content.push_str(&format!("\n{}", note));
+    }
+    EditInteractionResponse::new().content(content)
+}
+
+async fn run_plant(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let name = string_option(command, "name").unwrap_or_default().trim().to_string();
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching power data for /plant: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let units = &snapshot.data.power_analysis.units;
+
+    let plant_units: Vec<&PowerUnit> = units
+        .iter()
+        .filter(|u| extract_plant_name(&u.unit_name).as_deref() == Some(name.as_str()))
+        .collect();
+    if plant_units.is_empty() {
+        return EditInteractionResponse::new().content(format!("❌ 找不到電廠: {}", name));
+    }
+
+    let title = format!("🏭 {} ({} 部機組)", name, plant_units.len());
+    with_staleness(handler.pager.reply(command.id.get(), unit_pages(&title, &plant_units, true, numbers)), &snapshot)
+}
+
+/// /type choices, as `clean_energy_type` names them
+const TYPE_CHOICES: [&str; 13] = [
+    "核能", "燃煤", "燃氣", "燃油", "輕油", "汽電共生", "水力", "風力", "太陽能", "其它再生能源", "儲能", "民營燃煤", "民營燃氣",
+];
+/// Units per /type, /plant or /faults page
+const UNITS_PER_PAGE: usize = 20;
+
+async fn run_type(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let energy = string_option(command, "energy").unwrap_or_default();
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching power data for /type: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let units = &snapshot.data.power_analysis.units;
+
+    let mut type_units: Vec<&PowerUnit> = units
+        .iter()
+        .filter(|u| !u.unit_name.contains("小計") && clean_energy_type(&u.unit_type) == energy)
+        .collect();
+    if type_units.is_empty() {
+        return EditInteractionResponse::new().content(format!("📭 目前沒有 {} 機組的資料", energy));
+    }
+    type_units.sort_by(|a, b| b.generation.total_cmp(&a.generation));
+    let title = format!("{} ({} 部機組)", energy, type_units.len());
+    with_staleness(handler.pager.reply(command.id.get(), unit_pages(&title, &type_units, false, numbers)), &snapshot)
+}
+
+/// Embeds listing `units` with their totals, UNITS_PER_PAGE to a page
+fn unit_pages(title: &str, units: &[&PowerUnit], show_type: bool, numbers: NumberFormat) -> Vec<CreateEmbed> {
+    let capacity: f64 = units.iter().map(|u| u.capacity).sum();
+    let generation: f64 = units.iter().map(|u| u.generation).sum();
+    let header = format!(
+        "⚡ 淨發電量 {} / 裝置容量 {} ({})\n",
+        numbers.mw(generation, 1),
+        numbers.mw(capacity, 1),
+        numbers.percent(capacity_factor(generation, capacity), 1)
+    );
+    let lines: Vec<String> = units
+        .iter()
+        .map(|unit| {
+            let ratio = unit.ratio.unwrap_or_else(|| capacity_factor(unit.generation, unit.capacity));
+            let energy_type = if show_type { format!(" {}", clean_energy_type(&unit.unit_type)) } else { String::new() };
+            let mut line = format!(
+                "• **{}**{}: {} / {} ({})",
+                unit.unit_name,
+                energy_type,
+                numbers.mw(unit.generation, 1),
+                numbers.mw(unit.capacity, 1),
+                numbers.percent(ratio, 1)
+            );
+            let remark = unit.remark.trim();
+            if !remark.is_empty() && remark != "-" {
+                line.push_str(&format!(" — {}", remark));
+            }
+            line
+        })
+        .collect();
+    pages::split(&header, &lines, UNITS_PER_PAGE)
+        .into_iter()
+        .map(|description| CreateEmbed::new().title(title).description(description))
+        .collect()
+}
+
+/// /faults categories, in the order they're listed
+const FAULT_CATEGORIES: [(RemarkClass, &str); 3] = [
+    (RemarkClass::Fault, "⚠️ 故障"),
+    (RemarkClass::Maintenance, "🔧 歲修/檢修"),
+    (RemarkClass::Restricted, "🌱 環保/運轉限制"),
+];
+
+/// The last update cycle's phase timings against the budget, the day's cycles and how fresh the data is
+fn run_status(handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let now = taipei_now().naive_local();
+    let cycles = match handler.history.cycle_timings_since(now - Duration::hours(24)) {
+        Ok(cycles) => cycles,
+        Err(e) => {
+            error!("Error reading cycle timings: {:?}", e);
+            return EditInteractionResponse::new().content("❌ 無法讀取更新紀錄");
+        }
+    };
+    let budget = settings.cycle_budget;
+    let budget_label = budget.map(|b| format!("上限 {:.0} 秒", b.as_secs_f64())).unwrap_or_else(|| "未設上限".to_string());
+
+    let mut lines = vec!["🩺 **機器人狀態**".to_string()];
+    match cycles.last() {
+        Some((recorded_at, timings)) => {
+            let when = taipei_datetime(*recorded_at).map(|t| discord_timestamp(t, 'R')).unwrap_or_else(|| recorded_at.format("%H:%M").to_string());
+            lines.push(format!("最近一次更新: {}，耗時 {:.2} 秒 ({})", when, timings.total().as_secs_f64(), budget_label));
+            lines.push(format!("-# {}", timings.describe()));
+
+            let totals: Vec<f64> = cycles.iter().map(|(_, t)| t.total().as_secs_f64()).collect();
+            let over = budget.map(|b| cycles.iter().filter(|(_, t)| t.total() > b).count()).unwrap_or(0);
+            lines.push(format!(
+                "近 24 小時: {} 次更新，平均 {:.2} 秒，最慢 {:.2} 秒，超時 {} 次",
+                totals.len(),
+                totals.iter().sum::<f64>() / totals.len() as f64,
+                totals.iter().copied().fold(0.0, f64::max),
+                over
+            ));
+        }
+        None => lines.push("近 24 小時沒有完成的更新週期".to_string()),
+    }
+    if let Some(snapshot) = handler.snapshots.latest() {
+        lines.push(format!("資料取得於 {}", discord_time(snapshot.fetched_at)));
+    }
+    EditInteractionResponse::new().content(lines.join("\n"))
+}
+
+async fn run_faults(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let numbers = guild_numbers(command, &handler.history);
+    let snapshot = match latest_snapshot(handler, settings).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            error!("Error fetching power data for /faults: {:?}", e);
+            return EditInteractionResponse::new().content(reporting::USER_NOTICE);
+        }
+    };
+    let units = &snapshot.data.power_analysis.units;
+
+    let mut counts = Vec::new();
+    let mut lines = Vec::new();
+    for (class, label) in FAULT_CATEGORIES {
+        let mut category: Vec<&PowerUnit> = units
+            .iter()
+            .filter(|u| !u.unit_name.contains("小計") && classify_remark(&u.remark) == class)
+            .collect();
+        counts.push(format!("{} {} 部", label, category.len()));
+        if category.is_empty() {
+            continue;
+        }
+        category.sort_by(|a, b| b.capacity.total_cmp(&a.capacity));
+        let capacity: f64 = category.iter().map(|u| u.capacity).sum();
+        lines.push(format!("\n**{}** ({})", label, numbers.mw(capacity, 1)));
+        lines.extend(category.iter().map(|unit| {
+            format!(
+                "• **{}** {} ({}): {}",
+                unit.unit_name,
+                clean_energy_type(&unit.unit_type),
+                numbers.mw(unit.capacity, 1),
+                unit.remark.trim()
+            )
+        }));
+    }
+    if lines.is_empty() {
+        return EditInteractionResponse::new().content("✅ 目前沒有故障、歲修或限制運轉的機組");
+    }
+
+    let pages = pages::split(&counts.join(" · "), &lines, UNITS_PER_PAGE)
+        .into_iter()
+        .map(|description| CreateEmbed::new().title("🏭 機組異常狀態").description(description))
+        .collect();
+    with_staleness(handler.pager.reply(command.id.get(), pages), &snapshot)
+}
+
+fn run_unit_history(command: &CommandInteraction, history: &History, cache: &ChartCache, chart_key: &mut Option<String>) -> EditInteractionResponse {
+    let unit = string_option(command, "unit").unwrap_or_default().trim().to_string();
+    let raw_window = string_option(command, "window").unwrap_or_else(|| "7d".to_string());
+    unit_history_reply(history, cache, &unit, &raw_window, guild_numbers(command, history), chart_key)
+}
+
+fn unit_history_reply(
+    history: &History,
+    cache: &ChartCache,
+    unit: &str,
+    raw_window: &str,
+    numbers: NumberFormat,
+    chart_key: &mut Option<String>,
+) -> EditInteractionResponse {
+    let Some(window) = parse_window(raw_window) else {
+        return EditInteractionResponse::new().content(format!("❌ 無法解析時間範圍: {} (例如 24h、7d)", raw_window));
+    };
+
+    let since = (taipei_now() - window).naive_local();
+    let samples = match history.unit_series(unit, since) {
+        Ok(samples) => samples,
+        Err(e) => {
+            error!("Error reading unit history for {}: {:?}", unit, e);
+            return EditInteractionResponse::new().content("❌ 無法讀取機組歷史資料");
+        }
+    };
+
+    if samples.len() < 2 {
+        return EditInteractionResponse::new()
+            .content(format!("📭 {} 在最近 {} 內沒有足夠的紀錄 (需啟用 UNIT_HISTORY)", unit, raw_window));
+    }
+
+    let generation: Vec<_> = samples.iter().map(|(t, g, _)| (*t, *g)).collect();
+    let capacity: Vec<_> = samples.iter().map(|(t, _, c)| (*t, *c)).collect();
+    let max = generation.iter().map(|(_, g)| *g).fold(f64::MIN, f64::max);
+    let min = generation.iter().map(|(_, g)| *g).fold(f64::MAX, f64::min);
+    let average = generation.iter().map(|(_, g)| *g).sum::<f64>() / generation.len() as f64;
+
+    let content = format!(
+        "🏭 **{}** 最近 {} 發電量\n最高 {:.1} MW · 最低 {:.1} MW · 平均 {:.1} MW ({} 筆)",
+        unit, raw_window, max, min, average, samples.len()
+    );
+
+    let series = [
+        Series { label: "淨發電量 (MW)".to_string(), points: generation },
+        Series { label: "裝置容量 (MW)".to_string(), points: capacity },
+    ];
+    let key = ChartCache::key(&format!("unit:{}", unit), raw_window, &series, numbers);
+    let response = chart_response(cache, &key, content, "unit-history.png", || {
+        chart::line_chart(&format!("{} ({})", unit, raw_window), "MW", &series, numbers)
+    });
+    *chart_key = Some(key);
+    response.components(vec![window_picker(&format!("{}unit:{}", WINDOW_PICKER_PREFIX, unit), raw_window)])
+}
+
+fn run_loadcurve(command: &CommandInteraction, history: &History, cache: &ChartCache, chart_key: &mut Option<String>) -> EditInteractionResponse {
+    let overlay = bool_option(command, "yesterday").unwrap_or(true);
+    let numbers = guild_numbers(command, history);
+    let now = taipei_now().naive_local();
+    let since = now - Duration::hours(if overlay { 48 } else { 24 });
+
+    let points = match history.snapshot_series("current_load", since) {
+        Ok(points) => points,
+        Err(e) => {
+            error!("Error reading load history: {:?}", e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史資料");
+        }
+    };
+    let cutoff = now - Duration::hours(24);
+    let (yesterday, today): (Vec<_>, Vec<_>) = points.into_iter().partition(|(t, _)| *t < cutoff);
+    if today.len() < 2 {
+        return EditInteractionResponse::new().content("📭 最近 24 小時內沒有足夠的用電紀錄");
+    }
+
+    let max = today.iter().copied().fold((now, f64::MIN), |best, p| if p.1 > best.1 { p } else { best });
+    let min = today.iter().copied().fold((now, f64::MAX), |best, p| if p.1 < best.1 { p } else { best });
+    let mut content = format!(
+        "📊 **最近 24 小時用電曲線** ({} 筆)\n最高 {} ({}) · 最低 {} ({})",
+        today.len(),
+        numbers.wan_kw(max.1),
+        max.0.format("%H:%M"),
+        numbers.wan_kw(min.1),
+        min.0.format("%H:%M")
+    );
+
+    // Yesterday's curve is shifted forward a day so the same hours line up
+    let yesterday: Vec<_> = yesterday.into_iter().map(|(t, load)| (t + Duration::hours(24), load)).collect();
+    let mut series = vec![Series { label: "最近 24 小時".to_string(), points: today }];
+    if overlay {
+        if yesterday.len() >= 2 {
+            let (latest_time, latest) = series[0].points[series[0].points.len() - 1];
+            if let Some((_, then)) = yesterday.iter().min_by_key(|(t, _)| (*t - latest_time).num_seconds().abs()) {
+                let change = (latest - then) / then * 100.0;
+                content.push_str(&format!(
+                    "\n與前一天同時段相比 {}{}",
+                    if change >= 0.0 { "+" } else { "" },
+                    numbers.percent(change, 1)
+                ));
+            }
+            series.push(Series { label: "前一天".to_string(), points: yesterday });
+        } else {
+            content.push_str("\n-# 前一天沒有足夠的紀錄可供比較");
+        }
+    }
+
+    let (markers, notes_key) = note_markers(history, command.guild_id.map(|id| id.get()), cutoff);
+    let key = ChartCache::key(&format!("loadcurve{}", notes_key), if overlay { "24h+yesterday" } else { "24h" }, &series, numbers);
+    let response = chart_response(cache, &key, content, "loadcurve.png", || {
+        chart::line_chart_with_markers("用電量 (24 小時)", "萬瓩", &series, &markers, numbers)
+    });
+    *chart_key = Some(key);
+    response
+}
+
+/// Fuel groups compared by /transition, bottom to top of the stacked chart
+const TRANSITION_GROUPS: [&str; 5] = ["燃煤", "燃氣", "核能", "再生能源", "其他"];
+
+fn transition_group(energy_type: &str) -> usize {
+    if energy_type.contains('煤') {
+        0
+    } else if energy_type.contains('氣') {
+        1
+    } else if energy_type.contains('核') {
+        2
+    } else if is_renewable(energy_type) {
+        3
+    } else {
+        4
+    }
+}
+
+fn run_transition(command: &CommandInteraction, history: &History, cache: &ChartCache, chart_key: &mut Option<String>) -> EditInteractionResponse {
+    let months = integer_option(command, "months").unwrap_or(12).clamp(2, 120);
+    let numbers = guild_numbers(command, history);
+    let today = taipei_now().date_naive();
+    let since = today
+        .with_day(1)
+        .and_then(|d| d.checked_sub_months(chrono::Months::new(months as u32 - 1)))
+        .unwrap_or(today);
+
+    let mix = match history.monthly_generation_mix(since) {
+        Ok(mix) => mix,
+        Err(e) => {
+            error!("Error reading monthly generation mix: {:?}", e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史資料");
+        }
+    };
+    if mix.len() < 2 {
+        return EditInteractionResponse::new().content(format!("📭 最近 {} 個月內的紀錄不足兩個月，無法比較", months));
+    }
+
+    // Shares of positive generation; pumped-storage charging is negative and left out
+    let shares: Vec<(NaiveDate, [f64; 5])> = mix
+        .iter()
+        .map(|(month, by_type)| {
+            let mut groups = [0.0; 5];
+            for (energy_type, generation) in by_type {
+                groups[transition_group(energy_type)] += generation.max(0.0);
+            }
+            let total: f64 = groups.iter().sum();
+            if total > 0.0 {
+                groups.iter_mut().for_each(|g| *g = *g / total * 100.0);
+            }
+            (*month, groups)
+        })
+        .collect();
+
+    let (first_month, first) = shares[0];
+    let (last_month, last) = shares[shares.len() - 1];
+    let mut content = format!(
+        "🔄 **能源轉型追蹤** {} → {} ({} 個月)\n```\n",
+        first_month.format("%Y/%m"),
+        last_month.format("%Y/%m"),
+        shares.len()
+    );
+    for (i, group) in TRANSITION_GROUPS.iter().enumerate() {
+        let change = last[i] - first[i];
+        content.push_str(&format!(
+            "{} {:>7} → {:>7} ({}{})\n",
+            group,
+            numbers.percent(first[i], 1),
+            numbers.percent(last[i], 1),
+            if change >= 0.0 { "+" } else { "" },
+            numbers.decimal(change, 1)
+        ));
+    }
+    content.push_str("```\n-# 每月平均發電占比，僅含本機紀錄");
+
+    let series: Vec<Series> = TRANSITION_GROUPS
+        .iter()
+        .enumerate()
+        .map(|(i, group)| Series {
+            label: group.to_string(),
+            points: shares.iter().map(|(month, groups)| (month.and_time(NaiveTime::MIN), groups[i])).collect(),
+        })
+        .collect();
+    let key = ChartCache::key("transition", &months.to_string(), &series, numbers);
+    let response = chart_response(cache, &key, content, "transition.png", || {
+        chart::stacked_area_chart(&format!("發電占比 (最近 {} 個月)", months), "%", &series, numbers)
+    });
+    *chart_key = Some(key);
+    response
+}
+
+fn run_availability(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let months = integer_option(command, "months").unwrap_or(6).clamp(1, 12);
+    let numbers = guild_numbers(command, history);
+    let today = taipei_now().date_naive();
+    let since = today
+        .with_day(1)
+        .and_then(|d| d.checked_sub_months(chrono::Months::new(months as u32 - 1)))
+        .unwrap_or(today);
+
+    let availability = match history.monthly_availability(since) {
+        Ok(availability) => availability,
+        Err(e) => {
+            error!("Error reading monthly availability: {:?}", e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史資料");
+        }
+    };
+    let (Some((first_month, _)), Some((last_month, _))) = (availability.first(), availability.last()) else {
+        return EditInteractionResponse::new().content(format!("📭 最近 {} 個月內沒有可用率紀錄", months));
+    };
+
+    // Known types in /type order, then anything else Taipower adds
+    let mut energy_types: Vec<String> = TYPE_CHOICES.iter().map(|t| t.to_string()).collect();
+    for (_, by_type) in &availability {
+        let mut extra: Vec<&String> = by_type.keys().filter(|t| !energy_types.contains(t)).collect();
+        extra.sort();
+        energy_types.extend(extra.into_iter().cloned());
+    }
+
+    let mut content = format!("🔧 **機組可用率** {} → {}\n", first_month.format("%Y/%m"), last_month.format("%Y/%m"));
+    for energy_type in &energy_types {
+        let values: Vec<String> = availability
+            .iter()
+            .map(|(_, by_type)| by_type.get(energy_type).map_or("—".to_string(), |v| numbers.percent(*v, 1)))
+            .collect();
+        if values.iter().all(|v| v == "—") {
+            continue;
+        }
+        content.push_str(&format!("**{}**: {}\n", energy_type, values.join(" → ")));
+    }
+    content.push_str("-# 可用容量 ÷ 裝置容量，不含計畫歲修；每月平均，僅含本機紀錄");
+    EditInteractionResponse::new().content(content)
+}
+
+const WINDOW_PICKER_PREFIX: &str = "window:";
+const PICKER_WINDOWS: [(&str, &str); 4] = [("6h", "6 小時"), ("24h", "24 小時"), ("7d", "7 天"), ("30d", "30 天")];
+
+/// Select menu that re-renders the chart for another window; `custom_id` says which chart
+fn window_picker(custom_id: &str, selected: &str) -> CreateActionRow {
+    let options = PICKER_WINDOWS
+        .iter()
+        .map(|(value, label)| CreateSelectMenuOption::new(*label, *value).default_selection(*value == selected))
+        .collect();
+    CreateActionRow::SelectMenu(
+        CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options }).placeholder("切換時間範圍"),
+    )
+}
+
+/// A window was picked on a chart reply, or a listing's page turned: re-render it in place
+pub async fn handle_component(ctx: &Context, component: &ComponentInteraction, handler: &Handler) {
+    if handler.pager.handle(ctx, component).await || handler.refresher.handle(ctx, component, handler).await {
+        return;
+    }
+    let Some(target) = component.data.custom_id.strip_prefix(WINDOW_PICKER_PREFIX) else {
+        return;
+    };
+    let ComponentInteractionDataKind::StringSelect { values } = &component.data.kind else {
+        return;
+    };
+    let Some(window) = values.first() else {
+        return;
+    };
+
+    if let Err(why) = component.defer(&ctx.http).await {
+        error!("Error deferring window picker: {:?}", why);
+        return;
+    }
+
+    let numbers = handler.history.number_format(component.guild_id.map(|id| id.get()));
+    let mut chart_key = None;
+    let response = match target.split_once(':') {
+        Some(("chart", metric)) => {
+            let guild_id = component.guild_id.map(|id| id.get());
+            chart_reply(&handler.history, &handler.chart_cache, metric, window, guild_id, numbers, &mut chart_key)
+        }
+        Some(("unit", unit)) => unit_history_reply(&handler.history, &handler.chart_cache, unit, window, numbers, &mut chart_key),
+        _ => return,
+    };
+
+    match component.edit_response(&ctx.http, response).await {
+        Ok(message) => {
+            if let (Some(key), Some(attachment)) = (chart_key, message.attachments.first()) {
+                handler.chart_cache.set_url(&key, attachment.url.clone());
+            }
+        }
+        Err(why) => error!("Error updating chart: {:?}", why),
+    }
+}
+
+fn run_peakhours(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let days = integer_option(command, "days").unwrap_or(7).clamp(1, 90);
+    let since = taipei_now().date_naive() - Duration::days(days - 1);
+
+    let peaks = match history.daily_peaks(since) {
+        Ok(peaks) => peaks,
+        Err(e) => {
+            error!("Error reading daily peaks: {:?}", e);
+            return EditInteractionResponse::new().content("❌ 無法讀取歷史資料");
+        }
+    };
+    if peaks.is_empty() {
+        return EditInteractionResponse::new().content(format!("📭 最近 {} 天沒有用電紀錄", days));
+    }
+
+    let mut by_hour = [0usize; 24];
+    for (_, time, _) in &peaks {
+        by_hour[time.hour() as usize] += 1;
+    }
+    let first = by_hour.iter().position(|n| *n > 0).unwrap_or(0);
+    let last = by_hour.iter().rposition(|n| *n > 0).unwrap_or(23);
+
+    let mut content = format!("📈 **最近 {} 天每日尖峰時段** ({} 天有紀錄)\n```\n", days, peaks.len());
+    for (hour, count) in by_hour.iter().enumerate().take(last + 1).skip(first) {
+        content.push_str(&format!("{:02}時 {} {}\n", hour, "█".repeat(*count), count));
+    }
+    content.push_str("```\n");
+
+    // Compare the older and newer half to show whether the peak is drifting later
+    if peaks.len() >= 4 {
+        let half = peaks.len() / 2;
+        let average = |slice: &[(NaiveDate, NaiveTime, f64)]| {
+            slice.iter().map(|(_, t, _)| t.num_seconds_from_midnight() as f64).sum::<f64>() / slice.len() as f64
+        };
+        let earlier = average(&peaks[..half]);
+        let later = average(&peaks[peaks.len() - half..]);
+        content.push_str(&format!(
+            "🕐 平均尖峰時間: {} → {} ({:+.0} 分鐘)\n",
+            format_seconds(earlier),
+            format_seconds(later),
+            (later - earlier) / 60.0
+        ));
+    }
+
+    content.push_str("\n```\n");
+    for (day, time, load) in peaks.iter().rev().take(14) {
+        content.push_str(&format!("{} {} {:>7.1} 萬瓩\n", day.format("%m/%d"), time.format("%H:%M"), load));
+    }
+    content.push_str("```");
+
+    EditInteractionResponse::new().content(content)
+}
+
+fn format_seconds(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u32;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+async fn run_maintenance(command: &CommandInteraction, calendar: &MaintenanceCalendar, numbers: NumberFormat) -> EditInteractionResponse {
+    if !calendar.configured() {
+        return EditInteractionResponse::new().content("ℹ️ 尚未設定歲修計畫資料來源 (MAINTENANCE_SCHEDULE_URL)");
+    }
+    let weeks = command
+        .data
+        .options()
+        .into_iter()
+        .find_map(|opt| match opt.value {
+            ResolvedValue::SubCommand(sub_options) => sub_options.iter().find_map(|o| match o.value {
+                ResolvedValue::Integer(weeks) if o.name == "weeks" => Some(weeks),
+                _ => None,
+            }),
+            _ => None,
+        })
+        .unwrap_or(4);
+
+    calendar.refresh().await;
+    let windows = calendar.upcoming(taipei_now().date_naive(), weeks);
+    if windows.is_empty() {
+        return EditInteractionResponse::new().content(format!("📭 未來 {} 週沒有大型機組預定歲修", weeks));
+    }
+
+    let mut content = format!("🛠️ **未來 {} 週預定歲修** (裝置容量 {} 以上)", weeks, numbers.mw(calendar.major_unit_mw(), 0));
+    for w in &windows {
+        let capacity = w.capacity.map(|c| format!(" ({})", numbers.mw(c, 0))).unwrap_or_default();
+        let line = format!("\n• {} ~ {} **{}**{}", w.start.format("%m-%d"), w.end.format("%m-%d"), w.unit, capacity);
+        // Stay under Discord's 2000 character limit
+        if content.chars().count() + line.chars().count() > 1900 {
+            content.push_str("\n…");
+            break;
+        }
+        content.push_str(&line);
+    }
+    EditInteractionResponse::new().content(content)
+}
+
+/// Notes belong to the guild: they go out with its next scheduled report and show on its charts
+fn run_note(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let text = string_option(command, "text").unwrap_or_default();
+    let text = text.trim();
+    if text.is_empty() {
+        return EditInteractionResponse::new().content("❌ 備註內容不可為空白");
+    }
+    match history.add_note(guild_id.get(), command.user.id.get(), text) {
+        Ok(id) => EditInteractionResponse::new()
+            .content(format!("📝 已新增備註 #{}: {}\n-# 將附在下一次定時報告中，並標示於 /chart 與 /loadcurve 圖表", id, text))
+            .allowed_mentions(CreateAllowedMentions::new()),
+        Err(why) => {
+            error!("Error saving note: {:?}", why);
+            EditInteractionResponse::new().content("❌ 無法儲存備註")
+        }
+    }
+}
+
+fn run_incident(command: &CommandInteraction, history: &History, now: NaiveDateTime) -> EditInteractionResponse {
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+
+    match (subcommand.name, &subcommand.value) {
+        ("export", ResolvedValue::SubCommand(sub_options)) => {
+            let id = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::Integer(id) if opt.name == "id" => Some(id),
+                _ => None,
+            });
+            let Some(id) = id else {
+                return EditInteractionResponse::new().content("❌ 請提供事件編號");
+            };
+
+            match incident::export(history, id, guild_numbers(command, history), now) {
+                Ok(Some(export)) => {
+                    let mut response = EditInteractionResponse::new()
+                        .content(format!("📦 事件 #{} 匯出: {}", export.incident.id, export.incident.summary));
+                    for attachment in export.attachments {
+                        response = response.new_attachment(attachment);
+                    }
+                    response
+                }
+                Ok(None) => EditInteractionResponse::new().content(format!("📭 找不到事件 #{}", id)),
+                Err(e) => {
+                    error!("Error exporting incident {}: {:?}", id, e);
+                    EditInteractionResponse::new().content("❌ 無法匯出事件")
+                }
+            }
+        }
+        ("response", _) => match incident::dip_response(history, guild_numbers(command, history)) {
+            Ok(Some(text)) => EditInteractionResponse::new().content(text),
+            Ok(None) => EditInteractionResponse::new().content("📭 紀錄中沒有備轉容量率明顯下降的時段"),
+            Err(e) => {
+                error!("Error reading storage response: {:?}", e);
+                EditInteractionResponse::new().content("❌ 無法讀取紀錄")
+            }
+        },
+        _ => match history.recent_incidents(10) {
+            Ok(incidents) if incidents.is_empty() => EditInteractionResponse::new().content("📭 目前沒有事件紀錄"),
+            Ok(incidents) => {
+                let lines: Vec<String> = incidents
+                    .iter()
+                    .map(|i| format!(
+                        "`#{}` {} ~ {} · {}",
+                        i.id,
+                        i.started_at.format("%Y-%m-%d %H:%M"),
+                        i.ended_at.map(|t| t.format("%H:%M").to_string()).unwrap_or_else(|| "進行中".to_string()),
+                        i.summary
+                    ))
+                    .collect();
+                EditInteractionResponse::new().content(format!("🚨 **最近的事件**\n{}", lines.join("\n")))
+            }
+            Err(e) => {
+                error!("Error listing incidents: {:?}", e);
+                EditInteractionResponse::new().content("❌ 無法讀取事件紀錄")
+            }
+        },
+    }
+}
+
+fn run_ping_ladder(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id.map(|id| id.get()) else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+    let ResolvedValue::SubCommand(sub_options) = &subcommand.value else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+    let below = sub_options.iter().find_map(|opt| match opt.value {
+        ResolvedValue::Number(value) if opt.name == "below" => Some(value),
+        _ => None,
+    });
+
+    let reply = match (subcommand.name, below) {
+        ("add", Some(below)) => {
+            let mention = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::String(value) if opt.name == "broadcast" => LadderMention::parse(value),
+                ResolvedValue::Role(role) => Some(LadderMention::Role(role.id.get())),
+                _ => None,
+            });
+            let Some(mention) = mention else {
+                return EditInteractionResponse::new().content("❌ 請指定要提及的身分組，或選擇 @here / @everyone");
+            };
+            match history.set_ladder_rung(guild_id, below, mention) {
+                Ok(()) => format!("✅ 預估尖峰備轉容量率低於 {}% 時將提及 {}", below, mention.display()),
+                Err(e) => {
+                    error!("Error saving ping ladder for guild {}: {:?}", guild_id, e);
+                    "❌ 無法儲存設定".to_string()
+                }
+            }
+        }
+        ("remove", Some(below)) => match history.remove_ladder_rung(guild_id, below) {
+            Ok(true) => format!("✅ 已移除 {}% 的門檻", below),
+            Ok(false) => format!("❌ 沒有 {}% 的門檻", below),
+            Err(e) => {
+                error!("Error removing ping ladder rung for guild {}: {:?}", guild_id, e);
+                "❌ 無法儲存設定".to_string()
+            }
+        },
+        ("list", _) => match history.ping_ladder(guild_id) {
+            Ok(rungs) if rungs.is_empty() => "📭 尚未設定任何門檻，使用 /ping-ladder add 新增".to_string(),
+            Ok(rungs) => {
+                let mut lines = vec!["📶 **提及門檻** (預估尖峰備轉容量率)".to_string()];
+                for rung in rungs {
+                    let state = if rung.active { " · 🔔 已觸發" } else { "" };
+                    lines.push(format!("• 低於 {}%: {}{}", rung.below, rung.mention.display(), state));
+                }
+                lines.push(format!("-# 回升超過門檻 {} 個百分點後才會再次提及", mentions::ladder_hysteresis_from_env()));
+                lines.join("\n")
+            }
+            Err(e) => {
+                error!("Error reading ping ladder for guild {}: {:?}", guild_id, e);
+                "❌ 無法讀取設定".to_string()
+            }
+        },
+        _ => "❌ 請提供門檻".to_string(),
+    };
+    // Listing or confirming a rung shouldn't ping the role it names
+    EditInteractionResponse::new().content(reply).allowed_mentions(CreateAllowedMentions::new())
+}
+
+fn run_push(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let user_id = command.user.id.get();
+    let options = command.data.options();
+    let Some(subcommand) = options.first() else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+    let ResolvedValue::SubCommand(sub_options) = &subcommand.value else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+    let string = |name: &str| {
+        sub_options.iter().find_map(|opt| match opt.value {
+            ResolvedValue::String(value) if opt.name == name => Some(value.trim()),
+            _ => None,
+        })
+    };
+
+    match subcommand.name {
+        "add" => {
+            let (Some(service), Some(endpoint), Some(alert_type)) = (
+                string("service").and_then(PushService::parse),
+                string("endpoint"),
+                string("alert").and_then(AlertType::parse),
+            ) else {
+                return EditInteractionResponse::new().content("❌ 請提供推播服務、端點與通知類型");
+            };
+            if let Err(reason) = service.validate(endpoint) {
+                return EditInteractionResponse::new().content(format!("❌ {}", reason));
+            }
+            match history.user_push_subscriptions(user_id) {
+                Ok(existing) if existing.len() >= push::MAX_SUBSCRIPTIONS_PER_USER => {
+                    return EditInteractionResponse::new()
+                        .content(format!("❌ 每人最多 {} 個推播，請先用 /push remove 移除", push::MAX_SUBSCRIPTIONS_PER_USER));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Error reading push subscriptions for user {}: {:?}", user_id, e);
+                    return EditInteractionResponse::new().content("❌ 無法讀取推播設定");
+                }
+            }
+            match history.add_push_subscription(user_id, service, endpoint, alert_type) {
+                Ok(id) => EditInteractionResponse::new()
+                    .content(format!("✅ 已新增推播 #{} ({} · {})", id, service.code(), alert_type.code())),
+                Err(e) => {
+                    error!("Error saving push subscription for user {}: {:?}", user_id, e);
+                    EditInteractionResponse::new().content("❌ 無法儲存推播設定")
+                }
+            }
+        }
+        "remove" => {
+            let id = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::Integer(id) if opt.name == "id" => Some(id),
+                _ => None,
+            });
+            let Some(id) = id else {
+                return EditInteractionResponse::new().content("❌ 請提供推播編號");
+            };
+            match history.remove_push_subscription(user_id, id) {
+                Ok(true) => EditInteractionResponse::new().content(format!("✅ 已移除推播 #{}", id)),
+                Ok(false) => EditInteractionResponse::new().content(format!("📭 找不到你的推播 #{}", id)),
+                Err(e) => {
+                    error!("Error removing push subscription {}: {:?}", id, e);
+                    EditInteractionResponse::new().content("❌ 無法移除推播設定")
+                }
+            }
+        }
+        _ => match history.user_push_subscriptions(user_id) {
+            Ok(subscriptions) if subscriptions.is_empty() => EditInteractionResponse::new().content("📭 你還沒有設定推播"),
+            Ok(subscriptions) => {
+                let lines: Vec<String> = subscriptions
+                    .iter()
+                    .map(|s| format!("`#{}` {} · {} · `{}`", s.id, s.service.code(), s.alert_type.code(), s.endpoint))
+                    .collect();
+                EditInteractionResponse::new().content(format!("📱 **你的推播**\n{}", lines.join("\n")))
+            }
+            Err(e) => {
+                error!("Error reading push subscriptions for user {}: {:?}", user_id, e);
+                EditInteractionResponse::new().content("❌ 無法讀取推播設定")
+            }
+        },
+    }
+}
+
+fn run_mentions(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let mut target = MentionTarget { policy: MentionPolicy::Never, role_id: None, user_id: None };
+    for option in command.data.options() {
+        match option.value {
+            ResolvedValue::String(value) if option.name == "policy" => {
+                target.policy = MentionPolicy::parse(value).unwrap_or(MentionPolicy::Never);
+            }
+            ResolvedValue::Role(role) => target.role_id = Some(role.id.get()),
+            ResolvedValue::User(user, _) => target.user_id = Some(user.id.get()),
+            _ => {}
+        }
+    }
+
+    if target.policy != MentionPolicy::Never && target.role_id.is_none() && target.user_id.is_none() {
+        return EditInteractionResponse::new().content("❌ 請指定要提及的身分組或使用者");
+    }
+
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    match history.set_mention_target(guild_id.get(), command.channel_id.get(), &target) {
+        Ok(()) => {
+            let who = [
+                target.role_id.map(|id| format!("<@&{}>", id)),
+                target.user_id.map(|id| format!("<@{}>", id)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+            let content = match target.policy {
+                MentionPolicy::Never => "✅ 此頻道的定時電力資訊將不再提及任何人".to_string(),
+                MentionPolicy::Always => format!("✅ 每次定時電力資訊都會提及 {}", who),
+                MentionPolicy::CriticalOnly => format!("✅ 供電燈號橘燈以上的日子會提及 {}", who),
+            };
+            // Confirm without pinging the target
+            EditInteractionResponse::new()
+                .content(content)
+                .allowed_mentions(CreateAllowedMentions::new())
+        }
+        Err(e) => {
+            error!("Error saving mention policy for channel {}: {:?}", command.channel_id, e);
+            EditInteractionResponse::new().content("❌ 無法儲存提及設定")
+        }
+    }
+}
+
+fn run_follow(command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    if settings.channels.iter().any(|(id, _)| *id == command.channel_id) {
+        return EditInteractionResponse::new().content("ℹ️ 此頻道已是主要發布頻道");
+    }
+
+    let follow = Follow {
+        guild_id: guild_id.get(),
+        channel_id: command.channel_id.get(),
+        locale: string_option(command, "locale").and_then(|v| Locale::parse(&v)).unwrap_or_default(),
+        profile: string_option(command, "profile")
+            .and_then(|v| ReportProfile::parse(&v))
+            .unwrap_or(ReportProfile::Full),
+    };
+
+    match handler.history.add_follow(&follow, command.user.id.get()) {
+        Ok(()) => EditInteractionResponse::new().content(format!(
+            "✅ 此頻道將於每次更新時收到電力資訊 (語言: {}, 內容: {})",
+            follow.locale.code(),
+            follow.profile.code()
+        )),
+        Err(e) => {
+            error!("Error saving follow for channel {}: {:?}", command.channel_id, e);
+            EditInteractionResponse::new().content("❌ 無法儲存轉發設定")
+        }
+    }
+}
+
+fn run_unfollow(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    match history.remove_follow(command.channel_id.get()) {
+        Ok(true) => EditInteractionResponse::new().content("✅ 已停止在此頻道轉發電力資訊"),
+        Ok(false) => EditInteractionResponse::new().content("ℹ️ 此頻道未設定轉發"),
+        Err(e) => {
+            error!("Error removing follow for channel {}: {:?}", command.channel_id, e);
+            EditInteractionResponse::new().content("❌ 無法移除轉發設定")
+        }
+    }
+}
+
+fn run_numbers(command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let Some(numbers) = string_option(command, "style").and_then(|v| NumberFormat::parse(&v)) else {
+        return EditInteractionResponse::new().content("❌ 未知的數字格式");
+    };
+
+    let config = match history.guild_config(guild_id.get()) {
+        Ok(config) => GuildConfig { numbers, ..config },
+        Err(e) => {
+            error!("Error reading config for guild {}: {:?}", guild_id, e);
+            return EditInteractionResponse::new().content("❌ 無法讀取伺服器設定");
+        }
+    };
+    match history.save_guild_config(&config) {
+        Ok(()) => EditInteractionResponse::new().content(format!(
+            "✅ 此伺服器的報告與圖表將使用此格式，例如 {}、{}",
+            numbers.wan_kw(2981.4),
+            numbers.percent(12.34, 2)
+        )),
+        Err(e) => {
+            error!("Error saving number format for guild {}: {:?}", guild_id, e);
+            EditInteractionResponse::new().content("❌ 無法儲存數字格式")
+        }
+    }
+}
+
+async fn run_config(ctx: &Context, command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let history = &handler.history;
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let options = command.data.options();
+    let Some(ResolvedValue::SubCommand(sub_options)) = options.first().map(|o| &o.value) else {
+        return EditInteractionResponse::new().content("❌ 未知的指令");
+    };
+    if options[0].name == "reload" {
+        return reload_settings(ctx, command, handler).await;
+    }
+    let mut config = match history.guild_config(guild_id.get()) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Error reading config for guild {}: {:?}", guild_id, e);
+            return EditInteractionResponse::new().content("❌ 無法讀取伺服器設定");
+        }
+    };
+
+    let reply = match options[0].name {
+        "channel" => {
+            let thread_id = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::String(value) if opt.name == "thread_id" => Some(value),
+                _ => None,
+            });
+            // Archived threads don't show up in the picker, so they can be given by ID; the bot
+            // unarchives and joins them when posting
+            let channel_id = match thread_id {
+                Some(value) => match value.trim().parse::<u64>().ok().filter(|id| *id != 0) {
+                    Some(id) => ChannelId::new(id),
+                    None => return EditInteractionResponse::new().content(format!("❌ 無效的討論串 ID: {}", value)),
+                },
+                None => sub_options
+                    .iter()
+                    .find_map(|opt| match opt.value {
+                        ResolvedValue::Channel(channel) if opt.name == "channel" => Some(channel.id),
+                        _ => None,
+                    })
+                    .unwrap_or(command.channel_id),
+            };
+            config.channel_id = Some(channel_id.get());
+            format!("✅ 定時電力資訊將發布到 <#{}>", channel_id)
+        }
+        "stop" => {
+            config.channel_id = None;
+            "✅ 已停止在此伺服器發布定時電力資訊".to_string()
+        }
+        "interval" => {
+            let minutes = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::Integer(minutes) if opt.name == "minutes" => Some(minutes),
+                _ => None,
+            });
+            let Some(minutes) = minutes else {
+                return EditInteractionResponse::new().content("❌ 請提供間隔分鐘數");
+            };
+            // Data refreshes every 10 minutes, so finer intervals would just repeat the last report
+            config.interval_minutes = ((minutes.clamp(10, 1440) + 5) / 10 * 10) as u32;
+            format!("✅ 發布間隔已設為 {} 分鐘", config.interval_minutes)
+        }
+        "mode" => {
+            let mode = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::String(value) if opt.name == "mode" => ReportMode::parse(value),
+                _ => None,
+            });
+            let Some(mode) = mode else {
+                return EditInteractionResponse::new().content("❌ 未知的發布方式");
+            };
+            config.mode = Some(mode);
+            match mode {
+                ReportMode::Post => "✅ 每次更新將發送新訊息".to_string(),
+                ReportMode::Live => "✅ 將置頂一則電力資訊並於每次更新時編輯 (需要管理訊息權限才能置頂)".to_string(),
+                ReportMode::Dual => {
+                    // A full post every 10 minutes next to the live line would defeat the point
+                    if config.interval_minutes == DEFAULT_REPORT_INTERVAL_MINUTES {
+                        config.interval_minutes = 60;
+                    }
+                    format!(
+                        "✅ 將置頂一則精簡狀態並於每次更新時編輯，另每 {} 分鐘發送完整報告 (可用 /config interval 調整)",
+                        config.interval_minutes
+                    )
+                }
+            }
+        }
+        "format" => {
+            let value = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::String(value) if opt.name == "format" => Some(value),
+                _ => None,
+            });
+            config.format = match value {
+                Some("default") => None,
+                Some(value) => match ReportFormat::parse(value) {
+                    Some(format) => Some(format),
+                    None => return EditInteractionResponse::new().content("❌ 未知的報告格式"),
+                },
+                None => return EditInteractionResponse::new().content("❌ 請選擇報告格式"),
+            };
+            match config.format {
+                Some(ReportFormat::Accessible) => "✅ 報告將使用無障礙格式：不含表情符號、標籤在前並標示單位".to_string(),
+                Some(format) => format!("✅ 報告格式已設為 {}", format.code()),
+                None => "✅ 報告格式將依機器人預設".to_string(),
+            }
+        }
+        "alert-role" => {
+            config.alert_role_id = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::Role(role) if opt.name == "role" => Some(role.id.get()),
+                _ => None,
+            });
+            match config.alert_role_id {
+                Some(role_id) => format!("✅ 供電吃緊時將提及 <@&{}>", role_id),
+                None => "✅ 供電吃緊時將不提及任何身分組".to_string(),
+            }
+        }
+        "digest-csv" => {
+            config.digest_csv = sub_options.iter().any(|opt| opt.name == "enabled" && matches!(opt.value, ResolvedValue::Boolean(true)));
+            if config.digest_csv {
+                "✅ 每日摘要將附上當日每筆紀錄的 CSV 檔 (時間、用電、備轉容量率、各能源發電量)".to_string()
+            } else {
+                "✅ 每日摘要將不再附上 CSV 檔".to_string()
+            }
+        }
+        "ticker" => {
+            config.ticker_channel_id = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::Channel(channel) if opt.name == "channel" => Some(channel.id.get()),
+                _ => None,
+            });
+            match config.ticker_channel_id {
+                Some(channel_id) => format!("✅ <#{}> 將顯示目前的備轉容量率 (每 5 分鐘最多改名一次，需要管理頻道權限)", channel_id),
+                None => "✅ 已停用備轉容量率頻道名稱".to_string(),
+            }
+        }
+        "poll" => {
+            config.supply_poll = sub_options.iter().any(|opt| opt.name == "enabled" && matches!(opt.value, ResolvedValue::Boolean(true)));
+            if config.supply_poll {
+                "✅ 預估橘燈以上的日子將在報告頻道發起投票，結果於每日摘要公布".to_string()
+            } else {
+                "✅ 將不再發起供電警戒投票".to_string()
+            }
+        }
+        "sections" => {
+            let list = sub_options.iter().find_map(|opt| match opt.value {
+                ResolvedValue::String(value) if opt.name == "list" => Some(value),
+                _ => None,
+            });
+            let Some(sections) = list.and_then(ReportSections::parse) else {
+                return EditInteractionResponse::new()
+                    .content(format!("❌ 未知的報告內容，可用: {} 或 all", ReportSections::NAMES.join(", ")));
+            };
+            config.sections = sections;
+            format!("✅ 報告內容已設為 {}", sections.code())
+        }
+        _ => return EditInteractionResponse::new().embed(config_embed(command.channel_id, &config, handler, settings)).allowed_mentions(CreateAllowedMentions::new()),
+    };
+
+    match history.save_guild_config(&config) {
+        Ok(()) => EditInteractionResponse::new().content(reply),
+        Err(e) => {
+            error!("Error saving config for guild {}: {:?}", guild_id, e);
+            EditInteractionResponse::new().content("❌ 無法儲存伺服器設定")
+        }
+    }
+}
+
+/// `/config reload`: re-read config.toml and hand the result to the update loop, which takes it up
+/// before its next cycle without reconnecting. The settings are bot-wide, so only the owner or the
+/// admin channel may
+async fn reload_settings(ctx: &Context, command: &CommandInteraction, handler: &Handler) -> EditInteractionResponse {
+    if handler.admin.owner_id != Some(command.user.id) && handler.admin.channel_id != Some(command.channel_id) {
+        return EditInteractionResponse::new().content("❌ 只有機器人擁有者或在管理頻道中才能重新載入設定");
+    }
+    let changed = match config::reload() {
+        Ok(changed) => changed,
+        Err(errors) => {
+            let errors: Vec<String> = errors.iter().map(|e| format!("• {}", e)).collect();
+            return EditInteractionResponse::new().content(format!("❌ 設定有誤，仍沿用目前的設定:\n{}", errors.join("\n")));
+        }
+    };
+    match Config::from_env() {
+        Ok(settings) => {
+            let shared = super::shared_config(ctx).await;
+            let mut current = shared.write().unwrap();
+            *current = current.reloaded(settings);
+        }
+        Err(errors) => {
+            let errors: Vec<String> = errors.iter().map(|e| format!("• {}", e)).collect();
+            return EditInteractionResponse::new().content(format!("❌ 設定有誤，仍沿用目前的設定:\n{}", errors.join("\n")));
+        }
+    }
+    info!("Settings reloaded by {}: {:?}", command.user.id, changed);
+
+    if changed.is_empty() {
+        return EditInteractionResponse::new().content("✅ 已重新載入設定，沒有變更");
+    }
+    let (later, now): (Vec<&str>, Vec<&str>) = changed.iter().partition(|name| config::RESTART_REQUIRED.contains(name));
+    let mut lines = vec!["✅ 已重新載入設定".to_string()];
+    if !now.is_empty() {
+        lines.push(format!("下一輪更新起生效: {}", now.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ")));
+    }
+    if !later.is_empty() {
+        lines.push(format!("⚠️ 需重新啟動才會生效: {}", later.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ")));
+    }
+    EditInteractionResponse::new().content(lines.join("\n"))
+}
+
+/// Where an effective setting in `/config show` comes from
+#[derive(Clone, Copy)]
+enum ConfigSource {
+    Default,
+    /// Bot-wide, from an environment variable or config.toml
+    Env(&'static str),
+    Guild,
+    Channel,
+}
+
+impl ConfigSource {
+    fn label(self) -> String {
+        match self {
+            ConfigSource::Default => "預設".to_string(),
+            ConfigSource::Env(name) => match config::file_setting(name) {
+                Some(key) => format!("config.toml {}", key),
+                None => format!("機器人設定 {}", name),
+            },
+            ConfigSource::Guild => "伺服器設定".to_string(),
+            ConfigSource::Channel => "頻道設定".to_string(),
+        }
+    }
+
+    /// `Env(name)` when the variable is set, else the default
+    fn env_or_default(name: &'static str) -> Self {
+        if config::var(name).is_ok_and(|v| !v.trim().is_empty()) { ConfigSource::Env(name) } else { ConfigSource::Default }
+    }
+}
+
+/// The settings in effect for `channel_id`, each with the layer it came from: built-in defaults,
+/// then the bot's environment, then `/config`, then per-channel routes and `/mentions`
+fn config_embed(channel_id: ChannelId, config: &GuildConfig, handler: &Handler, settings: &Config) -> CreateEmbed {
+    let history = &handler.history;
+    let route = settings.channels.iter().find(|(id, _)| *id == channel_id).map(|(_, content)| *content);
+    let field = |value: String, source: ConfigSource| format!("{}\n-# {}", value, source.label());
+
+    let (destination, destination_source) = match (route, config.channel_id) {
+        (Some(content), _) => (format!("<#{}> ({})", channel_id, content.code()), ConfigSource::Env("CHANNEL_ID")),
+        (None, Some(id)) => (format!("<#{}>", id), ConfigSource::Guild),
+        (None, None) => ("未設定".to_string(), ConfigSource::Default),
+    };
+    let mode_source = if config.mode.is_some() { ConfigSource::Guild } else { ConfigSource::env_or_default("REPORT_MODE") };
+    let format_source = if config.format.is_some() { ConfigSource::Guild } else { ConfigSource::env_or_default("REPORT_FORMAT") };
+    let format = config.format.unwrap_or(settings.report_format);
+    let interval_source = if config.interval_minutes != DEFAULT_REPORT_INTERVAL_MINUTES { ConfigSource::Guild } else { ConfigSource::Default };
+    let (sections, sections_source) = match route {
+        Some(content) if content.sections(config.sections) != config.sections => (content.sections(config.sections), ConfigSource::Channel),
+        _ if config.sections != ReportSections::default() => (config.sections, ConfigSource::Guild),
+        _ => (config.sections, ConfigSource::Default),
+    };
+    let numbers_source = if config.numbers != NumberFormat::default() { ConfigSource::Guild } else { ConfigSource::Default };
+
+    let thresholds = match crate::config::var("ALERT_SENSITIVITY").ok().filter(|v| !v.trim().is_empty()) {
+        Some(rules) => field(format!("依時段: `{}`", rules.trim()), ConfigSource::Env("ALERT_SENSITIVITY")),
+        None => field("橘燈以上提及".to_string(), ConfigSource::Default),
+    };
+    let cooldown = crate::config::var("ALERT_COOLDOWN_MINUTES").ok().and_then(|v| v.trim().parse::<u64>().ok());
+    let cooldown = field(
+        format!("{} 分鐘", cooldown.unwrap_or(60)),
+        if cooldown.is_some() { ConfigSource::Env("ALERT_COOLDOWN_MINUTES") } else { ConfigSource::Default },
+    );
+
+    let role = match (config.alert_role_id, settings.alert_role_id.filter(|_| route.is_some())) {
+        (Some(id), _) => field(format!("<@&{}>", id), ConfigSource::Guild),
+        (None, Some(id)) => field(format!("<@&{}>", id), ConfigSource::Env("ALERT_ROLE_ID")),
+        (None, None) => field("無".to_string(), ConfigSource::Default),
+    };
+    let mention = match history.mention_target(channel_id.get()) {
+        Ok(Some(target)) => {
+            let who: Vec<String> = [target.role_id.map(|id| format!("<@&{}>", id)), target.user_id.map(|id| format!("<@{}>", id))]
+                .into_iter()
+                .flatten()
+                .collect();
+            field(format!("{} {}", target.policy.code(), who.join(" ")), ConfigSource::Channel)
+        }
+        Ok(None) => field("never".to_string(), ConfigSource::Default),
+        Err(e) => {
+            error!("Error reading mention policy: {:?}", e);
+            "❓".to_string()
+        }
+    };
+    let ladder = match history.ping_ladder(config.guild_id) {
+        Ok(rungs) if !rungs.is_empty() => field(
+            rungs.iter().map(|rung| format!("< {}% {}", rung.below, rung.mention.display())).collect::<Vec<_>>().join("\n"),
+            ConfigSource::Guild,
+        ),
+        Ok(_) => field("無".to_string(), ConfigSource::Default),
+        Err(e) => {
+            error!("Error reading ping ladder: {:?}", e);
+            "❓".to_string()
+        }
+    };
+    let guild_flag = |on: bool| field(if on { "是" } else { "否" }.to_string(), if on { ConfigSource::Guild } else { ConfigSource::Default });
+    let ticker = match config.ticker_channel_id {
+        Some(id) => field(format!("<#{}>", id), ConfigSource::Guild),
+        None => field("無".to_string(), ConfigSource::Default),
+    };
+
+    CreateEmbed::new()
+        .title("⚙️ 目前生效的設定")
+        .description(format!("<#{}> 的設定，與各項的來源 (預設 → 機器人設定 → 伺服器設定 → 頻道設定)", channel_id))
+        .field("發布頻道", field(destination, destination_source), true)
+        .field("發布方式", field(config.mode().code().to_string(), mode_source), true)
+        .field("報告格式", field(format.code().to_string(), format_source), true)
+        .field("發布間隔", field(format!("{} 分鐘", config.interval_minutes), interval_source), true)
+        .field("報告內容", field(sections.code(), sections_source), true)
+        .field("數字格式", field(config.numbers.code().to_string(), numbers_source), true)
+        .field("提醒門檻", thresholds, true)
+        .field("提醒冷卻", cooldown, true)
+        .field("供電吃緊提及", role, true)
+        .field("本頻道報告提及", mention, true)
+        .field("備轉率階梯提及", ladder, true)
+        .field("備轉率頻道", ticker, true)
+        .field("摘要附 CSV", guild_flag(config.digest_csv), true)
+        .field("供電警戒投票", guild_flag(config.supply_poll), true)
+}
+
+async fn run_alerts(ctx: &Context, command: &CommandInteraction, handler: &Handler, settings: &Config) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let options = command.data.options();
+    let kind = match options.first().map(|sub| (sub.name, &sub.value)) {
+        Some(("test", ResolvedValue::SubCommand(sub_options))) => sub_options.iter().find_map(|opt| match opt.value {
+            ResolvedValue::String(value) if opt.name == "type" => alerts::TestAlert::parse(value),
+            _ => None,
+        }),
+        _ => None,
+    };
+    let Some(kind) = kind else {
+        return EditInteractionResponse::new().content("❌ 未知的警報類型");
+    };
+
+    info!("Test alert {:?} for guild {} requested by user {}", kind, guild_id, command.user.id);
+    let lines = super::send_test_alert(&ctx.http, handler, settings, guild_id.get(), command.user.id.get(), kind).await;
+    if lines.is_empty() {
+        return EditInteractionResponse::new().content("ℹ️ 此伺服器沒有會收到警報的頻道，請先以 /config channel 設定");
+    }
+    EditInteractionResponse::new()
+        .content(format!("🧪 已發送測試警報:\n{}", lines.join("\n")))
+        .allowed_mentions(CreateAllowedMentions::new())
+}
+
+async fn run_purge_data(ctx: &Context, command: &CommandInteraction, history: &History) -> EditInteractionResponse {
+    let Some(guild_id) = command.guild_id else {
+        return EditInteractionResponse::new().content("❌ 此指令只能在伺服器頻道中使用");
+    };
+    let confirmed = command
+        .data
+        .options()
+        .into_iter()
+        .any(|opt| opt.name == "confirm" && matches!(opt.value, ResolvedValue::Boolean(true)));
+    if !confirmed {
+        return EditInteractionResponse::new().content("ℹ️ 已取消，未刪除任何資料");
+    }
+
+    // Channels the database doesn't link to the guild (e.g. mention policies set before guild
+    // IDs were recorded) can only be found by listing the guild's channels
+    let channel_ids: Vec<u64> = match guild_id.channels(&ctx.http).await {
+        Ok(channels) => channels.keys().map(|id| id.get()).collect(),
+        Err(e) => {
+            error!("Error listing channels for guild {}: {:?}", guild_id, e);
+            Vec::new()
+        }
+    };
+    match history.purge_guild(guild_id.get(), &channel_ids) {
+        Ok(deleted) => {
+            info!("Purged {} rows for guild {} at the request of user {}", deleted, guild_id, command.user.id);
+            EditInteractionResponse::new().content(format!(
+                "🗑️ 已刪除此伺服器的所有設定與資料 ({} 筆)。個人的 /push 推播請各自以 /push remove 移除",
+                deleted
+            ))
+        }
+        Err(e) => {
+            error!("Error purging data for guild {}: {:?}", guild_id, e);
+            EditInteractionResponse::new().content("❌ 無法刪除資料")
+        }
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    ["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(value, fmt).ok())
+}
+
+/// "2024-07-02 14:30", also with slashes or a "T" separator
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim().replace('T', " ");
+    let (date, time) = value.split_once(' ')?;
+    Some(parse_date(date)?.and_time(NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?))
+}
+
+/// "6h", "24h", "7d", "30d" → duration
+fn parse_window(value: &str) -> Option<Duration> {
+    let value = value.trim().to_ascii_lowercase();
+    let (amount, unit) = value.split_at(value.len().checked_sub(1)?);
+    let amount: i64 = amount.parse().ok().filter(|n| *n > 0)?;
+    match unit {
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        "w" => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+//! Previous/next buttons for listings too long for one embed. A command renders all its pages
+//! up front and hands them to the `Pager`, which keeps them keyed by the interaction for
+//! PAGE_TTL, so flipping pages shows the same snapshot instead of refetching.
+
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    EditInteractionResponse,
+};
+use serenity::model::application::{ButtonStyle, ComponentInteraction};
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::error;
+
+const PREFIX: &str = "page:";
+/// How long the buttons keep working after the command
+const PAGE_TTL: Duration = Duration::from_secs(15 * 60);
+/// Embed descriptions are capped at 4096 characters
+const DESCRIPTION_LIMIT: usize = 4000;
+
+/// Split `lines` into page descriptions of at most `per_page` lines each, every page starting
+/// with `header`
+pub fn split(header: &str, lines: &[String], per_page: usize) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = header.to_string();
+    let mut count = 0;
+    for line in lines {
+        if count > 0 && (count == per_page || page.chars().count() + line.chars().count() + 1 > DESCRIPTION_LIMIT) {
+            pages.push(std::mem::replace(&mut page, header.to_string()));
+            count = 0;
+        }
+        page.push('\n');
+        page.push_str(line);
+        count += 1;
+    }
+    pages.push(page);
+    pages
+}
+
+struct Paged {
+    pages: Vec<CreateEmbed>,
+    created_at: Instant,
+}
+
+/// Paged replies by interaction ID
+#[derive(Default)]
+pub struct Pager {
+    replies: Mutex<HashMap<u64, Paged>>,
+}
+
+impl Pager {
+    /// The first page, with buttons when there are more
+    pub fn reply(&self, interaction_id: u64, pages: Vec<CreateEmbed>) -> EditInteractionResponse {
+        let count = pages.len();
+        let first = pages.first().cloned().unwrap_or_default();
+        if count <= 1 {
+            return EditInteractionResponse::new().embed(first);
+        }
+
+        let mut replies = self.replies.lock().unwrap();
+        replies.retain(|_, paged| paged.created_at.elapsed() < PAGE_TTL);
+        replies.insert(interaction_id, Paged { pages, created_at: Instant::now() });
+        EditInteractionResponse::new()
+            .embed(with_page_number(first, 0, count))
+            .components(vec![buttons(interaction_id, 0, count)])
+    }
+
+    /// Turn the page if `component` is one of our buttons; false if it belongs to something else
+    pub async fn handle(&self, ctx: &Context, component: &ComponentInteraction) -> bool {
+        let Some(target) = component.data.custom_id.strip_prefix(PREFIX) else {
+            return false;
+        };
+        let Some((id, page)) = target.split_once(':').and_then(|(id, page)| Some((id.parse::<u64>().ok()?, page.parse::<usize>().ok()?)))
+        else {
+            return true;
+        };
+
+        let turned = {
+            let replies = self.replies.lock().unwrap();
+            replies.get(&id).filter(|paged| paged.created_at.elapsed() < PAGE_TTL).and_then(|paged| {
+                let embed = paged.pages.get(page)?.clone();
+                Some((with_page_number(embed, page, paged.pages.len()), buttons(id, page, paged.pages.len())))
+            })
+        };
+        let response = match turned {
+            Some((embed, row)) => {
+                CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new().embed(embed).components(vec![row]))
+            }
+            None => CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new().content("⌛ 這份列表已過期，請重新執行指令").ephemeral(true),
+            ),
+        };
+        if let Err(why) = component.create_response(&ctx.http, response).await {
+            error!("Error turning page: {:?}", why);
+        }
+        true
+    }
+}
+
+fn with_page_number(embed: CreateEmbed, page: usize, count: usize) -> CreateEmbed {
+    embed.footer(CreateEmbedFooter::new(format!("第 {}/{} 頁", page + 1, count)))
+}
+
+fn buttons(id: u64, page: usize, count: usize) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}{}:{}", PREFIX, id, page.saturating_sub(1)))
+            .label("◀ 上一頁")
+            .style(ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(format!("{}{}:{}", PREFIX, id, page + 1))
+            .label("下一頁 ▶")
+            .style(ButtonStyle::Secondary)
+            .disabled(page + 1 >= count),
+    ])
+}
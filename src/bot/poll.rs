@@ -0,0 +1,132 @@
+//! `/config poll`: on days Taipower forecasts orange or worse, a Discord poll asking whether the
+//! day will actually see a supply warning, answered in the daily digest from the last figures
+//! recorded that day.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serenity::builder::{CreateAllowedMentions, CreateMessage, CreatePoll, CreatePollAnswer};
+use serenity::http::Http;
+use serenity::model::channel::Poll;
+use serenity::model::id::{ChannelId, MessageId};
+use tracing::error;
+
+use crate::config::Config;
+use crate::history::History;
+use crate::locale::{NumberFormat, ZH_TW};
+use crate::render::{indicator_emoji, indicator_label};
+use crate::taipower_api::{LoadData, ReserveIndicator};
+
+const QUESTION: &str = "今天會不會出現供電警戒?";
+
+/// Forecast orange or worse, the peak still ahead and the digest that answers it not yet posted
+pub fn due(load_data: &LoadData, now: NaiveDateTime, config: &Config) -> bool {
+    config.daily_digest
+        && load_data.forecast_peak_reserve_indicator.is_critical()
+        && load_data.forecast_peak_hour_range.is_none_or(|(_, end)| now.time() < end)
+        && config.digest_time.is_none_or(|time| now.time() < time)
+}
+
+/// The poll closes when the digest goes out: DIGEST_TIME, or else midnight
+fn closes_at(now: NaiveDateTime, digest_time: Option<NaiveTime>) -> NaiveDateTime {
+    match digest_time {
+        Some(time) if now.time() < time => now.date().and_time(time),
+        _ => (now.date() + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap(),
+    }
+}
+
+pub fn message(now: NaiveDateTime, digest_time: Option<NaiveTime>) -> CreateMessage {
+    // Discord counts durations in whole hours, rounded down
+    let hours = (closes_at(now, digest_time) - now).num_hours().max(1) as u64;
+    let poll = CreatePoll::new()
+        .question(QUESTION)
+        .answers(vec![
+            CreatePollAnswer::new().text("會").emoji("🟠".to_string()),
+            CreatePollAnswer::new().text("不會").emoji("🟢".to_string()),
+        ])
+        .duration(std::time::Duration::from_secs(hours * 3600));
+    CreateMessage::new()
+        .content("🗳️ 今天預估備轉容量率偏低，猜猜看會不會亮橘燈? 結果將在每日摘要公布")
+        .poll(poll)
+        .allowed_mentions(CreateAllowedMentions::new())
+}
+
+/// The digest line for the guild's poll on `date`, if it was posted in `channel_id`
+pub async fn outcome(http: &Http, history: &History, guild_id: u64, channel_id: ChannelId, date: NaiveDate, numbers: NumberFormat) -> Option<String> {
+    let message_id = match history.supply_poll(guild_id, date) {
+        Ok(Some((poll_channel, message_id))) if poll_channel == channel_id.get() => MessageId::new(message_id),
+        Ok(_) => return None,
+        Err(why) => {
+            error!("Error reading supply poll for guild {}: {:?}", guild_id, why);
+            return None;
+        }
+    };
+    let day = match history.snapshots_between(date.and_hms_opt(0, 0, 0).unwrap(), date.and_hms_opt(23, 59, 59).unwrap()) {
+        Ok(samples) => samples,
+        Err(why) => {
+            error!("Error reading history for supply poll: {:?}", why);
+            return None;
+        }
+    };
+    // By evening the published figures have caught up with what happened at the peak
+    let last = day.iter().rev().find(|s| s.indicator.as_deref().is_some_and(|code| !code.is_empty()))?;
+    let indicator = ReserveIndicator::from_code(last.indicator.as_deref().unwrap_or_default());
+    let poll = final_poll(http, channel_id, message_id).await;
+    Some(outcome_line(poll.as_deref(), indicator, last.reserve_rate, numbers))
+}
+
+/// The poll with its counts, closed first if it's still open so they are final
+async fn final_poll(http: &Http, channel_id: ChannelId, message_id: MessageId) -> Option<Box<Poll>> {
+    let message = match channel_id.message(http, message_id).await {
+        Ok(message) => message,
+        Err(why) => {
+            error!("Error reading supply poll {} in {}: {:?}", message_id, channel_id, why);
+            return None;
+        }
+    };
+    if message.poll.as_ref().and_then(|p| p.results.as_ref()).is_none_or(|r| r.is_finalized) {
+        return message.poll;
+    }
+    match channel_id.end_poll(http, message_id).await {
+        Ok(ended) => ended.poll,
+        Err(why) => {
+            error!("Error ending supply poll {} in {}: {:?}", message_id, channel_id, why);
+            message.poll
+        }
+    }
+}
+
+fn outcome_line(poll: Option<&Poll>, indicator: ReserveIndicator, reserve_rate: Option<f64>, numbers: NumberFormat) -> String {
+    let warned = indicator.is_critical();
+    let mut line = format!(
+        "🗳️ **{}** 結果: {} {}，{}出現供電警戒",
+        QUESTION,
+        indicator_emoji(indicator),
+        indicator_label(indicator, &ZH_TW),
+        if warned { "有" } else { "沒有" }
+    );
+    if let Some(rate) = reserve_rate {
+        line.push_str(&format!(" (備轉容量率 {})", numbers.percent(rate, 1)));
+    }
+
+    let Some((poll, results)) = poll.and_then(|p| Some((p, p.results.as_ref()?))) else {
+        return line;
+    };
+    let count = |answer: usize| {
+        poll.answers
+            .get(answer)
+            .and_then(|a| results.answer_counts.iter().find(|c| c.id == a.answer_id))
+            .map(|c| c.count)
+            .unwrap_or(0)
+    };
+    let (yes, no) = (count(0), count(1));
+    let total = yes + no;
+    if total == 0 {
+        line.push_str("\n-# 沒有人投票");
+        return line;
+    }
+    let share = |votes: u64| numbers.percent(votes as f64 / total as f64 * 100.0, 0);
+    line.push_str(&format!("\n-# 投票: 會 {} 票 ({}) · 不會 {} 票 ({})", yes, share(yes), no, share(no)));
+    if yes != no {
+        line.push_str(if (yes > no) == warned { " · 多數人猜對了 🎉" } else { " · 多數人猜錯了" });
+    }
+    line
+}
@@ -0,0 +1,51 @@
+//! The bot's activity line ("⚡ 3512萬瓩 | 備轉 10.2%"), so the numbers show in the member list.
+//! Discord only accepts a handful of presence updates a minute per shard, and a cron schedule or
+//! a string of retries can run cycles closer together than that.
+
+use serenity::gateway::ActivityData;
+use serenity::prelude::*;
+use std::time::{Duration, Instant};
+
+/// At most one update per this long
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+pub struct Presence {
+    last: Option<(String, Instant)>,
+}
+
+impl Presence {
+    /// Show `text` unless it's what is already shown or the last update was too recent; a
+    /// skipped update is simply superseded by the next cycle's
+    pub fn update(&mut self, ctx: &Context, text: String) {
+        if !self.due(&text, Instant::now()) {
+            return;
+        }
+        ctx.set_activity(Some(ActivityData::custom(text.clone())));
+        self.last = Some((text, Instant::now()));
+    }
+
+    /// Whether `text` should be shown at `now`
+    fn due(&self, text: &str, now: Instant) -> bool {
+        match &self.last {
+            Some((shown, at)) => shown != text && now.duration_since(*at) >= MIN_INTERVAL,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_are_throttled_and_skip_unchanged_text() {
+        let start = Instant::now();
+        let mut presence = Presence::default();
+        assert!(presence.due("⚡ 3512萬瓩 | 備轉 10.2%", start));
+        presence.last = Some(("⚡ 3512萬瓩 | 備轉 10.2%".to_string(), start));
+        assert!(!presence.due("⚡ 3520萬瓩 | 備轉 10.0%", start + Duration::from_secs(30)));
+        assert!(presence.due("⚡ 3520萬瓩 | 備轉 10.0%", start + MIN_INTERVAL));
+        assert!(!presence.due("⚡ 3512萬瓩 | 備轉 10.2%", start + Duration::from_secs(3600)));
+    }
+}
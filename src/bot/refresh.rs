@@ -0,0 +1,166 @@
+//! The 🔄 button on scheduled reports: re-fetches and edits the report in place. Each user can
+//! press it once per USER_COOLDOWN, and presses within FETCH_MIN_AGE of the last fetch (or of
+//! the update loop's) reuse that data, so the button can't be used to hammer Taipower.
+
+use serenity::builder::{
+    CreateActionRow, CreateAttachment, CreateButton, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::model::application::{ButtonStyle, ComponentInteraction};
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+use crate::chart;
+use crate::clock::taipei_now;
+use crate::config::Config;
+use crate::format::generation_mix_chart;
+use crate::history::GuildConfig;
+use crate::render::ContentProfile;
+use crate::reporting;
+
+use super::snapshot::Snapshot;
+use super::{commands, Handler, ReportTarget};
+
+const CUSTOM_ID: &str = "refresh";
+const USER_COOLDOWN: Duration = Duration::from_secs(60);
+/// Taipower updates every 10 minutes; fetching more often than this only repeats the same data
+const FETCH_MIN_AGE: Duration = Duration::from_secs(2 * 60);
+
+pub fn button() -> CreateActionRow {
+    CreateActionRow::Buttons(vec![CreateButton::new(CUSTOM_ID).label("🔄 重新整理").style(ButtonStyle::Secondary)])
+}
+
+#[derive(Default)]
+pub struct Refresher {
+    pressed: Mutex<HashMap<u64, Instant>>,
+    /// The last fetch made for a press; held while fetching so presses together share one
+    fetched: tokio::sync::Mutex<Option<(Instant, Arc<Snapshot>)>>,
+}
+
+impl Refresher {
+    /// Refresh the report if `component` is our button; false if it belongs to something else
+    pub async fn handle(&self, ctx: &Context, component: &ComponentInteraction, handler: &Handler) -> bool {
+        if component.data.custom_id != CUSTOM_ID {
+            return false;
+        }
+
+        if let Some(wait) = self.press(component.user.id.get(), Instant::now()) {
+            let message = CreateInteractionResponseMessage::new()
+                .content(format!("⏳ 請等 {} 秒後再重新整理", wait.as_secs().max(1)))
+                .ephemeral(true);
+            if let Err(why) = component.create_response(&ctx.http, CreateInteractionResponse::Message(message)).await {
+                error!("Error replying to refresh: {:?}", why);
+            }
+            return true;
+        }
+
+        if let Err(why) = component.create_response(&ctx.http, CreateInteractionResponse::Acknowledge).await {
+            error!("Error deferring refresh: {:?}", why);
+            return true;
+        }
+        let settings = super::current_config(ctx).await;
+        let snapshot = match self.snapshot(handler, &settings).await {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                error!("Error fetching data for refresh: {:?}", e);
+                // The press was held against the cooldown while it ran; a failed one doesn't count
+                self.pressed.lock().unwrap().remove(&component.user.id.get());
+                let message = CreateInteractionResponseFollowup::new().content(reporting::USER_NOTICE).ephemeral(true);
+                if let Err(why) = component.create_followup(&ctx.http, message).await {
+                    error!("Error replying to refresh: {:?}", why);
+                }
+                return true;
+            }
+        };
+
+        // Rendered the way the scheduled report for this channel is
+        let config = match component.guild_id.map(|id| handler.history.guild_config(id.get())) {
+            Some(Ok(config)) => config,
+            Some(Err(why)) => {
+                error!("Error reading guild config: {:?}", why);
+                GuildConfig::new(component.guild_id.map(|id| id.get()).unwrap_or_default())
+            }
+            None => GuildConfig::new(0),
+        };
+        let content = settings
+            .channels
+            .iter()
+            .find(|(id, _)| *id == component.channel_id)
+            .map(|(_, content)| *content)
+            .unwrap_or(ContentProfile::Full);
+        let target = ReportTarget { channel_id: component.channel_id, config, content };
+        let text = target.renderer();
+        let (report, embed, badge) = target.format(settings.report_format).report_parts(&snapshot.data, &text);
+
+        let mut edit = EditInteractionResponse::new().clear_attachments().components(vec![button()]);
+        if let Some(report) = report {
+            edit = edit.content(report);
+        }
+        if let Some(embed) = embed {
+            edit = edit.embed(embed);
+        }
+        if let Some(badge) = badge {
+            edit = edit.new_attachment(badge);
+        }
+        if chart::report_charts_enabled()
+            && let Some(png) = generation_mix_chart(&handler.chart_cache, &snapshot.data, &text)
+        {
+            edit = edit.new_attachment(CreateAttachment::bytes(png, "generation-mix.png"));
+        }
+        if let Err(why) = component.edit_response(&ctx.http, edit).await {
+            error!("Error refreshing report in {}: {:?}", component.channel_id, why);
+        }
+        true
+    }
+
+    /// Hold `user`'s press at `now` against the cooldown; how long they must wait if it's still running
+    fn press(&self, user: u64, now: Instant) -> Option<Duration> {
+        let mut pressed = self.pressed.lock().unwrap();
+        pressed.retain(|_, at| now.duration_since(*at) < USER_COOLDOWN);
+        match pressed.get(&user) {
+            Some(at) => Some(USER_COOLDOWN - now.duration_since(*at)),
+            None => {
+                pressed.insert(user, now);
+                None
+            }
+        }
+    }
+
+    /// The update loop's snapshot or the last press's, if either is recent enough; else a new fetch
+    async fn snapshot(&self, handler: &Handler, settings: &Config) -> Result<Arc<Snapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut fetched = self.fetched.lock().await;
+        if let Some(latest) = handler.snapshots.latest()
+            && (taipei_now() - latest.fetched_at).to_std().is_ok_and(|age| age < FETCH_MIN_AGE)
+        {
+            return Ok(latest);
+        }
+        if let Some((at, snapshot)) = fetched.as_ref()
+            && at.elapsed() < FETCH_MIN_AGE
+        {
+            return Ok(snapshot.clone());
+        }
+        let snapshot = Arc::new(commands::fetch_snapshot(handler, settings).await?);
+        *fetched = Some((Instant::now(), snapshot.clone()));
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_user_waits_out_their_own_cooldown() {
+        let refresher = Refresher::default();
+        let start = Instant::now();
+        assert_eq!(refresher.press(1, start), None);
+        assert_eq!(refresher.press(2, start + Duration::from_secs(5)), None);
+        assert_eq!(refresher.press(1, start + Duration::from_secs(45)), Some(Duration::from_secs(15)));
+        // A refused press doesn't restart the cooldown
+        assert_eq!(refresher.press(1, start + USER_COOLDOWN), None);
+        assert_eq!(refresher.press(2, start + USER_COOLDOWN), Some(Duration::from_secs(5)));
+    }
+}
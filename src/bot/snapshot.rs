@@ -0,0 +1,92 @@
+//! The last cycle's data, shared with slash commands so a burst of queries is answered from
+//! memory instead of each one fetching from Taipower. Only before the first cycle completes is
+//! there anything to fetch, and callers arriving together share that one fetch.
+
+use chrono::{DateTime, FixedOffset};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use crate::analysis::CombinedPowerData;
+use crate::config;
+
+/// Replies from a snapshot older than this say so: a few missed cycles, not just a slow one
+const STALE_AFTER: chrono::Duration = chrono::Duration::minutes(45);
+
+/// CACHE_FALLBACK_MINUTES: how old a snapshot may be and still stand in for an outage; 0 disables
+fn fallback_limit_from_env() -> chrono::Duration {
+    chrono::Duration::minutes(config::var("CACHE_FALLBACK_MINUTES").ok().and_then(|v| v.trim().parse().ok()).unwrap_or(60))
+}
+
+pub struct Snapshot {
+    pub data: CombinedPowerData,
+    /// The forecast peak reserve rate of the load reading before this one, for /reserve's trend
+    pub previous_reserve_rate: Option<f64>,
+    /// When the bot took it (Taipei); the feeds' own timestamps say how fresh Taipower's data is
+    pub fetched_at: DateTime<FixedOffset>,
+    /// When the load reading was taken; earlier than `fetched_at` when a failed load fetch kept
+    /// the previous one
+    pub load_fetched_at: Option<DateTime<FixedOffset>>,
+}
+
+impl Snapshot {
+    pub fn is_stale(&self, now: DateTime<FixedOffset>) -> bool {
+        now - self.fetched_at > STALE_AFTER
+    }
+
+    /// The load and reserve figures are from an earlier cycle than the rest
+    pub fn load_is_carried_over(&self) -> bool {
+        self.data.load_data.is_some() && self.load_fetched_at.is_some_and(|at| at < self.fetched_at)
+    }
+}
+
+#[derive(Default)]
+pub struct SnapshotCache {
+    latest: RwLock<Option<Arc<Snapshot>>>,
+    /// Held while a cold cache is being filled
+    filling: tokio::sync::Mutex<()>,
+}
+
+impl SnapshotCache {
+    /// Replace the latest snapshot. A cycle whose load fetch failed keeps the previous reading,
+    /// along with when it was taken
+    pub fn store(&self, mut snapshot: Snapshot) {
+        let mut latest = self.latest.write().unwrap();
+        if snapshot.data.load_data.is_none()
+            && let Some(previous) = latest.as_ref()
+        {
+            snapshot.data.load_data = previous.data.load_data.clone();
+            snapshot.previous_reserve_rate = previous.previous_reserve_rate;
+            snapshot.load_fetched_at = previous.load_fetched_at;
+        }
+        *latest = Some(Arc::new(snapshot));
+    }
+
+    pub fn latest(&self) -> Option<Arc<Snapshot>> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// The latest snapshot, if it's recent enough to post while Taipower can't be reached
+    pub fn fallback(&self, now: DateTime<FixedOffset>) -> Option<Arc<Snapshot>> {
+        let limit = fallback_limit_from_env();
+        self.latest().filter(|snapshot| limit > chrono::Duration::zero() && now - snapshot.fetched_at <= limit)
+    }
+
+    /// The latest snapshot, or one taken with `fetch` if there is none yet
+    pub async fn get_or_fetch<F, Fut, E>(&self, fetch: F) -> Result<Arc<Snapshot>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Snapshot, E>>,
+    {
+        if let Some(snapshot) = self.latest() {
+            return Ok(snapshot);
+        }
+        let _filling = self.filling.lock().await;
+        // Whoever held the lock before us may have filled it
+        if let Some(snapshot) = self.latest() {
+            return Ok(snapshot);
+        }
+        let snapshot = Arc::new(fetch().await?);
+        *self.latest.write().unwrap() = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+}
@@ -0,0 +1,74 @@
+//! `/config ticker`: a voice channel per guild renamed every cycle to show the reserve rate.
+//! Discord allows a channel two renames per 10 minutes and holds back any past that, so each
+//! channel is renamed at most once per RENAME_INTERVAL, and only when the text changed.
+
+use serenity::builder::EditChannel;
+use serenity::http::Http;
+use serenity::model::id::ChannelId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const RENAME_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Name last given to each ticker channel
+#[derive(Default)]
+pub struct Ticker {
+    renamed: HashMap<ChannelId, (String, Instant)>,
+}
+
+impl Ticker {
+    /// Rename `channel_id` to `name` if it's due; a skipped rename is superseded by the next cycle's
+    pub async fn update(&mut self, http: &Http, channel_id: ChannelId, name: &str) -> serenity::Result<()> {
+        if !self.due(channel_id, name, Instant::now()) {
+            return Ok(());
+        }
+        channel_id.edit(http, EditChannel::new().name(name)).await?;
+        self.renamed.insert(channel_id, (name.to_string(), Instant::now()));
+        Ok(())
+    }
+
+    /// Whether `channel_id` should be renamed to `name` at `now`
+    fn due(&self, channel_id: ChannelId, name: &str, now: Instant) -> bool {
+        match self.renamed.get(&channel_id) {
+            Some((shown, at)) => shown != name && now.duration_since(*at) >= RENAME_INTERVAL,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_stay_within_two_per_ten_minutes() {
+        let channel = ChannelId::new(1);
+        let start = Instant::now();
+        let mut ticker = Ticker::default();
+        let mut renames = Vec::new();
+        // A new name every cycle for an hour
+        for minute in 0..60 {
+            let now = start + Duration::from_secs(minute * 60);
+            let name = format!("🔋 備轉率 {}.0%", minute);
+            if ticker.due(channel, &name, now) {
+                ticker.renamed.insert(channel, (name, now));
+                renames.push(now);
+            }
+        }
+        assert_eq!(renames.len(), 12);
+        for window in renames.windows(3) {
+            assert!(window[2].duration_since(window[0]) >= Duration::from_secs(10 * 60));
+        }
+    }
+
+    #[test]
+    fn unchanged_names_and_other_channels() {
+        let start = Instant::now();
+        let mut ticker = Ticker::default();
+        ticker.renamed.insert(ChannelId::new(1), ("🔋 備轉率 10.2% 🟢".to_string(), start));
+        let later = start + RENAME_INTERVAL;
+        assert!(!ticker.due(ChannelId::new(1), "🔋 備轉率 10.2% 🟢", later));
+        assert!(ticker.due(ChannelId::new(1), "🔋 備轉率 9.8% 🟡", later));
+        assert!(ticker.due(ChannelId::new(2), "🔋 備轉率 10.2% 🟢", start));
+    }
+}
@@ -0,0 +1,90 @@
+//! Summary of what changed while the bot was offline, posted once on startup.
+//!
+//! Nothing was sampled during the gap, so this compares the last stored snapshot with the first
+//! fetch after restart and adds the peak figures Taipower publishes for the day (and yesterday's,
+//! if the gap crossed midnight).
+
+use chrono::{Duration, NaiveDateTime};
+use tracing::error;
+
+use crate::history::{History, SnapshotRow};
+use crate::locale::{NumberFormat, ZH_TW};
+use crate::render::{format_peak_time, indicator_emoji, indicator_label};
+use crate::analysis::CombinedPowerData;
+use crate::taipower_api::ReserveIndicator;
+
+/// Normal cycles are 10 minutes apart; a longer gap means at least one was missed
+const MISSED_CYCLE_GAP: Duration = Duration::minutes(20);
+
+/// The last sample before this run, if it is old enough that cycles were missed
+pub fn offline_since(history: &History, now: NaiveDateTime) -> Option<SnapshotRow> {
+    match history.latest_snapshot() {
+        Ok(last) => last.filter(|last| now - last.recorded_at > MISSED_CYCLE_GAP),
+        Err(e) => {
+            error!("Error reading latest snapshot: {:?}", e);
+            None
+        }
+    }
+}
+
+fn format_gap(gap: Duration) -> String {
+    match (gap.num_days(), gap.num_hours() % 24, gap.num_minutes() % 60) {
+        (0, 0, minutes) => format!("{} 分鐘", minutes),
+        (0, hours, minutes) => format!("{} 小時 {} 分鐘", hours, minutes),
+        (days, hours, _) => format!("{} 天 {} 小時", days, hours),
+    }
+}
+
+/// "before → after" with the signed difference
+fn compare(before: f64, after: f64, format: impl Fn(f64) -> String) -> String {
+    let diff = after - before;
+    format!("{} → {} ({}{})", format(before), format(after), if diff >= 0.0 { "+" } else { "-" }, format(diff.abs()))
+}
+
+pub fn build(last: &SnapshotRow, data: &CombinedPowerData, now: NaiveDateTime, numbers: NumberFormat) -> String {
+    let analysis = &data.power_analysis;
+    let mut lines = vec![format!(
+        "🔌 **離線期間摘要** ({} ~ {}，約 {})",
+        last.recorded_at.format("%m-%d %H:%M"),
+        now.format("%m-%d %H:%M"),
+        format_gap(now - last.recorded_at)
+    )];
+
+    if let Some(load_data) = &data.load_data {
+        if let Some(before) = last.current_load {
+            lines.push(format!("📊 **目前用電量**: {}", compare(before, load_data.current_load, |v| numbers.wan_kw(v))));
+        }
+        if let Some(before) = last.reserve_rate {
+            let from = last.indicator.as_deref().map(ReserveIndicator::from_code).unwrap_or(ReserveIndicator::Unknown);
+            let to = load_data.forecast_peak_reserve_indicator;
+            lines.push(format!(
+                "🔋 **預估尖峰備轉容量率**: {} {} {}",
+                compare(before, load_data.forecast_peak_reserve_rate, |v| numbers.percent(v, 2)),
+                indicator_emoji(from),
+                if from == to { indicator_label(to, &ZH_TW).to_string() } else { format!("→ {} {}", indicator_emoji(to), indicator_label(to, &ZH_TW)) }
+            ));
+        }
+        if last.recorded_at.date() < now.date() {
+            lines.push(format!(
+                "📅 **昨日尖峰用電量**: {} (備轉容量率 {})",
+                numbers.wan_kw(load_data.yesterday_peak_demand_load),
+                numbers.percent(load_data.yesterday_peak_reserve_rate, 2)
+            ));
+        }
+        if load_data.real_hour_max_supply_capacity > 0.0 {
+            lines.push(format!(
+                "⏰ **今日即時最大供電能力**: {} ({})",
+                numbers.wan_kw(load_data.real_hour_max_supply_capacity),
+                format_peak_time(load_data, &ZH_TW)
+            ));
+        }
+    }
+
+    lines.push(format!("⚡ **總發電量**: {}", compare(last.total_generation, analysis.total_generation, |v| numbers.mw(v, 1))));
+    lines.push(format!("🌱 **再生能源**: {}", compare(last.renewable_ratio, analysis.renewable_ratio, |v| numbers.percent(v, 1))));
+    if analysis.fault_count != last.fault_count {
+        lines.push(format!("🔧 **故障機組**: {} → {} 部", last.fault_count, analysis.fault_count));
+    }
+    lines.push("-# 離線期間沒有取樣，以上為離線前最後一筆紀錄與目前資料的比較".to_string());
+    lines.join("\n")
+}
@@ -0,0 +1,373 @@
+use chrono::NaiveDateTime;
+use plotters::coord::types::RangedDateTime;
+use plotters::prelude::*;
+use plotters::style::text_anchor;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::locale::NumberFormat;
+use crate::reporting::payload_fingerprint;
+
+const WIDTH: u32 = 1000;
+const HEIGHT: u32 = 500;
+
+/// Font family for chart text; set CHART_FONT to a CJK font (e.g. "Noto Sans CJK TC") so Chinese labels render
+fn font() -> &'static str {
+    static FONT: OnceLock<String> = OnceLock::new();
+    FONT.get_or_init(|| config::var("CHART_FONT").unwrap_or_else(|_| "sans-serif".to_string()))
+}
+
+const PALETTE: [RGBColor; 5] = [
+    RGBColor(52, 152, 219),
+    RGBColor(230, 126, 34),
+    RGBColor(46, 204, 113),
+    RGBColor(155, 89, 182),
+    RGBColor(231, 76, 60),
+];
+
+/// Slice colours for donut charts; more slices than colours should be merged first
+pub const SLICE_PALETTE: [RGBColor; 8] = [
+    RGBColor(52, 152, 219),
+    RGBColor(230, 126, 34),
+    RGBColor(46, 204, 113),
+    RGBColor(155, 89, 182),
+    RGBColor(231, 76, 60),
+    RGBColor(241, 196, 15),
+    RGBColor(26, 188, 156),
+    RGBColor(149, 165, 166),
+];
+
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(NaiveDateTime, f64)>,
+}
+
+/// Whether reports carry a generation-mix chart (REPORT_CHART, on by default)
+pub fn report_charts_enabled() -> bool {
+    !matches!(config::var("REPORT_CHART").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// Whether embed reports carry a reserve indicator badge as their thumbnail (EMBED_BADGE, on by default)
+pub fn embed_badge_enabled() -> bool {
+    !matches!(config::var("EMBED_BADGE").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// Operator notes (/note) are drawn as labelled vertical lines
+const MARKER_COLOUR: RGBColor = RGBColor(127, 140, 141);
+/// Longer note texts are cut to this many characters on the chart
+const MARKER_LABEL_CHARS: usize = 16;
+
+/// Render one or more time series as a PNG line chart.
+pub fn line_chart(title: &str, y_label: &str, series: &[Series], numbers: NumberFormat) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    line_chart_with_markers(title, y_label, series, &[], numbers)
+}
+
+/// A line chart with a labelled vertical line at each marker inside the plotted time range
+pub fn line_chart_with_markers(
+    title: &str,
+    y_label: &str,
+    series: &[Series],
+    markers: &[(NaiveDateTime, String)],
+    numbers: NumberFormat,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let points = series.iter().flat_map(|s| s.points.iter());
+    let (mut x_min, mut x_max) = (NaiveDateTime::MAX, NaiveDateTime::MIN);
+    let (mut y_min, mut y_max) = (f64::MAX, f64::MIN);
+    for (x, y) in points {
+        x_min = x_min.min(*x);
+        x_max = x_max.max(*x);
+        y_min = y_min.min(*y);
+        y_max = y_max.max(*y);
+    }
+    if x_min >= x_max {
+        return Err("Not enough data points to draw a chart".into());
+    }
+
+    // Leave some headroom so lines don't hug the frame
+    let padding = ((y_max - y_min) * 0.1).max(1.0);
+    let y_range = (y_min - padding).max(0.0)..(y_max + padding);
+    let y_precision = if y_max - y_min < 10.0 { 1 } else { 0 };
+
+    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (font(), 24))
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(RangedDateTime::from(x_min..x_max), y_range.clone())?;
+
+        let span = x_max - x_min;
+        let x_format = if span > chrono::Duration::days(2) { "%m/%d" } else { "%H:%M" };
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|x| x.format(x_format).to_string())
+            .y_label_formatter(&|y| numbers.decimal(*y, y_precision))
+            .y_desc(y_label)
+            .label_style((font(), 14))
+            .draw()?;
+
+        for (i, s) in series.iter().enumerate() {
+            let colour = PALETTE[i % PALETTE.len()];
+            chart
+                .draw_series(LineSeries::new(s.points.iter().copied(), colour.stroke_width(2)))?
+                .label(s.label.clone())
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], colour.stroke_width(2)));
+        }
+
+        for (time, label) in markers.iter().filter(|(time, _)| (x_min..=x_max).contains(time)) {
+            chart.draw_series(LineSeries::new([(*time, y_range.start), (*time, y_range.end)], MARKER_COLOUR.stroke_width(1)))?;
+            let label: String = label.chars().take(MARKER_LABEL_CHARS).collect();
+            let style = TextStyle::from((font(), 13).into_font()).color(&MARKER_COLOUR);
+            chart.draw_series(std::iter::once(Text::new(format!(" {}", label), (*time, y_range.end), style)))?;
+        }
+
+        if series.len() > 1 {
+            chart
+                .configure_series_labels()
+                .background_style(WHITE.mix(0.8))
+                .border_style(BLACK)
+                .label_font((font(), 14))
+                .draw()?;
+        }
+
+        root.present()?;
+    }
+
+    encode_png(&buffer, WIDTH, HEIGHT)
+}
+
+/// Render series as stacked areas (first series at the bottom), e.g. shares adding up to 100%.
+/// All series must be sampled at the same x values.
+pub fn stacked_area_chart(title: &str, y_label: &str, series: &[Series], numbers: NumberFormat) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let xs: Vec<NaiveDateTime> = series.first().map(|s| s.points.iter().map(|(x, _)| *x).collect()).unwrap_or_default();
+    if xs.len() < 2 {
+        return Err("Not enough data points to draw a chart".into());
+    }
+
+    // Running totals: layer i is the sum of series 0..=i
+    let mut layers: Vec<Vec<(NaiveDateTime, f64)>> = Vec::new();
+    for s in series {
+        let below = layers.last();
+        let layer = xs
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let value = s.points.get(i).map(|(_, y)| y.max(0.0)).unwrap_or(0.0);
+                (*x, value + below.map(|b| b[i].1).unwrap_or(0.0))
+            })
+            .collect();
+        layers.push(layer);
+    }
+    let y_max = layers.last().map(|l| l.iter().map(|(_, y)| *y).fold(0.0, f64::max)).unwrap_or(0.0).max(1.0);
+
+    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+
+        let (x_min, x_max) = (xs[0], xs[xs.len() - 1]);
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (font(), 24))
+            .margin(16)
+            .x_label_area_size(40)
+            .y_label_area_size(70)
+            .build_cartesian_2d(RangedDateTime::from(x_min..x_max), 0.0..y_max)?;
+
+        chart
+            .configure_mesh()
+            .x_labels(8)
+            .x_label_formatter(&|x| x.format("%Y/%m").to_string())
+            .y_label_formatter(&|y| numbers.decimal(*y, 0))
+            .y_desc(y_label)
+            .label_style((font(), 14))
+            .draw()?;
+
+        // Top layer first so each lower layer paints over the part of it that isn't its own
+        for (i, (s, layer)) in series.iter().zip(&layers).enumerate().rev() {
+            let colour = PALETTE[i % PALETTE.len()];
+            chart
+                .draw_series(AreaSeries::new(layer.iter().copied(), 0.0, colour.mix(0.85)).border_style(colour.stroke_width(1)))?
+                .label(s.label.clone())
+                .legend(move |(x, y)| Rectangle::new([(x, y - 6), (x + 16, y + 6)], colour.filled()));
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .label_font((font(), 14))
+            .draw()?;
+
+        root.present()?;
+    }
+
+    encode_png(&buffer, WIDTH, HEIGHT)
+}
+
+/// Render labelled values as a PNG donut chart, with a legend listing each slice's value and share
+pub fn donut_chart(title: &str, slices: &[(String, f64)], unit: &str, numbers: NumberFormat) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let sizes: Vec<f64> = slices.iter().map(|(_, v)| v.max(0.0)).collect();
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 {
+        return Err("Nothing to draw".into());
+    }
+    let colours: Vec<RGBColor> = (0..slices.len()).map(|i| SLICE_PALETTE[i % SLICE_PALETTE.len()]).collect();
+    // Small slices are unreadable around the ring, so the legend carries the labels
+    let labels = vec![""; slices.len()];
+
+    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let root = root.titled(title, (font(), 24))?;
+
+        let center = (260, (HEIGHT as i32 - 40) / 2);
+        let radius = 190.0;
+        let mut pie = Pie::new(&center, &radius, &sizes, &colours, &labels);
+        pie.start_angle(-90.0);
+        pie.donut_hole(radius * 0.5);
+        root.draw(&pie)?;
+
+        for (i, ((label, value), colour)) in slices.iter().zip(&colours).enumerate() {
+            let y = 40 + i as i32 * 36;
+            root.draw(&Rectangle::new([(520, y), (540, y + 20)], colour.filled()))?;
+            let text = format!("{}  {} {} ({})", label, numbers.decimal(*value, 1), unit, numbers.percent(value.max(0.0) / total * 100.0, 1));
+            root.draw(&Text::new(text, (552, y), (font(), 18)))?;
+        }
+
+        root.present()?;
+    }
+
+    encode_png(&buffer, WIDTH, HEIGHT)
+}
+
+const BADGE_SIZE: u32 = 128;
+
+/// A small round badge in the indicator colour with `label` (the reserve rate) on it, for embed
+/// thumbnails so the status shows even when the embed is collapsed
+pub fn indicator_badge(colour: (u8, u8, u8), label: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let (r, g, b) = colour;
+    // Dark text on light colours (yellow), white on the rest
+    let luminance = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+    let text_colour = if luminance > 160.0 { BLACK } else { WHITE };
+    let mut buffer = vec![0u8; (BADGE_SIZE * BADGE_SIZE * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (BADGE_SIZE, BADGE_SIZE)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let center = BADGE_SIZE as i32 / 2;
+        root.draw(&Circle::new((center, center), center - 4, RGBColor(r, g, b).filled()))?;
+        let style = TextStyle::from((font(), 34).into_font())
+            .color(&text_colour)
+            .pos(text_anchor::Pos::new(text_anchor::HPos::Center, text_anchor::VPos::Center));
+        root.draw(&Text::new(label.to_string(), (center, center), style))?;
+        root.present()?;
+    }
+    encode_png(&buffer, BADGE_SIZE, BADGE_SIZE)
+}
+
+fn encode_png(rgb: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut png_bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png_bytes, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(rgb)?;
+    }
+    Ok(png_bytes)
+}
+
+/// What a cache lookup found: a Discord CDN URL from an earlier reply, or just the rendered PNG
+pub enum CachedChart {
+    Url(String),
+    Png(Vec<u8>),
+}
+
+struct CacheEntry {
+    png: Vec<u8>,
+    url: Option<String>,
+    created_at: Instant,
+}
+
+/// Rendered charts keyed by (chart name, window, data fingerprint, style), kept for CHART_CACHE_TTL_SECS
+pub struct ChartCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl ChartCache {
+    pub fn new(ttl: Duration) -> Self {
+        ChartCache { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn from_env() -> Self {
+        let ttl = config::var("CHART_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(600);
+        ChartCache::new(Duration::from_secs(ttl))
+    }
+
+    /// Identical data drawn the same way always gets the same key
+    pub fn key(name: &str, window: &str, series: &[Series], numbers: NumberFormat) -> String {
+        let mut data = Vec::new();
+        for s in series {
+            data.extend_from_slice(s.label.as_bytes());
+            for (x, y) in &s.points {
+                data.extend_from_slice(&x.and_utc().timestamp().to_le_bytes());
+                data.extend_from_slice(&y.to_bits().to_le_bytes());
+            }
+        }
+        format!("{}|{}|{}|{}", name, window, payload_fingerprint(&data), Self::style_fingerprint(numbers))
+    }
+
+    /// Key for a chart of labelled values rather than time series
+    pub fn slices_key(name: &str, slices: &[(String, f64)], numbers: NumberFormat) -> String {
+        let mut data = Vec::new();
+        for (label, value) in slices {
+            data.extend_from_slice(label.as_bytes());
+            data.extend_from_slice(&value.to_bits().to_le_bytes());
+        }
+        format!("{}|{}|{}", name, payload_fingerprint(&data), Self::style_fingerprint(numbers))
+    }
+
+    fn style_fingerprint(numbers: NumberFormat) -> String {
+        payload_fingerprint(format!("{}:{}x{}:{}", font(), WIDTH, HEIGHT, numbers.code()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedChart> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(key).filter(|e| e.created_at.elapsed() < self.ttl)?;
+        Some(match &entry.url {
+            Some(url) => CachedChart::Url(url.clone()),
+            None => CachedChart::Png(entry.png.clone()),
+        })
+    }
+
+    /// The rendered PNG itself, for uploads that can't link to an earlier attachment
+    pub fn png(&self, key: &str) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(key).filter(|e| e.created_at.elapsed() < self.ttl).map(|e| e.png.clone())
+    }
+
+    pub fn insert(&self, key: String, png: Vec<u8>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, e| e.created_at.elapsed() < self.ttl);
+        entries.insert(key, CacheEntry { png, url: None, created_at: Instant::now() });
+    }
+
+    /// Remember where Discord hosted the attachment so repeats can link instead of re-uploading
+    pub fn set_url(&self, key: &str, url: String) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(key) {
+            entry.url = Some(url);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Asia::Taipei;
+use std::sync::{Arc, Mutex};
+
+/// Times are kept as `DateTime<FixedOffset>` in Asia/Taipei (UTC+8, no DST since 1979)
+pub fn taipei_now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&Taipei).fixed_offset()
+}
+
+/// Where the update loop, history and scheduler get the time from, so replays and tests can
+/// drive it instead of the system clock
+pub trait Clock: Send + Sync {
+    /// The current Taipei time
+    fn now(&self) -> DateTime<FixedOffset>;
+
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        taipei_now()
+    }
+}
+
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to
+pub struct ManualClock {
+    now: Mutex<DateTime<FixedOffset>>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<FixedOffset>) -> Self {
+        ManualClock { now: Mutex::new(start) }
+    }
+
+    pub fn set(&self, now: DateTime<FixedOffset>) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Notices the first check on a new Taipei day, e.g. to post the digest of the day that ended
+pub struct DayRollover {
+    day: NaiveDate,
+}
+
+impl DayRollover {
+    pub fn new(clock: &dyn Clock) -> Self {
+        DayRollover { day: clock.today() }
+    }
+
+    /// The day that just ended, once per change of date
+    pub fn check(&mut self, clock: &dyn Clock) -> Option<NaiveDate> {
+        let today = clock.today();
+        (today > self.day).then(|| std::mem::replace(&mut self.day, today))
+    }
+}
+
+/// Parse the wall-clock timestamps Taipower publishes (always Asia/Taipei)
+pub fn parse_taipei_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y/%m/%d %H:%M:%S", "%Y/%m/%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+        .and_then(taipei_datetime)
+}
+
+/// A Taipei wall-clock time (as stored in history) pinned to its zone
+pub fn taipei_datetime(naive: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    naive.and_local_timezone(Taipei).single().map(|time| time.fixed_offset())
+}
+
+/// Discord renders `<t:unix:style>` in each reader's own timezone and language: `f` date and
+/// time, `t` time only, `R` relative ("3 minutes ago")
+pub fn discord_timestamp(time: DateTime<FixedOffset>, style: char) -> String {
+    format!("<t:{}:{}>", time.timestamp(), style)
+}
+
+/// "<date and time> (<relative>)"
+pub fn discord_time(time: DateTime<FixedOffset>) -> String {
+    format!("{} ({})", discord_timestamp(time, 'f'), discord_timestamp(time, 'R'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn day_rollover_fires_once_per_taipei_midnight(start in 0i64..4_000_000_000, steps in prop::collection::vec(1i64..2000, 1..200)) {
+            let clock = ManualClock::new(DateTime::from_timestamp(start, 0).unwrap().with_timezone(&Taipei).fixed_offset());
+            let first_day = clock.today();
+            let mut rollover = DayRollover::new(&clock);
+            let mut ended = Vec::new();
+            for minutes in steps {
+                let before = clock.today();
+                clock.set(clock.now() + Duration::minutes(minutes));
+                match rollover.check(&clock) {
+                    Some(day) => {
+                        prop_assert_eq!(day, before);
+                        prop_assert!(clock.today() > before);
+                        ended.push(day);
+                    }
+                    None => prop_assert_eq!(clock.today(), before),
+                }
+            }
+            prop_assert!(ended.windows(2).all(|w| w[0] < w[1]));
+            prop_assert!(ended.first().is_none_or(|day| *day == first_day));
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Settings read from the environment that the bot, `once` and `replay` share, parsed once into a
+//! `Config`. Features that only read a switch when they're set up read it themselves.
+//!
+//! Settings can also come from config.toml, grouped into sections (see config.example.toml).
+//! `var` answers from the file whatever the environment leaves unset or blank, so an environment
+//! variable always overrides it, and `/config reload` can re-read the file while the bot runs.
+
+use chrono::NaiveTime;
+use serenity::model::id::ChannelId;
+use serenity::prelude::TypeMapKey;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::history::UnitHistoryPolicy;
+use crate::render::{self, ContentProfile, ReportFormat};
+use crate::scheduler::{self, CronSchedule};
+use crate::stress::StressWeights;
+use crate::validation::SanityBounds;
+use crate::{alerts, digest, forecast, latency, records, regional, weekly};
+
+pub fn discord_token() -> Option<String> {
+    var("DISCORD_TOKEN").ok()
+}
+
+/// HISTORY_DB_PATH, history.db by default
+pub fn history_path() -> String {
+    var("HISTORY_DB_PATH").unwrap_or_else(|_| "history.db".to_string())
+}
+
+/// REPORT_FORMAT; embeds unless set
+pub fn report_format() -> Result<ReportFormat, String> {
+    match var("REPORT_FORMAT") {
+        Ok(value) => ReportFormat::parse(&value).ok_or_else(|| format!("REPORT_FORMAT: unknown format {:?}", value)),
+        Err(_) => Ok(ReportFormat::Embed),
+    }
+}
+
+/// CHANNEL_ID's routing table; empty when unset
+pub fn channel_routes() -> Result<Vec<(ChannelId, ContentProfile)>, String> {
+    let value = var("CHANNEL_ID").unwrap_or_default();
+    let routes = render::parse_channel_routes(&value).map_err(|why| format!("CHANNEL_ID: {}", why))?;
+    Ok(routes.into_iter().map(|(id, content)| (ChannelId::new(id), content)).collect())
+}
+
+/// A Discord ID setting such as ADMIN_CHANNEL_ID; None when unset or blank
+pub fn discord_id(name: &str) -> Result<Option<u64>, String> {
+    match var(name) {
+        Ok(value) if !value.trim().is_empty() => value.trim().parse().map(Some).map_err(|_| format!("{}: invalid ID {:?}", name, value)),
+        _ => Ok(None),
+    }
+}
+
+/// What the update cycle, its announcements and the commands go by. Parsed once at startup and
+/// again by `/config reload`, never per cycle
+#[derive(Clone, PartialEq)]
+pub struct Config {
+    /// Home channels from CHANNEL_ID and what each receives; guilds can also pick their own with /config
+    pub channels: Vec<(ChannelId, ContentProfile)>,
+    /// Role pinged in the home channel when the reserve indicator turns orange or red (ALERT_ROLE_ID)
+    pub alert_role_id: Option<u64>,
+    pub report_format: ReportFormat,
+    pub interval: Duration,
+    pub schedules: Vec<CronSchedule>,
+    pub cycle_budget: Option<Duration>,
+    pub daily_digest: bool,
+    /// DIGEST_TIME as the bot started with it; `reloaded` keeps it
+    pub digest_time: Option<NaiveTime>,
+    pub weekly_report: bool,
+    pub record_alerts: bool,
+    pub fault_alerts: bool,
+    pub own_forecast: bool,
+    pub sanity_bounds: SanityBounds,
+    pub region_import_warn: f64,
+    pub stress_weights: StressWeights,
+    /// Kept by `reloaded`, like `digest_time`
+    pub unit_history: UnitHistoryPolicy,
+}
+
+impl Config {
+    /// Every setting that doesn't parse, all at once
+    pub fn from_env() -> Result<Self, Vec<String>> {
+        fn check<T>(errors: &mut Vec<String>, result: Result<T, String>) -> Option<T> {
+            result.map_err(|why| errors.push(why)).ok()
+        }
+        let mut errors = Vec::new();
+        let channels = check(&mut errors, channel_routes());
+        let alert_role_id = check(&mut errors, discord_id("ALERT_ROLE_ID"));
+        let report_format = check(&mut errors, report_format());
+        let schedules = check(&mut errors, scheduler::parse_schedules(&var("UPDATE_SCHEDULE").unwrap_or_default()).map_err(|why| format!("UPDATE_SCHEDULE: {}", why)));
+        let digest_time = check(&mut errors, digest::parse_time(&var("DIGEST_TIME").unwrap_or_default()));
+        let (Some(channels), Some(alert_role_id), Some(report_format), Some(schedules), Some(digest_time)) =
+            (channels, alert_role_id, report_format, schedules, digest_time)
+        else {
+            return Err(errors);
+        };
+        Ok(Config {
+            channels,
+            alert_role_id,
+            report_format,
+            interval: scheduler::interval_from_env(),
+            schedules,
+            cycle_budget: latency::budget_from_env(),
+            daily_digest: digest::enabled(),
+            digest_time,
+            weekly_report: weekly::enabled(),
+            record_alerts: records::enabled(),
+            fault_alerts: alerts::FaultWatch::enabled(),
+            own_forecast: forecast::enabled(),
+            sanity_bounds: SanityBounds::from_env(),
+            region_import_warn: regional::import_warn_percent_from_env(),
+            stress_weights: StressWeights::from_env(),
+            unit_history: UnitHistoryPolicy::from_env(),
+        })
+    }
+
+    /// `new` as `/config reload` applies it: the settings in RESTART_REQUIRED stay as they were
+    pub fn reloaded(&self, new: Config) -> Config {
+        Config { digest_time: self.digest_time, unit_history: self.unit_history.clone(), ..new }
+    }
+}
+
+/// Where the bot keeps its `Config` in serenity's TypeMap; `/config reload` replaces it in place
+pub struct ConfigKey;
+
+impl TypeMapKey for ConfigKey {
+    type Value = Arc<RwLock<Config>>;
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    /// Must parse as a number
+    Number,
+    /// Arrays (and tables, as `key=value` pairs) are joined with this separator
+    List(&'static str),
+}
+
+/// config.toml's `[section] key`s and the environment variable each one fills in
+const FILE_KEYS: &[(&str, &str, &str, Kind)] = &[
+    ("discord", "token", "DISCORD_TOKEN", Kind::Text),
+    ("discord", "channels", "CHANNEL_ID", Kind::List(",")),
+    ("discord", "admin_channel_id", "ADMIN_CHANNEL_ID", Kind::Text),
+    ("discord", "owner_id", "OWNER_ID", Kind::Text),
+    ("discord", "alert_role_id", "ALERT_ROLE_ID", Kind::Text),
+    ("discord", "shutdown_notice", "SHUTDOWN_NOTICE", Kind::Text),
+    ("discord", "pushover_app_token", "PUSHOVER_APP_TOKEN", Kind::Text),
+    ("schedule", "interval_minutes", "UPDATE_INTERVAL_MINUTES", Kind::Number),
+    ("schedule", "cron", "UPDATE_SCHEDULE", Kind::List("; ")),
+    ("schedule", "cycle_budget_seconds", "CYCLE_BUDGET_SECONDS", Kind::Number),
+    ("report", "format", "REPORT_FORMAT", Kind::Text),
+    ("report", "mode", "REPORT_MODE", Kind::Text),
+    ("report", "chart", "REPORT_CHART", Kind::Text),
+    ("report", "chart_font", "CHART_FONT", Kind::Text),
+    ("report", "chart_cache_ttl_secs", "CHART_CACHE_TTL_SECS", Kind::Number),
+    ("report", "embed_badge", "EMBED_BADGE", Kind::Text),
+    ("report", "fuel_groups", "FUEL_GROUPS", Kind::List(";")),
+    ("report", "fuel_order", "FUEL_ORDER", Kind::List(",")),
+    ("report", "stale_warning_minutes", "STALE_WARNING_MINUTES", Kind::Number),
+    ("report", "cache_fallback_minutes", "CACHE_FALLBACK_MINUTES", Kind::Number),
+    ("report", "region_import_warn_percent", "REGION_IMPORT_WARN_PERCENT", Kind::Number),
+    ("report", "maintenance_major_unit_mw", "MAINTENANCE_MAJOR_UNIT_MW", Kind::Number),
+    ("report", "daily_digest", "DAILY_DIGEST", Kind::Text),
+    ("report", "digest_time", "DIGEST_TIME", Kind::Text),
+    ("report", "digest_stats", "DIGEST_STATS", Kind::Text),
+    ("report", "weekly_report", "WEEKLY_REPORT", Kind::Text),
+    ("report", "weekly_report_hour", "WEEKLY_REPORT_HOUR", Kind::Number),
+    ("alerts", "cooldown_minutes", "ALERT_COOLDOWN_MINUTES", Kind::Number),
+    ("alerts", "ping_ladder_hysteresis", "PING_LADDER_HYSTERESIS", Kind::Number),
+    ("alerts", "sensitivity", "ALERT_SENSITIVITY", Kind::List("; ")),
+    ("alerts", "stress_weights", "STRESS_WEIGHTS", Kind::List(",")),
+    ("alerts", "stress_threshold", "STRESS_ALERT_THRESHOLD", Kind::Number),
+    ("alerts", "records", "RECORD_ALERTS", Kind::Text),
+    ("alerts", "faults", "FAULT_ALERTS", Kind::Text),
+    ("alerts", "freeze_cycles", "FREEZE_ALERT_CYCLES", Kind::Number),
+    ("thresholds", "current_load_min", "SANITY_CURRENT_LOAD_MIN", Kind::Text),
+    ("thresholds", "current_load_max", "SANITY_CURRENT_LOAD_MAX", Kind::Text),
+    ("thresholds", "current_util_rate_min", "SANITY_CURRENT_UTIL_RATE_MIN", Kind::Text),
+    ("thresholds", "current_util_rate_max", "SANITY_CURRENT_UTIL_RATE_MAX", Kind::Text),
+    ("thresholds", "forecast_peak_reserve_rate_min", "SANITY_FORECAST_PEAK_RESERVE_RATE_MIN", Kind::Text),
+    ("thresholds", "forecast_peak_reserve_rate_max", "SANITY_FORECAST_PEAK_RESERVE_RATE_MAX", Kind::Text),
+    ("thresholds", "total_generation_min", "SANITY_TOTAL_GENERATION_MIN", Kind::Text),
+    ("thresholds", "total_generation_max", "SANITY_TOTAL_GENERATION_MAX", Kind::Text),
+    ("thresholds", "renewable_ratio_min", "SANITY_RENEWABLE_RATIO_MIN", Kind::Text),
+    ("thresholds", "renewable_ratio_max", "SANITY_RENEWABLE_RATIO_MAX", Kind::Text),
+    ("thresholds", "solar_output_min", "SANITY_SOLAR_OUTPUT_MIN", Kind::Text),
+    ("thresholds", "solar_output_max", "SANITY_SOLAR_OUTPUT_MAX", Kind::Text),
+    ("endpoints", "taipower_base_url", "TAIPOWER_BASE_URL", Kind::Text),
+    ("endpoints", "maintenance_schedule", "MAINTENANCE_SCHEDULE_URL", Kind::Text),
+    ("endpoints", "demand_response", "DEMAND_RESPONSE_URL", Kind::Text),
+    ("endpoints", "retry_attempts", "HTTP_RETRY_ATTEMPTS", Kind::Number),
+    ("endpoints", "retry_base_ms", "HTTP_RETRY_BASE_MS", Kind::Number),
+    ("forecast", "enabled", "OWN_FORECAST", Kind::Text),
+    ("forecast", "latitude", "WEATHER_LATITUDE", Kind::Number),
+    ("forecast", "longitude", "WEATHER_LONGITUDE", Kind::Number),
+    ("forecast", "holidays", "HOLIDAYS", Kind::List(",")),
+    ("errors", "notice_interval_minutes", "ERROR_NOTICE_INTERVAL_MINUTES", Kind::Number),
+    ("errors", "report_after_failures", "ERROR_REPORT_AFTER_FAILURES", Kind::Number),
+    ("errors", "sentry_dsn", "SENTRY_DSN", Kind::Text),
+    ("errors", "schema_dump_dir", "SCHEMA_DUMP_DIR", Kind::Text),
+    ("storage", "history_db_path", "HISTORY_DB_PATH", Kind::Text),
+    ("storage", "payload_archive_dir", "PAYLOAD_ARCHIVE_DIR", Kind::Text),
+    ("storage", "crash_loop_threshold", "CRASH_LOOP_THRESHOLD", Kind::Number),
+    ("storage", "unit_history", "UNIT_HISTORY", Kind::List(",")),
+    ("storage", "unit_history_min_capacity", "UNIT_HISTORY_MIN_CAPACITY", Kind::Number),
+    ("storage", "unit_history_retention_days", "UNIT_HISTORY_RETENTION_DAYS", Kind::Number),
+    ("standby", "leader_election", "LEADER_ELECTION", Kind::Text),
+    ("standby", "instance_id", "INSTANCE_ID", Kind::Text),
+    ("standby", "lease_seconds", "LEADER_LEASE_SECONDS", Kind::Number),
+    ("server", "dashboard_addr", "DASHBOARD_ADDR", Kind::Text),
+    ("server", "metrics_addr", "METRICS_ADDR", Kind::Text),
+    ("logging", "level", "LOG_LEVEL", Kind::Text),
+    ("logging", "format", "LOG_FORMAT", Kind::Text),
+];
+
+/// Settings read once at startup (or kept in state that outlives a cycle); a reload can't apply them
+pub const RESTART_REQUIRED: &[&str] = &[
+    "DISCORD_TOKEN",
+    "ADMIN_CHANNEL_ID",
+    "OWNER_ID",
+    "HISTORY_DB_PATH",
+    "CRASH_LOOP_THRESHOLD",
+    "LEADER_ELECTION",
+    "INSTANCE_ID",
+    "LEADER_LEASE_SECONDS",
+    "DASHBOARD_ADDR",
+    "METRICS_ADDR",
+    "LOG_LEVEL",
+    "LOG_FORMAT",
+    "SENTRY_DSN",
+    "PAYLOAD_ARCHIVE_DIR",
+    "CHART_FONT",
+    "CHART_CACHE_TTL_SECS",
+    "REPORT_CHART",
+    "DIGEST_TIME",
+    "HTTP_RETRY_ATTEMPTS",
+    "HTTP_RETRY_BASE_MS",
+    "UNIT_HISTORY",
+    "UNIT_HISTORY_MIN_CAPACITY",
+    "UNIT_HISTORY_RETENTION_DAYS",
+    "MAINTENANCE_SCHEDULE_URL",
+    "ERROR_NOTICE_INTERVAL_MINUTES",
+    "ERROR_REPORT_AFTER_FAILURES",
+    "STRESS_ALERT_THRESHOLD",
+    "FREEZE_ALERT_CYCLES",
+    "STALE_WARNING_MINUTES",
+];
+
+/// config.toml's settings by environment variable, with the `section.key` each came from
+type FileSettings = HashMap<&'static str, (String, String)>;
+
+static FILE: RwLock<Option<FileSettings>> = RwLock::new(None);
+
+thread_local! {
+    /// A re-read config.toml that `reload` is validating; `var` on that thread answers from it
+    /// while every other task keeps seeing the installed settings
+    static CANDIDATE: RefCell<Option<Option<FileSettings>>> = const { RefCell::new(None) };
+}
+
+/// CONFIG_PATH, config.toml by default; only a file named explicitly has to exist
+fn config_path() -> (PathBuf, bool) {
+    match env::var("CONFIG_PATH") {
+        Ok(path) if !path.trim().is_empty() => (PathBuf::from(path.trim()), true),
+        _ => (PathBuf::from("config.toml"), false),
+    }
+}
+
+/// A TOML value as an environment variable would spell it; booleans become on/off
+fn env_value(value: &toml::Value, kind: Kind) -> Result<String, String> {
+    let separator = match kind {
+        Kind::List(separator) => separator,
+        _ => ",",
+    };
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Float(n) => Ok(n.to_string()),
+        toml::Value::Boolean(on) => Ok(if *on { "on" } else { "off" }.to_string()),
+        toml::Value::Array(items) if matches!(kind, Kind::List(_)) => {
+            items.iter().map(|item| env_value(item, Kind::List(","))).collect::<Result<Vec<_>, _>>().map(|items| items.join(separator))
+        }
+        toml::Value::Table(entries) if matches!(kind, Kind::List(_)) => entries
+            .iter()
+            .map(|(key, item)| env_value(item, Kind::List(",")).map(|item| format!("{}={}", key, item)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|entries| entries.join(separator)),
+        toml::Value::Datetime(_) => Err("dates and times must be quoted strings".to_string()),
+        _ => Err("expected a single value".to_string()),
+    }
+}
+
+/// The file's settings, None if there's no file, or every problem with it
+fn read_file() -> Result<Option<(PathBuf, FileSettings)>, Vec<String>> {
+    let (path, explicit) = config_path();
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => return Ok(None),
+        Err(e) => return Err(vec![format!("{}: {}", path.display(), e)]),
+    };
+    let table: toml::Table = text.parse().map_err(|e| vec![format!("{}: {}", path.display(), e)])?;
+
+    let mut errors = Vec::new();
+    let mut values = HashMap::new();
+    for (section, entries) in &table {
+        let Some(entries) = entries.as_table() else {
+            errors.push(format!("{}: `{}` must be a [section]", path.display(), section));
+            continue;
+        };
+        for (key, value) in entries {
+            let Some((_, _, name, kind)) = FILE_KEYS.iter().find(|(s, k, _, _)| s == section && k == key) else {
+                errors.push(format!("{}: unknown setting {}.{}", path.display(), section, key));
+                continue;
+            };
+            match env_value(value, *kind) {
+                Ok(value) => {
+                    values.insert(*name, (format!("{}.{}", section, key), value));
+                }
+                Err(why) => errors.push(format!("{}: {}.{}: {}", path.display(), section, key, why)),
+            }
+        }
+    }
+    if errors.is_empty() { Ok(Some((path, values))) } else { Err(errors) }
+}
+
+/// Read config.toml at startup. Returns the file used, if any, or every problem with it
+pub fn load_file() -> Result<Option<PathBuf>, Vec<String>> {
+    let file = read_file()?;
+    let path = file.as_ref().map(|(path, _)| path.clone());
+    *FILE.write().unwrap() = file.map(|(_, values)| values);
+    Ok(path)
+}
+
+/// Re-read config.toml and validate it before installing it, so nothing else ever reads
+/// settings that don't validate. Returns the settings whose value changed
+pub fn reload() -> Result<Vec<&'static str>, Vec<String>> {
+    let values = read_file()?.map(|(_, values)| values);
+    let before: Vec<Option<String>> = FILE_KEYS.iter().map(|(_, _, name, _)| var(name).ok()).collect();
+    CANDIDATE.set(Some(values));
+    let errors = validate();
+    let values = CANDIDATE.take().expect("set above");
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    *FILE.write().unwrap() = values;
+    Ok(FILE_KEYS
+        .iter()
+        .zip(before)
+        .filter(|((_, _, name, _), before)| var(name).ok() != *before)
+        .map(|(&(_, _, name, _), _)| name)
+        .collect())
+}
+
+/// A setting: the environment variable `name` if it's set and not blank, else config.toml's
+/// value for it
+pub fn var(name: &str) -> Result<String, env::VarError> {
+    match env::var(name) {
+        Ok(value) if !value.trim().is_empty() => Ok(value),
+        from_env => {
+            let lookup = |file: &Option<FileSettings>| file.as_ref().and_then(|file| file.get(name)).map(|(_, value)| value.clone());
+            let value = CANDIDATE.with_borrow(|candidate| candidate.as_ref().map(lookup)).unwrap_or_else(|| lookup(&FILE.read().unwrap()));
+            match value {
+                Some(value) => Ok(value),
+                None => from_env,
+            }
+        }
+    }
+}
+
+/// The config.toml `section.key` that `name`'s current value comes from, if it does
+pub fn file_setting(name: &str) -> Option<String> {
+    if env::var(name).is_ok_and(|v| !v.trim().is_empty()) {
+        return None;
+    }
+    FILE.read().unwrap().as_ref()?.get(name).map(|(key, _)| key.clone())
+}
+
+/// Settings the bot can't run without or that would otherwise only fail once used, all at once
+pub fn validate() -> Vec<String> {
+    let mut errors = Vec::new();
+    if discord_token().is_none_or(|token| token.trim().is_empty()) {
+        errors.push("DISCORD_TOKEN must be set".to_string());
+    }
+    errors.extend(report_format().err());
+    errors.extend(channel_routes().err());
+    for name in ["ADMIN_CHANNEL_ID", "OWNER_ID", "ALERT_ROLE_ID"] {
+        errors.extend(discord_id(name).err());
+    }
+    if let Ok(value) = var("UPDATE_SCHEDULE")
+        && let Err(why) = crate::scheduler::parse_schedules(&value)
+    {
+        errors.push(format!("UPDATE_SCHEDULE: {}", why));
+    }
+    if let Ok(value) = var("DIGEST_TIME") {
+        errors.extend(crate::digest::parse_time(&value).err());
+    }
+    if let Ok(value) = var("STRESS_WEIGHTS")
+        && !value.trim().is_empty()
+        && crate::stress::StressWeights::parse(&value).is_none()
+    {
+        errors.push(format!("STRESS_WEIGHTS: expected reserve=5,ramp=2,fault=3, got {:?}", value));
+    }
+    if let Ok(value) = var("ALERT_SENSITIVITY") {
+        for rule in crate::alerts::invalid_sensitivity_rules(&value) {
+            errors.push(format!("ALERT_SENSITIVITY: invalid rule {:?}", rule));
+        }
+    }
+    for (_, _, name, kind) in FILE_KEYS {
+        if let Kind::Number = kind
+            && let Ok(value) = var(name)
+            && !value.trim().is_empty()
+            && value.trim().parse::<f64>().is_err()
+        {
+            errors.push(format!("{}: expected a number, got {:?}", name, value));
+        }
+    }
+    errors
+}
@@ -0,0 +1,131 @@
+//! Crash-loop protection. Each start bumps a counter file next to the history database and
+//! connecting to Discord with the database open clears it, so a count past CRASH_LOOP_THRESHOLD means the last
+//! few starts all died early, most likely on corrupt state. The database is then moved aside
+//! under a timestamped name and the bot starts over with a fresh one instead of staying down.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, warn};
+
+use crate::clock::taipei_now;
+use crate::config;
+
+const DEFAULT_THRESHOLD: u32 = 3;
+
+/// CRASH_LOOP_THRESHOLD: unfinished starts in a row before the state is quarantined; 0 disables
+fn threshold_from_env() -> u32 {
+    config::var("CRASH_LOOP_THRESHOLD").ok().and_then(|v| v.trim().parse().ok()).unwrap_or(DEFAULT_THRESHOLD)
+}
+
+pub struct StartupGuard {
+    /// None when disabled or the database is in memory
+    counter: Option<PathBuf>,
+    cleared: AtomicBool,
+}
+
+impl StartupGuard {
+    /// Count this start; past the threshold, quarantine the database at `history_path`. Returns
+    /// the guard and, if files were moved, a notice for the admin channel
+    pub fn begin(history_path: &str) -> (Self, Option<String>) {
+        Self::begin_with(history_path, threshold_from_env())
+    }
+
+    fn begin_with(history_path: &str, threshold: u32) -> (Self, Option<String>) {
+        if threshold == 0 || history_path == ":memory:" {
+            return (StartupGuard { counter: None, cleared: AtomicBool::new(false) }, None);
+        }
+        let counter = PathBuf::from(format!("{}.startups", history_path));
+        let starts = fs::read_to_string(&counter).ok().and_then(|v| v.trim().parse::<u32>().ok()).unwrap_or(0) + 1;
+
+        let mut notice = None;
+        let starts = if starts > threshold {
+            let moved = quarantine(history_path);
+            warn!("Crash loop detected ({} unfinished starts), quarantined: {:?}", starts - 1, moved);
+            notice = Some(format!(
+                "🩹 **偵測到連續啟動失敗** ({} 次)，已將可能損壞的資料庫移至 {}，以全新狀態繼續執行",
+                starts - 1,
+                if moved.is_empty() { "(無檔案)".to_string() } else { moved.join(", ") }
+            ));
+            1
+        } else {
+            starts
+        };
+        if let Err(why) = fs::write(&counter, starts.to_string()) {
+            error!("Error writing startup counter {}: {:?}", counter.display(), why);
+        }
+        (StartupGuard { counter: Some(counter), cleared: AtomicBool::new(false) }, notice)
+    }
+
+    /// The bot opened its database and connected; later crashes aren't a startup loop
+    pub fn healthy(&self) {
+        if let Some(counter) = &self.counter
+            && !self.cleared.swap(true, Ordering::Relaxed)
+            && let Err(why) = fs::remove_file(counter)
+            && why.kind() != std::io::ErrorKind::NotFound
+        {
+            error!("Error clearing startup counter {}: {:?}", counter.display(), why);
+        }
+    }
+}
+
+/// Rename the database and its WAL files to `<name>.quarantined-<timestamp>`; the new names
+fn quarantine(history_path: &str) -> Vec<String> {
+    let stamp = taipei_now().format("%Y%m%d-%H%M%S");
+    let mut moved = Vec::new();
+    for suffix in ["", "-wal", "-shm"] {
+        let path = format!("{}{}", history_path, suffix);
+        if !Path::new(&path).exists() {
+            continue;
+        }
+        let target = format!("{}.quarantined-{}", path, stamp);
+        match fs::rename(&path, &target) {
+            Ok(()) => moved.push(target),
+            Err(why) => error!("Error quarantining {}: {:?}", path, why),
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfinished_starts_past_the_threshold_quarantine_and_a_healthy_start_resets() {
+        let dir = std::env::temp_dir().join(format!("taipower-crashloop-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let history_path = dir.join("history.db").to_string_lossy().into_owned();
+        let counter = PathBuf::from(format!("{}.startups", history_path));
+        fs::write(&history_path, "corrupt").unwrap();
+
+        for start in 1..=2 {
+            let (_guard, notice) = StartupGuard::begin_with(&history_path, 2);
+            assert!(notice.is_none(), "start {} quarantined", start);
+            assert_eq!(fs::read_to_string(&counter).unwrap(), start.to_string());
+        }
+        let (guard, notice) = StartupGuard::begin_with(&history_path, 2);
+        assert!(notice.is_some_and(|n| n.contains("(2 次)")));
+        assert!(!Path::new(&history_path).exists());
+        assert!(fs::read_dir(&dir).unwrap().any(|e| e.unwrap().file_name().to_string_lossy().starts_with("history.db.quarantined-")));
+        // The fresh start counts as the first
+        assert_eq!(fs::read_to_string(&counter).unwrap(), "1");
+
+        guard.healthy();
+        assert!(!counter.exists());
+        guard.healthy();
+        let (_guard, notice) = StartupGuard::begin_with(&history_path, 2);
+        assert!(notice.is_none());
+        assert_eq!(fs::read_to_string(&counter).unwrap(), "1");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disabled_or_in_memory_keeps_no_counter() {
+        let (guard, notice) = StartupGuard::begin_with(":memory:", 3);
+        assert!(guard.counter.is_none() && notice.is_none());
+        let (guard, _) = StartupGuard::begin_with("unused.db", 0);
+        assert!(guard.counter.is_none());
+    }
+}
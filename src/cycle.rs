@@ -0,0 +1,527 @@
+//! One update cycle: fetch, sanity-check, store, and decide what to announce. The bot's update
+//! loop, `replay` and the soak test all run it on their own clock, with their own `Feeds` for
+//! where the data comes from and their own `Outlet` for where announcements go.
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serenity::async_trait;
+use std::sync::{Arc, RwLock};
+use tracing::{error, field, info, info_span, warn, Instrument, Span};
+
+use crate::alerts::{self, FaultChange, FreezeEvent, IndicatorChange};
+use crate::analysis::{CombinedPowerData, PowerAnalysis};
+use crate::clock::{Clock, DayRollover};
+use crate::config::Config;
+use crate::history::{History, SnapshotRow};
+use crate::latency::{self, Phase};
+use crate::maintenance::{MaintenanceCalendar, Outage};
+use crate::records::RecordBreak;
+use crate::regional::RegionalShare;
+use crate::reporting::{self, ErrorNotices, FailureTracker};
+use crate::stress::StressIndex;
+use crate::taipower_api::{fetch_and_analyze_power_data, fetch_load_data, LoadData, PowerUnit, TaipowerError};
+use crate::trend::Trend;
+use crate::validation::{Metric, Violation};
+use crate::weather::HourlyTemperatures;
+use crate::{catchup, demand_response, forecast, incident, metrics, records, regional, schema_watch, systemd, trend, weather, weekly};
+
+/// What a cycle fetches once generation and load are in
+pub struct Extras {
+    /// Only fetched with a trusted island-wide load to scale them by
+    pub shares: Option<Result<Vec<RegionalShare>, TaipowerError>>,
+    pub temperatures: Option<Result<HourlyTemperatures, Box<dyn std::error::Error + Send + Sync>>>,
+    pub demand_response_mw: Option<f64>,
+}
+
+/// Where a cycle's data comes from
+#[async_trait]
+pub trait Feeds: Send {
+    /// Generation and load, fetched together. Load is None when the source has nothing for it,
+    /// like an archived cycle without a load payload
+    async fn fetch(&mut self) -> (Result<PowerAnalysis, TaipowerError>, Option<Result<LoadData, TaipowerError>>);
+
+    /// Regional shares (when `with_regional`), temperatures (when `with_weather`) and activated
+    /// demand response
+    async fn extras(&mut self, with_regional: bool, with_weather: bool, today: NaiveDate) -> Extras;
+
+    /// Large units offline right now, checked against the maintenance schedule
+    fn outages(&self, _units: &[PowerUnit], _today: NaiveDate) -> Vec<Outage> {
+        Vec::new()
+    }
+}
+
+/// Taipower's feeds at TAIPOWER_BASE_URL, with the weather and maintenance schedule when configured
+pub struct LiveFeeds {
+    pub maintenance: Option<Arc<MaintenanceCalendar>>,
+}
+
+#[async_trait]
+impl Feeds for LiveFeeds {
+    async fn fetch(&mut self) -> (Result<PowerAnalysis, TaipowerError>, Option<Result<LoadData, TaipowerError>>) {
+        let (power, load) = tokio::join!(fetch_and_analyze_power_data(), fetch_load_data());
+        (power, Some(load))
+    }
+
+    async fn extras(&mut self, with_regional: bool, with_weather: bool, today: NaiveDate) -> Extras {
+        let (shares, temperatures, (), demand_response_mw) = tokio::join!(
+            async {
+                if with_regional { Some(regional::fetch_regional_shares().await) } else { None }
+            },
+            async {
+                if with_weather { Some(weather::fetch_hourly_temperatures().await) } else { None }
+            },
+            async {
+                if let Some(maintenance) = &self.maintenance {
+                    maintenance.refresh().await;
+                }
+            },
+            demand_response::fetch_activated_mw(today),
+        );
+        Extras { shares, temperatures, demand_response_mw }
+    }
+
+    fn outages(&self, units: &[PowerUnit], today: NaiveDate) -> Vec<Outage> {
+        self.maintenance.as_ref().map(|maintenance| maintenance.outages(units, today)).unwrap_or_default()
+    }
+}
+
+/// Where a cycle's announcements go. Only the instance that is posting hears about anything; a
+/// standby fetches and stores like the leader but stays quiet. Each announcement is optional
+#[async_trait]
+pub trait Outlet: Send {
+    /// Called first each cycle: whether this instance posts
+    async fn begin(&mut self) -> bool {
+        true
+    }
+
+    /// An operational notice for the admin
+    async fn admin(&mut self, _notice: &str) {}
+
+    /// The day that just ended, with the first cycle after midnight
+    async fn digest(&mut self, _date: NaiveDate) {}
+
+    /// The report on the week before the one starting `monday`
+    async fn weekly(&mut self, _monday: NaiveDate) {}
+
+    /// A cached report could stand in for generation data right now
+    fn has_fallback(&self) -> bool {
+        false
+    }
+
+    /// Generation has been down long enough to say so: the cached report if there is one, else
+    /// the outage notice. True if the cached report went out
+    async fn outage(&mut self) -> bool {
+        false
+    }
+
+    /// The load feed's publish_time stopped advancing, or moved again
+    async fn freeze(&mut self, _event: &FreezeEvent) {}
+
+    /// The cycle's data, once stored, and the load reading before it
+    async fn observed(&mut self, _data: &CombinedPowerData, _previous_load: Option<&LoadData>) {}
+
+    /// The reserve indicator changed colour; `ping` if it deserves the alert role
+    async fn indicator_change(&mut self, _change: &IndicatorChange, _ping: bool) {}
+
+    /// Each new load reading, after any indicator alert
+    async fn load_update(&mut self, _load: &LoadData, _previous: Option<&LoadData>, _trend: Option<Trend>) {}
+
+    async fn fault_change(&mut self, _change: &FaultChange) {}
+
+    async fn stress_alert(&mut self, _stress: &StressIndex) {}
+
+    /// What happened while the bot was offline, with the first cycle after a long gap
+    async fn catch_up(&mut self, _last: &SnapshotRow, _data: &CombinedPowerData) {}
+
+    async fn stale_data(&mut self, _data_time: DateTime<FixedOffset>, _age: chrono::Duration) {}
+
+    async fn record(&mut self, _record: &RecordBreak) {}
+
+    /// The scheduled reports, and relays to followers
+    async fn report(&mut self, _data: &CombinedPowerData, _indicator_change: Option<&IndicatorChange>) {}
+}
+
+/// What the update loop carries from one cycle to the next
+pub struct Updater {
+    history: Arc<History>,
+    clock: Arc<dyn Clock>,
+    /// Shared with whoever applies `/config reload`; each cycle reads it once
+    config: Arc<RwLock<Config>>,
+    cycles: u64,
+    last_violated: Vec<Metric>,
+    previous_load: Option<LoadData>,
+    failures: FailureTracker,
+    error_notices: ErrorNotices,
+    freeze_watchdog: alerts::FreezeWatchdog,
+    reserve_alerts: alerts::ReserveAlertGate,
+    fault_watch: alerts::FaultWatch,
+    stress_watch: alerts::StressWatch,
+    stale_data: alerts::StaleDataGate,
+    /// Checked before the first sample of this run is stored
+    offline_since: Option<SnapshotRow>,
+    rollover: DayRollover,
+    weekly_trigger: weekly::WeeklyTrigger,
+    /// A cached report stands in for the current generation outage
+    fallback_posted: bool,
+}
+
+impl Updater {
+    pub fn new(history: Arc<History>, clock: Arc<dyn Clock>, config: Arc<RwLock<Config>>) -> Self {
+        let now = clock.now().naive_local();
+        Updater {
+            offline_since: catchup::offline_since(&history, now),
+            rollover: DayRollover::new(clock.as_ref()),
+            weekly_trigger: weekly::WeeklyTrigger::new(now),
+            history,
+            clock,
+            config,
+            cycles: 0,
+            last_violated: Vec::new(),
+            previous_load: None,
+            failures: FailureTracker::from_env(),
+            error_notices: ErrorNotices::from_env(),
+            freeze_watchdog: alerts::FreezeWatchdog::from_env(),
+            reserve_alerts: alerts::ReserveAlertGate::from_env(),
+            fault_watch: alerts::FaultWatch::default(),
+            stress_watch: alerts::StressWatch::from_env(),
+            stale_data: alerts::StaleDataGate::from_env(),
+            fallback_posted: false,
+        }
+    }
+
+    /// Pick up the alert settings `Config` doesn't cover after `/config reload`, keeping the alert state
+    pub fn reload(&mut self) {
+        self.reserve_alerts.reload();
+    }
+
+    pub async fn cycle(&mut self, feeds: &mut impl Feeds, outlet: &mut impl Outlet) {
+        self.cycles += 1;
+        let span = info_span!("cycle", n = self.cycles, leading = field::Empty);
+        self.run(feeds, outlet).instrument(span).await
+    }
+
+    async fn run(&mut self, feeds: &mut impl Feeds, outlet: &mut impl Outlet) {
+        latency::start();
+        let history = self.history.clone();
+        let clock = self.clock.clone();
+        let config = self.config.read().unwrap().clone();
+        // A standby fetches and stores like the leader but has nowhere to post
+        let leading = outlet.begin().await;
+        Span::current().record("leading", leading);
+
+        // First cycle after midnight: digest of the day that just ended
+        if let Some(ended) = self.rollover.check(clock.as_ref())
+            && leading
+            && config.daily_digest
+            && config.digest_time.is_none()
+        {
+            outlet.digest(ended).await;
+        }
+        if config.weekly_report
+            && let Some(monday) = self.weekly_trigger.check(clock.now().naive_local())
+            && leading
+        {
+            outlet.weekly(monday).await;
+        }
+        let today = clock.today();
+
+        // Both feeds at once, so a slow one doesn't hold up the other
+        let (power, load) = latency::measure_async(Phase::Fetch, feeds.fetch()).await;
+        self.send_schema_changes(outlet, leading).await;
+        let power_analysis = match power {
+            Ok(analysis) => {
+                self.failures.success("generation");
+                self.fallback_posted = false;
+                metrics::fetch_succeeded("generation");
+                systemd::watchdog();
+                analysis
+            }
+            Err(e) => {
+                error!("Error fetching power data: {:?}", e);
+                let notice = self.fetch_error_notice(("generation", "generation_format"), "發電", &e);
+                send_admin(outlet, leading, notice).await;
+                record_fetch_failure(&history, "generation", &e);
+                // The public only hears about outages that outlast a blip, and gets the last
+                // good report instead while it isn't too old. Once that report ages past
+                // CACHE_FALLBACK_MINUTES mid-outage, the outage notice follows it
+                let reached = self.failures.failure("generation", &e.to_string());
+                let expired = self.fallback_posted && !outlet.has_fallback();
+                if (reached || expired) && leading {
+                    self.fallback_posted = outlet.outage().await;
+                }
+                return;
+            }
+        };
+
+        let mut load_data = match load {
+            Some(Ok(data)) => {
+                self.failures.success("load");
+                metrics::fetch_succeeded("load");
+                if let Some(event) = self.freeze_watchdog.observe(data.publish_time)
+                    && leading
+                {
+                    track_freeze(&history, &event);
+                    outlet.freeze(&event).await;
+                }
+                Some(data)
+            }
+            Some(Err(e)) => {
+                error!("Error fetching load data: {:?}", e);
+                self.failures.failure("load", &e.to_string());
+                record_fetch_failure(&history, "load", &e);
+                let notice = self.fetch_error_notice(("load", "load_format"), "負載", &e);
+                send_admin(outlet, leading, notice).await;
+                None
+            }
+            None => None,
+        };
+
+        // Sanity-check before anything is published or stored
+        let power_violations = config.sanity_bounds.check_power(&power_analysis);
+        let load_violations = load_data
+            .as_ref()
+            .map(|data| config.sanity_bounds.check_load(data))
+            .unwrap_or_default();
+        let violations: Vec<Violation> = power_violations.iter().chain(&load_violations).copied().collect();
+
+        for violation in &violations {
+            warn!("Data quality violation: {}", violation.describe());
+            if let Err(why) = history.record_violation(violation) {
+                error!("Error recording data quality violation: {:?}", why);
+            }
+        }
+
+        // Only notify admins when the set of failing metrics changes
+        let violated: Vec<Metric> = violations.iter().map(|v| v.metric).collect();
+        if violated != self.last_violated {
+            if !violations.is_empty() && leading {
+                let notice = format!(
+                    "⚠️ **資料品質警告**: 台電資料超出合理範圍，已暫停發布相關數值\n{}",
+                    violations.iter().map(|v| format!("• {}", v.describe())).collect::<Vec<_>>().join("\n")
+                );
+                outlet.admin(&notice).await;
+            }
+            self.last_violated = violated;
+        }
+
+        if power_violations.iter().any(|v| v.metric.blocks_publishing()) {
+            return;
+        }
+        if !load_violations.is_empty() {
+            load_data = None;
+        }
+
+        // The extras are independent of each other too. Regional shares are only
+        // meaningful scaled by a trusted island-wide load
+        let extras = latency::measure_async(Phase::Fetch, feeds.extras(load_data.is_some(), config.own_forecast, today)).await;
+        self.send_schema_changes(outlet, leading).await;
+        let analyzing = std::time::Instant::now();
+        let mut regions = Vec::new();
+        if let (Some(load_data), Some(shares)) = (&load_data, extras.shares) {
+            match shares {
+                Ok(shares) => {
+                    self.failures.success("regional");
+                    metrics::fetch_succeeded("regional");
+                    regions = regional::estimate(&shares, load_data.current_load, config.region_import_warn);
+                }
+                Err(e) => {
+                    error!("Error fetching regional data: {:?}", e);
+                    self.failures.failure("regional", &e.to_string());
+                    record_fetch_failure(&history, "regional", &e);
+                    let notice = self.fetch_error_notice(("regional", "regional_format"), "區域", &e);
+                    send_admin(outlet, leading, notice).await;
+                }
+            }
+        }
+
+        let temperatures = extras.temperatures.and_then(|t| t.inspect_err(|e| error!("Error fetching weather: {:?}", e)).ok());
+        let outages = feeds.outages(&power_analysis.units, today);
+
+        let stress = StressIndex::compute(&power_analysis, load_data.as_ref(), self.previous_load.as_ref(), &config.stress_weights);
+        let now = clock.now().naive_local();
+        let mut combined_data = CombinedPowerData {
+            power_analysis,
+            load_data,
+            regions,
+            temperature: temperatures.as_ref().and_then(|t| t.at(now)),
+            own_forecast: None,
+            outages,
+            demand_response_mw: extras.demand_response_mw,
+            stress,
+            trend: None,
+        };
+
+        // Records compare against history, so check them before this snapshot joins it
+        let record_break = if leading {
+            records::check(&history, combined_data.power_analysis.renewable_ratio, now).unwrap_or_else(|why| {
+                error!("Error checking renewable records: {:?}", why);
+                None
+            })
+        } else {
+            None
+        };
+        if let Err(why) = history.record(&combined_data) {
+            error!("Error recording history: {:?}", why);
+        }
+        combined_data.trend = trend::current(&history, now);
+        if let Some(temperatures) = &temperatures {
+            match forecast::run(&history, temperatures, now) {
+                Ok(own_forecast) => combined_data.own_forecast = own_forecast,
+                Err(why) => error!("Error running demand forecast: {:?}", why),
+            }
+        }
+        if let Err(why) = history.record_units(&combined_data.power_analysis.units, &config.unit_history) {
+            error!("Error recording unit history: {:?}", why);
+        }
+        metrics::observe(&combined_data);
+        outlet.observed(&combined_data, self.previous_load.as_ref()).await;
+        latency::record(Phase::Analyze, analyzing.elapsed());
+
+        let mut indicator_change = None;
+        if let Some(load_data) = &combined_data.load_data {
+            indicator_change = alerts::detect_indicator_change(load_data, self.previous_load.as_ref());
+            if let Some(change) = &indicator_change
+                && leading
+            {
+                if let Err(why) = incident::track(&history, change) {
+                    error!("Error tracking incident: {:?}", why);
+                }
+                // Escalations past the time-of-day threshold (orange by default) get a ping, unless one was sent within the cooldown
+                let ping = self.reserve_alerts.allow(change, now);
+                outlet.indicator_change(change, ping).await;
+            }
+            if leading {
+                outlet.load_update(load_data, self.previous_load.as_ref(), combined_data.trend).await;
+            }
+            self.previous_load = Some(load_data.clone());
+        }
+
+        if config.fault_alerts
+            && let Some(fault_change) = self.fault_watch.observe(&combined_data.power_analysis.units)
+            && leading
+        {
+            outlet.fault_change(&fault_change).await;
+        }
+
+        if let Some(stress) = &combined_data.stress
+            && self.stress_watch.observe(stress.value)
+            && leading
+        {
+            outlet.stress_alert(stress).await;
+        }
+
+        if let Some(last) = self.offline_since.take()
+            && leading
+        {
+            outlet.catch_up(&last, &combined_data).await;
+        }
+
+        let data_time = combined_data.data_time();
+        // Without a feed timestamp the data is as fresh as the fetch
+        if let Some(snapshot_id) = combined_data.snapshot_id()
+            && let Some(age) = self.stale_data.check(&snapshot_id, data_time, clock.now())
+        {
+            if leading {
+                outlet.stale_data(data_time, age).await;
+            }
+            let error = TaipowerError::StaleData { data_time, age };
+            let notice = self.error_notices.notice("stale", &format!("⏸️ {}", error), now);
+            send_admin(outlet, leading, notice).await;
+        }
+
+        if let Some(record) = &record_break
+            && config.record_alerts
+        {
+            outlet.record(record).await;
+        }
+
+        if leading {
+            outlet.report(&combined_data, indicator_change.as_ref()).await;
+        }
+
+        if let Some(timings) = latency::finish() {
+            let over_budget = config.cycle_budget.filter(|budget| timings.total() > *budget);
+            metrics::observe_cycle(&timings, over_budget.is_some());
+            if let Err(why) = history.record_cycle_timings(&timings) {
+                error!("Error recording cycle timings: {:?}", why);
+            }
+            info!(
+                total_ms = timings.total().as_millis() as u64,
+                fetch_ms = timings.get(Phase::Fetch).as_millis() as u64,
+                parse_ms = timings.get(Phase::Parse).as_millis() as u64,
+                analyze_ms = timings.get(Phase::Analyze).as_millis() as u64,
+                render_ms = timings.get(Phase::Render).as_millis() as u64,
+                post_ms = timings.get(Phase::Post).as_millis() as u64,
+                "Cycle finished"
+            );
+            if let Some(budget) = over_budget {
+                warn!("Cycle took {:.1}s, over the {:.0}s budget: {}", timings.total().as_secs_f64(), budget.as_secs_f64(), timings.describe());
+                let notice = format!(
+                    "🐢 **更新週期超時**: 耗時 {:.1} 秒 (上限 {:.0} 秒)\n{}",
+                    timings.total().as_secs_f64(),
+                    budget.as_secs_f64(),
+                    timings.describe()
+                );
+                let notice = self.error_notices.notice("budget", &notice, clock.now().naive_local());
+                send_admin(outlet, leading, notice).await;
+            }
+        }
+    }
+
+    /// Admin notice for a failed fetch of one feed. A changed payload format needs a code fix rather
+    /// than patience, so it says so and is rate-limited separately from network errors
+    fn fetch_error_notice(
+        &mut self,
+        (network_class, format_class): (&'static str, &'static str),
+        feed: &str,
+        error: &TaipowerError,
+    ) -> Option<String> {
+        let now = self.clock.now().naive_local();
+        if error.is_parse_failure() {
+            self.error_notices.notice(format_class, &format!("🧩 台電{}資料格式異常，可能需要更新解析程式\n{}", feed, reporting::admin_detail(error)), now)
+        } else {
+            self.error_notices.notice(network_class, &format!("❌ 無法取得台電{}資料\n{}", feed, reporting::admin_detail(error)), now)
+        }
+    }
+
+    /// Admin notices for endpoints whose layout changed, including ones a fallback endpoint covered for
+    async fn send_schema_changes(&mut self, outlet: &mut impl Outlet, leading: bool) {
+        for change in schema_watch::take() {
+            let notice = self.error_notices.notice("schema", &change.notice(), self.clock.now().naive_local());
+            send_admin(outlet, leading, notice).await;
+        }
+    }
+}
+
+/// Only the instance that posts tells the admin
+async fn send_admin(outlet: &mut impl Outlet, leading: bool, notice: Option<String>) {
+    if let Some(notice) = notice
+        && leading
+    {
+        outlet.admin(&notice).await;
+    }
+}
+
+fn record_fetch_failure(history: &History, feed: &str, error: &TaipowerError) {
+    metrics::fetch_failed(feed);
+    if let Err(why) = history.record_fetch_failure(feed, &error.to_string()) {
+        error!("Error recording fetch failure: {:?}", why);
+    }
+}
+
+/// HTTP 200 but stale data is a different failure from an outage, so it's an incident of its own
+fn track_freeze(history: &History, event: &FreezeEvent) {
+    match event {
+        FreezeEvent::Frozen { publish_time, cycles } => {
+            warn!("Load feed frozen at {} for {} cycles", publish_time, cycles);
+            if let Err(why) = history.open_incident("upstream_frozen", &format!("上游資料凍結 (停在 {})", publish_time.format("%Y-%m-%d %H:%M"))) {
+                error!("Error recording incident: {:?}", why);
+            }
+        }
+        FreezeEvent::Recovered { publish_time, cycles } => {
+            info!("Load feed recovered at {} after {} cycles", publish_time, cycles);
+            if let Err(why) = history.close_incidents("upstream_frozen") {
+                error!("Error closing incident: {:?}", why);
+            }
+        }
+    }
+}
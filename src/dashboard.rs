@@ -0,0 +1,154 @@
+//! Optional read-only web page with the latest report and a 24h load chart, for people who
+//! aren't in the Discord. Enabled by setting DASHBOARD_ADDR (e.g. 0.0.0.0:8080).
+//!
+//! The page is rebuilt once per fetch cycle and served as-is, so requests never touch Taipower
+//! or the database. It's a single self-contained document: the chart is inlined as a data URI.
+//! The same server answers Prometheus scrapes on `/metrics`.
+
+use base64::Engine;
+use chrono::Duration;
+use std::sync::{Arc, RwLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info, warn};
+
+use crate::chart::{self, Series};
+use crate::clock::taipei_now;
+use crate::config;
+use crate::history::History;
+use crate::locale::NumberFormat;
+use crate::metrics;
+use crate::render::{PlainRenderer, Renderer};
+use crate::analysis::CombinedPowerData;
+
+/// Half the fetch interval, so a new report shows up within five minutes
+const REFRESH_SECS: u64 = 300;
+
+pub fn addr_from_env() -> Option<String> {
+    config::var("DASHBOARD_ADDR").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+pub struct Dashboard {
+    page: RwLock<String>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Dashboard { page: RwLock::new(render_page("<p>尚無資料，請稍後再試。</p>")) }
+    }
+
+    /// Rebuild the page from the latest data and the last 24h of history
+    pub fn update(&self, data: &CombinedPowerData, history: &History) {
+        let mut body = format!("<pre>{}</pre>", escape(&PlainRenderer.report(data)));
+
+        let since = taipei_now().naive_local() - Duration::hours(24);
+        match history.snapshot_series("current_load", since) {
+            Ok(points) if points.len() >= 2 => {
+                let series = [Series { label: "目前用電量".to_string(), points }];
+                match chart::line_chart("近 24 小時用電量", "萬瓩", &series, NumberFormat::Plain) {
+                    Ok(png) => body.push_str(&format!(
+                        "<img alt=\"近 24 小時用電量\" src=\"data:image/png;base64,{}\">",
+                        base64::engine::general_purpose::STANDARD.encode(png)
+                    )),
+                    Err(e) => error!("Error rendering dashboard chart: {:?}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Error reading history for dashboard: {:?}", e),
+        }
+
+        *self.page.write().unwrap() = render_page(&body);
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_page(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"zh-Hant\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<meta http-equiv=\"refresh\" content=\"{}\">
+<title>台電即時電力資訊</title>
+<style>
+body {{ font-family: sans-serif; max-width: 1000px; margin: 2em auto; padding: 0 1em; color: #222; }}
+pre {{ white-space: pre-wrap; font-size: 15px; line-height: 1.5; }}
+img {{ max-width: 100%; }}
+footer {{ color: #777; font-size: 13px; }}
+</style>
+</head>
+<body>
+<h1>🔋 台電即時電力資訊</h1>
+{}
+<footer>頁面產生時間 {} · 每 {} 分鐘自動更新 · 本資料可能會有錯誤或延遲，造成損失與我們無關</footer>
+</body>
+</html>
+",
+        REFRESH_SECS,
+        body,
+        taipei_now().format("%Y-%m-%d %H:%M"),
+        REFRESH_SECS / 60,
+    )
+}
+
+/// Without a dashboard only `/metrics` is served, for METRICS_ADDR
+pub async fn serve(addr: String, dashboard: Option<Arc<Dashboard>>) {
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Error starting dashboard on {}: {:?}", addr, e);
+            return;
+        }
+    };
+    info!("{} listening on http://{}", if dashboard.is_some() { "Dashboard" } else { "Metrics" }, addr);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let dashboard = dashboard.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = respond(stream, dashboard.as_deref()).await {
+                        warn!("Dashboard connection error: {:?}", e);
+                    }
+                });
+            }
+            Err(e) => warn!("Dashboard accept error: {:?}", e),
+        }
+    }
+}
+
+/// Just enough HTTP/1.1 for a browser or a scraper to GET one page
+async fn respond(mut stream: TcpStream, dashboard: Option<&Dashboard>) -> std::io::Result<()> {
+    let mut buffer = [0u8; 4096];
+    let read = tokio::time::timeout(std::time::Duration::from_secs(10), stream.read(&mut buffer))
+        .await
+        .unwrap_or(Ok(0))?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+
+    let (status, content_type, body) = match (method, path.split('?').next().unwrap_or_default()) {
+        ("GET" | "HEAD", "/metrics") => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", metrics::render()),
+        ("GET" | "HEAD", "/") if let Some(dashboard) = dashboard => {
+            ("200 OK", "text/html; charset=utf-8", dashboard.page.read().unwrap().clone())
+        }
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not Found".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method Not Allowed".to_string()),
+    };
+
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes()).await?;
+    }
+    stream.shutdown().await
+}
@@ -0,0 +1,101 @@
+//! Deserializers for Taipower's loosely formatted numeric fields.
+//!
+//! Upstream numbers arrive as strings like "1,234.5", "567.8(12.3%)", "94.6%" or "-".
+//! Parsing them here means a malformed value fails deserialization with the field path
+//! attached, instead of turning into a silent zero somewhere in the analysis.
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawNumber {
+    Number(f64),
+    Text(String),
+}
+
+/// Parse an MW figure such as "1,234.5(12.3%)". Dashes, "N/A" and empty strings mean "no output".
+pub fn parse_mw_value(value: &str) -> Result<f64, String> {
+    // Remove parentheses content and thousands separators
+    let cleaned = value
+        .split('(')
+        .next()
+        .unwrap_or(value)
+        .replace(',', "");
+    let cleaned = cleaned.trim();
+
+    if cleaned == "-" || cleaned == "N/A" || cleaned.is_empty() {
+        return Ok(0.0);
+    }
+
+    cleaned
+        .parse::<f64>()
+        .map_err(|_| format!("invalid numeric value {:?}", value))
+}
+
+/// Like [`parse_mw_value`] but a missing value stays `None` and a trailing `%` is allowed.
+pub fn parse_optional_number(value: &str) -> Result<Option<f64>, String> {
+    let cleaned = value.trim().trim_end_matches('%').trim();
+    if cleaned == "-" || cleaned == "N/A" || cleaned.is_empty() {
+        return Ok(None);
+    }
+    parse_mw_value(cleaned).map(Some)
+}
+
+pub fn mw_value<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match RawNumber::deserialize(deserializer)? {
+        RawNumber::Number(n) => Ok(n),
+        RawNumber::Text(s) => parse_mw_value(&s).map_err(de::Error::custom),
+    }
+}
+
+pub fn optional_number<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<RawNumber>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(RawNumber::Number(n)) => Ok(Some(n)),
+        Some(RawNumber::Text(s)) => parse_optional_number(&s).map_err(de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn parse_mw_value_never_panics(value in "\\PC*") {
+            let _ = parse_mw_value(&value);
+            let _ = parse_optional_number(&value);
+        }
+
+        #[test]
+        fn parse_mw_value_reads_formatted_figures(mw in 0.0f64..100_000.0, share in 0.0f64..100.0) {
+            let mw = (mw * 10.0).round() / 10.0;
+            let whole = mw.trunc() as u64;
+            let grouped = whole
+                .to_string()
+                .as_bytes()
+                .rchunks(3)
+                .rev()
+                .map(|c| std::str::from_utf8(c).unwrap())
+                .collect::<Vec<_>>()
+                .join(",");
+            let text = format!("{}.{}({:.1}%)", grouped, (mw.fract() * 10.0).round() as u64 % 10, share);
+            let parsed = parse_mw_value(&text).unwrap();
+            prop_assert!((parsed - mw).abs() < 0.11, "{} parsed as {}", text, parsed);
+        }
+
+        #[test]
+        fn missing_values_are_zero_or_none(value in prop::sample::select(vec!["", " ", "-", "N/A", " - "])) {
+            prop_assert_eq!(parse_mw_value(value), Ok(0.0));
+            prop_assert_eq!(parse_optional_number(value), Ok(None));
+        }
+    }
+}
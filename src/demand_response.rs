@@ -0,0 +1,68 @@
+//! Taipower's demand-response (需量反應) activations on tight days.
+//!
+//! Activations aren't part of the live load feed, so the source is configured with
+//! DEMAND_RESPONSE_URL (a URL or a local file, JSON or CSV like the maintenance schedule). Columns
+//! are matched by name: 需量反應/抑低 (MW) and optionally 日期 (date); the latest row for today wins.
+
+use chrono::NaiveDate;
+use tracing::error;
+
+use crate::config;
+use crate::taipower_api::LoadData;
+use crate::maintenance::{parse_rows, parse_schedule_date, read_source};
+
+fn source() -> Option<String> {
+    config::var("DEMAND_RESPONSE_URL").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// MW of demand response activated today, if any; fetch errors are logged and treated as none
+pub async fn fetch_activated_mw(today: NaiveDate) -> Option<f64> {
+    let source = source()?;
+    let text = match read_source(&source, "demand response").await {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Error loading demand response data: {:?}", e);
+            return None;
+        }
+    };
+    match activated_mw(&text, today) {
+        Ok(mw) => mw,
+        Err(e) => {
+            error!("Error parsing demand response data: {:?}", e);
+            None
+        }
+    }
+}
+
+pub fn activated_mw(text: &str, today: NaiveDate) -> Result<Option<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = parse_rows(text)?;
+    let mut latest = None;
+    for row in &rows {
+        let column = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|n| row.iter().find(|(key, _)| key.contains(n)))
+                .map(|(_, value)| value.as_str())
+        };
+        // Rows without a date are taken as today's
+        if let Some(date) = column(&["日期"]).and_then(|d| parse_schedule_date(d.split_whitespace().next().unwrap_or_default()))
+            && date != today
+        {
+            continue;
+        }
+        if let Some(mw) = column(&["需量反應", "抑低"]).and_then(|v| v.replace(',', "").trim().parse::<f64>().ok()) {
+            latest = Some(mw);
+        }
+    }
+    Ok(latest.filter(|mw| *mw > 0.0))
+}
+
+/// Forecast peak reserve (萬瓩, %) had demand response not been activated: the curtailed load
+/// would have been demand on top of the forecast peak
+pub fn reserve_without(load: &LoadData, activated_mw: f64) -> (f64, f64) {
+    let curtailed = activated_mw / 10.0;
+    let capacity = load.forecast_peak_reserve_capacity - curtailed;
+    let demand = load.forecast_peak_demand_load + curtailed;
+    let rate = if demand > 0.0 { capacity / demand * 100.0 } else { 0.0 };
+    (capacity, rate)
+}
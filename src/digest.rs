@@ -0,0 +1,274 @@
+//! Once-a-day digest, posted to the report channels: the previous day's after midnight, or with
+//! DIGEST_TIME the same day's at that Taipei time, on its own schedule.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use serenity::builder::{CreateAllowedMentions, CreateAttachment, CreateMessage};
+use std::collections::BTreeSet;
+use std::f64::consts::PI;
+use std::fmt::Write;
+use tracing::error;
+
+use crate::chart::{self, Series};
+use crate::config;
+use crate::history::{DataCoverage, History};
+use crate::locale::NumberFormat;
+use crate::render::{DiscordTextRenderer, Renderer};
+use crate::scheduler;
+
+const SOLAR: &str = "太陽能";
+/// Centre of Taiwan's PV fleet (mostly the southwest plains)
+const LATITUDE: f64 = 23.3;
+const LONGITUDE: f64 = 120.4;
+/// Fleet output at solar noon on a cloudless day as a fraction of installed capacity (approximate)
+const CLEAR_SKY_PEAK: f64 = 0.75;
+
+pub fn enabled() -> bool {
+    !matches!(config::var("DAILY_DIGEST").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// DIGEST_TIME (HH:MM, Taipei), when the day's digest is posted before the day ends; None when unset
+pub fn parse_time(value: &str) -> Result<Option<NaiveTime>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map(Some).map_err(|_| format!("DIGEST_TIME: expected HH:MM, got {:?}", value))
+}
+
+/// The first `time` of day strictly after `now`
+pub fn next_run(time: NaiveTime, now: NaiveDateTime) -> NaiveDateTime {
+    let today = now.date().and_time(time);
+    if today > now { today } else { today + Duration::days(1) }
+}
+
+/// Append sample count, coverage, upstream failures and endpoints (DIGEST_STATS, off by default)
+fn stats_enabled() -> bool {
+    matches!(config::var("DIGEST_STATS").as_deref().map(str::trim), Ok("on") | Ok("true") | Ok("1"))
+}
+
+/// Small-print provenance for the day's figures
+fn stats_footer(coverage: &DataCoverage, numbers: NumberFormat) -> String {
+    let failures: usize = coverage.failures.iter().map(|(_, count)| count).sum();
+    let mut footer = format!(
+        "-# 📊 樣本 {} 筆 · 涵蓋率 {} · 上游錯誤 {} 次",
+        coverage.samples,
+        numbers.percent(coverage.coverage * 100.0, 0),
+        failures
+    );
+    if failures > 0 {
+        let by_feed: Vec<String> = coverage.failures.iter().map(|(feed, count)| format!("{} {}", feed_label(feed), count)).collect();
+        footer.push_str(&format!(" ({})", by_feed.join("、")));
+    }
+    if !coverage.sources.is_empty() {
+        let sources: Vec<&str> = coverage.sources.iter().map(|url| endpoint_name(url)).collect();
+        footer.push_str(&format!(" · 來源 {}", sources.join("、")));
+    }
+    footer
+}
+
+fn feed_label(feed: &str) -> &str {
+    match feed {
+        "generation" => "發電",
+        "load" => "負載",
+        "regional" => "區域",
+        other => other,
+    }
+}
+
+/// The last two path segments, e.g. `d006001/001.json`; enough to tell Taipower's endpoints apart
+fn endpoint_name(url: &str) -> &str {
+    let mut slashes = url.rmatch_indices('/').map(|(i, _)| i);
+    match (slashes.next(), slashes.next()) {
+        (Some(_), Some(start)) => &url[start + 1..],
+        _ => url,
+    }
+}
+
+/// Approximate fraction of installed PV capacity producing under a clear sky at `time` (Taipei)
+pub fn clear_sky_fraction(time: NaiveDateTime) -> f64 {
+    let day_of_year = time.ordinal() as f64;
+    let declination = (23.44_f64).to_radians() * (2.0 * PI * (284.0 + day_of_year) / 365.0).sin();
+    // UTC+8 is centred on 120°E; ignore the equation of time
+    let clock_hours = time.hour() as f64 + time.minute() as f64 / 60.0;
+    let solar_hours = clock_hours + (LONGITUDE - 120.0) / 15.0;
+    let hour_angle = (15.0 * (solar_hours - 12.0)).to_radians();
+    let latitude = LATITUDE.to_radians();
+
+    let sin_elevation = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    CLEAR_SKY_PEAK * sin_elevation.max(0.0)
+}
+
+struct SolarChart {
+    png: Vec<u8>,
+    /// Energy produced as a percentage of the clear-sky estimate
+    sunshine: f64,
+}
+
+/// Solar output vs the clear-sky curve; None without enough samples that day
+fn solar_chart(history: &History, date: NaiveDate, numbers: NumberFormat) -> Result<Option<SolarChart>, Box<dyn std::error::Error + Send + Sync>> {
+    let samples = history.energy_type_series(SOLAR, date)?;
+    let actual: Vec<(NaiveDateTime, f64)> = samples
+        .iter()
+        .filter(|(_, _, capacity)| *capacity > 0.0)
+        .map(|(time, generation, capacity)| (*time, generation / capacity * 100.0))
+        .collect();
+    if actual.len() < 2 {
+        return Ok(None);
+    }
+
+    let clear_sky: Vec<(NaiveDateTime, f64)> = (0..=24 * 6)
+        .map(|i| date.and_hms_opt(0, 0, 0).unwrap() + Duration::minutes(10 * i))
+        .map(|time| (time, clear_sky_fraction(time) * 100.0))
+        .filter(|(_, fraction)| *fraction > 0.0)
+        .collect();
+
+    // How much of the clear-sky energy was actually produced, over the sampled times
+    let produced: f64 = actual.iter().map(|(_, fraction)| fraction).sum();
+    let possible: f64 = actual.iter().map(|(time, _)| clear_sky_fraction(*time) * 100.0).sum();
+    let sunshine = if possible > 0.0 { produced / possible * 100.0 } else { 0.0 };
+
+    let series = [
+        Series { label: "太陽能出力 (% 裝置容量)".to_string(), points: actual },
+        Series { label: "晴空理論值 (估計)".to_string(), points: clear_sky },
+    ];
+    let png = chart::line_chart(&format!("{} 太陽能出力 vs 晴空曲線", date), "%", &series, numbers)?;
+    Ok(Some(SolarChart { png, sunshine }))
+}
+
+/// The digest message for `date`, or None if nothing was recorded that day. With `csv`, the
+/// day's samples come along as a spreadsheet (`/config digest-csv`); `poll` is the answer to
+/// the channel's `/config poll` question, if one was asked that day
+pub fn build(history: &History, date: NaiveDate, numbers: NumberFormat, csv: bool, poll: Option<String>) -> Option<CreateMessage> {
+    let (mut content, solar_png) = content(history, date, numbers)?;
+    if let Some(poll) = poll {
+        content.push_str(&format!("\n{}", poll));
+    }
+    let mut message = CreateMessage::new().allowed_mentions(CreateAllowedMentions::new());
+    if let Some(png) = solar_png {
+        message = message.add_file(CreateAttachment::bytes(png, "solar.png"));
+    }
+    if csv {
+        match day_csv(history, date) {
+            Ok(csv) => message = message.add_file(CreateAttachment::bytes(csv, format!("taipower-{}.csv", date))),
+            Err(e) => error!("Error reading samples for digest CSV: {:?}", e),
+        }
+    }
+    Some(message.content(content))
+}
+
+/// One row per sample: time, load (萬瓩), forecast peak reserve rate and MW by energy type
+fn day_csv(history: &History, date: NaiveDate) -> rusqlite::Result<String> {
+    let samples = history.day_samples(date)?;
+    let energy_types: BTreeSet<&String> = samples.iter().flat_map(|(_, mix)| mix.keys()).collect();
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    // The byte order mark makes Excel read the Chinese column names as UTF-8
+    let mut csv = String::from("\u{feff}time,load_wan_kw,reserve_rate");
+    for energy_type in &energy_types {
+        let _ = write!(csv, ",{}_mw", energy_type);
+    }
+    csv.push('\n');
+    for (sample, mix) in &samples {
+        let _ = write!(
+            csv,
+            "{},{},{}",
+            sample.recorded_at.format("%Y-%m-%d %H:%M:%S"),
+            optional(sample.current_load),
+            optional(sample.reserve_rate)
+        );
+        for energy_type in &energy_types {
+            let _ = write!(csv, ",{}", optional(mix.get(*energy_type).copied()));
+        }
+        csv.push('\n');
+    }
+    Ok(csv)
+}
+
+/// The digest text and its solar chart, if anything was recorded on `date`
+pub fn content(history: &History, date: NaiveDate, numbers: NumberFormat) -> Option<(String, Option<Vec<u8>>)> {
+    let summary = match history.daily_summary(date) {
+        Ok(Some(summary)) => summary,
+        Ok(None) => return None,
+        Err(e) => {
+            error!("Error reading history for digest: {:?}", e);
+            return None;
+        }
+    };
+
+    let mut content = format!("🗓️ **每日摘要**\n{}", DiscordTextRenderer { numbers, ..Default::default() }.daily_summary(&summary));
+    let mut solar_png = None;
+
+    match solar_chart(history, date, numbers) {
+        Ok(Some(SolarChart { png, sunshine })) => {
+            content.push_str(&format!("\n☀️ **太陽能日照達成率**: {} (相對晴空理論值)", numbers.percent(sunshine, 0)));
+            solar_png = Some(png);
+        }
+        Ok(None) => {}
+        Err(e) => error!("Error rendering solar chart: {:?}", e),
+    }
+
+    if stats_enabled() {
+        let interval_minutes = (scheduler::interval_from_env().as_secs() / 60) as u32;
+        match history.data_coverage(date, interval_minutes) {
+            Ok(coverage) => content.push_str(&format!("\n{}", stats_footer(&coverage, numbers))),
+            Err(e) => error!("Error reading data coverage for digest: {:?}", e),
+        }
+    }
+
+    Some((content, solar_png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{taipei_datetime, Clock, ManualClock};
+
+    fn at(date: (i32, u32, u32), hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn clock_at(time: NaiveDateTime) -> ManualClock {
+        ManualClock::new(taipei_datetime(time).unwrap())
+    }
+
+    #[test]
+    fn digest_time_is_hh_mm() {
+        assert_eq!(parse_time(""), Ok(None));
+        assert_eq!(parse_time(" 23:50 "), Ok(NaiveTime::from_hms_opt(23, 50, 0)));
+        for value in ["23.50", "24:00", "noon"] {
+            assert!(parse_time(value).is_err(), "{} parsed", value);
+        }
+    }
+
+    #[test]
+    fn digests_run_once_a_day_across_year_end() {
+        let time = NaiveTime::from_hms_opt(23, 50, 0).unwrap();
+        let clock = clock_at(at((2024, 12, 31), 9, 0));
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            let next = next_run(time, clock.now().naive_local());
+            runs.push(next);
+            clock.set(taipei_datetime(next).unwrap());
+        }
+        assert_eq!(runs, vec![at((2024, 12, 31), 23, 50), at((2025, 1, 1), 23, 50), at((2025, 1, 2), 23, 50)]);
+    }
+
+    #[test]
+    fn a_digest_time_just_past_midnight_waits_for_the_new_day() {
+        let time = NaiveTime::from_hms_opt(0, 5, 0).unwrap();
+        let clock = clock_at(at((2024, 2, 28), 23, 59));
+        assert_eq!(next_run(time, clock.now().naive_local()), at((2024, 2, 29), 0, 5));
+        clock.set(taipei_datetime(at((2024, 2, 29), 0, 5)).unwrap());
+        assert_eq!(next_run(time, clock.now().naive_local()), at((2024, 3, 1), 0, 5));
+    }
+
+    #[test]
+    fn clear_sky_follows_taipei_daylight() {
+        let clock = clock_at(at((2024, 6, 21), 0, 0));
+        assert_eq!(clear_sky_fraction(clock.now().naive_local()), 0.0);
+        clock.set(taipei_datetime(at((2024, 6, 21), 12, 0)).unwrap());
+        let noon = clear_sky_fraction(clock.now().naive_local());
+        assert!(noon > 0.7 && noon <= CLEAR_SKY_PEAK, "{}", noon);
+        clock.set(taipei_datetime(at((2024, 6, 21), 21, 0)).unwrap());
+        assert_eq!(clear_sky_fraction(clock.now().naive_local()), 0.0);
+    }
+}
@@ -0,0 +1,301 @@
+//! Rich Discord embeds: the reserve indicator colours the sidebar, supply/demand, generation and
+//! unit status get their own fields, and the footer carries the data source and publish time.
+
+use serenity::builder::{CreateAttachment, CreateEmbed, CreateEmbedFooter};
+use serenity::model::{Colour, Timestamp};
+use tracing::error;
+
+use crate::chart;
+use crate::clock::discord_timestamp;
+use crate::demand_response;
+use crate::alerts::{FaultChange, IndicatorChange};
+use crate::history::{DailySummary, SummarySource};
+use crate::locale::{Locale, NumberFormat, ZH_TW};
+use crate::render::{
+    format_accuracy, format_hour_range, format_outage, format_peak_time, format_pp_change, format_region, indicator_emoji,
+    indicator_label, sorted_generation, FuelDisplay, DiscordTextRenderer, Renderer, ReportSections, DATA_SOURCE_URL, DISCLAIMER,
+};
+use crate::analysis::CombinedPowerData;
+use crate::taipower_api::ReserveIndicator;
+
+#[derive(Default)]
+pub struct EmbedRenderer {
+    pub numbers: NumberFormat,
+    pub sections: ReportSections,
+}
+
+pub fn indicator_colour(indicator: ReserveIndicator) -> Colour {
+    match indicator {
+        ReserveIndicator::Green => Colour::from_rgb(46, 204, 113),
+        ReserveIndicator::Yellow => Colour::from_rgb(241, 196, 15),
+        ReserveIndicator::Orange => Colour::from_rgb(230, 126, 34),
+        ReserveIndicator::Red => Colour::from_rgb(231, 76, 60),
+        ReserveIndicator::Black => Colour::from_rgb(20, 20, 20),
+        ReserveIndicator::Unknown => Colour::from_rgb(149, 165, 166),
+    }
+}
+
+const BADGE_FILENAME: &str = "reserve-badge.png";
+
+impl EmbedRenderer {
+    /// The report with a reserve indicator badge as its thumbnail, and the badge to attach with it
+    pub fn report_with_badge(&self, data: &CombinedPowerData) -> (CreateEmbed, Option<CreateAttachment>) {
+        let embed = self.report(data);
+        let Some(load_data) = data.load_data.as_ref().filter(|_| chart::embed_badge_enabled()) else {
+            return (embed, None);
+        };
+        let colour = indicator_colour(load_data.forecast_peak_reserve_indicator);
+        let label = self.numbers.percent(load_data.forecast_peak_reserve_rate, 1);
+        match chart::indicator_badge((colour.r(), colour.g(), colour.b()), &label) {
+            Ok(png) => (
+                embed.thumbnail(format!("attachment://{}", BADGE_FILENAME)),
+                Some(CreateAttachment::bytes(png, BADGE_FILENAME)),
+            ),
+            Err(e) => {
+                error!("Error rendering reserve badge: {:?}", e);
+                (embed, None)
+            }
+        }
+    }
+}
+
+impl Renderer for EmbedRenderer {
+    type Output = CreateEmbed;
+
+    fn report(&self, data: &CombinedPowerData) -> CreateEmbed {
+        let analysis = &data.power_analysis;
+        let n = self.numbers;
+        let load = |value: f64| Locale::ZhTw.load_value(value, n);
+        let indicator = data
+            .load_data
+            .as_ref()
+            .map(|l| l.forecast_peak_reserve_indicator)
+            .unwrap_or(ReserveIndicator::Unknown);
+
+        let mut embed = CreateEmbed::new()
+            .title("🔋 台電即時電力資訊")
+            .url(DATA_SOURCE_URL)
+            .colour(indicator_colour(indicator));
+        if let Some(stress) = &data.stress {
+            embed = embed.description(format!("🌡️ **電網壓力指數** {}", stress.display()));
+        }
+
+        if let Some(load_data) = &data.load_data {
+            if self.sections.supply {
+                let mut reserve = format!(
+                    "{} **{}** ({})\n{}",
+                    indicator_emoji(load_data.forecast_peak_reserve_indicator),
+                    n.percent(load_data.forecast_peak_reserve_rate, 2),
+                    indicator_label(load_data.forecast_peak_reserve_indicator, &ZH_TW),
+                    load(load_data.forecast_peak_reserve_capacity),
+                );
+                if let Some(mw) = data.demand_response_mw {
+                    let (_, rate) = demand_response::reserve_without(load_data, mw);
+                    reserve.push_str(&format!("\n🤝 需量反應 {}\n不含需量反應: {}", n.mw(mw, 1), n.percent(rate, 2)));
+                }
+                let mut supply = format!(
+                    "目前用電量: **{}**\n目前使用率: **{}**\n預估最大供電能力: {}\n預估最高用電: {}\n預估尖峰用電時段: {}",
+                    load(load_data.current_load),
+                    n.percent(load_data.current_util_rate, 1),
+                    load(load_data.forecast_max_supply_capacity),
+                    load(load_data.forecast_peak_demand_load),
+                    format_hour_range(load_data, &ZH_TW),
+                );
+                if let Some(publish_time) = load_data.publish_time {
+                    supply.push_str(&format!("\n資料更新: {}", discord_timestamp(publish_time, 'R')));
+                }
+                embed = embed
+                    .field("⚡ 電力供需", supply, false)
+                    .field("🔋 預估尖峰備轉", reserve, true);
+
+                if let Some(own) = &data.own_forecast {
+                    let mut value = format!(
+                        "今日尖峰 {} ({})\n下一小時 {}",
+                        load(own.day_peak), own.day_peak_time.format("%H:%M"), load(own.next_hour),
+                    );
+                    if let Some(accuracy) = &own.accuracy {
+                        value.push_str(&format!("\n誤差: {}", format_accuracy(accuracy, &ZH_TW, n)));
+                    }
+                    embed = embed.field("🤖 本機預估", value, true);
+                }
+            }
+
+            if self.sections.yesterday {
+                embed = embed.field("📊 昨日尖峰", format!(
+                    "用電 {}\n{} 備轉 {}",
+                    load(load_data.yesterday_peak_demand_load),
+                    indicator_emoji(load_data.yesterday_peak_reserve_indicator),
+                    n.percent(load_data.yesterday_peak_reserve_rate, 2),
+                ), true);
+            }
+
+            if self.sections.realtime && load_data.real_hour_max_supply_capacity > 0.0 {
+                embed = embed.field("⏰ 即時尖峰", format!(
+                    "{}\n{}",
+                    load(load_data.real_hour_max_supply_capacity),
+                    format_peak_time(load_data, &ZH_TW),
+                ), true);
+            }
+        }
+
+        if self.sections.regions && !data.regions.is_empty() {
+            let regions = data
+                .regions
+                .iter()
+                .map(|region| format_region(region, Locale::ZhTw, n, " ⚠️"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed = embed.field(format!("🗺️ {}", ZH_TW.regions), regions, false);
+        }
+
+        if self.sections.generation {
+            let mix = sorted_generation(analysis)
+                .into_iter()
+                .map(|(energy_type, generation)| format!("{}: {}", energy_type, n.mw(generation, 1)))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            embed = embed
+                .field("🏭 發電機組", format!(
+                    "總發電量: **{}**\n裝置容量: {}\n發電占比: {}\n再生能源: {}\n民營+購電: {}",
+                    n.mw(analysis.total_generation, 1),
+                    n.mw(analysis.estimated_max_generation, 1),
+                    n.percent(analysis.generation_ratio(), 1),
+                    n.percent(analysis.renewable_ratio, 1),
+                    n.percent(analysis.private_ratio, 1),
+                ), false)
+                .field("各能源發電量", mix, false)
+                .field("🏆 最高", format!(
+                    "電廠: {} ({})\n機組: {} ({})",
+                    analysis.top_plant.0, n.mw(analysis.top_plant.1, 1),
+                    analysis.top_unit.0, n.mw(analysis.top_unit.1, 1),
+                ), false);
+        }
+
+        if self.sections.units {
+            embed = embed.field("📋 運轉狀態", format!(
+                "🌱 環保/運轉限制: {} 部\n🔧 歲修/檢修: {} 部\n⚠️ 故障: {} 部",
+                analysis.environmental_restrictions,
+                analysis.maintenance_count,
+                analysis.fault_count,
+            ), false);
+
+            if !data.outages.is_empty() {
+                let outages = data
+                    .outages
+                    .iter()
+                    .map(|outage| format_outage(outage, &ZH_TW, n))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                embed = embed.field("🛠️ 大型機組停機", outages, false);
+            }
+        }
+
+        let embed = embed
+            .footer(CreateEmbedFooter::new(format!("資料來源: 台電公司開放資料 · {}", DISCLAIMER)));
+
+        match Timestamp::from_unix_timestamp(analysis.update_time.timestamp()) {
+            Ok(timestamp) => embed.timestamp(timestamp),
+            Err(_) => embed,
+        }
+    }
+
+    fn compact(&self, data: &CombinedPowerData) -> CreateEmbed {
+        let indicator = data
+            .load_data
+            .as_ref()
+            .map(|l| l.forecast_peak_reserve_indicator)
+            .unwrap_or(ReserveIndicator::Unknown);
+        CreateEmbed::new()
+            .description(DiscordTextRenderer { numbers: self.numbers, ..Default::default() }.compact(data))
+            .colour(indicator_colour(indicator))
+    }
+
+    fn daily_summary(&self, summary: &DailySummary) -> CreateEmbed {
+        let mut embed = CreateEmbed::new().title(format!("📅 {} 電力摘要", summary.date));
+
+        embed = embed.field("⬆️ 尖峰用電", match (summary.peak_load, &summary.peak_time) {
+            (Some(peak), Some(time)) => format!("{:.1} 萬瓩 ({})", peak, time),
+            (Some(peak), None) => format!("{:.1} 萬瓩", peak),
+            _ => "無資料".to_string(),
+        }, true);
+        if let (Some(min), Some(time)) = (summary.min_load, &summary.min_time) {
+            embed = embed.field("⬇️ 最低用電", format!("{:.1} 萬瓩 ({})", min, time), true);
+        }
+        embed = match (summary.min_reserve_rate, summary.peak_reserve_rate) {
+            (Some(rate), _) => embed.field("🔋 最低備轉容量率", format!("{:.2}%", rate), true),
+            (None, Some(rate)) => embed.field("🔋 尖峰備轉容量率", format!("{:.2}%", rate), true),
+            (None, None) => embed.field("🔋 最低備轉容量率", "無資料", true),
+        };
+        if let Some(ratio) = summary.average_renewable_ratio {
+            embed = embed.field("🌱 平均再生能源占比", format!("{:.1}%", ratio), true);
+        }
+        if let Some(plant) = &summary.top_plant {
+            embed = embed.field("🏆 發電量最高電廠", plant, true);
+        }
+        if let Some(events) = summary.fault_events {
+            embed = embed.field("⚠️ 新增故障", format!("{} 部", events), true);
+        }
+
+        if !summary.generation_mix.is_empty() {
+            let mix = FuelDisplay::from_env()
+                .apply(summary.generation_mix.iter().map(|(t, g)| (t.as_str(), *g)))
+                .into_iter()
+                .map(|(energy_type, generation)| format!("{}: {:.1} MW", energy_type, generation))
+                .collect::<Vec<_>>()
+                .join("\n");
+            embed = embed.field("🏭 平均發電結構", mix, false);
+        }
+
+        if let (Some(faults), Some(maintenance), Some(restrictions)) = (
+            summary.max_fault_count,
+            summary.max_maintenance_count,
+            summary.max_environmental_restrictions,
+        ) {
+            embed = embed.field("📋 當日最多異常機組", format!(
+                "🌱 環保/運轉限制: {} 部\n🔧 歲修/檢修: {} 部\n⚠️ 故障: {} 部",
+                restrictions, maintenance, faults,
+            ), false);
+        }
+
+        let footer = match summary.source {
+            SummarySource::Local => format!("資料來源: 本機紀錄 ({} 筆)", summary.sample_count),
+            SummarySource::Archive => "資料來源: 台電過去電力供需資訊".to_string(),
+        };
+        embed.footer(CreateEmbedFooter::new(footer))
+    }
+    fn indicator_change(&self, change: &IndicatorChange) -> CreateEmbed {
+        CreateEmbed::new()
+            .title(format!("{} 供電燈號變更", indicator_emoji(change.to)))
+            .description(format!(
+                "{} {} → {} {}",
+                indicator_emoji(change.from),
+                indicator_label(change.from, &ZH_TW),
+                indicator_emoji(change.to),
+                indicator_label(change.to, &ZH_TW),
+            ))
+            .field("預估今日尖峰備轉容量率", format!(
+                "{:.1}% {}",
+                change.reserve_rate,
+                format_pp_change(change.reserve_rate_change),
+            ), false)
+            .colour(indicator_colour(change.to))
+    }
+
+    fn fault_change(&self, change: &FaultChange) -> CreateEmbed {
+        let n = self.numbers;
+        let list = |units: &[(String, f64)]| {
+            units.iter().map(|(unit, capacity)| format!("{} ({})", unit, n.mw(*capacity, 0))).collect::<Vec<_>>().join("\n")
+        };
+        let mut embed = CreateEmbed::new().title("⚠️ 機組故障狀態變更");
+        if !change.faulted.is_empty() {
+            embed = embed.field("🔴 新增故障", list(&change.faulted), true);
+        }
+        if !change.recovered.is_empty() {
+            embed = embed.field("🟢 恢復運轉", list(&change.recovered), true);
+        }
+        let colour = if change.faulted.is_empty() { ReserveIndicator::Green } else { ReserveIndicator::Red };
+        embed.colour(indicator_colour(colour))
+    }
+}
+
@@ -0,0 +1,54 @@
+//! Machine-readable snapshot for other bots (`/power format:json`).
+//!
+//! Field names and units are part of the contract: add fields freely, but bump SCHEMA_VERSION
+//! when renaming, removing or changing the meaning of one.
+
+use std::collections::BTreeMap;
+
+use crate::analysis::CombinedPowerData;
+
+pub const SCHEMA: &str = "taipower-discord/snapshot";
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The snapshot as JSON; MW for generation, 萬瓩 (10 MW) for load figures as Taipower publishes them
+pub fn snapshot_json(data: &CombinedPowerData) -> serde_json::Value {
+    let analysis = &data.power_analysis;
+    // Sorted so the same data always serialises the same way
+    let by_type: BTreeMap<&String, f64> = analysis.generation_by_type.iter().map(|(t, g)| (t, *g)).collect();
+    let capacity_by_type: BTreeMap<&String, f64> = analysis.capacity_by_type.iter().map(|(t, c)| (t, *c)).collect();
+    let load = data.load_data.as_ref().map(|l| {
+        serde_json::json!({
+            "publish_time": l.publish_time.map(|t| t.to_rfc3339()),
+            "current_wan_kw": l.current_load,
+            "util_rate": l.current_util_rate,
+            "forecast_max_supply_wan_kw": l.forecast_max_supply_capacity,
+            "forecast_peak_demand_wan_kw": l.forecast_peak_demand_load,
+            "forecast_peak_reserve_wan_kw": l.forecast_peak_reserve_capacity,
+            "forecast_peak_reserve_rate": l.forecast_peak_reserve_rate,
+            "forecast_peak_reserve_indicator": l.forecast_peak_reserve_indicator.code(),
+            "forecast_peak_hours": l.forecast_peak_hour_range.map(|(start, end)| format!("{}~{}", start.format("%H:%M"), end.format("%H:%M"))),
+        })
+    });
+
+    serde_json::json!({
+        "schema": SCHEMA,
+        "schema_version": SCHEMA_VERSION,
+        "snapshot_id": data.snapshot_id(),
+        "generation": {
+            "update_time": analysis.update_time.to_rfc3339(),
+            "total_mw": analysis.total_generation,
+            "installed_mw": analysis.estimated_max_generation,
+            "renewable_ratio": analysis.renewable_ratio,
+            "private_ratio": analysis.private_ratio,
+            "by_type_mw": by_type,
+            "capacity_by_type_mw": capacity_by_type,
+        },
+        "units": {
+            "restricted": analysis.environmental_restrictions,
+            "maintenance": analysis.maintenance_count,
+            "fault": analysis.fault_count,
+        },
+        "load": load,
+        "demand_response_mw": data.demand_response_mw,
+    })
+}
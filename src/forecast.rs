@@ -0,0 +1,182 @@
+//! The bot's own demand forecast: a small ridge regression of load on temperature, time of day
+//! and working/non-working day, refitted from recorded history every cycle.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use std::f64::consts::PI;
+
+use crate::config;
+use crate::history::History;
+use crate::weather::HourlyTemperatures;
+
+const FEATURES: usize = 10;
+/// Below this many training samples the forecast is too noisy to show
+const MIN_SAMPLES: usize = 144;
+const TRAINING_DAYS: i64 = 28;
+const ACCURACY_DAYS: i64 = 14;
+const RIDGE: f64 = 1e-3;
+
+/// Fixed-date national holidays; lunar ones come from HOLIDAYS
+const FIXED_HOLIDAYS: [(u32, u32); 5] = [(1, 1), (2, 28), (4, 4), (5, 1), (10, 10)];
+
+#[derive(Debug, Clone)]
+pub struct OwnForecast {
+    /// 萬瓩, one hour after the forecast was made
+    pub next_hour: f64,
+    /// 萬瓩, highest predicted hourly load for today
+    pub day_peak: f64,
+    pub day_peak_time: NaiveTime,
+    pub accuracy: Option<ForecastAccuracy>,
+}
+
+/// Mean absolute percentage errors over the last `days` completed days
+#[derive(Debug, Clone)]
+pub struct ForecastAccuracy {
+    pub days: i64,
+    pub bot_day_peak: Option<f64>,
+    pub taipower_day_peak: Option<f64>,
+    pub bot_next_hour: Option<f64>,
+}
+
+pub fn enabled() -> bool {
+    !matches!(config::var("OWN_FORECAST").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// Weekends, fixed national holidays and dates listed in HOLIDAYS (comma separated, YYYY-MM-DD)
+pub struct HolidayCalendar {
+    extra: Vec<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn from_env() -> Self {
+        let extra = config::var("HOLIDAYS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").ok())
+            .collect();
+        HolidayCalendar { extra }
+    }
+
+    pub fn is_off_day(&self, date: NaiveDate) -> bool {
+        matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+            || FIXED_HOLIDAYS.contains(&(date.month(), date.day()))
+            || self.extra.contains(&date)
+    }
+}
+
+fn features(time: NaiveDateTime, temperature: f64, holidays: &HolidayCalendar) -> [f64; FEATURES] {
+    let hour = time.hour() as f64 + time.minute() as f64 / 60.0;
+    let angle = 2.0 * PI * hour / 24.0;
+    let off_day = if holidays.is_off_day(time.date()) { 1.0 } else { 0.0 };
+    [
+        1.0,
+        temperature,
+        temperature * temperature,
+        angle.sin(),
+        angle.cos(),
+        (2.0 * angle).sin(),
+        (2.0 * angle).cos(),
+        off_day,
+        off_day * angle.sin(),
+        off_day * angle.cos(),
+    ]
+}
+
+pub struct Model {
+    coefficients: [f64; FEATURES],
+}
+
+impl Model {
+    /// Fit on (time, temperature °C, load 萬瓩) samples
+    pub fn fit(samples: &[(NaiveDateTime, f64, f64)], holidays: &HolidayCalendar) -> Option<Model> {
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        // Normal equations (XᵀX + λI)β = Xᵀy
+        let mut xtx = [[0.0; FEATURES]; FEATURES];
+        let mut xty = [0.0; FEATURES];
+        for (time, temperature, load) in samples {
+            let x = features(*time, *temperature, holidays);
+            for i in 0..FEATURES {
+                xty[i] += x[i] * load;
+                for j in 0..FEATURES {
+                    xtx[i][j] += x[i] * x[j];
+                }
+            }
+        }
+        // Leave the intercept unpenalised
+        for (i, row) in xtx.iter_mut().enumerate().skip(1) {
+            row[i] += RIDGE * samples.len() as f64;
+        }
+
+        solve(xtx, xty).map(|coefficients| Model { coefficients })
+    }
+
+    pub fn predict(&self, time: NaiveDateTime, temperature: f64, holidays: &HolidayCalendar) -> f64 {
+        features(time, temperature, holidays)
+            .iter()
+            .zip(&self.coefficients)
+            .map(|(x, b)| x * b)
+            .sum()
+    }
+}
+
+/// Gaussian elimination with partial pivoting
+fn solve(mut a: [[f64; FEATURES]; FEATURES], mut b: [f64; FEATURES]) -> Option<[f64; FEATURES]> {
+    for col in 0..FEATURES {
+        let pivot = (col..FEATURES).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col];
+        for row in col + 1..FEATURES {
+            let factor = a[row][col] / pivot_row[col];
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; FEATURES];
+    for row in (0..FEATURES).rev() {
+        let sum: f64 = (row + 1..FEATURES).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    x.iter().all(|v| v.is_finite()).then_some(x)
+}
+
+/// Refit on recent history, predict the hour after `now` (Taipei) and today's peak, and store the predictions
+pub fn run(history: &History, temperatures: &HourlyTemperatures, now: NaiveDateTime) -> Result<Option<OwnForecast>, Box<dyn std::error::Error + Send + Sync>> {
+    let holidays = HolidayCalendar::from_env();
+
+    let samples = history.load_temperature_samples(now - Duration::days(TRAINING_DAYS))?;
+    let Some(model) = Model::fit(&samples, &holidays) else {
+        return Ok(None);
+    };
+
+    let next_hour_time = now + Duration::hours(1);
+    let Some(next_hour_temperature) = temperatures.at(next_hour_time) else {
+        return Ok(None);
+    };
+    let next_hour = model.predict(next_hour_time, next_hour_temperature, &holidays);
+
+    let today = now.date();
+    let (day_peak_time, day_peak) = temperatures
+        .hours
+        .iter()
+        .filter(|(time, _)| time.date() == today)
+        .map(|(time, temperature)| (time.time(), model.predict(*time, *temperature, &holidays)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .ok_or("No temperature forecast for today")?;
+
+    history.record_forecast("next_hour", next_hour_time, next_hour)?;
+    history.record_forecast("day_peak", today.and_time(day_peak_time), day_peak)?;
+
+    let accuracy = history.forecast_accuracy(today - Duration::days(ACCURACY_DAYS), today)?;
+
+    Ok(Some(OwnForecast { next_hour, day_peak, day_peak_time, accuracy }))
+}
+
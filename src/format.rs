@@ -0,0 +1,91 @@
+//! Report output outside the renderers themselves: the generation mix chart attached to reports,
+//! and `analyze-file`'s offline report.
+
+use std::path::PathBuf;
+use tracing::{error, warn};
+
+use crate::analysis::CombinedPowerData;
+use crate::chart;
+use crate::embed::EmbedRenderer;
+use crate::regional;
+use crate::accessible::AccessibleRenderer;
+use crate::render::{self, DiscordTextRenderer, PlainRenderer, Renderer, ReportFormat};
+use crate::taipower_api::{read_payload_files, PayloadFiles};
+use crate::validation::SanityBounds;
+use crate::stress::{StressIndex, StressWeights};
+
+/// Slices beyond this are merged into one "other" slice so the donut stays readable
+const MIX_CHART_SLICES: usize = 7;
+
+/// Donut chart of generation by type in the target's language and number style; channels that
+/// share those get the cached PNG instead of a re-render
+pub fn generation_mix_chart(cache: &chart::ChartCache, data: &CombinedPowerData, text: &DiscordTextRenderer) -> Option<Vec<u8>> {
+    let mut slices: Vec<(String, f64)> = render::sorted_generation(&data.power_analysis)
+        .into_iter()
+        .filter(|(_, generation)| *generation > 0.0)
+        .map(|(energy_type, generation)| (text.locale.energy_type(&energy_type), generation))
+        .collect();
+    if slices.len() > MIX_CHART_SLICES {
+        let rest: f64 = slices.drain(MIX_CHART_SLICES - 1..).map(|(_, generation)| generation).sum();
+        slices.push((text.locale.labels().other.to_string(), rest));
+    }
+
+    let key = chart::ChartCache::slices_key(&format!("mix:{}", text.locale.code()), &slices, text.numbers);
+    if let Some(png) = cache.png(&key) {
+        return Some(png);
+    }
+    match chart::donut_chart(text.locale.labels().by_type, &slices, "MW", text.numbers) {
+        Ok(png) => {
+            cache.insert(key, png.clone());
+            Some(png)
+        }
+        Err(why) => {
+            error!("Error rendering generation mix chart: {:?}", why);
+            None
+        }
+    }
+}
+
+pub fn analyze_files(paths: &[PathBuf], format: ReportFormat) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let PayloadFiles { power_analysis, load_data, regional_shares } = read_payload_files(paths)?;
+    
+    let Some(power_analysis) = power_analysis else {
+        // Nothing to build a full report from, but the parsed values are still useful
+        return Ok(format!("No generation payload supplied; parsed load data:\n{:#?}", load_data));
+    };
+    
+    // Flag values the live bot would refuse to publish
+    let sanity_bounds = SanityBounds::from_env();
+    let violations = sanity_bounds
+        .check_power(&power_analysis)
+        .into_iter()
+        .chain(load_data.as_ref().map(|data| sanity_bounds.check_load(data)).unwrap_or_default());
+    for violation in violations {
+        warn!("Data quality violation: {}", violation.describe());
+    }
+    
+    let regions = match (&regional_shares, &load_data) {
+        (Some(shares), Some(load)) => regional::estimate(shares, load.current_load, regional::import_warn_percent_from_env()),
+        _ => Vec::new(),
+    };
+    
+    let stress = StressIndex::compute(&power_analysis, load_data.as_ref(), None, &StressWeights::from_env());
+    let combined_data = CombinedPowerData {
+        power_analysis,
+        load_data,
+        regions,
+        temperature: None,
+        own_forecast: None,
+        outages: Vec::new(),
+        demand_response_mw: None,
+        stress,
+        trend: None,
+    };
+    
+    Ok(match format {
+        ReportFormat::Text => DiscordTextRenderer::default().report(&combined_data),
+        ReportFormat::Plain => PlainRenderer.report(&combined_data),
+        ReportFormat::Accessible => AccessibleRenderer::default().report(&combined_data),
+        ReportFormat::Embed => serde_json::to_string_pretty(&EmbedRenderer::default().report(&combined_data))?,
+    })
+}
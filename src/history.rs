@@ -0,0 +1,1766 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+use crate::clock::{self, parse_taipei_datetime, Clock};
+use crate::config;
+use crate::forecast::ForecastAccuracy;
+use crate::latency::{CycleTimings, Phase};
+use crate::locale::{Locale, NumberFormat};
+use crate::mentions::{LadderMention, LadderRung, MentionPolicy, MentionTarget};
+use crate::push::{AlertType, PushService, PushSubscription};
+use crate::render::{ReportFormat, ReportMode, ReportProfile, ReportSections};
+use crate::validation::Violation;
+use crate::analysis::{self, availability_by_type, CombinedPowerData};
+use crate::taipower_api::PowerUnit;
+
+// Each entry upgrades the schema by one version (tracked in PRAGMA user_version)
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at TEXT NOT NULL,
+        day TEXT NOT NULL,
+        update_time TEXT NOT NULL,
+        total_generation REAL NOT NULL,
+        estimated_max_generation REAL NOT NULL,
+        renewable_ratio REAL NOT NULL,
+        private_ratio REAL NOT NULL,
+        environmental_restrictions INTEGER NOT NULL,
+        maintenance_count INTEGER NOT NULL,
+        fault_count INTEGER NOT NULL,
+        generation_by_type TEXT NOT NULL,
+        current_load REAL,
+        current_util_rate REAL,
+        forecast_peak_reserve_rate REAL,
+        forecast_peak_reserve_indicator TEXT,
+        publish_time TEXT
+    );
+    CREATE INDEX snapshots_day ON snapshots(day);",
+    "CREATE TABLE data_quality (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at TEXT NOT NULL,
+        metric TEXT NOT NULL,
+        value REAL NOT NULL,
+        min_bound REAL,
+        max_bound REAL
+    );",
+    "CREATE TABLE unit_samples (
+        recorded_at TEXT NOT NULL,
+        unit_name TEXT NOT NULL,
+        unit_type TEXT NOT NULL,
+        capacity REAL NOT NULL,
+        generation REAL NOT NULL
+    );
+    CREATE INDEX unit_samples_name_time ON unit_samples(unit_name, recorded_at);
+    CREATE INDEX unit_samples_time ON unit_samples(recorded_at);",
+    "CREATE TABLE follows (
+        channel_id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        locale TEXT NOT NULL,
+        profile TEXT NOT NULL,
+        created_by INTEGER NOT NULL,
+        created_at TEXT NOT NULL
+    );",
+    "ALTER TABLE snapshots ADD COLUMN temperature REAL;
+    ALTER TABLE snapshots ADD COLUMN forecast_peak_demand_load REAL;
+    CREATE TABLE forecasts (
+        made_at TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        target_time TEXT NOT NULL,
+        predicted REAL NOT NULL
+    );
+    CREATE INDEX forecasts_kind_target ON forecasts(kind, target_time);",
+    "CREATE TABLE incidents (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        kind TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        ended_at TEXT
+    );",
+    "CREATE TABLE mention_policies (
+        channel_id INTEGER PRIMARY KEY,
+        policy TEXT NOT NULL,
+        role_id INTEGER,
+        user_id INTEGER
+    );",
+    "ALTER TABLE snapshots ADD COLUMN capacity_by_type TEXT;",
+    "CREATE TABLE guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        number_format TEXT NOT NULL
+    );",
+    "CREATE TABLE push_subscriptions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL,
+        service TEXT NOT NULL,
+        endpoint TEXT NOT NULL,
+        alert_type TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX push_subscriptions_alert ON push_subscriptions(alert_type);",
+    "ALTER TABLE guild_settings ADD COLUMN channel_id INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN interval_minutes INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN sections TEXT;",
+    "ALTER TABLE guild_settings ADD COLUMN alert_role_id INTEGER;",
+    "ALTER TABLE snapshots ADD COLUMN snapshot_id TEXT;
+    CREATE INDEX snapshots_snapshot_id ON snapshots(snapshot_id);
+    CREATE TABLE deliveries (
+        snapshot_id TEXT NOT NULL,
+        channel_id INTEGER NOT NULL,
+        delivered_at TEXT NOT NULL,
+        PRIMARY KEY (snapshot_id, channel_id)
+    );",
+    "ALTER TABLE guild_settings ADD COLUMN report_mode TEXT;
+    CREATE TABLE live_messages (
+        channel_id INTEGER PRIMARY KEY,
+        message_id INTEGER NOT NULL
+    );",
+    "ALTER TABLE mention_policies ADD COLUMN guild_id INTEGER;",
+    "ALTER TABLE snapshots ADD COLUMN schema_version INTEGER NOT NULL DEFAULT 1;",
+    "CREATE TABLE leases (
+        name TEXT PRIMARY KEY,
+        holder TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );",
+    "ALTER TABLE snapshots ADD COLUMN source TEXT;
+    CREATE TABLE fetch_failures (
+        recorded_at TEXT NOT NULL,
+        feed TEXT NOT NULL,
+        error TEXT NOT NULL
+    );
+    CREATE INDEX fetch_failures_time ON fetch_failures(recorded_at);",
+    "ALTER TABLE guild_settings ADD COLUMN report_format TEXT;
+    CREATE TABLE user_settings (
+        user_id INTEGER PRIMARY KEY,
+        report_format TEXT
+    );",
+    "CREATE TABLE records (
+        name TEXT PRIMARY KEY,
+        value REAL NOT NULL,
+        recorded_at TEXT NOT NULL,
+        announced_on TEXT
+    );",
+    "CREATE TABLE notes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        author_id INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        text TEXT NOT NULL,
+        posted_at TEXT
+    );
+    CREATE INDEX notes_guild_time ON notes(guild_id, created_at);",
+    "CREATE TABLE ping_ladder (
+        guild_id INTEGER NOT NULL,
+        below REAL NOT NULL,
+        mention TEXT NOT NULL,
+        active INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (guild_id, below)
+    );",
+    "CREATE TABLE availability (
+        day TEXT NOT NULL,
+        energy_type TEXT NOT NULL,
+        available REAL NOT NULL,
+        capacity REAL NOT NULL,
+        PRIMARY KEY (day, energy_type)
+    );",
+    "ALTER TABLE snapshots ADD COLUMN top_plant TEXT;",
+    "ALTER TABLE guild_settings ADD COLUMN digest_csv INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE guild_settings ADD COLUMN ticker_channel_id INTEGER;",
+    "ALTER TABLE guild_settings ADD COLUMN supply_poll INTEGER NOT NULL DEFAULT 0;
+    CREATE TABLE supply_polls (
+        guild_id INTEGER NOT NULL,
+        day TEXT NOT NULL,
+        channel_id INTEGER NOT NULL,
+        message_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, day)
+    );",
+    "CREATE TABLE cycle_timings (
+        recorded_at TEXT NOT NULL,
+        fetch_ms REAL NOT NULL,
+        parse_ms REAL NOT NULL,
+        analyze_ms REAL NOT NULL,
+        render_ms REAL NOT NULL,
+        post_ms REAL NOT NULL
+    );
+    CREATE INDEX cycle_timings_time ON cycle_timings(recorded_at);",
+];
+
+/// What the rows `record` writes to `snapshots` mean. When that changes (a field's unit, a JSON
+/// column's keys, a newly required value), bump this and add an upgrade to SNAPSHOT_UPGRADES so
+/// older rows are rewritten on open. Every row an upgrade sees moves up a version, whatever it
+/// couldn't fill in left NULL, so no upgrade runs twice over the same rows
+pub const SNAPSHOT_SCHEMA_VERSION: i64 = 2;
+
+/// `SNAPSHOT_UPGRADES[i]` rewrites rows at schema version i + 1 to version i + 2 and bumps
+/// schema_version on all of them. A value it can't derive is left NULL (and logged), the same as
+/// a newly recorded row that lacks it
+const SNAPSHOT_UPGRADES: &[fn(&Connection) -> rusqlite::Result<()>] = &[snapshot_v1_to_v2];
+
+/// v2: rows carry a snapshot_id. Rows from before it was recorded get the one the bot would have
+/// derived from the same feed timestamps; one whose update_time doesn't parse gets none, like a
+/// payload without a timestamp
+fn snapshot_v1_to_v2(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("UPDATE snapshots SET schema_version = 2 WHERE schema_version = 1 AND snapshot_id IS NOT NULL", [])?;
+    let mut stmt = conn.prepare("SELECT id, update_time, publish_time FROM snapshots WHERE schema_version = 1")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut skipped = 0;
+    for (id, update_time, publish_time) in rows {
+        let Some(update_time) = parse_taipei_datetime(&update_time) else {
+            skipped += 1;
+            continue;
+        };
+        let publish_time = publish_time.as_deref().and_then(parse_taipei_datetime);
+        let snapshot_id = analysis::snapshot_id(&[(analysis::GENERATION_FEED, Some(update_time)), (analysis::LOAD_FEED, publish_time)]);
+        conn.execute("UPDATE snapshots SET snapshot_id = ?1, schema_version = 2 WHERE id = ?2", params![snapshot_id, id])?;
+    }
+    conn.execute("UPDATE snapshots SET schema_version = 2 WHERE schema_version = 1", [])?;
+    if skipped > 0 {
+        warn!("Upgraded {} snapshot(s) to schema v2 without a snapshot_id: update_time doesn't parse", skipped);
+    }
+    Ok(())
+}
+
+fn upgrade_snapshots(conn: &mut Connection) -> rusqlite::Result<()> {
+    for (i, upgrade) in SNAPSHOT_UPGRADES.iter().enumerate() {
+        let from = i as i64 + 1;
+        let tx = conn.transaction()?;
+        let count: i64 = tx.query_row("SELECT COUNT(*) FROM snapshots WHERE schema_version = ?1", params![from], |row| row.get(0))?;
+        if count > 0 {
+            info!("Upgrading {} snapshot(s) from schema v{} to v{}", count, from, from + 1);
+            upgrade(&tx)?;
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// How often a guild's channel gets the scheduled report unless /config says otherwise
+pub const DEFAULT_REPORT_INTERVAL_MINUTES: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub id: i64,
+    pub kind: String,
+    pub summary: String,
+    pub started_at: NaiveDateTime,
+    /// None while still ongoing
+    pub ended_at: Option<NaiveDateTime>,
+}
+
+/// An operator's annotation from /note
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub id: i64,
+    pub author_id: u64,
+    pub created_at: NaiveDateTime,
+    pub text: String,
+}
+
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        author_id: row.get::<_, i64>(1)? as u64,
+        created_at: parse_timestamp(&row.get::<_, String>(2)?),
+        text: row.get(3)?,
+    })
+}
+
+/// One stored snapshot, as needed for exports
+#[derive(Debug, Clone)]
+pub struct SnapshotRow {
+    pub recorded_at: NaiveDateTime,
+    pub total_generation: f64,
+    pub renewable_ratio: f64,
+    pub fault_count: i32,
+    pub maintenance_count: i32,
+    pub current_load: Option<f64>,
+    pub current_util_rate: Option<f64>,
+    pub reserve_rate: Option<f64>,
+    pub indicator: Option<String>,
+}
+
+/// Pumped storage and battery output at one sample, from the per-unit history
+#[derive(Debug, Clone)]
+pub struct StorageSample {
+    pub recorded_at: NaiveDateTime,
+    pub reserve_rate: Option<f64>,
+    /// MW; negative while pumping or charging
+    pub pumped: f64,
+    pub battery: f64,
+}
+
+const SNAPSHOT_ROW_COLUMNS: &str = "recorded_at, total_generation, renewable_ratio, fault_count, maintenance_count,
+    current_load, current_util_rate, forecast_peak_reserve_rate, forecast_peak_reserve_indicator";
+
+fn snapshot_row(row: &rusqlite::Row) -> rusqlite::Result<SnapshotRow> {
+    Ok(SnapshotRow {
+        recorded_at: parse_timestamp(&row.get::<_, String>(0)?),
+        total_generation: row.get(1)?,
+        renewable_ratio: row.get(2)?,
+        fault_count: row.get(3)?,
+        maintenance_count: row.get(4)?,
+        current_load: row.get(5)?,
+        current_util_rate: row.get(6)?,
+        reserve_rate: row.get(7)?,
+        indicator: row.get(8)?,
+    })
+}
+
+fn parse_timestamp(value: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap_or_default()
+}
+
+fn incident_from_row(row: &rusqlite::Row) -> rusqlite::Result<Incident> {
+    Ok(Incident {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        summary: row.get(2)?,
+        started_at: parse_timestamp(&row.get::<_, String>(3)?),
+        ended_at: row.get::<_, Option<String>>(4)?.map(|t| parse_timestamp(&t)),
+    })
+}
+
+/// MAPE (%) over (actual, predicted) pairs
+fn mean_absolute_percentage_error(pairs: impl Iterator<Item = (f64, f64)>) -> Option<f64> {
+    let errors: Vec<f64> = pairs
+        .filter(|(actual, _)| *actual > 0.0)
+        .map(|(actual, predicted)| (predicted - actual).abs() / actual * 100.0)
+        .collect();
+    (!errors.is_empty()).then(|| errors.iter().sum::<f64>() / errors.len() as f64)
+}
+
+/// Per-guild settings from /config and /numbers
+#[derive(Debug, Clone)]
+pub struct GuildConfig {
+    pub guild_id: u64,
+    /// Where the scheduled report goes; None until set with /config channel set
+    pub channel_id: Option<u64>,
+    pub interval_minutes: u32,
+    pub sections: ReportSections,
+    pub numbers: NumberFormat,
+    /// Pinged when the reserve indicator turns orange or red
+    pub alert_role_id: Option<u64>,
+    /// None follows REPORT_MODE
+    pub mode: Option<ReportMode>,
+    /// None follows REPORT_FORMAT
+    pub format: Option<ReportFormat>,
+    /// Attach the day's samples as CSV to the daily digest
+    pub digest_csv: bool,
+    /// Voice channel renamed every cycle to show the reserve rate
+    pub ticker_channel_id: Option<u64>,
+    /// Post a "供電警戒?" poll on days forecast orange or worse
+    pub supply_poll: bool,
+}
+
+impl GuildConfig {
+    pub fn new(guild_id: u64) -> Self {
+        GuildConfig {
+            guild_id,
+            channel_id: None,
+            interval_minutes: DEFAULT_REPORT_INTERVAL_MINUTES,
+            sections: ReportSections::default(),
+            numbers: NumberFormat::default(),
+            alert_role_id: None,
+            mode: None,
+            format: None,
+            digest_csv: false,
+            ticker_channel_id: None,
+            supply_poll: false,
+        }
+    }
+
+    pub fn mode(&self) -> ReportMode {
+        self.mode.unwrap_or_else(ReportMode::from_env)
+    }
+}
+
+fn guild_config_from_row(row: &rusqlite::Row) -> rusqlite::Result<GuildConfig> {
+    Ok(GuildConfig {
+        guild_id: row.get::<_, i64>(0)? as u64,
+        channel_id: row.get::<_, Option<i64>>(1)?.map(|id| id as u64),
+        interval_minutes: row.get::<_, Option<u32>>(2)?.unwrap_or(DEFAULT_REPORT_INTERVAL_MINUTES),
+        sections: row.get::<_, Option<String>>(3)?.and_then(|s| ReportSections::parse(&s)).unwrap_or_default(),
+        numbers: NumberFormat::parse(&row.get::<_, String>(4)?).unwrap_or_default(),
+        alert_role_id: row.get::<_, Option<i64>>(5)?.map(|id| id as u64),
+        mode: row.get::<_, Option<String>>(6)?.and_then(|m| ReportMode::parse(&m)),
+        format: row.get::<_, Option<String>>(7)?.and_then(|f| ReportFormat::parse(&f)),
+        digest_csv: row.get(8)?,
+        ticker_channel_id: row.get::<_, Option<i64>>(9)?.map(|id| id as u64),
+        supply_poll: row.get(10)?,
+    })
+}
+
+const GUILD_CONFIG_COLUMNS: &str =
+    "guild_id, channel_id, interval_minutes, sections, number_format, alert_role_id, report_mode, report_format, digest_csv, ticker_channel_id, supply_poll";
+
+/// A channel (usually in another server) that receives a relayed copy of the main feed
+#[derive(Debug, Clone)]
+pub struct Follow {
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub locale: Locale,
+    pub profile: ReportProfile,
+}
+
+/// Which units get per-unit history rows (UNIT_HISTORY*); off by default since it is ~200 rows per cycle
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitHistoryPolicy {
+    pub enabled: bool,
+    /// Plant/unit name prefixes to keep; empty means every unit
+    pub prefixes: Vec<String>,
+    /// Skip units smaller than this (MW)
+    pub min_capacity: f64,
+    pub retention_days: i64,
+}
+
+impl UnitHistoryPolicy {
+    pub fn from_env() -> Self {
+        let setting = config::var("UNIT_HISTORY").unwrap_or_default();
+        let setting = setting.trim();
+        let (enabled, prefixes) = match setting {
+            "" | "off" | "false" => (false, Vec::new()),
+            "all" | "on" | "true" => (true, Vec::new()),
+            list => (true, list.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect()),
+        };
+
+        UnitHistoryPolicy {
+            enabled,
+            prefixes,
+            min_capacity: config::var("UNIT_HISTORY_MIN_CAPACITY").ok().and_then(|v| v.parse().ok()).unwrap_or(0.0),
+            retention_days: config::var("UNIT_HISTORY_RETENTION_DAYS").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+        }
+    }
+
+    fn includes(&self, unit: &PowerUnit) -> bool {
+        self.enabled
+            && unit.capacity >= self.min_capacity
+            && !unit.unit_name.contains("小計")
+            && (self.prefixes.is_empty() || self.prefixes.iter().any(|p| unit.unit_name.starts_with(p.as_str())))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SummarySource {
+    Local,
+    Archive,
+}
+
+/// Where a day's numbers came from, for the digest's statistics footer
+#[derive(Debug, Clone)]
+pub struct DataCoverage {
+    pub samples: usize,
+    /// Fraction (0-1) of the day's update slots with at least one sample
+    pub coverage: f64,
+    /// Failed fetches per feed
+    pub failures: Vec<(String, usize)>,
+    /// Generation endpoints that served the samples
+    pub sources: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub source: SummarySource,
+    pub sample_count: usize,
+    pub peak_load: Option<f64>,
+    pub peak_time: Option<String>,
+    /// Lowest load of the day (萬瓩); only known from local samples
+    pub min_load: Option<f64>,
+    pub min_time: Option<String>,
+    pub min_reserve_rate: Option<f64>,
+    /// 備轉容量率 at the day's peak, which is what the archive has instead of the lowest
+    pub peak_reserve_rate: Option<f64>,
+    pub average_renewable_ratio: Option<f64>,
+    pub generation_mix: Vec<(String, f64)>,
+    /// Units newly in 故障 over the day, summed from rises in the fault count between samples
+    pub fault_events: Option<i32>,
+    /// The plant that led generation in the most samples
+    pub top_plant: Option<String>,
+    pub max_fault_count: Option<i32>,
+    pub max_maintenance_count: Option<i32>,
+    pub max_environmental_restrictions: Option<i32>,
+}
+
+pub struct History {
+    conn: Mutex<Connection>,
+    /// Timestamps rows are recorded with; a replay's simulated time instead of the system clock
+    clock: Arc<dyn Clock>,
+}
+
+impl History {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::open_with_clock(path, clock::system())
+    }
+
+    pub fn open_with_clock(path: impl AsRef<Path>, clock: Arc<dyn Clock>) -> rusqlite::Result<Self> {
+        let mut conn = Connection::open(path)?;
+        migrate(&conn)?;
+        upgrade_snapshots(&mut conn)?;
+        Ok(History { conn: Mutex::new(conn), clock })
+    }
+
+    /// Before exit: waits for a write in progress, then lets SQLite update its query statistics
+    pub fn flush(&self) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute_batch("PRAGMA optimize;")
+    }
+
+    pub fn record(&self, data: &CombinedPowerData) -> rusqlite::Result<()> {
+        let now = self.clock.now();
+        let analysis = &data.power_analysis;
+        let load = data.load_data.as_ref();
+        let generation_by_type = serde_json::to_string(&analysis.generation_by_type)
+            .unwrap_or_else(|_| "{}".to_string());
+        let capacity_by_type = serde_json::to_string(&analysis.capacity_by_type)
+            .unwrap_or_else(|_| "{}".to_string());
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO snapshots (
+                recorded_at, day, update_time, total_generation, estimated_max_generation,
+                renewable_ratio, private_ratio, environmental_restrictions, maintenance_count,
+                fault_count, generation_by_type, current_load, current_util_rate,
+                forecast_peak_reserve_rate, forecast_peak_reserve_indicator, publish_time,
+                temperature, forecast_peak_demand_load, capacity_by_type, snapshot_id, schema_version, source, top_plant
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            params![
+                now.format("%Y-%m-%d %H:%M:%S").to_string(),
+                now.format("%Y-%m-%d").to_string(),
+                analysis.update_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                analysis.total_generation,
+                analysis.estimated_max_generation,
+                analysis.renewable_ratio,
+                analysis.private_ratio,
+                analysis.environmental_restrictions,
+                analysis.maintenance_count,
+                analysis.fault_count,
+                generation_by_type,
+                load.map(|l| l.current_load),
+                load.map(|l| l.current_util_rate),
+                load.map(|l| l.forecast_peak_reserve_rate),
+                load.map(|l| l.forecast_peak_reserve_indicator.code()),
+                load.and_then(|l| l.publish_time).map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+                data.temperature,
+                load.map(|l| l.forecast_peak_demand_load),
+                capacity_by_type,
+                data.snapshot_id(),
+                SNAPSHOT_SCHEMA_VERSION,
+                analysis.source,
+                analysis.top_plant.0,
+            ],
+        )?;
+
+        // Summed over the day's snapshots, so a day's ratio weighs every sample equally
+        let day = now.format("%Y-%m-%d").to_string();
+        for (energy_type, (available, capacity)) in availability_by_type(&analysis.units) {
+            conn.execute(
+                "INSERT INTO availability (day, energy_type, available, capacity) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(day, energy_type) DO UPDATE SET
+                    available = available + excluded.available, capacity = capacity + excluded.capacity",
+                params![day, energy_type, available, capacity],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Store per-unit output for the units selected by `policy`, pruning rows past retention
+    pub fn record_units(&self, units: &[PowerUnit], policy: &UnitHistoryPolicy) -> rusqlite::Result<()> {
+        if !policy.enabled {
+            return Ok(());
+        }
+
+        let now = self.clock.now();
+        let recorded_at = now.format("%Y-%m-%d %H:%M:%S").to_string();
+        let cutoff = (now - chrono::Duration::days(policy.retention_days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO unit_samples (recorded_at, unit_name, unit_type, capacity, generation)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for unit in units.iter().filter(|u| policy.includes(u)) {
+                insert.execute(params![recorded_at, unit.unit_name, unit.unit_type, unit.capacity, unit.generation])?;
+            }
+        }
+        tx.execute("DELETE FROM unit_samples WHERE recorded_at < ?1", params![cutoff])?;
+        tx.commit()
+    }
+
+    /// (time, generation MW, capacity MW) for one unit since `since` (Taipei time)
+    pub fn unit_series(&self, unit_name: &str, since: NaiveDateTime) -> rusqlite::Result<Vec<(NaiveDateTime, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT recorded_at, generation, capacity FROM unit_samples
+             WHERE unit_name = ?1 AND recorded_at >= ?2 ORDER BY recorded_at",
+        )?;
+        let rows = stmt.query_map(params![unit_name, since.format("%Y-%m-%d %H:%M:%S").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?))
+        })?;
+
+        let mut series = Vec::new();
+        for row in rows {
+            let (recorded_at, generation, capacity) = row?;
+            if let Ok(time) = NaiveDateTime::parse_from_str(&recorded_at, "%Y-%m-%d %H:%M:%S") {
+                series.push((time, generation, capacity));
+            }
+        }
+        Ok(series)
+    }
+
+    /// The latest sample whose reserve rate fell at least `min_drop` points from the sample
+    /// before, as (time, rate before, rate after)
+    pub fn latest_reserve_dip(&self, min_drop: f64) -> rusqlite::Result<Option<(NaiveDateTime, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT recorded_at, previous, rate FROM (
+                SELECT recorded_at, forecast_peak_reserve_rate AS rate,
+                    LAG(forecast_peak_reserve_rate) OVER (ORDER BY recorded_at) AS previous
+                FROM snapshots WHERE forecast_peak_reserve_rate > 0
+            ) WHERE previous - rate >= ?1 ORDER BY recorded_at DESC LIMIT 1",
+            params![min_drop],
+            |row| Ok((parse_timestamp(&row.get::<_, String>(0)?), row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+    }
+
+    /// Pumped storage (明湖/明潭 or 抽蓄 types) and battery (儲能) output summed per sample between
+    /// `from` and `to`. Units are stored a moment after their snapshot, so the two are matched by
+    /// minute; samples without unit history are left out
+    pub fn storage_response(&self, from: NaiveDateTime, to: NaiveDateTime) -> rusqlite::Result<Vec<StorageSample>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT s.recorded_at, s.forecast_peak_reserve_rate,
+                SUM(CASE WHEN u.unit_type LIKE '%抽蓄%' OR u.unit_name LIKE '明湖%' OR u.unit_name LIKE '明潭%' THEN u.generation ELSE 0 END),
+                SUM(CASE WHEN u.unit_type LIKE '%儲能%' THEN u.generation ELSE 0 END)
+             FROM snapshots s
+             JOIN unit_samples u ON substr(u.recorded_at, 1, 16) = substr(s.recorded_at, 1, 16)
+             WHERE s.recorded_at BETWEEN ?1 AND ?2 AND u.recorded_at BETWEEN ?1 AND datetime(?2, '+1 minute')
+             GROUP BY s.recorded_at ORDER BY s.recorded_at",
+        )?;
+        let rows = stmt.query_map(
+            params![from.format("%Y-%m-%d %H:%M:%S").to_string(), to.format("%Y-%m-%d %H:%M:%S").to_string()],
+            |row| {
+                Ok(StorageSample {
+                    recorded_at: parse_timestamp(&row.get::<_, String>(0)?),
+                    reserve_rate: row.get(1)?,
+                    pumped: row.get(2)?,
+                    battery: row.get(3)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// Record that `snapshot_id` is being posted to `channel_id`; false if it already was
+    pub fn claim_delivery(&self, snapshot_id: &str, channel_id: u64) -> rusqlite::Result<bool> {
+        let now = self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let conn = self.conn.lock().unwrap();
+        // Claims only matter while the same snapshot can still come around again; pruned first
+        // so an expired one doesn't block this claim
+        conn.execute("DELETE FROM deliveries WHERE delivered_at < datetime(?1, '-7 days')", params![now])?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO deliveries (snapshot_id, channel_id, delivered_at) VALUES (?1, ?2, ?3)",
+            params![snapshot_id, channel_id as i64, now],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Log a failed fetch of `feed` for the digest's statistics; kept for 30 days
+    pub fn record_fetch_failure(&self, feed: &str, error: &str) -> rusqlite::Result<()> {
+        let now = self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO fetch_failures (recorded_at, feed, error) VALUES (?1, ?2, ?3)",
+            params![now, feed, error],
+        )?;
+        conn.execute("DELETE FROM fetch_failures WHERE recorded_at < datetime(?1, '-30 days')", params![now])?;
+        Ok(())
+    }
+
+    /// How long each phase of the cycle just finished took; kept for 30 days
+    pub fn record_cycle_timings(&self, timings: &CycleTimings) -> rusqlite::Result<()> {
+        let now = self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let ms = |phase: Phase| timings.get(phase).as_secs_f64() * 1000.0;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO cycle_timings (recorded_at, fetch_ms, parse_ms, analyze_ms, render_ms, post_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![now, ms(Phase::Fetch), ms(Phase::Parse), ms(Phase::Analyze), ms(Phase::Render), ms(Phase::Post)],
+        )?;
+        conn.execute("DELETE FROM cycle_timings WHERE recorded_at < datetime(?1, '-30 days')", params![now])?;
+        Ok(())
+    }
+
+    /// Cycles recorded since `from`, oldest first
+    pub fn cycle_timings_since(&self, from: NaiveDateTime) -> rusqlite::Result<Vec<(NaiveDateTime, CycleTimings)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT recorded_at, fetch_ms, parse_ms, analyze_ms, render_ms, post_ms FROM cycle_timings
+             WHERE recorded_at >= ?1 ORDER BY recorded_at",
+        )?;
+        let rows = stmt.query_map(params![from.format("%Y-%m-%d %H:%M:%S").to_string()], |row| {
+            let mut phases = [std::time::Duration::ZERO; Phase::ALL.len()];
+            for (i, phase) in phases.iter_mut().enumerate() {
+                *phase = std::time::Duration::from_secs_f64(row.get::<_, f64>(i + 1)?.max(0.0) / 1000.0);
+            }
+            Ok((row.get::<_, String>(0)?, CycleTimings::new(phases)))
+        })?;
+        let mut cycles = Vec::new();
+        for row in rows {
+            let (recorded_at, timings) = row?;
+            if let Ok(recorded_at) = NaiveDateTime::parse_from_str(&recorded_at, "%Y-%m-%d %H:%M:%S") {
+                cycles.push((recorded_at, timings));
+            }
+        }
+        Ok(cycles)
+    }
+
+    /// How completely `date` was sampled, given one update every `interval_minutes`
+    pub fn data_coverage(&self, date: NaiveDate, interval_minutes: u32) -> rusqlite::Result<DataCoverage> {
+        let conn = self.conn.lock().unwrap();
+        let day = date.format("%Y-%m-%d").to_string();
+        let interval_minutes = interval_minutes.max(1);
+
+        let mut stmt = conn.prepare("SELECT recorded_at, source FROM snapshots WHERE day = ?1")?;
+        let rows = stmt.query_map(params![day], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?;
+        let mut samples = 0;
+        let mut slots = HashSet::new();
+        let mut sources = Vec::new();
+        for row in rows {
+            let (recorded_at, source) = row?;
+            samples += 1;
+            if let Ok(time) = NaiveDateTime::parse_from_str(&recorded_at, "%Y-%m-%d %H:%M:%S") {
+                slots.insert((time.hour() * 60 + time.minute()) / interval_minutes);
+            }
+            if let Some(source) = source
+                && !sources.contains(&source)
+            {
+                sources.push(source);
+            }
+        }
+        sources.sort();
+
+        let next_day = (date + chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+        let mut stmt = conn.prepare(
+            "SELECT feed, COUNT(*) FROM fetch_failures WHERE recorded_at >= ?1 AND recorded_at < ?2 GROUP BY feed ORDER BY feed",
+        )?;
+        let failures = stmt
+            .query_map(params![day, next_day], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(DataCoverage { samples, coverage: slots.len() as f64 / (24 * 60u32).div_ceil(interval_minutes) as f64, failures, sources })
+    }
+
+    /// Take or renew lease `name` for `holder` until `ttl` from now. Fails (false) while another
+    /// holder's lease hasn't expired
+    pub fn claim_lease(&self, name: &str, holder: &str, ttl: chrono::Duration) -> rusqlite::Result<bool> {
+        let now = self.clock.now();
+        let conn = self.conn.lock().unwrap();
+        let claimed = conn.execute(
+            "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+             WHERE leases.holder = excluded.holder OR leases.expires_at < ?4",
+            params![
+                name,
+                holder,
+                (now + ttl).format("%Y-%m-%d %H:%M:%S").to_string(),
+                now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ],
+        )?;
+        Ok(claimed > 0)
+    }
+
+    pub fn release_lease(&self, name: &str, holder: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM leases WHERE name = ?1 AND holder = ?2", params![name, holder])?;
+        Ok(())
+    }
+
+    pub fn release_delivery(&self, snapshot_id: &str, channel_id: u64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM deliveries WHERE snapshot_id = ?1 AND channel_id = ?2",
+            params![snapshot_id, channel_id as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_follow(&self, follow: &Follow, created_by: u64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO follows (channel_id, guild_id, locale, profile, created_by, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(channel_id) DO UPDATE SET locale = excluded.locale, profile = excluded.profile",
+            params![
+                follow.channel_id as i64,
+                follow.guild_id as i64,
+                follow.locale.code(),
+                follow.profile.code(),
+                created_by as i64,
+                self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether the channel was following
+    pub fn remove_follow(&self, channel_id: u64) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute("DELETE FROM follows WHERE channel_id = ?1", params![channel_id as i64])?;
+        Ok(removed > 0)
+    }
+
+    pub fn follows(&self) -> rusqlite::Result<Vec<Follow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT guild_id, channel_id, locale, profile FROM follows")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Follow {
+                guild_id: row.get::<_, i64>(0)? as u64,
+                channel_id: row.get::<_, i64>(1)? as u64,
+                locale: Locale::parse(&row.get::<_, String>(2)?).unwrap_or_default(),
+                profile: ReportProfile::parse(&row.get::<_, String>(3)?).unwrap_or(ReportProfile::Full),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Returns the new subscription's id
+    pub fn add_push_subscription(&self, user_id: u64, service: PushService, endpoint: &str, alert_type: AlertType) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO push_subscriptions (user_id, service, endpoint, alert_type, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                user_id as i64,
+                service.code(),
+                endpoint,
+                alert_type.code(),
+                self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Only the owner can remove a subscription; returns whether one was removed
+    pub fn remove_push_subscription(&self, user_id: u64, id: i64) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM push_subscriptions WHERE id = ?1 AND user_id = ?2",
+            params![id, user_id as i64],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn user_push_subscriptions(&self, user_id: u64) -> rusqlite::Result<Vec<PushSubscription>> {
+        self.query_push_subscriptions("user_id = ?1", params![user_id as i64])
+    }
+
+    pub fn push_subscriptions(&self, alert_type: AlertType) -> rusqlite::Result<Vec<PushSubscription>> {
+        self.query_push_subscriptions("alert_type = ?1", params![alert_type.code()])
+    }
+
+    fn query_push_subscriptions(&self, filter: &str, params: &[&dyn rusqlite::ToSql]) -> rusqlite::Result<Vec<PushSubscription>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, user_id, service, endpoint, alert_type FROM push_subscriptions WHERE {} ORDER BY id",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        let mut subscriptions = Vec::new();
+        for row in rows {
+            let (id, user_id, service, endpoint, alert_type) = row?;
+            // Skip rows written by a newer version with services or alert types we don't know
+            if let (Some(service), Some(alert_type)) = (PushService::parse(&service), AlertType::parse(&alert_type)) {
+                subscriptions.push(PushSubscription { id, user_id, service, endpoint, alert_type });
+            }
+        }
+        Ok(subscriptions)
+    }
+
+    /// Append a sanity-check failure to the data-quality log
+    pub fn record_violation(&self, violation: &Violation) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO data_quality (recorded_at, metric, value, min_bound, max_bound)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                violation.metric.key(),
+                violation.value,
+                violation.bounds.min,
+                violation.bounds.max,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_mention_target(&self, guild_id: u64, channel_id: u64, target: &MentionTarget) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO mention_policies (channel_id, policy, role_id, user_id, guild_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                channel_id as i64,
+                target.policy.code(),
+                target.role_id.map(|id| id as i64),
+                target.user_id.map(|id| id as i64),
+                guild_id as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete everything stored for a guild: settings, follows, mention policies and the
+    /// per-channel delivery state. `channel_ids` are the guild's channels, if they could be
+    /// listed; channels the database already links to the guild are always included.
+    /// Returns the number of rows deleted
+    pub fn purge_guild(&self, guild_id: u64, channel_ids: &[u64]) -> rusqlite::Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let guild = guild_id as i64;
+
+        let mut channels: Vec<i64> = channel_ids.iter().map(|id| *id as i64).collect();
+        for sql in [
+            "SELECT channel_id FROM guild_settings WHERE guild_id = ?1 AND channel_id IS NOT NULL",
+            "SELECT channel_id FROM follows WHERE guild_id = ?1",
+            "SELECT channel_id FROM mention_policies WHERE guild_id = ?1",
+        ] {
+            let mut stmt = tx.prepare(sql)?;
+            let rows = stmt.query_map(params![guild], |row| row.get::<_, i64>(0))?;
+            for row in rows {
+                channels.push(row?);
+            }
+        }
+        channels.sort_unstable();
+        channels.dedup();
+
+        let mut deleted = 0;
+        for sql in [
+            "DELETE FROM guild_settings WHERE guild_id = ?1",
+            "DELETE FROM follows WHERE guild_id = ?1",
+            "DELETE FROM notes WHERE guild_id = ?1",
+            "DELETE FROM ping_ladder WHERE guild_id = ?1",
+            "DELETE FROM supply_polls WHERE guild_id = ?1",
+        ] {
+            deleted += tx.execute(sql, params![guild])?;
+        }
+        for channel in &channels {
+            for sql in [
+                "DELETE FROM mention_policies WHERE channel_id = ?1",
+                "DELETE FROM live_messages WHERE channel_id = ?1",
+                "DELETE FROM deliveries WHERE channel_id = ?1",
+            ] {
+                deleted += tx.execute(sql, params![channel])?;
+            }
+        }
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Annotate the current time in `guild_id`'s feed; returns the note's id
+    pub fn add_note(&self, guild_id: u64, author_id: u64, text: &str) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO notes (guild_id, author_id, created_at, text) VALUES (?1, ?2, ?3, ?4)",
+            params![guild_id as i64, author_id as i64, self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string(), text],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Notes not yet included in a scheduled report, oldest first
+    pub fn pending_notes(&self, guild_id: u64) -> rusqlite::Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, author_id, created_at, text FROM notes WHERE guild_id = ?1 AND posted_at IS NULL ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map(params![guild_id as i64], note_from_row)?;
+        rows.collect()
+    }
+
+    pub fn mark_notes_posted(&self, ids: &[i64]) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string();
+        for id in ids {
+            conn.execute("UPDATE notes SET posted_at = ?2 WHERE id = ?1", params![id, now])?;
+        }
+        Ok(())
+    }
+
+    /// Notes made since `since`, oldest first, for marking on charts
+    pub fn notes_since(&self, guild_id: u64, since: NaiveDateTime) -> rusqlite::Result<Vec<Note>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, author_id, created_at, text FROM notes WHERE guild_id = ?1 AND created_at >= ?2 ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map(params![guild_id as i64, since.format("%Y-%m-%d %H:%M:%S").to_string()], note_from_row)?;
+        rows.collect()
+    }
+
+    /// The guild's ping ladder, highest threshold first
+    pub fn ping_ladder(&self, guild_id: u64) -> rusqlite::Result<Vec<LadderRung>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT below, mention, active FROM ping_ladder WHERE guild_id = ?1 ORDER BY below DESC")?;
+        let rows = stmt.query_map(params![guild_id as i64], |row| {
+            Ok((row.get::<_, f64>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?))
+        })?;
+        let mut rungs = Vec::new();
+        for row in rows {
+            let (below, mention, active) = row?;
+            if let Some(mention) = LadderMention::parse(&mention) {
+                rungs.push(LadderRung { below, mention, active });
+            }
+        }
+        Ok(rungs)
+    }
+
+    /// Add a rung, or change who an existing threshold mentions
+    pub fn set_ladder_rung(&self, guild_id: u64, below: f64, mention: LadderMention) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO ping_ladder (guild_id, below, mention) VALUES (?1, ?2, ?3)
+             ON CONFLICT(guild_id, below) DO UPDATE SET mention = excluded.mention",
+            params![guild_id as i64, below, mention.code()],
+        )?;
+        Ok(())
+    }
+
+    /// False if the guild had no rung at that threshold
+    pub fn remove_ladder_rung(&self, guild_id: u64, below: f64) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute("DELETE FROM ping_ladder WHERE guild_id = ?1 AND below = ?2", params![guild_id as i64, below])?;
+        Ok(deleted > 0)
+    }
+
+    pub fn set_ladder_active(&self, guild_id: u64, below: f64, active: bool) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE ping_ladder SET active = ?3 WHERE guild_id = ?1 AND below = ?2",
+            params![guild_id as i64, below, active],
+        )?;
+        Ok(())
+    }
+
+    pub fn mention_target(&self, channel_id: u64) -> rusqlite::Result<Option<MentionTarget>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT policy, role_id, user_id FROM mention_policies WHERE channel_id = ?1",
+            params![channel_id as i64],
+            |row| {
+                Ok(MentionTarget {
+                    policy: MentionPolicy::parse(&row.get::<_, String>(0)?).unwrap_or(MentionPolicy::Never),
+                    role_id: row.get::<_, Option<i64>>(1)?.map(|id| id as u64),
+                    user_id: row.get::<_, Option<i64>>(2)?.map(|id| id as u64),
+                })
+            },
+        )
+        .optional()
+    }
+
+    /// The guild's settings, or the defaults if it never changed any
+    pub fn guild_config(&self, guild_id: u64) -> rusqlite::Result<GuildConfig> {
+        let conn = self.conn.lock().unwrap();
+        let config = conn
+            .query_row(
+                &format!("SELECT {} FROM guild_settings WHERE guild_id = ?1", GUILD_CONFIG_COLUMNS),
+                params![guild_id as i64],
+                guild_config_from_row,
+            )
+            .optional()?;
+        Ok(config.unwrap_or_else(|| GuildConfig::new(guild_id)))
+    }
+
+    pub fn save_guild_config(&self, config: &GuildConfig) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO guild_settings ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", GUILD_CONFIG_COLUMNS),
+            params![
+                config.guild_id as i64,
+                config.channel_id.map(|id| id as i64),
+                config.interval_minutes,
+                config.sections.code(),
+                config.numbers.code(),
+                config.alert_role_id.map(|id| id as i64),
+                config.mode.map(|m| m.code()),
+                config.format.map(|f| f.code()),
+                config.digest_csv,
+                config.ticker_channel_id.map(|id| id as i64),
+                config.supply_poll,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The report format a user picked for their own queries, e.g. for a screen reader
+    pub fn user_report_format(&self, user_id: u64) -> rusqlite::Result<Option<ReportFormat>> {
+        let conn = self.conn.lock().unwrap();
+        let format = conn
+            .query_row("SELECT report_format FROM user_settings WHERE user_id = ?1", params![user_id as i64], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .optional()?;
+        Ok(format.flatten().and_then(|f| ReportFormat::parse(&f)))
+    }
+
+    /// None clears the preference
+    pub fn set_user_report_format(&self, user_id: u64, format: Option<ReportFormat>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match format {
+            Some(format) => conn.execute(
+                "INSERT OR REPLACE INTO user_settings (user_id, report_format) VALUES (?1, ?2)",
+                params![user_id as i64, format.code()],
+            )?,
+            None => conn.execute("DELETE FROM user_settings WHERE user_id = ?1", params![user_id as i64])?,
+        };
+        Ok(())
+    }
+
+    /// The pinned live-status message in `channel_id`, if one was posted
+    pub fn live_message(&self, channel_id: u64) -> rusqlite::Result<Option<u64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT message_id FROM live_messages WHERE channel_id = ?1",
+            params![channel_id as i64],
+            |row| row.get::<_, i64>(0).map(|id| id as u64),
+        )
+        .optional()
+    }
+
+    pub fn set_live_message(&self, channel_id: u64, message_id: u64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO live_messages (channel_id, message_id) VALUES (?1, ?2)",
+            params![channel_id as i64, message_id as i64],
+        )?;
+        Ok(())
+    }
+
+    /// The channel and message of the guild's supply poll on `day`, if one was posted
+    pub fn supply_poll(&self, guild_id: u64, day: NaiveDate) -> rusqlite::Result<Option<(u64, u64)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT channel_id, message_id FROM supply_polls WHERE guild_id = ?1 AND day = ?2",
+            params![guild_id as i64, day.format("%Y-%m-%d").to_string()],
+            |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+        )
+        .optional()
+    }
+
+    pub fn record_supply_poll(&self, guild_id: u64, day: NaiveDate, channel_id: u64, message_id: u64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO supply_polls (guild_id, day, channel_id, message_id) VALUES (?1, ?2, ?3, ?4)",
+            params![guild_id as i64, day.format("%Y-%m-%d").to_string(), channel_id as i64, message_id as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Guilds that picked a channel for the scheduled report
+    pub fn report_guilds(&self) -> rusqlite::Result<Vec<GuildConfig>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM guild_settings WHERE channel_id IS NOT NULL", GUILD_CONFIG_COLUMNS))?;
+        let rows = stmt.query_map([], guild_config_from_row)?;
+        rows.collect()
+    }
+
+    /// Guilds that picked a voice channel for the reserve rate ticker
+    pub fn ticker_guilds(&self) -> rusqlite::Result<Vec<GuildConfig>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM guild_settings WHERE ticker_channel_id IS NOT NULL", GUILD_CONFIG_COLUMNS))?;
+        let rows = stmt.query_map([], guild_config_from_row)?;
+        rows.collect()
+    }
+
+    /// The guild's chosen number format, or the default if it never set one
+    pub fn number_format(&self, guild_id: Option<u64>) -> NumberFormat {
+        let Some(guild_id) = guild_id else {
+            return NumberFormat::default();
+        };
+        match self.guild_config(guild_id) {
+            Ok(config) => config.numbers,
+            Err(e) => {
+                error!("Error reading number format for guild {}: {:?}", guild_id, e);
+                NumberFormat::default()
+            }
+        }
+    }
+
+    /// A stored record: (value, when it was set, the day it was last announced)
+    pub fn record_value(&self, name: &str) -> rusqlite::Result<Option<(f64, NaiveDateTime, Option<NaiveDate>)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT value, recorded_at, announced_on FROM records WHERE name = ?1",
+            params![name],
+            |row| {
+                let announced_on: Option<String> = row.get(2)?;
+                Ok((
+                    row.get(0)?,
+                    parse_timestamp(&row.get::<_, String>(1)?),
+                    announced_on.and_then(|day| NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()),
+                ))
+            },
+        )
+        .optional()
+    }
+
+    pub fn set_record(&self, name: &str, value: f64, at: NaiveDateTime, announced_on: Option<NaiveDate>) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO records (name, value, recorded_at, announced_on) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET value = excluded.value, recorded_at = excluded.recorded_at, announced_on = excluded.announced_on",
+            params![
+                name,
+                value,
+                at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                announced_on.map(|day| day.format("%Y-%m-%d").to_string()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Highest renewable share ever recorded, and when
+    pub fn max_renewable_ratio(&self) -> rusqlite::Result<Option<(f64, NaiveDateTime)>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT renewable_ratio, recorded_at FROM snapshots ORDER BY renewable_ratio DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, parse_timestamp(&row.get::<_, String>(1)?))),
+        )
+        .optional()
+    }
+
+    /// Highest renewable share recorded on `day`
+    pub fn day_max_renewable_ratio(&self, day: NaiveDate) -> rusqlite::Result<Option<f64>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT MAX(renewable_ratio) FROM snapshots WHERE day = ?1",
+            params![day.format("%Y-%m-%d").to_string()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Start an incident unless one of the same kind is already open; returns the open incident's id
+    pub fn open_incident(&self, kind: &str, summary: &str) -> rusqlite::Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let open: Option<i64> = conn
+            .query_row("SELECT id FROM incidents WHERE kind = ?1 AND ended_at IS NULL", params![kind], |row| row.get(0))
+            .optional()?;
+        if let Some(id) = open {
+            return Ok(id);
+        }
+        conn.execute(
+            "INSERT INTO incidents (kind, summary, started_at) VALUES (?1, ?2, ?3)",
+            params![kind, summary, self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn close_incidents(&self, kind: &str) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE incidents SET ended_at = ?2 WHERE kind = ?1 AND ended_at IS NULL",
+            params![kind, self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn incident(&self, id: i64) -> rusqlite::Result<Option<Incident>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT id, kind, summary, started_at, ended_at FROM incidents WHERE id = ?1",
+            params![id],
+            incident_from_row,
+        )
+        .optional()
+    }
+
+    /// Most recent first
+    pub fn recent_incidents(&self, limit: i64) -> rusqlite::Result<Vec<Incident>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, summary, started_at, ended_at FROM incidents ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit], incident_from_row)?;
+        rows.collect()
+    }
+
+    pub fn snapshots_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> rusqlite::Result<Vec<SnapshotRow>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM snapshots WHERE recorded_at BETWEEN ?1 AND ?2 ORDER BY recorded_at",
+            SNAPSHOT_ROW_COLUMNS
+        ))?;
+        let rows = stmt.query_map(
+            params![from.format("%Y-%m-%d %H:%M:%S").to_string(), to.format("%Y-%m-%d %H:%M:%S").to_string()],
+            snapshot_row,
+        )?;
+        rows.collect()
+    }
+
+    /// The most recent sample, e.g. to see how long the bot was offline
+    pub fn latest_snapshot(&self) -> rusqlite::Result<Option<SnapshotRow>> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!("SELECT {} FROM snapshots ORDER BY recorded_at DESC LIMIT 1", SNAPSHOT_ROW_COLUMNS),
+            [],
+            snapshot_row,
+        )
+        .optional()
+    }
+
+    /// The sample closest to `at`, if one was recorded within `tolerance` of it
+    pub fn snapshot_near(&self, at: NaiveDateTime, tolerance: chrono::Duration) -> rusqlite::Result<Option<SnapshotRow>> {
+        let samples = self.snapshots_between(at - tolerance, at + tolerance)?;
+        Ok(samples.into_iter().min_by_key(|s| (s.recorded_at - at).num_seconds().abs()))
+    }
+
+    /// The sample closest to `at` however far away, with its MW per energy type
+    pub fn nearest_snapshot(&self, at: NaiveDateTime) -> rusqlite::Result<Option<(SnapshotRow, HashMap<String, f64>)>> {
+        let conn = self.conn.lock().unwrap();
+        let at_text = at.format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut candidates = Vec::new();
+        for query in [
+            "WHERE recorded_at <= ?1 ORDER BY recorded_at DESC LIMIT 1",
+            "WHERE recorded_at > ?1 ORDER BY recorded_at LIMIT 1",
+        ] {
+            let sql = format!("SELECT {}, generation_by_type FROM snapshots {}", SNAPSHOT_ROW_COLUMNS, query);
+            let found = conn
+                .query_row(&sql, params![at_text], |row| Ok((snapshot_row(row)?, row.get::<_, String>(9)?)))
+                .optional()?;
+            candidates.extend(found);
+        }
+        Ok(candidates
+            .into_iter()
+            .min_by_key(|(s, _)| (s.recorded_at - at).num_seconds().abs())
+            .map(|(sample, mix)| (sample, serde_json::from_str(&mix).unwrap_or_default())))
+    }
+
+    /// Every snapshot recorded on `date`, oldest first, with its generation by energy type (MW)
+    pub fn day_samples(&self, date: NaiveDate) -> rusqlite::Result<Vec<(SnapshotRow, HashMap<String, f64>)>> {
+        let conn = self.conn.lock().unwrap();
+        let sql = format!("SELECT {}, generation_by_type FROM snapshots WHERE day = ?1 ORDER BY recorded_at", SNAPSHOT_ROW_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![date.format("%Y-%m-%d").to_string()], |row| {
+            Ok((snapshot_row(row)?, row.get::<_, String>(9)?))
+        })?;
+        rows.map(|row| row.map(|(sample, mix)| (sample, serde_json::from_str(&mix).unwrap_or_default()))).collect()
+    }
+
+    /// (recorded_at, metric key, value) of data-quality violations in the range
+    pub fn violations_between(&self, from: NaiveDateTime, to: NaiveDateTime) -> rusqlite::Result<Vec<(NaiveDateTime, String, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT recorded_at, metric, value FROM data_quality
+             WHERE recorded_at BETWEEN ?1 AND ?2 ORDER BY recorded_at",
+        )?;
+        let rows = stmt.query_map(
+            params![from.format("%Y-%m-%d %H:%M:%S").to_string(), to.format("%Y-%m-%d %H:%M:%S").to_string()],
+            |row| Ok((parse_timestamp(&row.get::<_, String>(0)?), row.get(1)?, row.get(2)?)),
+        )?;
+        rows.collect()
+    }
+
+    /// (recorded_at, generation, installed capacity) in MW of one energy type on a Taipei calendar day
+    pub fn energy_type_series(&self, energy_type: &str, date: NaiveDate) -> rusqlite::Result<Vec<(NaiveDateTime, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT recorded_at, generation_by_type, capacity_by_type FROM snapshots
+             WHERE day = ?1 AND capacity_by_type IS NOT NULL ORDER BY recorded_at",
+        )?;
+        let rows = stmt.query_map(params![date.format("%Y-%m-%d").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+
+        let mut series = Vec::new();
+        for row in rows {
+            let (recorded_at, generation, capacity) = row?;
+            let value = |json: &str| {
+                serde_json::from_str::<HashMap<String, f64>>(json)
+                    .ok()
+                    .and_then(|by_type| by_type.get(energy_type).copied())
+            };
+            if let (Some(generation), Some(capacity)) = (value(&generation), value(&capacity)) {
+                series.push((parse_timestamp(&recorded_at), generation, capacity));
+            }
+        }
+        Ok(series)
+    }
+
+    /// Average MW per energy type for each month since `since`, oldest first
+    pub fn monthly_generation_mix(&self, since: NaiveDate) -> rusqlite::Result<Vec<(NaiveDate, HashMap<String, f64>)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT day, generation_by_type FROM snapshots WHERE day >= ?1 ORDER BY day")?;
+        let rows = stmt.query_map(params![since.format("%Y-%m-%d").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut months: Vec<(NaiveDate, usize, HashMap<String, f64>)> = Vec::new();
+        for row in rows {
+            let (day, mix) = row?;
+            let Some(month) = NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok().and_then(|d| d.with_day(1)) else {
+                continue;
+            };
+            let Ok(mix) = serde_json::from_str::<HashMap<String, f64>>(&mix) else {
+                continue;
+            };
+            if months.last().is_none_or(|(m, _, _)| *m != month) {
+                months.push((month, 0, HashMap::new()));
+            }
+            let (_, samples, totals) = months.last_mut().unwrap();
+            *samples += 1;
+            for (energy_type, generation) in mix {
+                *totals.entry(energy_type).or_insert(0.0) += generation;
+            }
+        }
+
+        Ok(months
+            .into_iter()
+            .map(|(month, samples, totals)| {
+                let averages = totals.into_iter().map(|(t, total)| (t, total / samples as f64)).collect();
+                (month, averages)
+            })
+            .collect())
+    }
+
+    /// Capacity-weighted availability (%) per energy type for each month since `since`, oldest first
+    pub fn monthly_availability(&self, since: NaiveDate) -> rusqlite::Result<Vec<(NaiveDate, HashMap<String, f64>)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT substr(day, 1, 7), energy_type, SUM(available), SUM(capacity) FROM availability
+             WHERE day >= ?1 GROUP BY 1, 2 ORDER BY 1",
+        )?;
+        let rows = stmt.query_map(params![since.format("%Y-%m-%d").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?))
+        })?;
+
+        let mut months: Vec<(NaiveDate, HashMap<String, f64>)> = Vec::new();
+        for row in rows {
+            let (month, energy_type, available, capacity) = row?;
+            let Ok(month) = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d") else {
+                continue;
+            };
+            if capacity <= 0.0 {
+                continue;
+            }
+            if months.last().is_none_or(|(m, _)| *m != month) {
+                months.push((month, HashMap::new()));
+            }
+            months.last_mut().unwrap().1.insert(energy_type, available / capacity * 100.0);
+        }
+        Ok(months)
+    }
+
+    /// Time series of one snapshot column; `column` must be a known numeric column name
+    pub fn snapshot_series(&self, column: &str, since: NaiveDateTime) -> rusqlite::Result<Vec<(NaiveDateTime, f64)>> {
+        const COLUMNS: [&str; 5] = ["current_load", "forecast_peak_reserve_rate", "renewable_ratio", "total_generation", "current_util_rate"];
+        if !COLUMNS.contains(&column) {
+            return Err(rusqlite::Error::InvalidColumnName(column.to_string()));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT recorded_at, {0} FROM snapshots WHERE recorded_at >= ?1 AND {0} > 0 ORDER BY recorded_at",
+            column
+        ))?;
+        let rows = stmt.query_map(params![since.format("%Y-%m-%d %H:%M:%S").to_string()], |row| {
+            Ok((parse_timestamp(&row.get::<_, String>(0)?), row.get::<_, f64>(1)?))
+        })?;
+        rows.collect()
+    }
+
+    /// (recorded_at, temperature, load) for training the demand forecaster
+    pub fn load_temperature_samples(&self, since: NaiveDateTime) -> rusqlite::Result<Vec<(NaiveDateTime, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT recorded_at, temperature, current_load FROM snapshots
+             WHERE recorded_at >= ?1 AND temperature IS NOT NULL AND current_load > 0",
+        )?;
+        let rows = stmt.query_map(params![since.format("%Y-%m-%d %H:%M:%S").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, f64>(2)?))
+        })?;
+
+        let mut samples = Vec::new();
+        for row in rows {
+            let (recorded_at, temperature, load) = row?;
+            if let Ok(time) = NaiveDateTime::parse_from_str(&recorded_at, "%Y-%m-%d %H:%M:%S") {
+                samples.push((time, temperature, load));
+            }
+        }
+        Ok(samples)
+    }
+
+    pub fn record_forecast(&self, kind: &str, target_time: NaiveDateTime, predicted: f64) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO forecasts (made_at, kind, target_time, predicted) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.clock.now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                kind,
+                target_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                predicted,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Compare the bot's and Taipower's forecasts with what was recorded, for days in [from, until)
+    pub fn forecast_accuracy(&self, from: NaiveDate, until: NaiveDate) -> rusqlite::Result<Option<ForecastAccuracy>> {
+        let conn = self.conn.lock().unwrap();
+        let from = from.format("%Y-%m-%d").to_string();
+        let until = until.format("%Y-%m-%d").to_string();
+
+        // Day peak: the first forecast of each day against that day's highest recorded load
+        let mut stmt = conn.prepare(
+            "SELECT MAX(s.current_load),
+                    (SELECT f.predicted FROM forecasts f
+                     WHERE f.kind = 'day_peak' AND substr(f.target_time, 1, 10) = s.day
+                     ORDER BY f.made_at LIMIT 1),
+                    (SELECT t.forecast_peak_demand_load FROM snapshots t
+                     WHERE t.day = s.day AND t.forecast_peak_demand_load > 0
+                     ORDER BY t.recorded_at LIMIT 1)
+             FROM snapshots s
+             WHERE s.day >= ?1 AND s.day < ?2 AND s.current_load > 0
+             GROUP BY s.day",
+        )?;
+        let days = stmt
+            .query_map(params![from, until], |row| {
+                Ok((row.get::<_, f64>(0)?, row.get::<_, Option<f64>>(1)?, row.get::<_, Option<f64>>(2)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // Next hour: each prediction against the closest snapshot within 10 minutes of its target
+        let mut stmt = conn.prepare(
+            "SELECT f.predicted,
+                    (SELECT s.current_load FROM snapshots s
+                     WHERE s.current_load > 0
+                       AND s.recorded_at BETWEEN datetime(f.target_time, '-10 minutes') AND datetime(f.target_time, '+10 minutes')
+                     ORDER BY abs(julianday(s.recorded_at) - julianday(f.target_time)) LIMIT 1)
+             FROM forecasts f
+             WHERE f.kind = 'next_hour' AND f.target_time >= ?1 AND f.target_time < ?2",
+        )?;
+        let next_hours = stmt
+            .query_map(params![from, until], |row| Ok((row.get::<_, f64>(0)?, row.get::<_, Option<f64>>(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        if days.is_empty() {
+            return Ok(None);
+        }
+
+        let bot_day_peak = mean_absolute_percentage_error(days.iter().filter_map(|(actual, bot, _)| Some((*actual, (*bot)?))));
+        let taipower_day_peak = mean_absolute_percentage_error(days.iter().filter_map(|(actual, _, taipower)| Some((*actual, (*taipower)?))));
+        let bot_next_hour = mean_absolute_percentage_error(next_hours.iter().filter_map(|(predicted, actual)| Some(((*actual)?, *predicted))));
+
+        Ok(Some(ForecastAccuracy {
+            days: days.len() as i64,
+            bot_day_peak,
+            taipower_day_peak,
+            bot_next_hour,
+        }))
+    }
+
+    /// Time and load of each day's highest recorded load since `since`
+    pub fn daily_peaks(&self, since: NaiveDate) -> rusqlite::Result<Vec<(NaiveDate, NaiveTime, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        // SQLite returns the bare recorded_at from the row that holds the MAX
+        let mut stmt = conn.prepare(
+            "SELECT day, recorded_at, MAX(current_load) FROM snapshots
+             WHERE day >= ?1 AND current_load > 0
+             GROUP BY day ORDER BY day",
+        )?;
+        let rows = stmt.query_map(params![since.format("%Y-%m-%d").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?))
+        })?;
+
+        let mut peaks = Vec::new();
+        for row in rows {
+            let (day, recorded_at, load) = row?;
+            let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok();
+            let time = recorded_at.get(11..16).and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok());
+            if let (Some(day), Some(time)) = (day, time) {
+                peaks.push((day, time, load));
+            }
+        }
+        Ok(peaks)
+    }
+
+    /// Summarise every snapshot recorded on the given Taipei calendar day. Peak and low times are
+    /// the feeds' own (the load feed's publish time, else the generation feed's), not when we polled
+    pub fn daily_summary(&self, date: NaiveDate) -> rusqlite::Result<Option<DailySummary>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(publish_time, update_time), current_load, forecast_peak_reserve_rate, generation_by_type,
+                    fault_count, maintenance_count, environmental_restrictions, renewable_ratio, top_plant
+             FROM snapshots WHERE day = ?1 ORDER BY recorded_at",
+        )?;
+        let rows = stmt.query_map(params![date.format("%Y-%m-%d").to_string()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<f64>>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i32>(4)?,
+                row.get::<_, i32>(5)?,
+                row.get::<_, i32>(6)?,
+                row.get::<_, f64>(7)?,
+                row.get::<_, Option<String>>(8)?,
+            ))
+        })?;
+
+        let mut summary = DailySummary {
+            date,
+            source: SummarySource::Local,
+            sample_count: 0,
+            peak_load: None,
+            peak_time: None,
+            min_load: None,
+            min_time: None,
+            min_reserve_rate: None,
+            peak_reserve_rate: None,
+            average_renewable_ratio: None,
+            generation_mix: Vec::new(),
+            fault_events: None,
+            top_plant: None,
+            max_fault_count: None,
+            max_maintenance_count: None,
+            max_environmental_restrictions: None,
+        };
+        let mut mix_totals: HashMap<String, f64> = HashMap::new();
+        let mut renewable_total = 0.0;
+        let mut fault_events = 0;
+        let mut previous_faults = None;
+        let mut top_plants: HashMap<String, usize> = HashMap::new();
+
+        for row in rows {
+            let (data_time, load, reserve_rate, mix, faults, maintenance, restrictions, renewable_ratio, top_plant) = row?;
+            summary.sample_count += 1;
+            renewable_total += renewable_ratio;
+            fault_events += (faults - previous_faults.unwrap_or(faults)).max(0);
+            previous_faults = Some(faults);
+            if let Some(plant) = top_plant.filter(|p| p != "未知") {
+                *top_plants.entry(plant).or_insert(0) += 1;
+            }
+
+            if let Some(load) = load.filter(|l| *l > 0.0)
+                && summary.peak_load.is_none_or(|peak| load > peak)
+            {
+                summary.peak_load = Some(load);
+                summary.peak_time = data_time.get(11..16).map(str::to_string);
+            }
+            if let Some(load) = load.filter(|l| *l > 0.0)
+                && summary.min_load.is_none_or(|min| load < min)
+            {
+                summary.min_load = Some(load);
+                summary.min_time = data_time.get(11..16).map(str::to_string);
+            }
+            if let Some(rate) = reserve_rate.filter(|r| *r > 0.0)
+                && summary.min_reserve_rate.is_none_or(|min| rate < min)
+            {
+                summary.min_reserve_rate = Some(rate);
+            }
+            if let Ok(mix) = serde_json::from_str::<HashMap<String, f64>>(&mix) {
+                for (energy_type, generation) in mix {
+                    *mix_totals.entry(energy_type).or_insert(0.0) += generation;
+                }
+            }
+            summary.max_fault_count = summary.max_fault_count.max(Some(faults));
+            summary.max_maintenance_count = summary.max_maintenance_count.max(Some(maintenance));
+            summary.max_environmental_restrictions =
+                summary.max_environmental_restrictions.max(Some(restrictions));
+        }
+
+        if summary.sample_count == 0 {
+            return Ok(None);
+        }
+
+        // Average each energy type over the day's samples
+        let samples = summary.sample_count as f64;
+        summary.average_renewable_ratio = Some(renewable_total / samples);
+        summary.fault_events = Some(fault_events);
+        summary.top_plant = top_plants.into_iter().max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0))).map(|(plant, _)| plant);
+        summary.generation_mix = mix_totals
+            .into_iter()
+            .map(|(energy_type, total)| (energy_type, total / samples))
+            .collect();
+        summary
+            .generation_mix
+            .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(Some(summary))
+    }
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .optional()?
+        .unwrap_or(0);
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        info!("Applying history migration {}", i + 1);
+        conn.execute_batch(migration)?;
+        conn.pragma_update(None, "user_version", (i + 1) as i64)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_upgrade_finishes_rows_it_cannot_derive_an_id_for() {
+        let history = History::open(":memory:").unwrap();
+        let mut conn = history.conn.lock().unwrap();
+        for update_time in ["2024-07-15 14:30:00", "not a time"] {
+            conn.execute(
+                "INSERT INTO snapshots (recorded_at, day, update_time, total_generation, estimated_max_generation, renewable_ratio,
+                    private_ratio, environmental_restrictions, maintenance_count, fault_count, generation_by_type, schema_version)
+                 VALUES ('2024-07-15 14:31:00', '2024-07-15', ?1, 0, 0, 0, 0, 0, 0, 0, '{}', 1)",
+                params![update_time],
+            )
+            .unwrap();
+        }
+        upgrade_snapshots(&mut conn).unwrap();
+        let rows = |conn: &Connection| -> Vec<(i64, Option<String>)> {
+            let mut stmt = conn.prepare("SELECT schema_version, snapshot_id FROM snapshots ORDER BY id").unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(Result::unwrap).collect()
+        };
+        let upgraded = rows(&conn);
+        assert!(matches!(upgraded.as_slice(), [(2, Some(_)), (2, None)]), "{:?}", upgraded);
+
+        // Nothing is left at v1, so a second open changes (and warns about) nothing
+        upgrade_snapshots(&mut conn).unwrap();
+        assert_eq!(rows(&conn), upgraded);
+    }
+
+    #[test]
+    fn deliveries_are_claimed_once_per_channel_until_released_or_expired() {
+        let start = clock::taipei_datetime(NaiveDate::from_ymd_opt(2024, 7, 15).unwrap().and_hms_opt(14, 0, 0).unwrap()).unwrap();
+        let clock = Arc::new(clock::ManualClock::new(start));
+        let history = History::open_with_clock(":memory:", clock.clone()).unwrap();
+        assert!(history.claim_delivery("abc", 1).unwrap());
+        assert!(!history.claim_delivery("abc", 1).unwrap());
+        assert!(history.claim_delivery("abc", 2).unwrap());
+        assert!(history.claim_delivery("abc/compact", 1).unwrap());
+
+        // A failed send gives the claim back for the retry
+        history.release_delivery("abc", 1).unwrap();
+        assert!(history.claim_delivery("abc", 1).unwrap());
+        assert!(!history.claim_delivery("abc", 1).unwrap());
+
+        clock.set(start + chrono::Duration::days(8));
+        assert!(history.claim_delivery("abc", 2).unwrap());
+    }
+}
@@ -0,0 +1,95 @@
+//! The shared HTTP client and the retry policy for Taipower fetches, so a momentary network
+//! blip is retried instead of ending the cycle.
+//!
+//! HTTP_RETRY_ATTEMPTS (default 3) is the total number of tries; the wait before try n is
+//! HTTP_RETRY_BASE_MS (default 2000) × 2^(n-2), capped at 30 seconds, of which a random half is
+//! jitter so several bots don't hammer the endpoint in lockstep.
+//!
+//! TAIPOWER_BASE_URL (e.g. `http://127.0.0.1:8080`) sends every Taipower fetch to another host
+//! with the same paths, for testing against a mock server.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::taipower_api::TaipowerError;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// One client for every outbound fetch, so connections (and TLS sessions) to Taipower are kept
+/// in its pool between cycles instead of being set up again every time
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30))
+            .pool_idle_timeout(Duration::from_secs(15 * 60))
+            .gzip(true)
+            .build()
+            .expect("Error building HTTP client")
+    })
+}
+
+/// `url` on TAIPOWER_BASE_URL's scheme and host when that is set
+pub fn endpoint(url: &str) -> String {
+    let base = crate::config::var("TAIPOWER_BASE_URL").unwrap_or_default();
+    let base = base.trim().trim_end_matches('/');
+    if base.is_empty() {
+        return url.to_string();
+    }
+    let path = url.split_once("://").and_then(|(_, rest)| rest.find('/').map(|i| &rest[i..])).unwrap_or("/");
+    format!("{}{}", base, path)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    attempts: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Self {
+        static POLICY: OnceLock<RetryPolicy> = OnceLock::new();
+        *POLICY.get_or_init(|| {
+            let attempts = crate::config::var("HTTP_RETRY_ATTEMPTS").ok().and_then(|v| v.trim().parse().ok()).unwrap_or(3);
+            let base_ms = crate::config::var("HTTP_RETRY_BASE_MS").ok().and_then(|v| v.trim().parse().ok()).unwrap_or(2000);
+            RetryPolicy { attempts: u32::max(attempts, 1), base_delay: Duration::from_millis(base_ms) }
+        })
+    }
+
+    /// Wait before retry number `retry` (1-based): exponential, with the upper half jittered
+    fn delay(&self, retry: u32) -> Duration {
+        let delay = self.base_delay.saturating_mul(1 << (retry - 1).min(16)).min(MAX_DELAY);
+        let jitter = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+/// Run `fetch` until it succeeds or the policy's attempts run out. Only transient errors are
+/// retried: a payload that couldn't be parsed, or a 404, would fail the same way again
+pub async fn with_retry<T, F, Fut>(what: &str, mut fetch: F) -> Result<T, TaipowerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, TaipowerError>>,
+{
+    let policy = RetryPolicy::from_env();
+    let mut retry = 0;
+    loop {
+        match fetch().await {
+            Ok(value) => return Ok(value),
+            Err(e) if !e.is_transient() || retry + 1 >= policy.attempts => return Err(e),
+            Err(e) => {
+                retry += 1;
+                let delay = policy.delay(retry);
+                warn!(endpoint = what, retry, delay_ms = delay.as_millis() as u64, "Fetching {} failed ({}), retry {}/{}", what, e, retry, policy.attempts - 1);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
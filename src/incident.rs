@@ -0,0 +1,236 @@
+//! Notable grid events (the reserve indicator reaching orange or worse) and their postmortem export.
+
+use chrono::{Duration, NaiveDateTime};
+use serenity::builder::CreateAttachment;
+use std::fmt::Write;
+use tracing::error;
+
+use crate::alerts::IndicatorChange;
+use crate::chart::{self, Series};
+use crate::history::{History, Incident, SnapshotRow, StorageSample};
+use crate::locale::{NumberFormat, ZH_TW};
+use crate::render::indicator_label;
+use crate::taipower_api::ReserveIndicator;
+
+const RESERVE_KIND: &str = "reserve";
+/// Samples before the start and after the end included in an export
+const EXPORT_MARGIN_HOURS: i64 = 1;
+/// A smaller fall of the reserve rate between two samples isn't a dip
+const DIP_MIN_DROP: f64 = 1.0;
+/// Samples after a dip shown in the storage response
+const RESPONSE_SAMPLES: usize = 6;
+
+/// Open an incident when the indicator turns orange or worse, close it once it recovers
+pub fn track(history: &History, change: &IndicatorChange) -> rusqlite::Result<()> {
+    if change.to.is_critical() {
+        let summary = format!(
+            "供電燈號 {}→{} (備轉 {:.2}%)",
+            indicator_label(change.from, &ZH_TW),
+            indicator_label(change.to, &ZH_TW),
+            change.reserve_rate
+        );
+        history.open_incident(RESERVE_KIND, &summary)?;
+    } else if change.from.is_critical() {
+        history.close_incidents(RESERVE_KIND)?;
+    }
+    Ok(())
+}
+
+pub struct IncidentExport {
+    pub incident: Incident,
+    pub attachments: Vec<CreateAttachment>,
+}
+
+/// Markdown report, CSV samples and charts for one incident, an open one up to `now` (Taipei);
+/// None if the id is unknown
+pub fn export(history: &History, id: i64, numbers: NumberFormat, now: NaiveDateTime) -> Result<Option<IncidentExport>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(incident) = history.incident(id)? else {
+        return Ok(None);
+    };
+
+    let from = incident.started_at - Duration::hours(EXPORT_MARGIN_HOURS);
+    let to = incident.ended_at.unwrap_or(now) + Duration::hours(EXPORT_MARGIN_HOURS);
+    let samples = history.snapshots_between(from, to)?;
+    let violations = history.violations_between(from, to)?;
+
+    let storage = storage_lines(history, incident.started_at, numbers)?;
+
+    let mut attachments = vec![
+        CreateAttachment::bytes(markdown(&incident, from, to, &samples, &violations, &storage), format!("incident-{}.md", id)),
+        CreateAttachment::bytes(csv(&samples), format!("incident-{}.csv", id)),
+    ];
+
+    let load: Vec<_> = samples.iter().filter_map(|s| Some((s.recorded_at, s.current_load?))).collect();
+    let reserve: Vec<_> = samples.iter().filter_map(|s| Some((s.recorded_at, s.reserve_rate?))).collect();
+    let charts = [
+        ("load", "萬瓩", Series { label: "目前用電量".to_string(), points: load }),
+        ("reserve", "%", Series { label: "預估尖峰備轉容量率".to_string(), points: reserve }),
+    ];
+    for (name, unit, series) in charts {
+        if series.points.len() < 2 {
+            continue;
+        }
+        match chart::line_chart(&format!("#{} {}", id, series.label), unit, &[series], numbers) {
+            Ok(png) => attachments.push(CreateAttachment::bytes(png, format!("incident-{}-{}.png", id, name))),
+            Err(e) => error!("Error rendering incident chart: {:?}", e),
+        }
+    }
+
+    Ok(Some(IncidentExport { incident, attachments }))
+}
+
+/// How pumped storage and batteries answered the latest reserve dip, for `/incident response`;
+/// None if the stored history has no dip
+pub fn dip_response(history: &History, numbers: NumberFormat) -> rusqlite::Result<Option<String>> {
+    let Some((at, before, after)) = history.latest_reserve_dip(DIP_MIN_DROP)? else {
+        return Ok(None);
+    };
+    let mut lines = vec![format!(
+        "🔋 **備轉下降後的儲能與抽蓄反應** ({}: 備轉 {} → {})",
+        at.format("%m/%d %H:%M"),
+        numbers.percent(before, 2),
+        numbers.percent(after, 2)
+    )];
+    let storage = storage_lines(history, at, numbers)?;
+    if storage.is_empty() {
+        lines.push("-# 這段期間沒有機組紀錄 (需啟用 UNIT_HISTORY 並包含明湖、明潭與儲能機組)".to_string());
+    }
+    lines.extend(storage);
+    Ok(Some(lines.join("\n")))
+}
+
+/// The sample before `at`, then up to RESPONSE_SAMPLES after, each with its change since that
+/// first one, and the largest combined increase; empty without unit history around `at`
+fn storage_lines(history: &History, at: NaiveDateTime, numbers: NumberFormat) -> rusqlite::Result<Vec<String>> {
+    let samples = history.storage_response(at - Duration::hours(1), at + Duration::hours(3))?;
+    let start = samples.iter().rposition(|s| s.recorded_at < at).unwrap_or(0);
+    let window: Vec<&StorageSample> = samples.iter().skip(start).take(RESPONSE_SAMPLES + 1).collect();
+    let Some(baseline) = window.first() else {
+        return Ok(Vec::new());
+    };
+
+    let change = |value: f64| format!("{}{}", if value >= 0.0 { "+" } else { "" }, numbers.mw(value, 0));
+    let mut lines: Vec<String> = window
+        .iter()
+        .map(|s| {
+            format!(
+                "`{}` 備轉 {} · 抽蓄 {} ({}) · 儲能 {} ({})",
+                s.recorded_at.format("%H:%M"),
+                s.reserve_rate.map(|r| numbers.percent(r, 2)).unwrap_or_else(|| "-".to_string()),
+                numbers.mw(s.pumped, 0),
+                change(s.pumped - baseline.pumped),
+                numbers.mw(s.battery, 0),
+                change(s.battery - baseline.battery)
+            )
+        })
+        .collect();
+    let total = |s: &StorageSample| s.pumped + s.battery;
+    if let Some(peak) = window.iter().skip(1).max_by(|a, b| total(a).total_cmp(&total(b)))
+        && total(peak) > total(baseline)
+    {
+        lines.push(format!("合計最多增加 {} ({})", change(total(peak) - total(baseline)), peak.recorded_at.format("%H:%M")));
+    }
+    Ok(lines)
+}
+
+fn markdown(
+    incident: &Incident,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    samples: &[SnapshotRow],
+    violations: &[(NaiveDateTime, String, f64)],
+    storage: &[String],
+) -> String {
+    let time = |t: NaiveDateTime| t.format("%Y-%m-%d %H:%M").to_string();
+    let mut md = String::new();
+
+    let _ = writeln!(md, "# 事件 #{}: {}\n", incident.id, incident.summary);
+    let _ = writeln!(md, "- 類型: {}", incident.kind);
+    let _ = writeln!(md, "- 開始: {}", time(incident.started_at));
+    let _ = writeln!(md, "- 結束: {}", incident.ended_at.map(time).unwrap_or_else(|| "進行中".to_string()));
+    let _ = writeln!(md, "- 資料範圍: {} ~ {} ({} 筆)\n", time(from), time(to), samples.len());
+
+    md.push_str("## 摘要\n\n");
+    if let Some((at, load)) = samples
+        .iter()
+        .filter_map(|s| Some((s.recorded_at, s.current_load?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+    {
+        let _ = writeln!(md, "- 最高用電: {:.1} 萬瓩 ({})", load, at.format("%H:%M"));
+    }
+    if let Some((at, rate)) = samples
+        .iter()
+        .filter_map(|s| Some((s.recorded_at, s.reserve_rate.filter(|r| *r > 0.0)?)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+    {
+        let _ = writeln!(md, "- 最低備轉容量率: {:.2}% ({})", rate, at.format("%H:%M"));
+    }
+    if let Some(faults) = samples.iter().map(|s| s.fault_count).max() {
+        let _ = writeln!(md, "- 最多故障機組: {} 部", faults);
+    }
+
+    md.push_str("\n## 時間軸\n\n| 時間 | 事件 |\n|---|---|\n");
+    let mut timeline: Vec<(NaiveDateTime, String)> = vec![(incident.started_at, "事件開始".to_string())];
+    if let Some(ended_at) = incident.ended_at {
+        timeline.push((ended_at, "事件結束".to_string()));
+    }
+    for pair in samples.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        if before.indicator != after.indicator
+            && let (Some(from), Some(to)) = (&before.indicator, &after.indicator)
+        {
+            timeline.push((after.recorded_at, format!(
+                "燈號 {}→{} (備轉 {:.2}%)",
+                indicator_label(ReserveIndicator::from_code(from), &ZH_TW),
+                indicator_label(ReserveIndicator::from_code(to), &ZH_TW),
+                after.reserve_rate.unwrap_or_default()
+            )));
+        }
+        if after.fault_count > before.fault_count {
+            timeline.push((after.recorded_at, format!("故障機組 {} → {} 部", before.fault_count, after.fault_count)));
+        }
+    }
+    for (at, metric, value) in violations {
+        timeline.push((*at, format!("資料品質警告: {} = {}", metric, value)));
+    }
+    timeline.sort_by_key(|(at, _)| *at);
+    for (at, event) in timeline {
+        let _ = writeln!(md, "| {} | {} |", time(at), event);
+    }
+
+    if !storage.is_empty() {
+        md.push_str("\n## 儲能與抽蓄反應\n\n");
+        for line in storage {
+            let _ = writeln!(md, "- {}", line);
+        }
+    }
+
+    md.push_str("\n## 附件\n\n");
+    let _ = writeln!(md, "- incident-{}.csv: 期間內所有紀錄", incident.id);
+    md.push_str("- incident-*.png: 用電量與備轉容量率圖表\n");
+    md.push_str("\n資料來源: 台電公司開放資料 (本機紀錄)\n");
+    md
+}
+
+fn csv(samples: &[SnapshotRow]) -> String {
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+    let mut csv = String::from(
+        "recorded_at,total_generation_mw,renewable_ratio,current_load_wan_kw,current_util_rate,reserve_rate,indicator,fault_count,maintenance_count\n",
+    );
+    for s in samples {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{}",
+            s.recorded_at.format("%Y-%m-%d %H:%M:%S"),
+            s.total_generation,
+            s.renewable_ratio,
+            optional(s.current_load),
+            optional(s.current_util_rate),
+            optional(s.reserve_rate),
+            s.indicator.as_deref().unwrap_or_default(),
+            s.fault_count,
+            s.maintenance_count,
+        );
+    }
+    csv
+}
@@ -0,0 +1,131 @@
+//! Where an update cycle's time goes. The update loop runs inside `scope`, and the code it calls
+//! times its own phases with `measure`, so the API parsers and the report sender don't
+//! need a timer passed down to them; outside a cycle (slash commands, `once`) measuring is a
+//! no-op. Cycles over CYCLE_BUDGET_SECONDS are logged and reported to the admin channel.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Waiting on Taipower and the other feeds
+    Fetch,
+    /// Decoding the payloads
+    Parse,
+    /// Checks, history, forecasts and everything else derived from the data
+    Analyze,
+    /// Building report messages and charts
+    Render,
+    /// Sending and editing the reports
+    Post,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [Phase::Fetch, Phase::Parse, Phase::Analyze, Phase::Render, Phase::Post];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Phase::Fetch => "fetch",
+            Phase::Parse => "parse",
+            Phase::Analyze => "analyze",
+            Phase::Render => "render",
+            Phase::Post => "post",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Phase::Fetch => "抓取",
+            Phase::Parse => "解析",
+            Phase::Analyze => "分析",
+            Phase::Render => "繪製",
+            Phase::Post => "發布",
+        }
+    }
+
+    fn index(&self) -> usize {
+        Phase::ALL.iter().position(|phase| phase == self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CycleTimings {
+    phases: [Duration; Phase::ALL.len()],
+}
+
+impl CycleTimings {
+    pub fn new(phases: [Duration; Phase::ALL.len()]) -> Self {
+        CycleTimings { phases }
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.phases[phase.index()]
+    }
+
+    pub fn add(&mut self, phase: Phase, elapsed: Duration) {
+        self.phases[phase.index()] += elapsed;
+    }
+
+    pub fn total(&self) -> Duration {
+        self.phases.iter().sum()
+    }
+
+    /// "抓取 2.91 秒 · 解析 0.04 秒 · …"
+    pub fn describe(&self) -> String {
+        Phase::ALL
+            .iter()
+            .map(|phase| format!("{} {:.2} 秒", phase.label(), self.get(*phase).as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: RefCell<CycleTimings>;
+}
+
+/// Run the update loop `f` with somewhere for its cycles' timings to go
+pub async fn scope<F: Future>(f: F) -> F::Output {
+    CURRENT.scope(RefCell::new(CycleTimings::default()), f).await
+}
+
+/// A cycle is starting; whatever an abandoned one measured is dropped
+pub fn start() {
+    let _ = CURRENT.try_with(|timings| *timings.borrow_mut() = CycleTimings::default());
+}
+
+/// What the phases measured since `start` add up to. Parsing happens while the fetches are in
+/// flight, so it is taken out of the fetch time rather than counted twice
+pub fn finish() -> Option<CycleTimings> {
+    let mut timings = CURRENT.try_with(|timings| *timings.borrow()).ok()?;
+    timings.phases[Phase::Fetch.index()] = timings.get(Phase::Fetch).saturating_sub(timings.get(Phase::Parse));
+    Some(timings)
+}
+
+/// Add `elapsed` to the running cycle's `phase`
+pub fn record(phase: Phase, elapsed: Duration) {
+    let _ = CURRENT.try_with(|timings| timings.borrow_mut().add(phase, elapsed));
+}
+
+pub fn measure<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let output = f();
+    record(phase, started.elapsed());
+    output
+}
+
+pub async fn measure_async<F: Future>(phase: Phase, f: F) -> F::Output {
+    let started = Instant::now();
+    let output = f.await;
+    record(phase, started.elapsed());
+    output
+}
+
+/// CYCLE_BUDGET_SECONDS: a cycle taking longer than this is warned about; 0 disables
+pub fn budget_from_env() -> Option<Duration> {
+    let seconds = config::var("CYCLE_BUDGET_SECONDS").ok().and_then(|v| v.trim().parse::<f64>().ok()).unwrap_or(60.0);
+    (seconds > 0.0).then(|| Duration::from_secs_f64(seconds))
+}
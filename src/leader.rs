@@ -0,0 +1,126 @@
+//! Hot standby for running the bot on two hosts against one shared HISTORY_DB_PATH. With
+//! LEADER_ELECTION=on each instance claims a lease row at the start of every cycle, and only the
+//! holder posts to Discord; the standby keeps fetching and storing, so its history is complete
+//! when it takes over.
+//!
+//! The lease lasts LEADER_LEASE_SECONDS (default the update interval plus a minute), so a leader
+//! that stops renewing is replaced on the standby's first cycle after one missed beat. A clean
+//! shutdown releases it straight away.
+
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, info};
+
+use crate::config;
+use crate::history::History;
+use crate::scheduler;
+
+const LEASE: &str = "leader";
+
+pub struct Leadership {
+    /// INSTANCE_ID, else host name and process ID
+    instance: String,
+    ttl: chrono::Duration,
+    leading: AtomicBool,
+}
+
+impl Leadership {
+    /// None unless LEADER_ELECTION is on: a single instance always posts
+    pub fn from_env() -> Option<Self> {
+        if !matches!(config::var("LEADER_ELECTION").as_deref().map(str::trim), Ok("on") | Ok("true") | Ok("1")) {
+            return None;
+        }
+        let instance = config::var("INSTANCE_ID")
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("{}-{}", host_name(), std::process::id()));
+        let ttl = config::var("LEADER_LEASE_SECONDS")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|s| *s > 0)
+            .map(chrono::Duration::seconds)
+            .unwrap_or_else(|| chrono::Duration::from_std(scheduler::interval_from_env()).unwrap_or_default() + chrono::Duration::minutes(1));
+        info!("Leader election on as {} (lease {}s)", instance, ttl.num_seconds());
+        Some(Leadership { instance, ttl, leading: AtomicBool::new(false) })
+    }
+
+    /// Claim or renew the lease; whether this instance may post this cycle. A database error
+    /// keeps the previous role rather than risking two leaders or none
+    pub fn heartbeat(&self, history: &History) -> bool {
+        let was_leading = self.is_leading();
+        let leading = match history.claim_lease(LEASE, &self.instance, self.ttl) {
+            Ok(leading) => leading,
+            Err(why) => {
+                error!("Error renewing leader lease: {:?}", why);
+                was_leading
+            }
+        };
+        if leading != was_leading {
+            info!("{} is now {}", self.instance, if leading { "the leader" } else { "on standby" });
+        }
+        self.leading.store(leading, Ordering::Relaxed);
+        leading
+    }
+
+    pub fn is_leading(&self) -> bool {
+        self.leading.load(Ordering::Relaxed)
+    }
+
+    /// Hand over on shutdown so the standby doesn't wait for the lease to run out
+    pub fn resign(&self, history: &History) {
+        if self.leading.swap(false, Ordering::Relaxed)
+            && let Err(why) = history.release_lease(LEASE, &self.instance)
+        {
+            error!("Error releasing leader lease: {:?}", why);
+        }
+    }
+}
+
+fn host_name() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "bot".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{taipei_datetime, ManualClock};
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+
+    fn instance(name: &str) -> Leadership {
+        Leadership { instance: name.to_string(), ttl: chrono::Duration::minutes(11), leading: AtomicBool::new(false) }
+    }
+
+    #[test]
+    fn standby_takes_over_once_the_lease_lapses_or_is_resigned() {
+        let start = taipei_datetime(NaiveDate::from_ymd_opt(2024, 7, 15).unwrap().and_hms_opt(14, 0, 0).unwrap()).unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let history = History::open_with_clock(":memory:", clock.clone()).unwrap();
+        let (a, b) = (instance("a"), instance("b"));
+
+        assert!(a.heartbeat(&history));
+        assert!(!b.heartbeat(&history));
+        // Renewing keeps the lease ahead of the standby
+        clock.set(start + chrono::Duration::minutes(10));
+        assert!(a.heartbeat(&history));
+        clock.set(start + chrono::Duration::minutes(20));
+        assert!(!b.heartbeat(&history));
+
+        // The leader stops renewing: the standby waits out the lease, then takes over
+        clock.set(start + chrono::Duration::minutes(32));
+        assert!(b.heartbeat(&history));
+        assert!(!a.heartbeat(&history));
+        assert!(b.is_leading() && !a.is_leading());
+
+        // A clean shutdown hands over straight away
+        b.resign(&history);
+        assert!(!b.is_leading());
+        assert!(a.heartbeat(&history));
+    }
+}
@@ -0,0 +1,53 @@
+//! 台電即時電力資訊 Discord bot. `taipower_api` fetches and parses Taipower's feeds, `analysis`
+//! summarises them, `format` and the renderers turn that into reports, and `bot` posts them.
+
+pub mod analysis;
+pub mod bot;
+pub mod config;
+pub mod format;
+pub mod logging;
+pub mod render;
+pub mod replay;
+pub mod reporting;
+#[cfg(feature = "soak")]
+pub mod soak;
+pub mod taipower_api;
+
+mod accessible;
+mod alerts;
+mod archive;
+mod catchup;
+mod chart;
+mod clock;
+mod crashloop;
+mod cycle;
+mod dashboard;
+mod de;
+mod demand_response;
+mod digest;
+mod embed;
+mod export;
+mod forecast;
+mod history;
+mod http;
+mod incident;
+mod latency;
+mod leader;
+mod locale;
+mod maintenance;
+mod mentions;
+mod metrics;
+mod payload_archive;
+mod push;
+mod records;
+mod regional;
+mod scheduler;
+mod schema_watch;
+#[cfg(test)]
+mod snapshot_tests;
+mod stress;
+mod systemd;
+mod trend;
+mod validation;
+mod weather;
+mod weekly;
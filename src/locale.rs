@@ -0,0 +1,434 @@
+/// Output language for text reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    ZhTw,
+    En,
+}
+
+impl Locale {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "zh-tw" | "zh" | "tw" => Some(Locale::ZhTw),
+            "en" | "en-us" | "en-gb" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::ZhTw => "zh-TW",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn labels(&self) -> &'static Labels {
+        match self {
+            Locale::ZhTw => &ZH_TW,
+            Locale::En => &EN,
+        }
+    }
+
+    /// Taipower reports load in 萬瓩 (10 MW); English output uses MW instead
+    pub fn load_value(&self, wan_kw: f64, numbers: NumberFormat) -> String {
+        match self {
+            Locale::ZhTw => numbers.wan_kw(wan_kw),
+            Locale::En => numbers.mw(wan_kw * 10.0, 0),
+        }
+    }
+
+    /// Upstream fuel names are Chinese; translate the common ones
+    pub fn energy_type(&self, energy_type: &str) -> String {
+        if *self == Locale::ZhTw {
+            return energy_type.to_string();
+        }
+        let translated = match energy_type {
+            "核能" => "Nuclear",
+            "燃煤" => "Coal",
+            "燃氣" => "Gas",
+            "燃油" => "Oil",
+            "輕油" => "Diesel",
+            "汽電共生" => "Cogeneration",
+            "水力" => "Hydro",
+            "風力" => "Wind",
+            "太陽能" => "Solar",
+            "其它再生能源" => "Other renewables",
+            "儲能" => "Storage",
+            "民營燃煤" => "IPP coal",
+            "民營燃氣" => "IPP gas",
+            other => return other.to_string(),
+        };
+        translated.to_string()
+    }
+
+    pub fn region_name(&self, area: &str) -> String {
+        if *self == Locale::ZhTw {
+            return area.to_string();
+        }
+        let translated = match area {
+            "北部" => "North",
+            "中部" => "Central",
+            "南部" => "South",
+            "東部" => "East",
+            "離島" => "Outlying islands",
+            other => return other.to_string(),
+        };
+        translated.to_string()
+    }
+}
+
+/// How numbers are written in reports and charts, chosen per guild with /numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// 2981.4
+    #[default]
+    Plain,
+    /// 2,981.4
+    Grouped,
+    /// 2.981,4
+    DecimalComma,
+    /// 2.98 千萬瓩: large 萬瓩 values in 千萬/億, everything else grouped
+    Chinese,
+}
+
+impl NumberFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "plain" => Some(NumberFormat::Plain),
+            "grouped" => Some(NumberFormat::Grouped),
+            "decimal-comma" => Some(NumberFormat::DecimalComma),
+            "chinese" => Some(NumberFormat::Chinese),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            NumberFormat::Plain => "plain",
+            NumberFormat::Grouped => "grouped",
+            NumberFormat::DecimalComma => "decimal-comma",
+            NumberFormat::Chinese => "chinese",
+        }
+    }
+
+    pub fn decimal(&self, value: f64, precision: usize) -> String {
+        let plain = format!("{:.*}", precision, value.abs());
+        let (thousands, point) = match self {
+            NumberFormat::Plain => return format!("{:.*}", precision, value),
+            NumberFormat::Grouped | NumberFormat::Chinese => (',', '.'),
+            NumberFormat::DecimalComma => ('.', ','),
+        };
+        let (integer, fraction) = plain.split_once('.').unwrap_or((&plain, ""));
+
+        let mut out = String::new();
+        if value < 0.0 && plain.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                out.push(thousands);
+            }
+            out.push(digit);
+        }
+        if !fraction.is_empty() {
+            out.push(point);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    pub fn percent(&self, value: f64, precision: usize) -> String {
+        format!("{}%", self.decimal(value, precision))
+    }
+
+    pub fn mw(&self, value: f64, precision: usize) -> String {
+        format!("{} MW", self.decimal(value, precision))
+    }
+
+    pub fn wan_kw(&self, value: f64) -> String {
+        match self {
+            NumberFormat::Chinese if value.abs() >= 10_000.0 => format!("{:.2} 億瓩", value / 10_000.0),
+            NumberFormat::Chinese if value.abs() >= 1_000.0 => format!("{:.2} 千萬瓩", value / 1_000.0),
+            _ => format!("{} 萬瓩", self.decimal(value, 1)),
+        }
+    }
+}
+
+pub struct Labels {
+    pub report_title: &'static str,
+    pub last_updated: &'static str,
+    pub supply_demand: &'static str,
+    pub current_load: &'static str,
+    pub util_rate: &'static str,
+    pub forecast_max_supply: &'static str,
+    pub forecast_peak_demand: &'static str,
+    pub forecast_reserve_capacity: &'static str,
+    pub forecast_reserve_rate: &'static str,
+    pub forecast_peak_hours: &'static str,
+    pub data_updated: &'static str,
+    pub yesterday: &'static str,
+    pub max_supply: &'static str,
+    pub peak_demand: &'static str,
+    pub peak_reserve_capacity: &'static str,
+    pub peak_reserve_rate: &'static str,
+    pub realtime_peak: &'static str,
+    pub realtime_max_supply: &'static str,
+    pub peak_time: &'static str,
+    pub generation: &'static str,
+    pub updated: &'static str,
+    pub total_generation: &'static str,
+    pub installed_capacity: &'static str,
+    pub generation_ratio: &'static str,
+    pub by_type: &'static str,
+    pub top_plant: &'static str,
+    pub top_unit: &'static str,
+    pub unit_status: &'static str,
+    pub restrictions: &'static str,
+    pub maintenance: &'static str,
+    pub faults: &'static str,
+    pub units_suffix: &'static str,
+    pub renewable_ratio: &'static str,
+    pub private_ratio: &'static str,
+    pub source: &'static str,
+    pub source_name: &'static str,
+    pub disclaimer: &'static str,
+    pub unknown: &'static str,
+    pub no_data: &'static str,
+    pub reserve_short: &'static str,
+    pub renewable_short: &'static str,
+    pub indicator_changed: &'static str,
+    pub supply_alert: &'static str,
+    pub indicator_green: &'static str,
+    pub indicator_yellow: &'static str,
+    pub indicator_orange: &'static str,
+    pub indicator_red: &'static str,
+    pub indicator_black: &'static str,
+    pub summary_title: &'static str,
+    pub peak_load: &'static str,
+    pub min_load: &'static str,
+    pub min_reserve_rate: &'static str,
+    pub average_renewable: &'static str,
+    pub fault_events: &'static str,
+    pub average_mix: &'static str,
+    pub most_affected: &'static str,
+    pub local_records: &'static str,
+    pub samples_suffix: &'static str,
+    pub archive_source: &'static str,
+    pub regions: &'static str,
+    pub region_generation: &'static str,
+    pub region_load: &'static str,
+    pub importing: &'static str,
+    pub exporting: &'static str,
+    pub own_forecast: &'static str,
+    pub next_hour: &'static str,
+    pub forecast_error: &'static str,
+    pub bot: &'static str,
+    pub taipower: &'static str,
+    pub last_days_prefix: &'static str,
+    pub days_suffix: &'static str,
+    pub outages: &'static str,
+    pub planned_until: &'static str,
+    pub unplanned: &'static str,
+    pub other: &'static str,
+    pub demand_response: &'static str,
+    pub reserve_without_dr: &'static str,
+    pub fault_change: &'static str,
+    pub newly_faulted: &'static str,
+    pub recovered: &'static str,
+    pub heavy_import: &'static str,
+    pub rose: &'static str,
+    pub fell: &'static str,
+    pub percentage_points: &'static str,
+    pub previously: &'static str,
+    pub stress_index: &'static str,
+    pub trend_tightening: &'static str,
+    pub trend_rising: &'static str,
+    pub trend_falling: &'static str,
+    pub trend_steady: &'static str,
+}
+
+pub static ZH_TW: Labels = Labels {
+    report_title: "台電即時電力資訊",
+    last_updated: "最後更新",
+    supply_demand: "電力供需資訊",
+    current_load: "目前用電量",
+    util_rate: "目前使用率",
+    forecast_max_supply: "預估今日最大供電能力",
+    forecast_peak_demand: "預估今日最高用電",
+    forecast_reserve_capacity: "預估今日尖峰備轉容量",
+    forecast_reserve_rate: "預估今日尖峰備轉容量率",
+    forecast_peak_hours: "預估尖峰用電時段",
+    data_updated: "資料更新時間",
+    yesterday: "昨日電力資訊",
+    max_supply: "最大供電能力",
+    peak_demand: "尖峰用電量",
+    peak_reserve_capacity: "尖峰備轉容量",
+    peak_reserve_rate: "尖峰備轉容量率",
+    realtime_peak: "即時尖峰資訊",
+    realtime_max_supply: "即時最大供電能力",
+    peak_time: "尖峰時間",
+    generation: "發電機組資訊",
+    updated: "更新時間",
+    total_generation: "總發電量",
+    installed_capacity: "裝置容量",
+    generation_ratio: "發電占比",
+    by_type: "各能源發電量",
+    top_plant: "發電量最高電廠",
+    top_unit: "發電量最高機組",
+    unit_status: "運轉狀態統計",
+    restrictions: "環保限制/運轉限制",
+    maintenance: "歲修/檢修",
+    faults: "故障",
+    units_suffix: " 部",
+    renewable_ratio: "再生能源占比",
+    private_ratio: "民營電廠+購電占比",
+    source: "資料來源",
+    source_name: "台電公司開放資料",
+    disclaimer: "本資料可能會有錯誤或延遲，造成損失與我們無關",
+    unknown: "未知",
+    no_data: "無資料",
+    reserve_short: "備轉",
+    renewable_short: "再生",
+    indicator_changed: "供電燈號變更",
+    supply_alert: "供電警戒",
+    indicator_green: "綠燈",
+    indicator_yellow: "黃燈",
+    indicator_orange: "橘燈",
+    indicator_red: "紅燈",
+    indicator_black: "黑燈",
+    summary_title: "電力摘要",
+    peak_load: "尖峰用電",
+    min_load: "最低用電",
+    min_reserve_rate: "最低備轉容量率",
+    average_renewable: "平均再生能源占比",
+    fault_events: "新增故障",
+    average_mix: "平均發電結構",
+    most_affected: "當日最多異常機組",
+    local_records: "本機紀錄",
+    samples_suffix: " 筆",
+    archive_source: "台電過去電力供需資訊",
+    regions: "各區域供需 (估計)",
+    region_generation: "發電",
+    region_load: "負載",
+    importing: "輸入",
+    exporting: "輸出",
+    own_forecast: "本機預估今日尖峰",
+    next_hour: "下一小時",
+    forecast_error: "尖峰預估平均誤差",
+    bot: "本機",
+    taipower: "台電",
+    last_days_prefix: "近 ",
+    days_suffix: " 日",
+    outages: "大型機組停機",
+    planned_until: "計畫歲修，預計至",
+    unplanned: "未列入歲修計畫",
+    other: "其他",
+    demand_response: "已啟動需量反應",
+    reserve_without_dr: "不含需量反應的備轉容量率",
+    fault_change: "機組故障狀態變更",
+    newly_faulted: "新增故障",
+    recovered: "恢復運轉",
+    heavy_import: "輸入比例偏高",
+    rose: "上升",
+    fell: "下降",
+    percentage_points: "個百分點",
+    previously: "原為",
+    stress_index: "電網壓力指數",
+    trend_tightening: "備轉趨緊",
+    trend_rising: "用電攀升中",
+    trend_falling: "用電回落中",
+    trend_steady: "供需平穩",
+};
+
+pub static EN: Labels = Labels {
+    report_title: "Taipower Live Grid Status",
+    last_updated: "Last updated",
+    supply_demand: "Supply & demand",
+    current_load: "Current load",
+    util_rate: "Utilization",
+    forecast_max_supply: "Forecast max supply today",
+    forecast_peak_demand: "Forecast peak demand today",
+    forecast_reserve_capacity: "Forecast peak operating reserve",
+    forecast_reserve_rate: "Forecast peak reserve margin",
+    forecast_peak_hours: "Forecast peak hours",
+    data_updated: "Data published",
+    yesterday: "Yesterday",
+    max_supply: "Max supply",
+    peak_demand: "Peak demand",
+    peak_reserve_capacity: "Peak operating reserve",
+    peak_reserve_rate: "Peak reserve margin",
+    realtime_peak: "Real-time peak",
+    realtime_max_supply: "Real-time max supply",
+    peak_time: "Peak time",
+    generation: "Generation",
+    updated: "Updated",
+    total_generation: "Total generation",
+    installed_capacity: "Installed capacity",
+    generation_ratio: "Output / capacity",
+    by_type: "Generation by source",
+    top_plant: "Top plant",
+    top_unit: "Top unit",
+    unit_status: "Unit status",
+    restrictions: "Environmental/operating limits",
+    maintenance: "Maintenance",
+    faults: "Faults",
+    units_suffix: " units",
+    renewable_ratio: "Renewable share",
+    private_ratio: "IPP + purchased share",
+    source: "Source",
+    source_name: "Taipower open data",
+    disclaimer: "Data may be inaccurate or delayed; use at your own risk",
+    unknown: "unknown",
+    no_data: "no data",
+    reserve_short: "Reserve",
+    renewable_short: "Renewables",
+    indicator_changed: "Reserve indicator changed",
+    supply_alert: "Supply alert",
+    indicator_green: "Green",
+    indicator_yellow: "Yellow",
+    indicator_orange: "Orange",
+    indicator_red: "Red",
+    indicator_black: "Black",
+    summary_title: "grid summary",
+    peak_load: "Peak load",
+    min_load: "Minimum load",
+    min_reserve_rate: "Lowest reserve margin",
+    average_renewable: "Average renewable share",
+    fault_events: "New faults",
+    average_mix: "Average generation mix",
+    most_affected: "Most units affected",
+    local_records: "local records",
+    samples_suffix: " samples",
+    archive_source: "Taipower historical supply data",
+    regions: "Regional balance (estimated)",
+    region_generation: "gen",
+    region_load: "load",
+    importing: "importing",
+    exporting: "exporting",
+    own_forecast: "Bot's peak forecast today",
+    next_hour: "next hour",
+    forecast_error: "Peak forecast error (MAPE)",
+    bot: "bot",
+    taipower: "Taipower",
+    last_days_prefix: "last ",
+    days_suffix: " days",
+    outages: "Major units offline",
+    planned_until: "planned maintenance until",
+    unplanned: "not on the maintenance schedule",
+    other: "Other",
+    demand_response: "Demand response activated",
+    reserve_without_dr: "Reserve margin without demand response",
+    fault_change: "Unit fault changes",
+    newly_faulted: "Newly faulted",
+    recovered: "Recovered",
+    heavy_import: "heavy import",
+    rose: "up",
+    fell: "down",
+    percentage_points: "percentage points",
+    previously: "previously",
+    stress_index: "Grid stress index",
+    trend_tightening: "Reserve tightening",
+    trend_rising: "Demand rising",
+    trend_falling: "Demand easing",
+    trend_steady: "Supply steady",
+};
@@ -0,0 +1,35 @@
+//! Diagnostics go through `tracing`, written to stderr so `once --json` keeps stdout to itself.
+//! Each update cycle runs in a `cycle` span, and fetches log their endpoint, size and latency as
+//! fields, so JSON output can be filtered and graphed by a log collector. Serenity's own events
+//! come through the same subscriber.
+
+use tracing_subscriber::EnvFilter;
+
+use crate::config;
+
+/// Where everything else logs when LOG_LEVEL is only a level
+const BASELINE: &str = "warn,serenity=info";
+
+/// LOG_LEVEL: a level (error, warn, info, debug, trace) for the bot's own logs, default info, or a
+/// full filter like `info,serenity=debug`
+fn filter() -> EnvFilter {
+    let level = config::var("LOG_LEVEL").ok().map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty());
+    let directives = match level.as_deref() {
+        None => format!("{},taipower_discord=info,soak=info", BASELINE),
+        Some(level @ ("error" | "warn" | "info" | "debug" | "trace" | "off")) => {
+            format!("{},taipower_discord={},soak={}", BASELINE, level, level)
+        }
+        Some(directives) => directives.to_string(),
+    };
+    EnvFilter::try_new(&directives).unwrap_or_else(|why| {
+        eprintln!("Invalid LOG_LEVEL {:?} ({}), using info", directives, why);
+        EnvFilter::new(format!("{},taipower_discord=info,soak=info", BASELINE))
+    })
+}
+
+/// Install the subscriber. LOG_FORMAT=json writes one JSON object per line; anything else is text
+pub fn init() {
+    let builder = tracing_subscriber::fmt().with_env_filter(filter()).with_writer(std::io::stderr);
+    let json = config::var("LOG_FORMAT").is_ok_and(|v| v.trim().eq_ignore_ascii_case("json"));
+    let _ = if json { builder.json().with_current_span(true).with_span_list(false).try_init() } else { builder.try_init() };
+}
@@ -0,0 +1,260 @@
+//! Taipower's annual maintenance (歲修) schedule, used to tell planned outages from unexpected ones.
+//!
+//! The schedule is published yearly as open data (JSON or CSV) rather than through the live
+//! generation feed, so its location is configured with MAINTENANCE_SCHEDULE_URL (a URL or a local
+//! file). Columns are matched by name: 機組 (unit), 開始 (start), 結束/完成 (end), 容量 (MW, optional).
+
+use chrono::{Duration, NaiveDate};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::{debug, error, info};
+
+use crate::analysis::{classify_remark, RemarkClass};
+use crate::config;
+use crate::taipower_api::PowerUnit;
+
+/// The schedule changes a few times a year at most
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceWindow {
+    pub unit: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// MW, if the schedule lists it
+    pub capacity: Option<f64>,
+}
+
+/// A large unit currently offline for maintenance or a fault
+#[derive(Debug, Clone)]
+pub struct Outage {
+    pub unit: String,
+    pub capacity: f64,
+    /// End of the scheduled window, or None if the outage isn't on the schedule
+    pub planned_until: Option<NaiveDate>,
+}
+
+struct Cached {
+    fetched_at: Option<Instant>,
+    windows: Vec<MaintenanceWindow>,
+}
+
+pub struct MaintenanceCalendar {
+    source: Option<String>,
+    cached: Mutex<Cached>,
+}
+
+impl MaintenanceCalendar {
+    pub fn from_env() -> Self {
+        let source = config::var("MAINTENANCE_SCHEDULE_URL").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        MaintenanceCalendar {
+            source,
+            cached: Mutex::new(Cached { fetched_at: None, windows: Vec::new() }),
+        }
+    }
+
+    /// MAINTENANCE_MAJOR_UNIT_MW: units below this capacity are left out of report context and
+    /// /maintenance; read on each use so `/config reload` applies it
+    pub fn major_unit_mw(&self) -> f64 {
+        config::var("MAINTENANCE_MAJOR_UNIT_MW").ok().and_then(|v| v.trim().parse().ok()).unwrap_or(500.0)
+    }
+
+    pub fn configured(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Reload the schedule if it's older than a day; keeps the previous copy if the reload fails
+    pub async fn refresh(&self) {
+        let Some(source) = &self.source else {
+            return;
+        };
+        let stale = self.cached.lock().unwrap().fetched_at.is_none_or(|t| t.elapsed() >= REFRESH_INTERVAL);
+        if !stale {
+            return;
+        }
+
+        match fetch_schedule(source).await {
+            Ok(windows) => {
+                info!("Loaded {} maintenance windows", windows.len());
+                *self.cached.lock().unwrap() = Cached { fetched_at: Some(Instant::now()), windows };
+            }
+            Err(e) => error!("Error loading maintenance schedule: {:?}", e),
+        }
+    }
+
+    /// Major units whose scheduled maintenance starts within `weeks` weeks of `today`
+    pub fn upcoming(&self, today: NaiveDate, weeks: i64) -> Vec<MaintenanceWindow> {
+        let until = today + Duration::weeks(weeks);
+        let major_unit_mw = self.major_unit_mw();
+        let mut windows: Vec<MaintenanceWindow> = self
+            .cached
+            .lock()
+            .unwrap()
+            .windows
+            .iter()
+            .filter(|w| w.start >= today && w.start <= until)
+            .filter(|w| w.capacity.is_none_or(|c| c >= major_unit_mw))
+            .cloned()
+            .collect();
+        windows.sort_by_key(|w| w.start);
+        windows
+    }
+
+    /// Major units that are offline right now, marked planned or unexpected
+    pub fn outages(&self, units: &[PowerUnit], today: NaiveDate) -> Vec<Outage> {
+        if !self.configured() {
+            return Vec::new();
+        }
+        let major_unit_mw = self.major_unit_mw();
+        let cached = self.cached.lock().unwrap();
+        units
+            .iter()
+            .filter(|u| u.capacity >= major_unit_mw)
+            .filter(|u| matches!(classify_remark(&u.remark), RemarkClass::Maintenance | RemarkClass::Fault))
+            .map(|u| Outage {
+                unit: u.unit_name.clone(),
+                capacity: u.capacity,
+                planned_until: cached
+                    .windows
+                    .iter()
+                    .find(|w| w.start <= today && today <= w.end && same_unit(&w.unit, &u.unit_name))
+                    .map(|w| w.end),
+            })
+            .collect()
+    }
+}
+
+/// Names differ slightly between datasets ("台中 #9", "台中＃9", "台中#9(註)")
+fn normalize_unit(name: &str) -> String {
+    name.split(['(', '（'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '＃' { '#' } else { c })
+        .collect()
+}
+
+fn same_unit(a: &str, b: &str) -> bool {
+    let a = normalize_unit(a);
+    !a.is_empty() && a == normalize_unit(b)
+}
+
+/// Accepts Gregorian and ROC (民國) years: 2025-03-01, 2025/3/1, 114/03/01
+pub fn parse_schedule_date(value: &str) -> Option<NaiveDate> {
+    let parts: Vec<i32> = value.trim().split(['-', '/', '.']).map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+    let [year, month, day] = parts[..] else {
+        return None;
+    };
+    let year = if year < 1911 { year + 1911 } else { year };
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+async fn fetch_schedule(source: &str) -> Result<Vec<MaintenanceWindow>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_schedule(&read_source(source, "maintenance schedule").await?)
+}
+
+/// Open-data file from a URL or a local path; `what` names it in the log
+pub async fn read_source(source: &str, what: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let client = crate::http::client();
+        debug!(endpoint = source, "Fetching {}", what);
+        let response = client.get(source).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()).into());
+        }
+        Ok(response.text().await?)
+    } else {
+        Ok(tokio::fs::read_to_string(source).await?)
+    }
+}
+
+pub fn parse_schedule(text: &str) -> Result<Vec<MaintenanceWindow>, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = parse_rows(text)?;
+    let windows: Vec<MaintenanceWindow> = rows.iter().filter_map(window_from_row).collect();
+    if windows.is_empty() && !rows.is_empty() {
+        return Err("No maintenance windows found; expected 機組/開始/結束 columns".into());
+    }
+    Ok(windows)
+}
+
+pub type Row = Vec<(String, String)>;
+
+/// Records of a JSON or CSV open-data table as (column, value) pairs
+pub fn parse_rows(text: &str) -> Result<Vec<Row>, Box<dyn std::error::Error + Send + Sync>> {
+    let text = text.trim_start_matches('\u{feff}');
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(value) => json_rows(value),
+        Err(_) => Ok(csv_rows(text)),
+    }
+}
+
+fn json_rows(value: serde_json::Value) -> Result<Vec<Row>, Box<dyn std::error::Error + Send + Sync>> {
+    // Either a bare array or wrapped like the other Taipower feeds
+    let items = match value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(mut map) => match ["data", "records", "aaData"].iter().find_map(|k| map.remove(*k)) {
+            Some(serde_json::Value::Array(items)) => items,
+            _ => return Err("JSON has no record array".into()),
+        },
+        _ => return Err("JSON has no record array".into()),
+    };
+    Ok(items
+        .into_iter()
+        .filter_map(|item| match item {
+            serde_json::Value::Object(map) => Some(
+                map.into_iter()
+                    .map(|(k, v)| (k, v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .collect())
+}
+
+fn csv_rows(text: &str) -> Vec<Row> {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let header = split_csv_line(header);
+    lines
+        .map(|line| header.iter().cloned().zip(split_csv_line(line)).collect())
+        .collect()
+}
+
+/// Enough CSV for open-data exports: commas, double-quoted fields, "" escapes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn window_from_row(row: &Row) -> Option<MaintenanceWindow> {
+    // Earlier names win, so 機組名稱 is preferred over e.g. 機組容量
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|n| row.iter().find(|(key, _)| key.contains(n)))
+            .map(|(_, value)| value.as_str())
+    };
+    let unit = column(&["機組名稱", "機組"])?.trim().to_string();
+    let start = parse_schedule_date(column(&["開始", "起"])?)?;
+    let end = parse_schedule_date(column(&["結束", "完成", "迄"])?)?;
+    let capacity = column(&["容量"]).and_then(|v| v.replace(',', "").trim().parse().ok());
+    (!unit.is_empty() && start <= end).then_some(MaintenanceWindow { unit, start, end, capacity })
+}
@@ -0,0 +1,224 @@
+//! Per-channel mention policies for the scheduled report (`/mentions`).
+
+use serenity::builder::{CreateAllowedMentions, CreateMessage};
+use serenity::model::id::{RoleId, UserId};
+
+use crate::analysis::CombinedPowerData;
+use crate::history::History;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MentionPolicy {
+    Never,
+    Always,
+    /// Only when today's forecast reserve indicator is orange or worse
+    CriticalOnly,
+}
+
+impl MentionPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "never" => Some(MentionPolicy::Never),
+            "always" => Some(MentionPolicy::Always),
+            "critical" | "critical-only" => Some(MentionPolicy::CriticalOnly),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            MentionPolicy::Never => "never",
+            MentionPolicy::Always => "always",
+            MentionPolicy::CriticalOnly => "critical",
+        }
+    }
+}
+
+/// Who to mention in a channel's reports, and when
+#[derive(Debug, Clone)]
+pub struct MentionTarget {
+    pub policy: MentionPolicy,
+    pub role_id: Option<u64>,
+    pub user_id: Option<u64>,
+}
+
+impl MentionTarget {
+    pub fn applies(&self, data: &CombinedPowerData) -> bool {
+        match self.policy {
+            MentionPolicy::Never => false,
+            MentionPolicy::Always => true,
+            MentionPolicy::CriticalOnly => data
+                .load_data
+                .as_ref()
+                .is_some_and(|load| load.forecast_peak_reserve_indicator.is_critical()),
+        }
+    }
+
+    fn prefix(&self) -> String {
+        let role = self.role_id.map(|id| format!("<@&{}>", id));
+        let user = self.user_id.map(|id| format!("<@{}>", id));
+        role.into_iter().chain(user).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Prepend the channel's mention (if due) and restrict pings to exactly that role/user.
+/// Without a due mention nothing in the report can ping anyone.
+pub fn apply(message: CreateMessage, content: Option<String>, target: Option<&MentionTarget>, data: &CombinedPowerData) -> CreateMessage {
+    let due = target.filter(|t| t.applies(data));
+
+    let mut allowed = CreateAllowedMentions::new().everyone(false).all_users(false).all_roles(false);
+    if let Some(target) = due {
+        allowed = allowed
+            .roles(target.role_id.map(RoleId::new))
+            .users(target.user_id.map(UserId::new));
+    }
+
+    let prefix = due.map(MentionTarget::prefix).filter(|p| !p.is_empty());
+    let content = match (prefix, content) {
+        (Some(prefix), Some(content)) => Some(format!("{}\n{}", prefix, content)),
+        (Some(prefix), None) => Some(prefix),
+        (None, content) => content,
+    };
+
+    let message = message.allowed_mentions(allowed);
+    match content {
+        Some(content) => message.content(content),
+        None => message,
+    }
+}
+
+/// Who a ping-ladder rung mentions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LadderMention {
+    Role(u64),
+    Here,
+    Everyone,
+}
+
+impl LadderMention {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "here" => Some(LadderMention::Here),
+            "everyone" => Some(LadderMention::Everyone),
+            other => other.strip_prefix("role:")?.parse().ok().map(LadderMention::Role),
+        }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            LadderMention::Role(id) => format!("role:{}", id),
+            LadderMention::Here => "here".to_string(),
+            LadderMention::Everyone => "everyone".to_string(),
+        }
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            LadderMention::Role(id) => format!("<@&{}>", id),
+            LadderMention::Here => "@here".to_string(),
+            LadderMention::Everyone => "@everyone".to_string(),
+        }
+    }
+}
+
+/// One step of a guild's ping ladder (`/ping-ladder`): mention `mention` once the reserve rate
+/// falls below `below`. `active` is set while the rate stays under it, so it pings once per dip
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadderRung {
+    pub below: f64,
+    pub mention: LadderMention,
+    pub active: bool,
+}
+
+/// Percentage points the rate must climb back above a rung before it can ping again
+/// (PING_LADDER_HYSTERESIS, default 1)
+pub fn ladder_hysteresis_from_env() -> f64 {
+    crate::config::var("PING_LADDER_HYSTERESIS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|v: &f64| v.is_finite() && *v >= 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Which rungs `rate` newly fell below, and which it has recovered from by at least `hysteresis`
+pub fn evaluate_ladder(rungs: &[LadderRung], rate: f64, hysteresis: f64) -> (Vec<&LadderRung>, Vec<&LadderRung>) {
+    let crossed = rungs.iter().filter(|r| !r.active && rate < r.below).collect();
+    let rearmed = rungs.iter().filter(|r| r.active && rate >= r.below + hysteresis).collect();
+    (crossed, rearmed)
+}
+
+/// Re-arm the guild's rungs `rate` has recovered from, and return the ones it just fell below;
+/// the caller marks those active once their ping is sent
+pub fn advance_ladder(history: &History, guild_id: u64, rate: f64, hysteresis: f64) -> rusqlite::Result<Vec<LadderRung>> {
+    let rungs = history.ping_ladder(guild_id)?;
+    let (crossed, rearmed) = evaluate_ladder(&rungs, rate, hysteresis);
+    for rung in rearmed {
+        history.set_ladder_active(guild_id, rung.below, false)?;
+    }
+    Ok(crossed.into_iter().cloned().collect())
+}
+
+/// The ping for rungs just crossed, allowed to mention exactly those
+pub fn ladder_message(rate: f64, crossed: &[LadderRung]) -> CreateMessage {
+    let mut allowed = CreateAllowedMentions::new().everyone(false).all_users(false).all_roles(false);
+    let mut roles = Vec::new();
+    for rung in crossed {
+        match rung.mention {
+            LadderMention::Role(id) => roles.push(RoleId::new(id)),
+            LadderMention::Here | LadderMention::Everyone => allowed = allowed.everyone(true),
+        }
+    }
+    let lowest = crossed.iter().map(|r| r.below).fold(f64::MAX, f64::min);
+    let mentions = crossed.iter().map(|r| r.mention.display()).collect::<Vec<_>>().join(" ");
+    CreateMessage::new()
+        .content(format!("{}\n📉 **預估尖峰備轉容量率降至 {:.2}%**，已低於 {}%", mentions, rate, lowest))
+        .allowed_mentions(allowed.roles(roles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rung(below: f64, active: bool) -> LadderRung {
+        LadderRung { below, mention: LadderMention::Here, active }
+    }
+
+    fn belows(rungs: Vec<&LadderRung>) -> Vec<f64> {
+        rungs.into_iter().map(|r| r.below).collect()
+    }
+
+    #[test]
+    fn ladder_crosses_once_and_rearms_past_the_hysteresis() {
+        let armed = [rung(10.0, false), rung(6.0, false)];
+        let (crossed, rearmed) = evaluate_ladder(&armed, 5.5, 1.0);
+        assert_eq!(belows(crossed), vec![10.0, 6.0]);
+        assert!(rearmed.is_empty());
+        // Still under, or back over by less than the hysteresis: nothing changes
+        let active = [rung(10.0, true), rung(6.0, true)];
+        for rate in [5.0, 6.0, 6.9] {
+            let (crossed, rearmed) = evaluate_ladder(&active, rate, 1.0);
+            assert!(crossed.is_empty() && rearmed.is_empty(), "changed at {}", rate);
+        }
+        let (crossed, rearmed) = evaluate_ladder(&active, 7.0, 1.0);
+        assert!(crossed.is_empty());
+        assert_eq!(belows(rearmed), vec![6.0]);
+    }
+
+    #[test]
+    fn advance_ladder_rearms_in_history_and_pings_again_on_the_next_dip() {
+        let history = History::open(":memory:").unwrap();
+        history.set_ladder_rung(1, 10.0, LadderMention::Role(5)).unwrap();
+        history.set_ladder_rung(1, 6.0, LadderMention::Everyone).unwrap();
+
+        let crossed = advance_ladder(&history, 1, 8.0, 1.0).unwrap();
+        assert_eq!(crossed.iter().map(|r| r.below).collect::<Vec<_>>(), vec![10.0]);
+        history.set_ladder_active(1, 10.0, true).unwrap();
+        assert!(advance_ladder(&history, 1, 9.5, 1.0).unwrap().is_empty());
+
+        // 10.5 is back above 10 but within the hysteresis, 11 rearms it
+        assert!(advance_ladder(&history, 1, 10.5, 1.0).unwrap().is_empty());
+        assert!(history.ping_ladder(1).unwrap().iter().any(|r| r.below == 10.0 && r.active));
+        assert!(advance_ladder(&history, 1, 11.0, 1.0).unwrap().is_empty());
+        assert!(history.ping_ladder(1).unwrap().iter().all(|r| !r.active));
+        assert_eq!(advance_ladder(&history, 1, 9.0, 1.0).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,140 @@
+//! Prometheus metrics for the latest data and the bot's own health, served as `/metrics` in the
+//! text exposition format by the dashboard's HTTP server (or on METRICS_ADDR alone).
+//!
+//! Values live in one process-wide registry that the update loop and Discord sends write to, so
+//! a scrape only formats what's already there.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::analysis::CombinedPowerData;
+use crate::clock::taipei_now;
+use crate::config;
+use crate::latency::{CycleTimings, Phase};
+
+/// Upper bounds (seconds) of the Discord send latency histogram buckets
+const SEND_BUCKETS: [f64; 8] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+pub fn addr_from_env() -> Option<String> {
+    config::var("METRICS_ADDR").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+struct Registry {
+    /// (current load MW, reserve rate %) when the load feed was usable
+    load: Option<(f64, f64)>,
+    /// (total generation MW, renewable ratio %, data time as a Unix timestamp)
+    generation: Option<(f64, f64, i64)>,
+    last_success: BTreeMap<&'static str, i64>,
+    fetch_errors: BTreeMap<String, u64>,
+    send_buckets: [u64; SEND_BUCKETS.len()],
+    send_count: u64,
+    send_sum: f64,
+    /// Phases of the last update cycle
+    cycle: Option<CycleTimings>,
+    cycles_over_budget: u64,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    load: None,
+    generation: None,
+    last_success: BTreeMap::new(),
+    fetch_errors: BTreeMap::new(),
+    send_buckets: [0; SEND_BUCKETS.len()],
+    send_count: 0,
+    send_sum: 0.0,
+    cycle: None,
+    cycles_over_budget: 0,
+});
+
+/// Gauges for the data a cycle just published
+pub fn observe(data: &CombinedPowerData) {
+    let analysis = &data.power_analysis;
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.generation = Some((analysis.total_generation, analysis.renewable_ratio, analysis.update_time.timestamp()));
+    // Taipower reports load in 萬瓩; Prometheus wants base-ish units
+    registry.load = data.load_data.as_ref().map(|load| (load.current_load * 10.0, load.forecast_peak_reserve_rate));
+}
+
+pub fn fetch_succeeded(feed: &'static str) {
+    REGISTRY.lock().unwrap().last_success.insert(feed, taipei_now().timestamp());
+}
+
+pub fn fetch_failed(feed: &str) {
+    *REGISTRY.lock().unwrap().fetch_errors.entry(feed.to_string()).or_insert(0) += 1;
+}
+
+/// How long one Discord message took to send, successful or not
+pub fn discord_send(elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    let mut registry = REGISTRY.lock().unwrap();
+    for (bucket, bound) in registry.send_buckets.iter_mut().zip(SEND_BUCKETS) {
+        if seconds <= bound {
+            *bucket += 1;
+        }
+    }
+    registry.send_count += 1;
+    registry.send_sum += seconds;
+}
+
+/// How long the cycle just finished took, and whether that was over CYCLE_BUDGET_SECONDS
+pub fn observe_cycle(timings: &CycleTimings, over_budget: bool) {
+    let mut registry = REGISTRY.lock().unwrap();
+    registry.cycle = Some(*timings);
+    if over_budget {
+        registry.cycles_over_budget += 1;
+    }
+}
+
+/// Everything in the Prometheus text format
+pub fn render() -> String {
+    let registry = REGISTRY.lock().unwrap();
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, value: Option<f64>| {
+        if let Some(value) = value {
+            let _ = write!(out, "# HELP {0} {1}\n# TYPE {0} gauge\n{0} {2}\n", name, help, value);
+        }
+    };
+    gauge("taipower_current_load_megawatts", "Island-wide load", registry.load.map(|(load, _)| load));
+    gauge("taipower_reserve_rate_percent", "Forecast peak reserve rate", registry.load.map(|(_, rate)| rate));
+    gauge("taipower_total_generation_megawatts", "Total generation", registry.generation.map(|(total, _, _)| total));
+    gauge("taipower_renewable_ratio_percent", "Renewable share of generation", registry.generation.map(|(_, ratio, _)| ratio));
+    gauge("taipower_data_timestamp_seconds", "When Taipower published the latest generation data", registry.generation.map(|(_, _, time)| time as f64));
+
+    out.push_str("# HELP taipower_last_fetch_success_timestamp_seconds Last successful fetch of each feed\n");
+    out.push_str("# TYPE taipower_last_fetch_success_timestamp_seconds gauge\n");
+    for (feed, time) in &registry.last_success {
+        let _ = writeln!(out, "taipower_last_fetch_success_timestamp_seconds{{feed=\"{}\"}} {}", feed, time);
+    }
+
+    out.push_str("# HELP taipower_fetch_errors_total Failed fetches of each feed\n");
+    out.push_str("# TYPE taipower_fetch_errors_total counter\n");
+    for (feed, count) in &registry.fetch_errors {
+        let _ = writeln!(out, "taipower_fetch_errors_total{{feed=\"{}\"}} {}", feed, count);
+    }
+
+    if let Some(cycle) = &registry.cycle {
+        out.push_str("# HELP taipower_cycle_phase_seconds Time spent in each phase of the last update cycle\n");
+        out.push_str("# TYPE taipower_cycle_phase_seconds gauge\n");
+        for phase in Phase::ALL {
+            let _ = writeln!(out, "taipower_cycle_phase_seconds{{phase=\"{}\"}} {}", phase.code(), cycle.get(phase).as_secs_f64());
+        }
+        out.push_str("# HELP taipower_cycle_duration_seconds Total time of the last update cycle\n");
+        out.push_str("# TYPE taipower_cycle_duration_seconds gauge\n");
+        let _ = writeln!(out, "taipower_cycle_duration_seconds {}", cycle.total().as_secs_f64());
+    }
+    out.push_str("# HELP taipower_cycles_over_budget_total Update cycles that took longer than CYCLE_BUDGET_SECONDS\n");
+    out.push_str("# TYPE taipower_cycles_over_budget_total counter\n");
+    let _ = writeln!(out, "taipower_cycles_over_budget_total {}", registry.cycles_over_budget);
+
+    out.push_str("# HELP discord_send_duration_seconds Time taken to send a message to Discord\n");
+    out.push_str("# TYPE discord_send_duration_seconds histogram\n");
+    for (count, bound) in registry.send_buckets.iter().zip(SEND_BUCKETS) {
+        let _ = writeln!(out, "discord_send_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+    }
+    let _ = writeln!(out, "discord_send_duration_seconds_bucket{{le=\"+Inf\"}} {}", registry.send_count);
+    let _ = writeln!(out, "discord_send_duration_seconds_sum {}", registry.send_sum);
+    let _ = writeln!(out, "discord_send_duration_seconds_count {}", registry.send_count);
+    out
+}
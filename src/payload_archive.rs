@@ -0,0 +1,83 @@
+//! Raw feed payloads as fetched, kept for `replay` under
+//! PAYLOAD_ARCHIVE_DIR/<YYYY-MM-DD>/<HHMMSS>-<kind>.json (Taipei time of the fetch).
+//!
+//! A payload identical to the previous one of its kind isn't written again, so a day holds one
+//! file per upstream update rather than one per cycle.
+
+use chrono::{NaiveDate, NaiveTime};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing::error;
+
+use crate::clock::taipei_now;
+use crate::config;
+use crate::reporting::payload_fingerprint;
+
+pub const GENERATION: &str = "generation";
+pub const LOAD: &str = "load";
+pub const REGIONAL: &str = "regional";
+
+fn dir_from_env() -> Option<&'static Path> {
+    static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DIR.get_or_init(|| config::var("PAYLOAD_ARCHIVE_DIR").ok().filter(|v| !v.trim().is_empty()).map(PathBuf::from))
+        .as_deref()
+}
+
+/// Archive a payload that parsed successfully; a no-op unless PAYLOAD_ARCHIVE_DIR is set
+pub fn save(kind: &'static str, text: &str) {
+    let Some(dir) = dir_from_env() else {
+        return;
+    };
+    static LAST: OnceLock<Mutex<HashMap<&'static str, String>>> = OnceLock::new();
+    let fingerprint = payload_fingerprint(text);
+    let mut last = LAST.get_or_init(Default::default).lock().unwrap();
+    if last.get(kind) == Some(&fingerprint) {
+        return;
+    }
+
+    let now = taipei_now();
+    let day_dir = dir.join(now.format("%Y-%m-%d").to_string());
+    let path = day_dir.join(format!("{}-{}.json", now.format("%H%M%S"), kind));
+    match std::fs::create_dir_all(&day_dir).and_then(|_| std::fs::write(&path, text)) {
+        Ok(()) => {
+            last.insert(kind, fingerprint);
+        }
+        Err(why) => error!("Error archiving payload to {}: {:?}", path.display(), why),
+    }
+}
+
+/// A day's archived payloads as replay cycles: one per fetch minute, each holding the newest
+/// payload of every kind seen so far, since unchanged payloads weren't written again
+pub fn day_cycles(date: NaiveDate) -> Result<Vec<Vec<PathBuf>>, Box<dyn std::error::Error + Send + Sync>> {
+    let dir = dir_from_env().ok_or("PAYLOAD_ARCHIVE_DIR is not set")?.join(date.format("%Y-%m-%d").to_string());
+    let mut files: Vec<(NaiveTime, &'static str, PathBuf)> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let (time, kind) = parse_file_name(path.file_name()?.to_str()?)?;
+            Some((time, kind, path))
+        })
+        .collect();
+    files.sort();
+
+    let mut cycles = Vec::new();
+    let mut newest: HashMap<&'static str, PathBuf> = HashMap::new();
+    let mut minutes = files.iter().map(|(time, _, _)| time.format("%H%M").to_string()).peekable();
+    for (_, kind, path) in &files {
+        newest.insert(kind, path.clone());
+        let minute = minutes.next();
+        if minutes.peek() != minute.as_ref() {
+            let mut cycle: Vec<PathBuf> = newest.values().cloned().collect();
+            cycle.sort();
+            cycles.push(cycle);
+        }
+    }
+    Ok(cycles)
+}
+
+fn parse_file_name(name: &str) -> Option<(NaiveTime, &'static str)> {
+    let (time, kind) = name.strip_suffix(".json")?.split_once('-')?;
+    let kind = [GENERATION, LOAD, REGIONAL].into_iter().find(|k| *k == kind)?;
+    Some((NaiveTime::parse_from_str(time, "%H%M%S").ok()?, kind))
+}
@@ -0,0 +1,183 @@
+//! Phone push notifications (ntfy, Pushover, Bark) for individual users, registered with `/push`.
+
+
+use tracing::error;
+
+use crate::config;
+use crate::history::History;
+
+/// Per user, so one person can't turn the bot into a notification cannon
+pub const MAX_SUBSCRIPTIONS_PER_USER: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushService {
+    /// Endpoint is the topic URL, e.g. https://ntfy.sh/my-topic
+    Ntfy,
+    /// Endpoint is the user key; the app token comes from PUSHOVER_APP_TOKEN
+    Pushover,
+    /// Endpoint is the device URL, e.g. https://api.day.app/<key>
+    Bark,
+}
+
+impl PushService {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ntfy" => Some(PushService::Ntfy),
+            "pushover" => Some(PushService::Pushover),
+            "bark" => Some(PushService::Bark),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            PushService::Ntfy => "ntfy",
+            PushService::Pushover => "pushover",
+            PushService::Bark => "bark",
+        }
+    }
+
+    /// Reject endpoints that can't work before they're stored
+    pub fn validate(&self, endpoint: &str) -> Result<(), &'static str> {
+        match self {
+            PushService::Ntfy | PushService::Bark => match reqwest::Url::parse(endpoint) {
+                Ok(url) if url.scheme() == "https" && url.host_str().is_some() => Ok(()),
+                _ => Err("端點必須是 https:// 開頭的網址"),
+            },
+            PushService::Pushover if pushover_app_token().is_none() => Err("此機器人未設定 Pushover 應用程式 (PUSHOVER_APP_TOKEN)"),
+            PushService::Pushover if endpoint.len() == 30 && endpoint.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(()),
+            PushService::Pushover => Err("Pushover 使用者金鑰應為 30 個英數字元"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertType {
+    /// The reserve indicator turned orange or worse
+    ReserveCritical,
+    /// Any reserve indicator change
+    IndicatorChange,
+    /// The load feed stopped updating
+    UpstreamFrozen,
+    /// The grid stress index reached STRESS_ALERT_THRESHOLD
+    StressHigh,
+}
+
+impl AlertType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reserve_critical" => Some(AlertType::ReserveCritical),
+            "indicator_change" => Some(AlertType::IndicatorChange),
+            "upstream_frozen" => Some(AlertType::UpstreamFrozen),
+            "stress_high" => Some(AlertType::StressHigh),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AlertType::ReserveCritical => "reserve_critical",
+            AlertType::IndicatorChange => "indicator_change",
+            AlertType::UpstreamFrozen => "upstream_frozen",
+            AlertType::StressHigh => "stress_high",
+        }
+    }
+
+    fn urgent(&self) -> bool {
+        *self == AlertType::ReserveCritical
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PushSubscription {
+    pub id: i64,
+    pub user_id: u64,
+    pub service: PushService,
+    pub endpoint: String,
+    pub alert_type: AlertType,
+}
+
+fn pushover_app_token() -> Option<String> {
+    config::var("PUSHOVER_APP_TOKEN").ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Send `title`/`message` to everyone subscribed to `alert_type`
+pub async fn notify(history: &History, alert_type: AlertType, title: &str, message: &str) {
+    let subscriptions = match history.push_subscriptions(alert_type) {
+        Ok(subscriptions) => subscriptions,
+        Err(why) => {
+            error!("Error reading push subscriptions: {:?}", why);
+            return;
+        }
+    };
+    deliver(&subscriptions, title, message).await;
+}
+
+/// Send to `user_id`'s own subscriptions for `alert_type` only (for `/alerts test`); returns how
+/// many went through
+pub async fn notify_user(history: &History, user_id: u64, alert_type: AlertType, title: &str, message: &str) -> usize {
+    let subscriptions: Vec<PushSubscription> = match history.user_push_subscriptions(user_id) {
+        Ok(subscriptions) => subscriptions.into_iter().filter(|s| s.alert_type == alert_type).collect(),
+        Err(why) => {
+            error!("Error reading push subscriptions: {:?}", why);
+            return 0;
+        }
+    };
+    deliver(&subscriptions, title, message).await
+}
+
+async fn deliver(subscriptions: &[PushSubscription], title: &str, message: &str) -> usize {
+    let mut sent = 0;
+    for subscription in subscriptions {
+        match send(subscription, title, message).await {
+            Ok(()) => sent += 1,
+            Err(why) => error!(
+                "Error sending {} push #{} to user {}: {:?}",
+                subscription.service.code(),
+                subscription.id,
+                subscription.user_id,
+                why
+            ),
+        }
+    }
+    sent
+}
+
+async fn send(
+    subscription: &PushSubscription,
+    title: &str,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http::client();
+    let urgent = subscription.alert_type.urgent();
+    let request = match subscription.service {
+        PushService::Ntfy => client
+            .post(&subscription.endpoint)
+            .header("Title", title)
+            .header("Priority", if urgent { "urgent" } else { "default" })
+            .header("Tags", "zap")
+            .body(message.to_string()),
+        PushService::Pushover => {
+            let token = pushover_app_token().ok_or("PUSHOVER_APP_TOKEN is not set")?;
+            client.post("https://api.pushover.net/1/messages.json").form(&[
+                ("token", token.as_str()),
+                ("user", subscription.endpoint.as_str()),
+                ("title", title),
+                ("message", message),
+                ("priority", if urgent { "1" } else { "0" }),
+            ])
+        }
+        PushService::Bark => client.post(&subscription.endpoint).json(&serde_json::json!({
+            "title": title,
+            "body": message,
+            "level": if urgent { "timeSensitive" } else { "active" },
+            "group": "taipower",
+        })),
+    };
+
+    let response = request.timeout(std::time::Duration::from_secs(15)).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()).into());
+    }
+    Ok(())
+}
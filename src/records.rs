@@ -0,0 +1,134 @@
+//! Renewable-share records: a celebratory post when the share beats its all-time high, or passes
+//! yesterday's peak. On a sunny morning the share climbs past the record cycle after cycle and
+//! only the first crossing is news, so each kind is announced at most once a day; the day is
+//! stored with the record so a restart doesn't announce it again.
+//!
+//! RECORD_ALERTS=off stops the posts; records are still kept up to date.
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::history::History;
+
+pub(crate) const ALL_TIME: &str = "renewable_ratio";
+const DAILY: &str = "renewable_ratio_daily";
+/// Shares within this many percentage points of a record tie it rather than break it
+const MARGIN: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordBreak {
+    AllTime { value: f64, previous: f64, previous_at: NaiveDateTime },
+    /// Today's share passed yesterday's peak
+    Daily { value: f64, yesterday: f64 },
+}
+
+pub fn enabled() -> bool {
+    !matches!(crate::config::var("RECORD_ALERTS").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// Compare the latest renewable share with the stored records and update them. Must run before
+/// the snapshot is recorded, since the records table starts out from the snapshots' maximum
+pub fn check(history: &History, ratio: f64, now: NaiveDateTime) -> rusqlite::Result<Option<RecordBreak>> {
+    let today = now.date();
+    let stored = match history.record_value(ALL_TIME)? {
+        Some(record) => Some(record),
+        None => history.max_renewable_ratio()?.map(|(value, at)| (value, at, None)),
+    };
+    let Some((previous, previous_at, announced_on)) = stored else {
+        // The very first reading is trivially a record, but not news
+        history.set_record(ALL_TIME, ratio, now, None)?;
+        return Ok(None);
+    };
+
+    if ratio > previous + MARGIN {
+        let announce = announced_on != Some(today);
+        history.set_record(ALL_TIME, ratio, now, Some(today))?;
+        if announce {
+            // Passing yesterday's peak goes without saying now
+            history.set_record(DAILY, ratio, now, Some(today))?;
+            return Ok(Some(RecordBreak::AllTime { value: ratio, previous, previous_at }));
+        }
+        return Ok(None);
+    }
+
+    let Some(yesterday) = history.day_max_renewable_ratio(today - Duration::days(1))? else {
+        return Ok(None);
+    };
+    let daily_announced = history.record_value(DAILY)?.and_then(|(_, _, day)| day);
+    if ratio > yesterday + MARGIN && daily_announced != Some(today) {
+        history.set_record(DAILY, ratio, now, Some(today))?;
+        return Ok(Some(RecordBreak::Daily { value: ratio, yesterday }));
+    }
+    Ok(None)
+}
+
+pub fn announcement(record: &RecordBreak) -> String {
+    match record {
+        RecordBreak::AllTime { value, previous, previous_at } => format!(
+            "🎉 **再生能源占比創歷史新高！** 目前 {:.1}%，打破 {} 的 {:.1}% 紀錄",
+            value,
+            previous_at.format("%Y-%m-%d %H:%M"),
+            previous
+        ),
+        RecordBreak::Daily { value, yesterday } => {
+            format!("🌞 **再生能源占比超越昨日高峰** 目前 {:.1}%（昨日最高 {:.1}%）", value, yesterday)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{self, CombinedPowerData};
+    use crate::clock::{self, ManualClock};
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 7, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    /// A history holding one snapshot with renewable share `ratio`, recorded at `recorded_at`
+    fn history_with(ratio: f64, recorded_at: NaiveDateTime) -> History {
+        let history = History::open_with_clock(":memory:", Arc::new(ManualClock::new(clock::taipei_datetime(recorded_at).unwrap()))).unwrap();
+        let mut power_analysis = analysis::analyze_power_data(Vec::new(), None).unwrap();
+        power_analysis.renewable_ratio = ratio;
+        history
+            .record(&CombinedPowerData {
+                power_analysis,
+                load_data: None,
+                regions: Vec::new(),
+                temperature: None,
+                own_forecast: None,
+                outages: Vec::new(),
+                demand_response_mw: None,
+                stress: None,
+                trend: None,
+            })
+            .unwrap();
+        history
+    }
+
+    #[test]
+    fn all_time_records_are_announced_once_a_day() {
+        let history = history_with(30.0, at(1, 12));
+        assert_eq!(
+            check(&history, 31.0, at(2, 10)).unwrap(),
+            Some(RecordBreak::AllTime { value: 31.0, previous: 30.0, previous_at: at(1, 12) })
+        );
+        // Climbing further the same morning updates the record quietly
+        assert_eq!(check(&history, 32.0, at(2, 11)).unwrap(), None);
+        assert_eq!(history.record_value(ALL_TIME).unwrap().map(|(value, _, _)| value), Some(32.0));
+        // A tie isn't a record
+        assert_eq!(check(&history, 32.04, at(3, 11)).unwrap(), None);
+        assert!(matches!(check(&history, 33.0, at(3, 12)).unwrap(), Some(RecordBreak::AllTime { previous, .. }) if previous == 32.0));
+    }
+
+    #[test]
+    fn passing_yesterdays_peak_is_announced_once() {
+        let history = history_with(25.0, at(2, 12));
+        history.set_record(ALL_TIME, 50.0, at(1, 12), None).unwrap();
+        assert_eq!(check(&history, 24.0, at(3, 10)).unwrap(), None);
+        assert_eq!(check(&history, 26.0, at(3, 11)).unwrap(), Some(RecordBreak::Daily { value: 26.0, yesterday: 25.0 }));
+        assert_eq!(check(&history, 27.0, at(3, 12)).unwrap(), None);
+    }
+}
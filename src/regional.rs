@@ -0,0 +1,110 @@
+//! Per-region supply/demand estimates from Taipower's regional generation and load shares.
+//!
+//! genloadareaperc.json only publishes each region's share of island-wide generation and load,
+//! so absolute figures are estimated by scaling both shares by the current island-wide load.
+//! Island generation and load balance at any instant, and the unit list behind our own total
+//! generation omits small generators, so the load is the better common base.
+
+use serde::Deserialize;
+use std::time::Instant;
+use tracing::{debug, info};
+
+use crate::de;
+use crate::http;
+use crate::taipower_api::{deserialize_with_path, TaipowerError};
+
+const REGIONAL_URL: &str = "https://www.taipower.com.tw/d006/loadGraph/loadGraph/data/genloadareaperc.json";
+
+#[derive(Debug, Deserialize)]
+struct RegionalPayload {
+    #[serde(rename = "data")]
+    data: Vec<RegionalShare>,
+}
+
+/// A region's share (%) of island-wide generation and load
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegionalShare {
+    #[serde(rename = "area")]
+    pub area: String,
+    #[serde(rename = "genPerc", deserialize_with = "de::mw_value")]
+    pub generation_percent: f64,
+    #[serde(rename = "loadPerc", deserialize_with = "de::mw_value")]
+    pub load_percent: f64,
+}
+
+/// Estimated balance for one region, in MW
+#[derive(Debug, Clone)]
+pub struct RegionBalance {
+    pub area: String,
+    pub generation: f64,
+    pub load: f64,
+    pub heavy_import: bool,
+}
+
+impl RegionBalance {
+    /// Generation left over after the region's own load; negative when it relies on other regions
+    pub fn margin(&self) -> f64 {
+        self.generation - self.load
+    }
+
+    /// Share of the region's load supplied across inter-area ties; negative when exporting
+    pub fn import_share(&self) -> f64 {
+        if self.load > 0.0 {
+            (self.load - self.generation) / self.load * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Import share (%) above which a region is flagged in the report
+pub fn import_warn_percent_from_env() -> f64 {
+    crate::config::var("REGION_IMPORT_WARN_PERCENT")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(25.0)
+}
+
+pub async fn fetch_regional_shares() -> Result<Vec<RegionalShare>, TaipowerError> {
+    http::with_retry("regional data", fetch_regional_shares_once).await
+}
+
+async fn fetch_regional_shares_once() -> Result<Vec<RegionalShare>, TaipowerError> {
+    let client = http::client();
+
+    let url = http::endpoint(REGIONAL_URL);
+    debug!(endpoint = %url, "Fetching regional data");
+    let started = Instant::now();
+
+    let response = client.get(&url).send().await?.error_for_status()?;
+    let text = response.text().await?;
+    info!(endpoint = %url, bytes = text.len(), elapsed_ms = started.elapsed().as_millis() as u64, "Fetched regional data");
+    let shares = parse_regional_payload(&text).inspect_err(|e| crate::reporting::report_parse_error(&url, &e.to_string(), &text))?;
+    crate::payload_archive::save(crate::payload_archive::REGIONAL, &text);
+    crate::schema_watch::parsed(&url, &text);
+    Ok(shares)
+}
+
+pub fn parse_regional_payload(text: &str) -> Result<Vec<RegionalShare>, TaipowerError> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let payload: RegionalPayload = deserialize_with_path(value)?;
+    Ok(payload.data)
+}
+
+/// Scale the published shares by the island-wide load (萬瓩)
+pub fn estimate(shares: &[RegionalShare], current_load: f64, import_warn_percent: f64) -> Vec<RegionBalance> {
+    let total_load = current_load * 10.0;
+    shares
+        .iter()
+        .map(|share| {
+            let mut balance = RegionBalance {
+                area: share.area.trim().to_string(),
+                generation: total_load * share.generation_percent / 100.0,
+                load: total_load * share.load_percent / 100.0,
+                heavy_import: false,
+            };
+            balance.heavy_import = balance.import_share() > import_warn_percent;
+            balance
+        })
+        .collect()
+}
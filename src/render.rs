@@ -0,0 +1,959 @@
+use serenity::builder::{CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage};
+use serenity::model::id::RoleId;
+
+use crate::clock::discord_time;
+use crate::accessible::AccessibleRenderer;
+use crate::alerts::{FaultChange, IndicatorChange};
+use crate::config;
+use crate::demand_response;
+use crate::embed::EmbedRenderer;
+use crate::forecast::ForecastAccuracy;
+use crate::history::{DailySummary, SummarySource};
+use crate::locale::{Labels, Locale, NumberFormat, ZH_TW};
+use crate::maintenance::Outage;
+use crate::mentions::{self, MentionTarget};
+use crate::regional::RegionBalance;
+use crate::analysis::{CombinedPowerData, PowerAnalysis};
+use crate::taipower_api::{LoadData, ReserveIndicator};
+use crate::trend::Trend;
+
+pub const DATA_SOURCE_URL: &str = "https://data.gov.tw/dataset/8931";
+const ARCHIVE_SOURCE_URL: &str = "https://data.gov.tw/dataset/19995";
+pub const DISCLAIMER: &str = "本資料可能會有錯誤或延遲，造成損失與我們無關";
+
+/// Turns analysed data into a particular output format.
+pub trait Renderer {
+    type Output;
+
+    fn report(&self, data: &CombinedPowerData) -> Self::Output;
+    /// One-line status for frequent or space-constrained posts
+    fn compact(&self, data: &CombinedPowerData) -> Self::Output;
+    fn daily_summary(&self, summary: &DailySummary) -> Self::Output;
+    fn indicator_change(&self, change: &IndicatorChange) -> Self::Output;
+    fn fault_change(&self, change: &FaultChange) -> Self::Output;
+}
+
+/// Markdown + emoji text for regular Discord messages
+#[derive(Default)]
+pub struct DiscordTextRenderer {
+    pub locale: Locale,
+    pub numbers: NumberFormat,
+    pub sections: ReportSections,
+}
+
+/// Which parts of the full text report a guild wants (`/config sections`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSections {
+    pub supply: bool,
+    pub yesterday: bool,
+    pub realtime: bool,
+    pub regions: bool,
+    pub generation: bool,
+    pub units: bool,
+}
+
+impl Default for ReportSections {
+    fn default() -> Self {
+        ReportSections { supply: true, yesterday: true, realtime: true, regions: true, generation: true, units: true }
+    }
+}
+
+impl ReportSections {
+    pub const NAMES: [&'static str; 6] = ["supply", "yesterday", "realtime", "regions", "generation", "units"];
+
+    /// Comma-separated section names, or "all"
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.trim().eq_ignore_ascii_case("all") {
+            return Some(ReportSections::default());
+        }
+        let mut sections = ReportSections {
+            supply: false,
+            yesterday: false,
+            realtime: false,
+            regions: false,
+            generation: false,
+            units: false,
+        };
+        let mut any = false;
+        for name in value.split(',').map(|n| n.trim().to_ascii_lowercase()).filter(|n| !n.is_empty()) {
+            *sections.flag(&name)? = true;
+            any = true;
+        }
+        any.then_some(sections)
+    }
+
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "supply" => Some(&mut self.supply),
+            "yesterday" => Some(&mut self.yesterday),
+            "realtime" => Some(&mut self.realtime),
+            "regions" => Some(&mut self.regions),
+            "generation" => Some(&mut self.generation),
+            "units" => Some(&mut self.units),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> String {
+        let mut copy = *self;
+        Self::NAMES
+            .iter()
+            .filter(|name| copy.flag(name).is_some_and(|on| *on))
+            .copied()
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Unformatted text for consoles and bridges that don't understand markdown
+pub struct PlainRenderer;
+
+pub fn indicator_emoji(indicator: ReserveIndicator) -> &'static str {
+    match indicator {
+        ReserveIndicator::Green => "🟢",
+        ReserveIndicator::Yellow => "🟡",
+        ReserveIndicator::Orange => "🟠",
+        ReserveIndicator::Red => "🔴",
+        ReserveIndicator::Black => "⚫",
+        ReserveIndicator::Unknown => "⚪",
+    }
+}
+
+pub fn indicator_label(indicator: ReserveIndicator, l: &Labels) -> &'static str {
+    match indicator {
+        ReserveIndicator::Green => l.indicator_green,
+        ReserveIndicator::Yellow => l.indicator_yellow,
+        ReserveIndicator::Orange => l.indicator_orange,
+        ReserveIndicator::Red => l.indicator_red,
+        ReserveIndicator::Black => l.indicator_black,
+        ReserveIndicator::Unknown => l.unknown,
+    }
+}
+
+pub fn format_hour_range(load_data: &LoadData, l: &Labels) -> String {
+    match load_data.forecast_peak_hour_range {
+        Some((start, end)) => format!("{}~{}", start.format("%H:%M"), end.format("%H:%M")),
+        None => l.unknown.to_string(),
+    }
+}
+
+fn format_publish_time(load_data: &LoadData, l: &Labels) -> String {
+    load_data
+        .publish_time
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| l.unknown.to_string())
+}
+
+pub fn format_peak_time(load_data: &LoadData, l: &Labels) -> String {
+    load_data
+        .real_hour_peak_time
+        .map(|t| t.format("%H:%M").to_string())
+        .unwrap_or_else(|| l.unknown.to_string())
+}
+
+/// e.g. "▼0.4pp"
+pub fn format_pp_change(change: f64) -> String {
+    if change >= 0.05 {
+        format!("▲{:.1}pp", change)
+    } else if change <= -0.05 {
+        format!("▼{:.1}pp", -change)
+    } else {
+        "±0.0pp".to_string()
+    }
+}
+
+/// Short status line for the bot's Discord presence, e.g. "⚡ 3512萬瓩 | 備轉 7.1% ▼0.4pp | 備轉趨緊"
+pub fn presence_text(load_data: &LoadData, reserve_rate_change: Option<f64>, trend: Option<Trend>) -> String {
+    let mut text = format!(
+        "⚡ {:.0}萬瓩 | 備轉 {:.1}%",
+        load_data.current_load, load_data.forecast_peak_reserve_rate
+    );
+    if let Some(change) = reserve_rate_change {
+        text.push(' ');
+        text.push_str(&format_pp_change(change));
+    }
+    if let Some(trend) = trend {
+        text.push_str(" | ");
+        text.push_str(trend.label(&ZH_TW));
+    }
+    text
+}
+
+/// Voice channel name for the reserve rate ticker, e.g. "🔋 備轉率 10.2% 🟢"
+pub fn ticker_text(load_data: &LoadData, numbers: NumberFormat) -> String {
+    format!(
+        "🔋 備轉率 {} {}",
+        numbers.percent(load_data.forecast_peak_reserve_rate, 1),
+        indicator_emoji(load_data.forecast_peak_reserve_indicator)
+    )
+}
+
+/// e.g. "本機 2.1% · 台電 1.8% · 下一小時 1.2% (近 14 日)"
+pub fn format_accuracy(accuracy: &ForecastAccuracy, l: &Labels, numbers: NumberFormat) -> String {
+    let percent = |value: Option<f64>| value.map(|v| numbers.percent(v, 1)).unwrap_or_else(|| "-".to_string());
+    format!(
+        "{} {} · {} {} · {} {} ({}{}{})",
+        l.bot, percent(accuracy.bot_day_peak),
+        l.taipower, percent(accuracy.taipower_day_peak),
+        l.next_hour, percent(accuracy.bot_next_hour),
+        l.last_days_prefix, accuracy.days, l.days_suffix,
+    )
+}
+
+/// e.g. "北部: 發電 9100 MW / 負載 13500 MW (輸入 32.6%) ⚠️"
+pub fn format_region(region: &RegionBalance, locale: Locale, numbers: NumberFormat, warning: &str) -> String {
+    let l = locale.labels();
+    let share = region.import_share();
+    let direction = if share >= 0.0 { l.importing } else { l.exporting };
+    format!(
+        "{}: {} {} / {} {} ({} {}){}",
+        locale.region_name(&region.area),
+        l.region_generation,
+        numbers.mw(region.generation, 0),
+        l.region_load,
+        numbers.mw(region.load, 0),
+        direction,
+        numbers.percent(share.abs(), 1),
+        if region.heavy_import { warning } else { "" },
+    )
+}
+
+/// e.g. "台中#9 (550 MW): 計畫歲修，預計至 2025-03-01"
+pub fn format_outage(outage: &Outage, l: &Labels, numbers: NumberFormat) -> String {
+    let status = match outage.planned_until {
+        Some(until) => format!("{} {}", l.planned_until, until),
+        None => l.unplanned.to_string(),
+    };
+    format!("{} ({}): {}", outage.unit, numbers.mw(outage.capacity, 0), status)
+}
+
+/// How the generation mix is listed: fuel types merged into groups (FUEL_GROUPS, e.g.
+/// "燃油=輕油,重油") and types pinned to the top in a fixed order (FUEL_ORDER, e.g. "核能,燃煤");
+/// everything else follows by output
+#[derive(Debug, Default)]
+pub struct FuelDisplay {
+    groups: Vec<(String, Vec<String>)>,
+    order: Vec<String>,
+}
+
+impl FuelDisplay {
+    pub fn parse(groups: &str, order: &str) -> Self {
+        let list = |value: &str| value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect::<Vec<_>>();
+        let groups = groups
+            .split(';')
+            .filter_map(|group| group.split_once('='))
+            .map(|(name, members)| (name.trim().to_string(), list(members)))
+            .filter(|(name, members)| !name.is_empty() && !members.is_empty())
+            .collect();
+        FuelDisplay { groups, order: list(order) }
+    }
+
+    pub fn from_env() -> Self {
+        FuelDisplay::parse(&config::var("FUEL_GROUPS").unwrap_or_default(), &config::var("FUEL_ORDER").unwrap_or_default())
+    }
+
+    fn group_of<'a>(&'a self, energy_type: &'a str) -> &'a str {
+        self.groups
+            .iter()
+            .find(|(_, members)| members.iter().any(|m| m == energy_type))
+            .map(|(name, _)| name.as_str())
+            .unwrap_or(energy_type)
+    }
+
+    /// Merge and order (energy type, MW) pairs for display
+    pub fn apply<'a>(&self, mix: impl IntoIterator<Item = (&'a str, f64)>) -> Vec<(String, f64)> {
+        let mut merged: Vec<(String, f64)> = Vec::new();
+        for (energy_type, generation) in mix {
+            let name = self.group_of(energy_type);
+            match merged.iter_mut().find(|(n, _)| n == name) {
+                Some((_, total)) => *total += generation,
+                None => merged.push((name.to_string(), generation)),
+            }
+        }
+        let rank = |name: &str| self.order.iter().position(|o| o == name).unwrap_or(usize::MAX);
+        merged.sort_by(|a, b| rank(&a.0).cmp(&rank(&b.0)).then(b.1.total_cmp(&a.1)));
+        merged
+    }
+
+    /// Like `apply`, but unpinned types follow by name so the list reads the same every time
+    pub fn apply_by_name<'a>(&self, mix: impl IntoIterator<Item = (&'a str, f64)>) -> Vec<(String, f64)> {
+        let mut merged = self.apply(mix);
+        let rank = |name: &str| self.order.iter().position(|o| o == name).unwrap_or(usize::MAX);
+        merged.sort_by(|a, b| rank(&a.0).cmp(&rank(&b.0)).then_with(|| a.0.cmp(&b.0)));
+        merged
+    }
+}
+
+pub fn sorted_generation(analysis: &PowerAnalysis) -> Vec<(String, f64)> {
+    FuelDisplay::from_env().apply(analysis.generation_by_type.iter().map(|(t, g)| (t.as_str(), *g)))
+}
+
+impl Renderer for DiscordTextRenderer {
+    type Output = String;
+
+    fn report(&self, data: &CombinedPowerData) -> String {
+        let l = self.locale.labels();
+        let load = |value: f64| self.locale.load_value(value, self.numbers);
+        let n = self.numbers;
+        let mut message = String::new();
+
+        message.push_str(&format!("🔋 **{}** 🔋\n\n", l.report_title));
+        if let Some(stress) = &data.stress {
+            message.push_str(&format!("🌡️ **{}**: {}\n\n", l.stress_index, stress.display()));
+        }
+
+        // Load data section (if available)
+        if let Some(load_data) = &data.load_data {
+            if self.sections.supply {
+                message.push_str(&format!("⚡ **{}**\n", l.supply_demand));
+                message.push_str(&format!("📊 **{}**: {}\n", l.current_load, load(load_data.current_load)));
+                message.push_str(&format!("📈 **{}**: {}\n", l.util_rate, n.percent(load_data.current_util_rate, 1)));
+                message.push_str(&format!("🔌 **{}**: {}\n", l.forecast_max_supply, load(load_data.forecast_max_supply_capacity)));
+                message.push_str(&format!("⬆️ **{}**: {}\n", l.forecast_peak_demand, load(load_data.forecast_peak_demand_load)));
+                if let Some(own) = &data.own_forecast {
+                    message.push_str(&format!("🤖 **{}**: {} ({}) · {} {}\n",
+                        l.own_forecast, load(own.day_peak), own.day_peak_time.format("%H:%M"), l.next_hour, load(own.next_hour)));
+                }
+                message.push_str(&format!("🔋 **{}**: {}\n", l.forecast_reserve_capacity, load(load_data.forecast_peak_reserve_capacity)));
+                message.push_str(&format!("{} **{}**: {}\n",
+                    indicator_emoji(load_data.forecast_peak_reserve_indicator),
+                    l.forecast_reserve_rate,
+                    n.percent(load_data.forecast_peak_reserve_rate, 2)));
+                if let Some(mw) = data.demand_response_mw {
+                    let (capacity, rate) = demand_response::reserve_without(load_data, mw);
+                    message.push_str(&format!("🤝 **{}**: {}\n", l.demand_response, n.mw(mw, 1)));
+                    message.push_str(&format!("   • {}: {} ({})\n", l.reserve_without_dr, n.percent(rate, 2), load(capacity)));
+                }
+                message.push_str(&format!("🕐 **{}**: {}\n", l.forecast_peak_hours, format_hour_range(load_data, l)));
+                if let Some(accuracy) = data.own_forecast.as_ref().and_then(|f| f.accuracy.as_ref()) {
+                    message.push_str(&format!("🎯 **{}**: {}\n", l.forecast_error, format_accuracy(accuracy, l, n)));
+                }
+                let published = load_data.publish_time.map(discord_time).unwrap_or_else(|| l.unknown.to_string());
+                message.push_str(&format!("📅 **{}**: {}\n\n", l.data_updated, published));
+            }
+
+            // Yesterday's data
+            if self.sections.yesterday {
+                message.push_str(&format!("📊 **{}**\n", l.yesterday));
+                message.push_str(&format!("🔌 **{}**: {}\n", l.max_supply, load(load_data.yesterday_max_supply_capacity)));
+                message.push_str(&format!("⬆️ **{}**: {}\n", l.peak_demand, load(load_data.yesterday_peak_demand_load)));
+                message.push_str(&format!("🔋 **{}**: {}\n", l.peak_reserve_capacity, load(load_data.yesterday_peak_reserve_capacity)));
+                message.push_str(&format!("{} **{}**: {}\n\n",
+                    indicator_emoji(load_data.yesterday_peak_reserve_indicator),
+                    l.peak_reserve_rate,
+                    n.percent(load_data.yesterday_peak_reserve_rate, 2)));
+            }
+
+            // Real-time peak data
+            if self.sections.realtime && load_data.real_hour_max_supply_capacity > 0.0 {
+                message.push_str(&format!("⏰ **{}**\n", l.realtime_peak));
+                message.push_str(&format!("🔌 **{}**: {}\n", l.realtime_max_supply, load(load_data.real_hour_max_supply_capacity)));
+                message.push_str(&format!("🕰️ **{}**: {}\n\n", l.peak_time, format_peak_time(load_data, l)));
+            }
+        }
+
+        if self.sections.regions && !data.regions.is_empty() {
+            message.push_str(&format!("🗺️ **{}**\n", l.regions));
+            for region in &data.regions {
+                message.push_str(&format!("   • {}\n", format_region(region, self.locale, n, " ⚠️")));
+            }
+            message.push('\n');
+        }
+
+        // Power generation analysis section
+        let analysis = &data.power_analysis;
+        if self.sections.generation {
+            message.push_str(&format!("🏭 **{}**\n", l.generation));
+            message.push_str(&format!("📅 **{}**: {}\n", l.updated, discord_time(analysis.update_time)));
+            message.push_str(&format!("⚡ **{}**: {}\n", l.total_generation, n.mw(analysis.total_generation, 1)));
+            message.push_str(&format!("🔄 **{}**: {}\n", l.installed_capacity, n.mw(analysis.estimated_max_generation, 1)));
+            message.push_str(&format!("📊 **{}**: {}\n\n", l.generation_ratio, n.percent(analysis.generation_ratio(), 1)));
+
+            message.push_str(&format!("🏭 **{}**:\n", l.by_type));
+            for (energy_type, generation) in sorted_generation(analysis) {
+                message.push_str(&format!("   • {}: {}\n", self.locale.energy_type(&energy_type), self.numbers.mw(generation, 1)));
+            }
+
+            message.push_str(&format!("\n🏆 **{}**: {} ({})\n",
+                l.top_plant, analysis.top_plant.0, n.mw(analysis.top_plant.1, 1)));
+            message.push_str(&format!("🥇 **{}**: {} ({})\n",
+                l.top_unit, analysis.top_unit.0, n.mw(analysis.top_unit.1, 1)));
+        }
+
+        if self.sections.units {
+            message.push_str(&format!("\n📋 **{}**:\n", l.unit_status));
+            message.push_str(&format!("   🌱 {}: {}{}\n", l.restrictions, analysis.environmental_restrictions, l.units_suffix));
+            message.push_str(&format!("   🔧 {}: {}{}\n", l.maintenance, analysis.maintenance_count, l.units_suffix));
+            message.push_str(&format!("   ⚠️ {}: {}{}\n", l.faults, analysis.fault_count, l.units_suffix));
+
+            if !data.outages.is_empty() {
+                message.push_str(&format!("\n🛠️ **{}**:\n", l.outages));
+                for outage in &data.outages {
+                    let emoji = if outage.planned_until.is_some() { "🔧" } else { "⚠️" };
+                    message.push_str(&format!("   {} {}\n", emoji, format_outage(outage, l, n)));
+                }
+            }
+        }
+
+        if self.sections.generation {
+            message.push_str(&format!("\n🌿 **{}**: {}\n", l.renewable_ratio, n.percent(analysis.renewable_ratio, 1)));
+            message.push_str(&format!("🏢 **{}**: {}\n", l.private_ratio, n.percent(analysis.private_ratio, 1)));
+        }
+
+        message.push_str(&format!("\n📊 {}: [{}](<{}>)", l.source, l.source_name, DATA_SOURCE_URL));
+        message.push_str(&format!("\n⚠️{}", l.disclaimer));
+
+        message
+    }
+
+    fn compact(&self, data: &CombinedPowerData) -> String {
+        let l = self.locale.labels();
+        let analysis = &data.power_analysis;
+        let trend = data.trend.map(|t| format!(" | {} {}", t.emoji(), t.label(l))).unwrap_or_default();
+        match &data.load_data {
+            Some(load_data) => format!(
+                "⚡ {}: **{}** | {} {} **{}**{} | 🌿 {} {} | 🕐 {}",
+                l.current_load,
+                self.locale.load_value(load_data.current_load, self.numbers),
+                indicator_emoji(load_data.forecast_peak_reserve_indicator),
+                l.reserve_short,
+                self.numbers.percent(load_data.forecast_peak_reserve_rate, 1),
+                trend,
+                l.renewable_short,
+                self.numbers.percent(analysis.renewable_ratio, 1),
+                analysis.update_time.format("%H:%M"),
+            ),
+            None => format!(
+                "⚡ {}: **{}** | 🌿 {} {} | 🕐 {}",
+                l.total_generation,
+                self.numbers.mw(analysis.total_generation, 1),
+                l.renewable_short,
+                self.numbers.percent(analysis.renewable_ratio, 1),
+                analysis.update_time.format("%H:%M"),
+            ),
+        }
+    }
+
+    fn daily_summary(&self, summary: &DailySummary) -> String {
+        let l = self.locale.labels();
+        let mut message = String::new();
+
+        message.push_str(&format!("📅 **{} {}**\n\n", summary.date, l.summary_title));
+
+        match summary.peak_load {
+            Some(peak) => match &summary.peak_time {
+                Some(time) => message.push_str(&format!("⬆️ **{}**: {} ({})\n", l.peak_load, self.locale.load_value(peak, self.numbers), time)),
+                None => message.push_str(&format!("⬆️ **{}**: {}\n", l.peak_load, self.locale.load_value(peak, self.numbers))),
+            },
+            None => message.push_str(&format!("⬆️ **{}**: {}\n", l.peak_load, l.no_data)),
+        }
+        if let (Some(min), Some(time)) = (summary.min_load, &summary.min_time) {
+            message.push_str(&format!("⬇️ **{}**: {} ({})\n", l.min_load, self.locale.load_value(min, self.numbers), time));
+        }
+        match (summary.min_reserve_rate, summary.peak_reserve_rate) {
+            (Some(rate), _) => message.push_str(&format!("🔋 **{}**: {}\n", l.min_reserve_rate, self.numbers.percent(rate, 2))),
+            (None, Some(rate)) => message.push_str(&format!("🔋 **{}**: {}\n", l.peak_reserve_rate, self.numbers.percent(rate, 2))),
+            (None, None) => message.push_str(&format!("🔋 **{}**: {}\n", l.min_reserve_rate, l.no_data)),
+        }
+        if let Some(ratio) = summary.average_renewable_ratio {
+            message.push_str(&format!("🌱 **{}**: {}\n", l.average_renewable, self.numbers.percent(ratio, 1)));
+        }
+        if let Some(plant) = &summary.top_plant {
+            message.push_str(&format!("🏆 **{}**: {}\n", l.top_plant, plant));
+        }
+        if let Some(events) = summary.fault_events {
+            message.push_str(&format!("⚠️ **{}**: {}{}\n", l.fault_events, events, l.units_suffix));
+        }
+
+        if !summary.generation_mix.is_empty() {
+            message.push_str(&format!("\n🏭 **{}**:\n", l.average_mix));
+            for (energy_type, generation) in FuelDisplay::from_env().apply(summary.generation_mix.iter().map(|(t, g)| (t.as_str(), *g))) {
+                message.push_str(&format!("   • {}: {}\n", self.locale.energy_type(&energy_type), self.numbers.mw(generation, 1)));
+            }
+        }
+
+        if let (Some(faults), Some(maintenance), Some(restrictions)) = (
+            summary.max_fault_count,
+            summary.max_maintenance_count,
+            summary.max_environmental_restrictions,
+        ) {
+            message.push_str(&format!("\n📋 **{}**:\n", l.most_affected));
+            message.push_str(&format!("   🌱 {}: {}{}\n", l.restrictions, restrictions, l.units_suffix));
+            message.push_str(&format!("   🔧 {}: {}{}\n", l.maintenance, maintenance, l.units_suffix));
+            message.push_str(&format!("   ⚠️ {}: {}{}\n", l.faults, faults, l.units_suffix));
+        }
+
+        match summary.source {
+            SummarySource::Local => message.push_str(&format!("\n📊 {}: {} ({}{})", l.source, l.local_records, summary.sample_count, l.samples_suffix)),
+            SummarySource::Archive => message.push_str(&format!("\n📊 {}: [{}](<{}>)", l.source, l.archive_source, ARCHIVE_SOURCE_URL)),
+        }
+
+        message
+    }
+
+    fn indicator_change(&self, change: &IndicatorChange) -> String {
+        let l = self.locale.labels();
+        format!(
+            "{} **{}**: {} {} → {} {}\n🔋 **{}**: {} {}",
+            indicator_emoji(change.to),
+            l.indicator_changed,
+            indicator_emoji(change.from),
+            indicator_label(change.from, l),
+            indicator_emoji(change.to),
+            indicator_label(change.to, l),
+            l.forecast_reserve_rate,
+            self.numbers.percent(change.reserve_rate, 1),
+            format_pp_change(change.reserve_rate_change),
+        )
+    }
+
+    fn fault_change(&self, change: &FaultChange) -> String {
+        let l = self.locale.labels();
+        let list = |units: &[(String, f64)]| {
+            units.iter().map(|(unit, capacity)| format!("{} ({})", unit, self.numbers.mw(*capacity, 0))).collect::<Vec<_>>().join("、")
+        };
+        let mut message = format!("⚠️ **{}**", l.fault_change);
+        if !change.faulted.is_empty() {
+            message.push_str(&format!("\n🔴 **{}**: {}", l.newly_faulted, list(&change.faulted)));
+        }
+        if !change.recovered.is_empty() {
+            message.push_str(&format!("\n🟢 **{}**: {}", l.recovered, list(&change.recovered)));
+        }
+        message
+    }
+}
+
+impl Renderer for PlainRenderer {
+    type Output = String;
+
+    fn report(&self, data: &CombinedPowerData) -> String {
+        let mut lines = vec!["台電即時電力資訊".to_string()];
+        if let Some(stress) = &data.stress {
+            lines.push(format!("電網壓力指數: {:.0}/100", stress.value));
+        }
+
+        if let Some(load_data) = &data.load_data {
+            lines.push(String::new());
+            lines.push(format!("目前用電量: {:.1} 萬瓩", load_data.current_load));
+            lines.push(format!("目前使用率: {:.1}%", load_data.current_util_rate));
+            lines.push(format!("預估今日最大供電能力: {:.1} 萬瓩", load_data.forecast_max_supply_capacity));
+            lines.push(format!("預估今日最高用電: {:.1} 萬瓩", load_data.forecast_peak_demand_load));
+            if let Some(own) = &data.own_forecast {
+                lines.push(format!("本機預估今日尖峰: {:.1} 萬瓩 ({}) / 下一小時 {:.1} 萬瓩",
+                    own.day_peak, own.day_peak_time.format("%H:%M"), own.next_hour));
+                if let Some(accuracy) = &own.accuracy {
+                    lines.push(format!("尖峰預估平均誤差: {}", format_accuracy(accuracy, &ZH_TW, NumberFormat::Plain)));
+                }
+            }
+            lines.push(format!("預估今日尖峰備轉容量: {:.1} 萬瓩", load_data.forecast_peak_reserve_capacity));
+            lines.push(format!("預估今日尖峰備轉容量率: {:.2}% ({})",
+                load_data.forecast_peak_reserve_rate,
+                indicator_label(load_data.forecast_peak_reserve_indicator, &ZH_TW)));
+            if let Some(mw) = data.demand_response_mw {
+                let (capacity, rate) = demand_response::reserve_without(load_data, mw);
+                lines.push(format!("已啟動需量反應: {:.1} MW", mw));
+                lines.push(format!("不含需量反應的備轉容量率: {:.2}% ({:.1} 萬瓩)", rate, capacity));
+            }
+            lines.push(format!("預估尖峰用電時段: {}", format_hour_range(load_data, &ZH_TW)));
+            lines.push(format!("資料更新時間: {}", format_publish_time(load_data, &ZH_TW)));
+            lines.push(format!("昨日尖峰用電量: {:.1} 萬瓩", load_data.yesterday_peak_demand_load));
+            lines.push(format!("昨日尖峰備轉容量率: {:.2}% ({})",
+                load_data.yesterday_peak_reserve_rate,
+                indicator_label(load_data.yesterday_peak_reserve_indicator, &ZH_TW)));
+        }
+
+        if !data.regions.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("{}:", ZH_TW.regions));
+            for region in &data.regions {
+                lines.push(format!("  {}", format_region(region, Locale::ZhTw, NumberFormat::Plain, " (!)")));
+            }
+        }
+
+        let analysis = &data.power_analysis;
+        lines.push(String::new());
+        lines.push(format!("更新時間: {}", analysis.update_time.format("%Y-%m-%d %H:%M")));
+        lines.push(format!("總發電量: {:.1} MW", analysis.total_generation));
+        lines.push(format!("裝置容量: {:.1} MW", analysis.estimated_max_generation));
+        lines.push(format!("發電占比: {:.1}%", analysis.generation_ratio()));
+        for (energy_type, generation) in sorted_generation(analysis) {
+            lines.push(format!("  {}: {:.1} MW", energy_type, generation));
+        }
+        lines.push(format!("發電量最高電廠: {} ({:.1} MW)", analysis.top_plant.0, analysis.top_plant.1));
+        lines.push(format!("發電量最高機組: {} ({:.1} MW)", analysis.top_unit.0, analysis.top_unit.1));
+        lines.push(format!("環保限制/運轉限制: {} 部", analysis.environmental_restrictions));
+        lines.push(format!("歲修/檢修: {} 部", analysis.maintenance_count));
+        lines.push(format!("故障: {} 部", analysis.fault_count));
+        for outage in &data.outages {
+            lines.push(format!("  {}", format_outage(outage, &ZH_TW, NumberFormat::Plain)));
+        }
+        lines.push(format!("再生能源占比: {:.1}%", analysis.renewable_ratio));
+        lines.push(format!("民營電廠+購電占比: {:.1}%", analysis.private_ratio));
+        lines.push(String::new());
+        lines.push(format!("資料來源: 台電公司開放資料 {}", DATA_SOURCE_URL));
+
+        lines.join("\n")
+    }
+
+    fn compact(&self, data: &CombinedPowerData) -> String {
+        let analysis = &data.power_analysis;
+        match &data.load_data {
+            Some(load_data) => format!(
+                "用電 {:.1} 萬瓩 | 備轉 {:.1}% ({}){} | 再生 {:.1}% | {}",
+                load_data.current_load,
+                load_data.forecast_peak_reserve_rate,
+                indicator_label(load_data.forecast_peak_reserve_indicator, &ZH_TW),
+                data.trend.map(|t| format!(" | {}", t.label(&ZH_TW))).unwrap_or_default(),
+                analysis.renewable_ratio,
+                analysis.update_time.format("%H:%M"),
+            ),
+            None => format!(
+                "總發電量 {:.1} MW | 再生 {:.1}% | {}",
+                analysis.total_generation,
+                analysis.renewable_ratio,
+                analysis.update_time.format("%H:%M"),
+            ),
+        }
+    }
+
+    fn daily_summary(&self, summary: &DailySummary) -> String {
+        let mut lines = vec![format!("{} 電力摘要", summary.date)];
+
+        match (summary.peak_load, &summary.peak_time) {
+            (Some(peak), Some(time)) => lines.push(format!("尖峰用電: {:.1} 萬瓩 ({})", peak, time)),
+            (Some(peak), None) => lines.push(format!("尖峰用電: {:.1} 萬瓩", peak)),
+            _ => lines.push("尖峰用電: 無資料".to_string()),
+        }
+        if let (Some(min), Some(time)) = (summary.min_load, &summary.min_time) {
+            lines.push(format!("最低用電: {:.1} 萬瓩 ({})", min, time));
+        }
+        match (summary.min_reserve_rate, summary.peak_reserve_rate) {
+            (Some(rate), _) => lines.push(format!("最低備轉容量率: {:.2}%", rate)),
+            (None, Some(rate)) => lines.push(format!("尖峰備轉容量率: {:.2}%", rate)),
+            (None, None) => lines.push("最低備轉容量率: 無資料".to_string()),
+        }
+        if let Some(ratio) = summary.average_renewable_ratio {
+            lines.push(format!("平均再生能源占比: {:.1}%", ratio));
+        }
+        if let Some(plant) = &summary.top_plant {
+            lines.push(format!("發電量最高電廠: {}", plant));
+        }
+        for (energy_type, generation) in &summary.generation_mix {
+            lines.push(format!("  {}: {:.1} MW", energy_type, generation));
+        }
+        if let Some(faults) = summary.max_fault_count {
+            lines.push(format!("當日最多故障機組: {} 部", faults));
+        }
+        if let Some(events) = summary.fault_events {
+            lines.push(format!("新增故障: {} 部", events));
+        }
+
+        lines.join("\n")
+    }
+    fn indicator_change(&self, change: &IndicatorChange) -> String {
+        format!(
+            "供電燈號變更: {} -> {}\n預估今日尖峰備轉容量率: {:.1}% ({:+.1} 個百分點)",
+            indicator_label(change.from, &ZH_TW),
+            indicator_label(change.to, &ZH_TW),
+            change.reserve_rate,
+            change.reserve_rate_change,
+        )
+    }
+
+    fn fault_change(&self, change: &FaultChange) -> String {
+        let list = |units: &[(String, f64)]| {
+            units.iter().map(|(unit, capacity)| format!("{} ({:.0} MW)", unit, capacity)).collect::<Vec<_>>().join(", ")
+        };
+        let mut lines = vec!["機組故障狀態變更".to_string()];
+        if !change.faulted.is_empty() {
+            lines.push(format!("新增故障: {}", list(&change.faulted)));
+        }
+        if !change.recovered.is_empty() {
+            lines.push(format!("恢復運轉: {}", list(&change.recovered)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// How much of the report a relayed channel receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportProfile {
+    Full,
+    Compact,
+}
+
+impl ReportProfile {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(ReportProfile::Full),
+            "compact" => Some(ReportProfile::Compact),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReportProfile::Full => "full",
+            ReportProfile::Compact => "compact",
+        }
+    }
+
+    pub fn render(&self, renderer: &DiscordTextRenderer, data: &CombinedPowerData) -> String {
+        match self {
+            ReportProfile::Full => renderer.report(data),
+            ReportProfile::Compact => renderer.compact(data),
+        }
+    }
+}
+
+/// How a channel receives the scheduled report (`/config mode`, REPORT_MODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportMode {
+    /// A new message every interval
+    Post,
+    /// One pinned message, edited every cycle
+    Live,
+    /// A pinned one-line status edited every cycle, plus a full post every interval
+    Dual,
+}
+
+/// One renderer/schedule pair: what gets sent, and whether it edits the pinned live message
+/// every cycle or is posted anew every interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cadence {
+    pub profile: ReportProfile,
+    pub live: bool,
+}
+
+impl ReportMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "post" => Some(ReportMode::Post),
+            "live" => Some(ReportMode::Live),
+            "dual" => Some(ReportMode::Dual),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReportMode::Post => "post",
+            ReportMode::Live => "live",
+            ReportMode::Dual => "dual",
+        }
+    }
+
+    pub fn cadences(&self) -> &'static [Cadence] {
+        match self {
+            ReportMode::Post => &[Cadence { profile: ReportProfile::Full, live: false }],
+            ReportMode::Live => &[Cadence { profile: ReportProfile::Full, live: true }],
+            ReportMode::Dual => &[
+                Cadence { profile: ReportProfile::Compact, live: true },
+                Cadence { profile: ReportProfile::Full, live: false },
+            ],
+        }
+    }
+
+    /// Default for channels that haven't picked a mode
+    pub fn from_env() -> Self {
+        config::var("REPORT_MODE").ok().and_then(|v| ReportMode::parse(&v)).unwrap_or(ReportMode::Post)
+    }
+}
+
+/// What a home channel listed in CHANNEL_ID receives, written as `<id>:<profile>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentProfile {
+    /// The scheduled report with every section the guild enabled, plus alerts
+    #[default]
+    Full,
+    /// The scheduled report cut down to the supply and real-time load sections, plus alerts
+    Load,
+    /// Only alerts (reserve indicator, faults); no scheduled report or digest
+    Alerts,
+}
+
+impl ContentProfile {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(ContentProfile::Full),
+            "load" => Some(ContentProfile::Load),
+            "alerts" => Some(ContentProfile::Alerts),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContentProfile::Full => "full",
+            ContentProfile::Load => "load",
+            ContentProfile::Alerts => "alerts",
+        }
+    }
+
+    /// The guild's sections, narrowed to what this profile shows
+    pub fn sections(&self, sections: ReportSections) -> ReportSections {
+        match self {
+            ContentProfile::Load => ReportSections {
+                supply: sections.supply,
+                realtime: sections.realtime,
+                yesterday: false,
+                regions: false,
+                generation: false,
+                units: false,
+            },
+            _ => sections,
+        }
+    }
+
+    pub fn receives_reports(&self) -> bool {
+        *self != ContentProfile::Alerts
+    }
+}
+
+/// Parse CHANNEL_ID's comma-separated `<id>[:<profile>]` entries; a bare ID gets the full report
+pub fn parse_channel_routes(value: &str) -> Result<Vec<(u64, ContentProfile)>, String> {
+    let mut routes: Vec<(u64, ContentProfile)> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (id, profile) = match entry.split_once(':') {
+            Some((id, profile)) => {
+                (id, ContentProfile::parse(profile).ok_or_else(|| format!("unknown profile in {:?}; use full, load or alerts", entry))?)
+            }
+            None => (entry, ContentProfile::Full),
+        };
+        let id = id.trim().parse::<u64>().ok().filter(|id| *id > 0).ok_or_else(|| format!("invalid channel ID in {:?}", entry))?;
+        if routes.iter().any(|(existing, _)| *existing == id) {
+            return Err(format!("channel {} is listed twice", id));
+        }
+        routes.push((id, profile));
+    }
+    Ok(routes)
+}
+
+/// Which renderer the scheduled report uses (REPORT_FORMAT, or `/config format` per guild)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Embed,
+    Plain,
+    /// For screen readers; see `accessible`
+    Accessible,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" | "markdown" => Some(ReportFormat::Text),
+            "embed" => Some(ReportFormat::Embed),
+            "plain" => Some(ReportFormat::Plain),
+            "accessible" => Some(ReportFormat::Accessible),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Embed => "embed",
+            ReportFormat::Plain => "plain",
+            ReportFormat::Accessible => "accessible",
+        }
+    }
+
+    /// `text` carries the guild's settings, which text and embeds honour; plain text keeps its
+    /// fixed layout
+    pub fn report_message(&self, data: &CombinedPowerData, mention: Option<&MentionTarget>, text: &DiscordTextRenderer) -> CreateMessage {
+        let (content, embed, badge) = self.report_parts(data, text);
+        let mut message = CreateMessage::new();
+        if let Some(embed) = embed {
+            message = message.embed(embed);
+        }
+        if let Some(badge) = badge {
+            message = message.add_file(badge);
+        }
+        mentions::apply(message, content, mention, data)
+    }
+
+    /// The report's text, or its embed and the embed's reserve badge
+    pub fn report_parts(&self, data: &CombinedPowerData, text: &DiscordTextRenderer) -> (Option<String>, Option<CreateEmbed>, Option<CreateAttachment>) {
+        match self {
+            ReportFormat::Text => (Some(text.report(data)), None, None),
+            ReportFormat::Embed => {
+                let (embed, badge) = EmbedRenderer::from(text).report_with_badge(data);
+                (None, Some(embed), badge)
+            }
+            ReportFormat::Plain => (Some(PlainRenderer.report(data)), None, None),
+            ReportFormat::Accessible => (Some(AccessibleRenderer::from(text).report(data)), None, None),
+        }
+    }
+
+    /// Content and embed for a pinned live-status message, stamped with `updated_at` (unix seconds)
+    /// as a Discord timestamp so every reader sees it in their own time zone
+    pub fn live_status(
+        &self,
+        data: &CombinedPowerData,
+        text: &DiscordTextRenderer,
+        profile: ReportProfile,
+        updated_at: i64,
+    ) -> (String, Option<CreateEmbed>) {
+        let icon = if *self == ReportFormat::Accessible { "" } else { "🕒 " };
+        let stamp = format!("{}{}: <t:{}:R>", icon, text.locale.labels().last_updated, updated_at);
+        let embed = EmbedRenderer::from(text);
+        let accessible = AccessibleRenderer::from(text);
+        match (self, profile) {
+            (ReportFormat::Text, ReportProfile::Full) => (format!("{}\n\n{}", text.report(data), stamp), None),
+            (ReportFormat::Text, ReportProfile::Compact) => (format!("{}\n{}", text.compact(data), stamp), None),
+            (ReportFormat::Embed, ReportProfile::Full) => (stamp, Some(embed.report(data))),
+            (ReportFormat::Embed, ReportProfile::Compact) => (stamp, Some(embed.compact(data))),
+            (ReportFormat::Plain, ReportProfile::Full) => (format!("{}\n\n{}", PlainRenderer.report(data), stamp), None),
+            (ReportFormat::Plain, ReportProfile::Compact) => (format!("{}\n{}", PlainRenderer.compact(data), stamp), None),
+            (ReportFormat::Accessible, ReportProfile::Full) => (format!("{}\n\n{}", accessible.report(data), stamp), None),
+            (ReportFormat::Accessible, ReportProfile::Compact) => (format!("{}\n{}", accessible.compact(data), stamp), None),
+        }
+    }
+
+    /// The indicator change as an urgent alert, pinging `role_id` if set
+    pub fn reserve_alert_message(&self, change: &IndicatorChange, text: &DiscordTextRenderer, role_id: Option<u64>) -> CreateMessage {
+        let l = text.locale.labels();
+        let mut prefix = match self {
+            ReportFormat::Accessible => format!("**{}**", l.supply_alert),
+            _ => format!("🚨 **{}**", l.supply_alert),
+        };
+        if let Some(role_id) = role_id {
+            prefix.push_str(&format!(" <@&{}>", role_id));
+        }
+        let allowed = CreateAllowedMentions::new().everyone(false).all_users(false).roles(role_id.map(RoleId::new));
+        match self {
+            ReportFormat::Text => CreateMessage::new().content(format!("{}\n{}", prefix, text.indicator_change(change))),
+            ReportFormat::Embed => CreateMessage::new().content(prefix).embed(EmbedRenderer::from(text).indicator_change(change)),
+            ReportFormat::Plain => CreateMessage::new().content(format!("{}\n{}", prefix, PlainRenderer.indicator_change(change))),
+            ReportFormat::Accessible => {
+                CreateMessage::new().content(format!("{}\n{}", prefix, AccessibleRenderer::from(text).indicator_change(change)))
+            }
+        }
+        .allowed_mentions(allowed)
+    }
+
+    pub fn indicator_change_message(&self, change: &IndicatorChange, text: &DiscordTextRenderer) -> CreateMessage {
+        match self {
+            ReportFormat::Text => CreateMessage::new().content(text.indicator_change(change)),
+            ReportFormat::Embed => CreateMessage::new().embed(EmbedRenderer::from(text).indicator_change(change)),
+            ReportFormat::Plain => CreateMessage::new().content(PlainRenderer.indicator_change(change)),
+            ReportFormat::Accessible => CreateMessage::new().content(AccessibleRenderer::from(text).indicator_change(change)),
+        }
+    }
+
+    pub fn fault_change_message(&self, change: &FaultChange, text: &DiscordTextRenderer) -> CreateMessage {
+        match self {
+            ReportFormat::Text => CreateMessage::new().content(text.fault_change(change)),
+            ReportFormat::Embed => CreateMessage::new().embed(EmbedRenderer::from(text).fault_change(change)),
+            ReportFormat::Plain => CreateMessage::new().content(PlainRenderer.fault_change(change)),
+            ReportFormat::Accessible => CreateMessage::new().content(AccessibleRenderer::from(text).fault_change(change)),
+        }
+    }
+}
+
+impl From<&DiscordTextRenderer> for EmbedRenderer {
+    fn from(text: &DiscordTextRenderer) -> Self {
+        EmbedRenderer { numbers: text.numbers, sections: text.sections }
+    }
+}
@@ -0,0 +1,236 @@
+//! `replay`: feed archived payloads through the update pipeline on a simulated clock, so a past
+//! day's alerts, digest and reports can be reproduced without the live database, e.g. to check
+//! new alert rules against historic events.
+//!
+//! Sources are dates archived under PAYLOAD_ARCHIVE_DIR, or directories of payload files.
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serenity::all::{ChannelId, CreateAllowedMentions, CreateMessage, Http};
+use serenity::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{error, warn};
+
+use crate::alerts::{self, FaultChange, IndicatorChange};
+use crate::analysis::{CombinedPowerData, PowerAnalysis};
+use crate::bot::send_to;
+use crate::clock::{Clock, ManualClock};
+use crate::config::{self, Config};
+use crate::cycle::{Extras, Feeds, Outlet, Updater};
+use crate::history::History;
+use crate::records::{self, RecordBreak};
+use crate::regional::RegionalShare;
+use crate::render::{DiscordTextRenderer, Renderer};
+use crate::stress::{self, StressIndex};
+use crate::taipower_api::{read_payload_files, LoadData, PayloadFiles, TaipowerError};
+use crate::{digest, payload_archive};
+
+struct Cycle {
+    /// When Taipower published it; the simulated clock is set to this
+    time: DateTime<FixedOffset>,
+    /// Where the payloads came from, for messages
+    label: String,
+    payloads: PayloadFiles,
+}
+
+/// Prints what the bot would say, and posts it to a test channel if one was given
+struct Output {
+    channel: Option<(Http, ChannelId)>,
+}
+
+impl Output {
+    async fn emit(&self, text: &str) {
+        println!("{}", text);
+        if let Some((http, channel_id)) = &self.channel {
+            let message = CreateMessage::new().content(text).allowed_mentions(CreateAllowedMentions::new());
+            if let Err(why) = send_to(http, *channel_id, message).await {
+                error!("Error posting replay output to {}: {:?}", channel_id, why);
+            }
+        }
+    }
+}
+
+/// One archived cycle's payloads, handed to the update cycle as if just fetched
+struct ReplayFeeds {
+    power: PowerAnalysis,
+    load: Option<LoadData>,
+    shares: Option<Vec<RegionalShare>>,
+}
+
+#[async_trait]
+impl Feeds for ReplayFeeds {
+    async fn fetch(&mut self) -> (Result<PowerAnalysis, TaipowerError>, Option<Result<LoadData, TaipowerError>>) {
+        (Ok(self.power.clone()), self.load.clone().map(Ok))
+    }
+
+    async fn extras(&mut self, with_regional: bool, _with_weather: bool, _today: NaiveDate) -> Extras {
+        Extras {
+            shares: self.shares.take().filter(|_| with_regional).map(Ok),
+            temperatures: None,
+            demand_response_mw: None,
+        }
+    }
+}
+
+/// The cycle's announcements as text, through `Output`
+struct ReplayOutlet {
+    output: Output,
+    history: Arc<History>,
+    clock: Arc<ManualClock>,
+    renderer: DiscordTextRenderer,
+}
+
+#[async_trait]
+impl Outlet for ReplayOutlet {
+    async fn digest(&mut self, date: NaiveDate) {
+        if let Some((content, _)) = digest::content(&self.history, date, self.renderer.numbers) {
+            self.output.emit(&content).await;
+        }
+    }
+
+    async fn indicator_change(&mut self, change: &IndicatorChange, _ping: bool) {
+        self.output.emit(&self.renderer.indicator_change(change)).await;
+    }
+
+    async fn fault_change(&mut self, change: &FaultChange) {
+        self.output.emit(&self.renderer.fault_change(change)).await;
+    }
+
+    async fn stress_alert(&mut self, stress: &StressIndex) {
+        self.output.emit(&stress::alert_message(stress)).await;
+    }
+
+    async fn stale_data(&mut self, data_time: DateTime<FixedOffset>, age: chrono::Duration) {
+        self.output.emit(&alerts::stale_data_warning_plain(data_time, age)).await;
+    }
+
+    async fn record(&mut self, record: &RecordBreak) {
+        self.output.emit(&records::announcement(record)).await;
+    }
+
+    async fn report(&mut self, data: &CombinedPowerData, _indicator_change: Option<&IndicatorChange>) {
+        let line = format!("[{}] {}", self.clock.now().format("%Y-%m-%d %H:%M"), self.renderer.compact(data));
+        self.output.emit(&line).await;
+    }
+}
+
+/// "60x" or "60"; 0 doesn't wait between cycles
+pub fn parse_speed(value: &str) -> Result<f64, String> {
+    let speed = value.trim().trim_end_matches(['x', 'X']).parse::<f64>().map_err(|_| format!("invalid speed {:?}", value))?;
+    if !speed.is_finite() || speed < 0.0 {
+        return Err("speed must be 0 or a positive number".to_string());
+    }
+    Ok(speed)
+}
+
+/// Directories of payload files, expanding one level when a directory only holds directories
+fn cycle_directories(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut directories = Vec::new();
+    for path in paths {
+        let entries = sorted_entries(path)?;
+        if !entries.is_empty() && entries.iter().all(|entry| entry.is_dir()) {
+            directories.extend(entries);
+        } else {
+            directories.push(path.clone());
+        }
+    }
+    Ok(directories)
+}
+
+fn sorted_entries(directory: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut entries = std::fs::read_dir(directory)
+        .map_err(|e| format!("{}: {}", directory.display(), e))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn read_cycle(label: String, files: &[PathBuf]) -> Result<Cycle, Box<dyn std::error::Error + Send + Sync>> {
+    let payloads = read_payload_files(files)?;
+    let time = payloads
+        .load_data
+        .as_ref()
+        .and_then(|load| load.publish_time)
+        .or(payloads.power_analysis.as_ref().map(|analysis| analysis.update_time))
+        .ok_or_else(|| format!("{}: no generation or load payload", label))?;
+    Ok(Cycle { time, label, payloads })
+}
+
+/// Each source's cycles, and the archived dates among the sources
+fn read_sources(sources: &[String]) -> Result<(Vec<Cycle>, Vec<NaiveDate>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut cycles = Vec::new();
+    let mut dates = Vec::new();
+    let mut directories = Vec::new();
+    for source in sources {
+        match NaiveDate::parse_from_str(source.trim(), "%Y-%m-%d") {
+            Ok(date) if !Path::new(source).exists() => {
+                for (i, files) in payload_archive::day_cycles(date)?.iter().enumerate() {
+                    cycles.push(read_cycle(format!("{} #{}", date, i + 1), files)?);
+                }
+                dates.push(date);
+            }
+            _ => directories.push(PathBuf::from(source)),
+        }
+    }
+    for directory in cycle_directories(&directories)? {
+        let files: Vec<PathBuf> = sorted_entries(&directory)?.into_iter().filter(|path| path.is_file()).collect();
+        cycles.push(read_cycle(directory.display().to_string(), &files)?);
+    }
+    Ok((cycles, dates))
+}
+
+pub async fn run(sources: &[String], speed: f64, history_path: &str, channel_id: Option<ChannelId>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let channel = match channel_id {
+        Some(channel_id) => {
+            let token = config::discord_token().ok_or("DISCORD_TOKEN must be set to post to a channel")?;
+            Some((Http::new(&token), channel_id))
+        }
+        None => None,
+    };
+    let output = Output { channel };
+    let (mut cycles, dates) = read_sources(sources)?;
+    cycles.sort_by_key(|cycle| cycle.time);
+    let Some(first) = cycles.first() else {
+        return Err("no cycles to replay".into());
+    };
+
+    let config = Config::from_env().map_err(|errors| errors.join("; "))?;
+    let clock = Arc::new(ManualClock::new(first.time));
+    let history = Arc::new(History::open_with_clock(history_path, clock.clone())?);
+    let mut updater = Updater::new(history.clone(), clock.clone(), Arc::new(RwLock::new(config.clone())));
+    let mut outlet = ReplayOutlet { output, history: history.clone(), clock: clock.clone(), renderer: DiscordTextRenderer::default() };
+    let mut previous_time: Option<DateTime<FixedOffset>> = None;
+    let mut replayed = 0;
+
+    for cycle in cycles {
+        if let Some(previous) = previous_time
+            && speed > 0.0
+            && let Ok(gap) = (cycle.time - previous).to_std()
+        {
+            tokio::time::sleep(gap.div_f64(speed)).await;
+        }
+        previous_time = Some(cycle.time);
+        clock.set(cycle.time);
+
+        let PayloadFiles { power_analysis, load_data, regional_shares } = cycle.payloads;
+        let Some(power) = power_analysis else {
+            warn!("{}: no generation payload, skipped", cycle.label);
+            continue;
+        };
+        let mut feeds = ReplayFeeds { power, load: load_data, shares: regional_shares };
+        updater.cycle(&mut feeds, &mut outlet).await;
+        replayed += 1;
+    }
+
+    // An archived day ends with the digest the bot posts after midnight
+    if let Some(last) = dates.iter().max()
+        && *last == clock.today()
+        && config.daily_digest
+    {
+        outlet.digest(*last).await;
+    }
+
+    println!("Replayed {} cycle(s) up to {}", replayed, clock.now().format("%Y-%m-%d %H:%M"));
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Optional error reporting (Sentry). Compiled in with `--features sentry` and enabled by
+//! setting SENTRY_DSN; otherwise every function here is a no-op beyond local logging.
+//! Also decides what a failure looks like where: a short notice for users, details for admins.
+
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{error, warn};
+
+use crate::taipower_api::TaipowerError;
+
+/// What users see when Taipower data can't be fetched; the details go to admins
+pub const USER_NOTICE: &str = "⚠️ 資料暫時無法取得，稍後重試";
+
+/// Posted to report channels when an outage outlasts a blip and there's no recent report to repost
+pub const OUTAGE_NOTICE: &str = "⚠️ 台電資料暫時無法取得，恢復後將自動繼續更新";
+
+/// Payload characters quoted in admin notices
+const SNIPPET_CHARS: usize = 300;
+
+/// Endpoint and start of the last payload that failed to parse, for the admin notice about it
+static LAST_BAD_PAYLOAD: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Keeps the reporting client alive; flushes pending events when dropped.
+pub struct ReportingGuard {
+    #[cfg(feature = "sentry")]
+    _guard: Option<sentry::ClientInitGuard>,
+}
+
+pub fn init() -> ReportingGuard {
+    #[cfg(feature = "sentry")]
+    {
+        let guard = crate::config::var("SENTRY_DSN")
+            .ok()
+            .filter(|dsn| !dsn.trim().is_empty())
+            .map(|dsn| {
+                tracing::info!("Sentry error reporting enabled");
+                sentry::init((dsn, sentry::ClientOptions {
+                    release: sentry::release_name!(),
+                    ..Default::default()
+                }))
+            });
+        ReportingGuard { _guard: guard }
+    }
+
+    #[cfg(not(feature = "sentry"))]
+    ReportingGuard {}
+}
+
+/// Stable short fingerprint (FNV-1a) so identical bad payloads group together
+pub fn payload_fingerprint(payload: impl AsRef<[u8]>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in payload.as_ref() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// A payload was fetched but could not be decoded; also checked against what the endpoint sent
+/// last time it parsed
+pub fn report_parse_error(source: &str, error: &str, payload: &str) {
+    let fingerprint = payload_fingerprint(payload);
+    error!("Parse error from {} (payload {}): {}", source, fingerprint, error);
+    *LAST_BAD_PAYLOAD.lock().unwrap() = Some((source.to_string(), payload.chars().take(SNIPPET_CHARS).collect()));
+    crate::schema_watch::failed(source, error, payload);
+
+    #[cfg(feature = "sentry")]
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("kind", "parse_error");
+            scope.set_tag("source", source);
+            scope.set_tag("payload_fingerprint", &fingerprint);
+            scope.set_extra("payload_length", payload.len().into());
+            scope.set_fingerprint(Some(&["parse_error", source, &fingerprint]));
+        },
+        || sentry::capture_message(&format!("Parse error from {}: {}", source, error), sentry::Level::Error),
+    );
+}
+
+/// The details of a failed fetch for admins: endpoint, status code, error and, for payloads that
+/// didn't parse, how the payload starts
+pub fn admin_detail(error: &TaipowerError) -> String {
+    let mut lines = Vec::new();
+    let bad_payload = if error.is_parse_failure() { LAST_BAD_PAYLOAD.lock().unwrap().take() } else { None };
+    if let TaipowerError::Http(e) = error {
+        if let Some(url) = e.url() {
+            lines.push(format!("端點: {}", url));
+        }
+        if let Some(status) = e.status() {
+            lines.push(format!("狀態碼: {}", status));
+        }
+    } else if let Some((endpoint, _)) = &bad_payload {
+        lines.push(format!("端點: {}", endpoint));
+    }
+    lines.push(format!("錯誤: {}", error));
+    if let Some((_, snippet)) = bad_payload {
+        lines.push(format!("內容片段:\n```\n{}\n```", snippet.replace("```", "ˋˋˋ")));
+    }
+    lines.join("\n")
+}
+
+/// Counts consecutive fetch failures per source and reports once a streak reaches the threshold
+pub struct FailureTracker {
+    threshold: u32,
+    streaks: HashMap<&'static str, u32>,
+}
+
+impl FailureTracker {
+    pub fn new(threshold: u32) -> Self {
+        FailureTracker { threshold: threshold.max(1), streaks: HashMap::new() }
+    }
+
+    pub fn from_env() -> Self {
+        let threshold = crate::config::var("ERROR_REPORT_AFTER_FAILURES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(3);
+        FailureTracker::new(threshold)
+    }
+
+    /// True once per streak, when it reaches the threshold
+    pub fn failure(&mut self, source: &'static str, error: &str) -> bool {
+        let streak = self.streaks.entry(source).or_insert(0);
+        *streak += 1;
+
+        // Report exactly once per streak
+        let reached = *streak == self.threshold;
+        if reached {
+            report_fetch_failures(source, error, *streak);
+        }
+        reached
+    }
+
+    pub fn success(&mut self, source: &'static str) {
+        self.streaks.remove(source);
+    }
+}
+
+/// Rate-limits operator notices to one per ERROR_NOTICE_INTERVAL_MINUTES (default 60) per error
+/// class; the next notice mentions how many were held back in between
+pub struct ErrorNotices {
+    interval: Duration,
+    last_sent: HashMap<&'static str, NaiveDateTime>,
+    suppressed: HashMap<&'static str, u32>,
+}
+
+impl ErrorNotices {
+    pub fn from_env() -> Self {
+        let minutes = crate::config::var("ERROR_NOTICE_INTERVAL_MINUTES")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(60);
+        ErrorNotices { interval: Duration::from_secs(minutes * 60), last_sent: HashMap::new(), suppressed: HashMap::new() }
+    }
+
+    /// The notice to send for this error at `now` (Taipei), or None while `class` is rate limited
+    pub fn notice(&mut self, class: &'static str, message: &str, now: NaiveDateTime) -> Option<String> {
+        if self.last_sent.get(class).is_some_and(|sent| (now - *sent).to_std().is_ok_and(|elapsed| elapsed < self.interval)) {
+            *self.suppressed.entry(class).or_insert(0) += 1;
+            return None;
+        }
+        self.last_sent.insert(class, now);
+        Some(match self.suppressed.remove(class) {
+            Some(count) => format!("{}\n(上次通知後另有 {} 次同類錯誤)", message, count),
+            None => message.to_string(),
+        })
+    }
+}
+
+fn report_fetch_failures(source: &str, error: &str, streak: u32) {
+    warn!("{} consecutive fetch failures from {}: {}", streak, source, error);
+
+    #[cfg(feature = "sentry")]
+    sentry::with_scope(
+        |scope| {
+            scope.set_tag("kind", "fetch_failure");
+            scope.set_tag("source", source);
+            scope.set_extra("consecutive_failures", streak.into());
+            scope.set_fingerprint(Some(&["fetch_failure", source]));
+        },
+        || sentry::capture_message(&format!("Repeated fetch failures from {}: {}", source, error), sentry::Level::Warning),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 7, 1).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn notices_are_held_back_per_class_and_counted() {
+        let mut notices = ErrorNotices { interval: Duration::from_secs(3600), last_sent: HashMap::new(), suppressed: HashMap::new() };
+        assert_eq!(notices.notice("fetch", "抓取失敗", at(10, 0)).as_deref(), Some("抓取失敗"));
+        assert_eq!(notices.notice("fetch", "抓取失敗", at(10, 20)), None);
+        assert_eq!(notices.notice("fetch", "抓取失敗", at(10, 59)), None);
+        assert_eq!(notices.notice("post", "發送失敗", at(10, 30)).as_deref(), Some("發送失敗"));
+        assert_eq!(notices.notice("fetch", "抓取失敗", at(11, 0)).as_deref(), Some("抓取失敗\n(上次通知後另有 2 次同類錯誤)"));
+        assert_eq!(notices.notice("fetch", "抓取失敗", at(12, 0)).as_deref(), Some("抓取失敗"));
+    }
+}
@@ -0,0 +1,298 @@
+//! When the update cycle runs: a fixed interval (UPDATE_INTERVAL_MINUTES, default 10) or, if
+//! UPDATE_SCHEDULE is set, cron-style schedules in Taipei time.
+//!
+//! UPDATE_SCHEDULE holds one or more `;`-separated expressions of five fields (minute, hour,
+//! day of month, month, day of week with 0 = Sunday), each `*`, a number, a range `a-b`, a step
+//! `*/n` or `a-b/n`, or a comma list of those. For example `*/10 7-22 * * *; */30 0-6,23 * * *`
+//! runs every 10 minutes during the day and every 30 minutes at night.
+
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::clock::Clock;
+use crate::config::{self, Config};
+
+/// Taipower refreshes its feeds every 10 minutes
+const DEFAULT_INTERVAL_MINUTES: u64 = 10;
+
+/// Looking further ahead than this means the expression can never match (e.g. `0 0 31 2 *`).
+/// Feb 29 can be eight years from the next one (2096 to 2104), so a year isn't enough
+const SEARCH_LIMIT: Duration = Duration::days(8 * 366);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    allowed: Vec<u32>,
+    /// `*` with no step; matters for the day-of-month/day-of-week rule
+    any: bool,
+}
+
+impl Field {
+    fn parse(value: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut allowed = Vec::new();
+        for part in value.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("invalid step in {:?}", part))?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(format!("step must be positive in {:?}", part));
+            }
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (parse_value(start, part)?, parse_value(end, part)?),
+                    // "5/15" means from 5 to the end in steps of 15
+                    None if step > 1 => (parse_value(range, part)?, max),
+                    None => {
+                        let value = parse_value(range, part)?;
+                        (value, value)
+                    }
+                },
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("{:?} is outside {}-{}", part, min, max));
+            }
+            allowed.extend((start..=end).step_by(step as usize));
+        }
+        allowed.sort_unstable();
+        allowed.dedup();
+        Ok(Field { allowed, any: value == "*" })
+    }
+
+    fn matches(&self, value: u32) -> bool {
+        self.allowed.contains(&value)
+    }
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u32, String> {
+    value.trim().parse().map_err(|_| format!("invalid number in {:?}", part))
+}
+
+/// One five-field cron expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    weekday: Field,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields in {:?}", expression));
+        };
+        let mut weekday = Field::parse(weekday, 0, 7)?;
+        // 7 is also Sunday
+        if weekday.matches(7) {
+            weekday.allowed.retain(|d| *d != 7);
+            if !weekday.matches(0) {
+                weekday.allowed.insert(0, 0);
+            }
+        }
+        Ok(CronSchedule {
+            minute: Field::parse(minute, 0, 59)?,
+            hour: Field::parse(hour, 0, 23)?,
+            day: Field::parse(day, 1, 31)?,
+            month: Field::parse(month, 1, 12)?,
+            weekday,
+        })
+    }
+
+    fn day_matches(&self, time: NaiveDateTime) -> bool {
+        let day = self.day.matches(time.day());
+        let weekday = self.weekday.matches(time.weekday().num_days_from_sunday());
+        // As in cron: when both day fields are restricted, either one matching is enough
+        match (self.day.any, self.weekday.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    pub fn matches(&self, time: NaiveDateTime) -> bool {
+        self.minute.matches(time.minute()) && self.hour.matches(time.hour()) && self.month.matches(time.month()) && self.day_matches(time)
+    }
+
+    /// The first matching minute strictly after `after`
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + SEARCH_LIMIT;
+        let mut time = start;
+        while time < limit {
+            if !self.month.matches(time.month()) {
+                // Jump to the first minute of the next month
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hour.matches(time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if self.matches(time) {
+                return Some(time);
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+/// Parse UPDATE_SCHEDULE's `;`-separated expressions
+pub fn parse_schedules(value: &str) -> Result<Vec<CronSchedule>, String> {
+    value.split(';').map(str::trim).filter(|e| !e.is_empty()).map(CronSchedule::parse).collect()
+}
+
+/// UPDATE_INTERVAL_MINUTES; also the fallback when no cron schedule can match
+pub fn interval_from_env() -> std::time::Duration {
+    let minutes = config::var("UPDATE_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|m| *m > 0)
+        .unwrap_or(DEFAULT_INTERVAL_MINUTES);
+    std::time::Duration::from_secs(minutes * 60)
+}
+
+pub struct Scheduler {
+    interval: tokio::time::Interval,
+    schedules: Vec<CronSchedule>,
+    /// The first tick is immediate so the bot posts as soon as it starts
+    started: bool,
+    clock: Arc<dyn Clock>,
+}
+
+impl Scheduler {
+    pub fn new(interval: std::time::Duration, schedules: Vec<CronSchedule>, clock: Arc<dyn Clock>) -> Self {
+        Scheduler { interval: tokio::time::interval(interval), schedules, started: false, clock }
+    }
+
+    pub fn from_config(config: &Config, clock: Arc<dyn Clock>) -> Self {
+        if !config.schedules.is_empty() {
+            info!("Update cycle follows {} cron schedule(s)", config.schedules.len());
+        }
+        Scheduler::new(config.interval, config.schedules.clone(), clock)
+    }
+
+    /// Take up UPDATE_INTERVAL_MINUTES and UPDATE_SCHEDULE again after `/config reload`; unlike a
+    /// new scheduler it doesn't tick straight away
+    pub fn reload(&mut self, config: &Config) {
+        *self = Scheduler::from_config(config, self.clock.clone());
+        self.interval.reset();
+        self.started = true;
+    }
+
+    /// The next cron match after `now` (Taipei), if any schedule can still match
+    pub fn next_run(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.schedules.iter().filter_map(|s| s.next_after(now)).min()
+    }
+
+    /// Wait for the next cycle
+    pub async fn tick(&mut self) {
+        if self.schedules.is_empty() {
+            self.interval.tick().await;
+            return;
+        }
+        if !self.started {
+            self.started = true;
+            return;
+        }
+        let now = self.clock.now().naive_local();
+        match self.next_run(now) {
+            Some(next) => tokio::time::sleep((next - now).to_std().unwrap_or_default()).await,
+            None => {
+                warn!("No cron schedule will ever match; falling back to the fixed interval");
+                self.interval.tick().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{taipei_datetime, ManualClock};
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    fn at(date: (i32, u32, u32), hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn cron(expression: &str) -> CronSchedule {
+        CronSchedule::parse(expression).unwrap()
+    }
+
+    #[test]
+    fn fields_parse_steps_ranges_and_sunday_as_7() {
+        assert_eq!(Field::parse("5/15", 0, 59).unwrap().allowed, vec![5, 20, 35, 50]);
+        assert_eq!(Field::parse("10-20/5,1", 0, 59).unwrap().allowed, vec![1, 10, 15, 20]);
+        assert_eq!(cron("0 0 * * 7"), cron("0 0 * * 0"));
+        assert_eq!(cron("0 0 * * 5-7").weekday.allowed, vec![0, 5, 6]);
+        assert!(cron("0 0 * * 7").matches(at((2024, 7, 14), 0, 0)));
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in ["* * * *", "*/0 * * * *", "60 * * * *", "0 24 * * *", "0 0 0 * *", "0 0 * 13 *", "0 0 * * 8", "5-1 * * * *", "x * * * *"] {
+            assert!(CronSchedule::parse(expression).is_err(), "{} parsed", expression);
+        }
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_either() {
+        // The 1st of the month or any Monday
+        let either = cron("0 12 1 * 1");
+        assert!(either.matches(at((2024, 7, 8), 12, 0)));
+        assert!(either.matches(at((2024, 8, 1), 12, 0)));
+        assert!(!either.matches(at((2024, 8, 2), 12, 0)));
+        // With the day of month left open, only the weekday counts
+        let mondays = cron("0 12 * * 1");
+        assert!(mondays.matches(at((2024, 7, 8), 12, 0)));
+        assert!(!mondays.matches(at((2024, 8, 1), 12, 0)));
+        assert_eq!(either.next_after(at((2024, 7, 22), 12, 0)), Some(at((2024, 7, 29), 12, 0)));
+        assert_eq!(either.next_after(at((2024, 7, 29), 12, 0)), Some(at((2024, 8, 1), 12, 0)));
+    }
+
+    #[test]
+    fn next_after_crosses_midnight_month_and_year() {
+        let day = cron("*/10 7-22 * * *");
+        assert_eq!(day.next_after(at((2024, 7, 15), 22, 50)), Some(at((2024, 7, 16), 7, 0)));
+        assert_eq!(day.next_after(at((2024, 12, 31), 22, 55)), Some(at((2025, 1, 1), 7, 0)));
+        // Strictly after, even on a match
+        assert_eq!(day.next_after(at((2024, 7, 15), 7, 0)), Some(at((2024, 7, 15), 7, 10)));
+        assert_eq!(cron("30 8 31 * *").next_after(at((2024, 4, 1), 0, 0)), Some(at((2024, 5, 31), 8, 30)));
+    }
+
+    #[test]
+    fn leap_days_are_found_and_impossible_dates_are_not() {
+        assert_eq!(cron("0 0 29 2 *").next_after(at((2024, 3, 1), 0, 0)), Some(at((2028, 2, 29), 0, 0)));
+        // 2100 isn't a leap year
+        assert_eq!(cron("0 0 29 2 *").next_after(at((2096, 3, 1), 0, 0)), Some(at((2104, 2, 29), 0, 0)));
+        assert_eq!(cron("0 0 31 2 *").next_after(at((2024, 1, 1), 0, 0)), None);
+        assert_eq!(cron("0 0 30 2 *").next_after(at((2024, 1, 1), 0, 0)), None);
+    }
+
+    // Scheduler holds a tokio interval, so it needs a runtime even though nothing is awaited
+    #[tokio::test]
+    async fn scheduler_follows_taipei_time() {
+        // 2024-07-15 16:05 UTC is already Tuesday 00:05 in Taipei
+        let utc = DateTime::<Utc>::from_naive_utc_and_offset(at((2024, 7, 15), 16, 5), Utc);
+        let clock = Arc::new(ManualClock::new(utc.with_timezone(&chrono_tz::Asia::Taipei).fixed_offset()));
+        let tuesdays = Scheduler::new(std::time::Duration::from_secs(600), vec![cron("0 0 * * 2")], clock.clone());
+        assert_eq!(tuesdays.next_run(clock.now().naive_local()), Some(at((2024, 7, 23), 0, 0)));
+
+        let night = Scheduler::new(std::time::Duration::from_secs(600), parse_schedules("*/10 7-22 * * *; */30 0-6,23 * * *").unwrap(), clock.clone());
+        clock.set(taipei_datetime(at((2024, 7, 15), 22, 55)).unwrap());
+        assert_eq!(night.next_run(clock.now().naive_local()), Some(at((2024, 7, 15), 23, 0)));
+        clock.set(taipei_datetime(at((2024, 7, 15), 23, 45)).unwrap());
+        assert_eq!(night.next_run(clock.now().naive_local()), Some(at((2024, 7, 16), 0, 0)));
+        clock.set(taipei_datetime(at((2024, 7, 16), 6, 30)).unwrap());
+        assert_eq!(night.next_run(clock.now().naive_local()), Some(at((2024, 7, 16), 7, 0)));
+    }
+}
@@ -0,0 +1,177 @@
+//! Noticing when Taipower changes a feed's layout. The shape of each endpoint's last payload that
+//! parsed (every JSON path with its value types) is kept in memory; when a payload from an
+//! endpoint that used to parse stops parsing, it's written to SCHEMA_DUMP_DIR and the paths that
+//! appeared, disappeared or changed type are queued for the admin channel. That happens even when
+//! a fallback endpoint then succeeds, so a broken primary feed doesn't go unnoticed.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tracing::{error, warn};
+
+use crate::config;
+use crate::reporting::payload_fingerprint;
+
+/// Diff lines quoted in a notice; the rest are counted
+const MAX_DIFF_LINES: usize = 15;
+
+/// JSON path (`aaData[].機組名稱`) → the value types seen there
+type Shape = BTreeMap<String, BTreeSet<&'static str>>;
+
+struct Endpoint {
+    shape: Shape,
+    /// Already reported; stays quiet until a payload parses again
+    broken: bool,
+}
+
+static PENDING: Mutex<Vec<SchemaChange>> = Mutex::new(Vec::new());
+
+/// A payload from an endpoint that used to parse didn't
+#[derive(Debug)]
+pub struct SchemaChange {
+    pub endpoint: String,
+    pub error: String,
+    /// Where the payload was written, if it was
+    pub dump: Option<PathBuf>,
+    /// `+ path (type)`, `- path (type)` and `~ path: old → new` lines; empty if the payload isn't JSON
+    pub diff: Vec<String>,
+}
+
+impl SchemaChange {
+    pub fn notice(&self) -> String {
+        let mut lines = vec![
+            "🧩 **台電資料格式變更**: 先前可解析的端點開始解析失敗".to_string(),
+            format!("端點: {}", self.endpoint),
+            format!("錯誤: {}", self.error),
+        ];
+        if let Some(dump) = &self.dump {
+            lines.push(format!("內容已存至: {}", dump.display()));
+        }
+        if self.diff.is_empty() {
+            lines.push("回應不是 JSON，或欄位結構與上次相同 (可能是數值格式改變)".to_string());
+        } else {
+            let mut diff: Vec<String> = self.diff.iter().take(MAX_DIFF_LINES).cloned().collect();
+            if self.diff.len() > MAX_DIFF_LINES {
+                diff.push(format!("… 另有 {} 項差異", self.diff.len() - MAX_DIFF_LINES));
+            }
+            lines.push(format!("與上次成功的結構相比:\n```diff\n{}\n```", diff.join("\n").replace("```", "ˋˋˋ")));
+        }
+        lines.join("\n")
+    }
+}
+
+fn endpoints() -> &'static Mutex<HashMap<String, Endpoint>> {
+    static ENDPOINTS: OnceLock<Mutex<HashMap<String, Endpoint>>> = OnceLock::new();
+    ENDPOINTS.get_or_init(Default::default)
+}
+
+/// SCHEMA_DUMP_DIR: where payloads that stopped parsing are kept (default `schema-changes`); empty disables
+fn dump_dir() -> Option<PathBuf> {
+    match config::var("SCHEMA_DUMP_DIR") {
+        Ok(dir) if dir.trim().is_empty() => None,
+        Ok(dir) => Some(PathBuf::from(dir.trim())),
+        Err(_) => Some(PathBuf::from("schema-changes")),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn collect(value: &Value, path: &str, shape: &mut Shape) {
+    shape.entry(path.to_string()).or_default().insert(type_name(value));
+    match value {
+        Value::Array(items) => {
+            let path = format!("{}[]", path);
+            for item in items {
+                collect(item, &path, shape);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect(field, &path, shape);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn shape(value: &Value) -> Shape {
+    let mut shape = Shape::new();
+    collect(value, "", &mut shape);
+    shape
+}
+
+fn types(types: &BTreeSet<&'static str>) -> String {
+    types.iter().copied().collect::<Vec<_>>().join("|")
+}
+
+/// What changed from `old` to `new`, one line per path
+fn diff(old: &Shape, new: &Shape) -> Vec<String> {
+    let path = |p: &str| if p.is_empty() { "(root)".to_string() } else { p.to_string() };
+    let mut lines = Vec::new();
+    for (p, old_types) in old {
+        match new.get(p) {
+            None => lines.push(format!("- {} ({})", path(p), types(old_types))),
+            Some(new_types) if new_types != old_types => lines.push(format!("~ {}: {} → {}", path(p), types(old_types), types(new_types))),
+            Some(_) => {}
+        }
+    }
+    for (p, new_types) in new {
+        if !old.contains_key(p) {
+            lines.push(format!("+ {} ({})", path(p), types(new_types)));
+        }
+    }
+    lines
+}
+
+/// `text` from `endpoint` parsed: remember its shape
+pub fn parsed(endpoint: &str, text: &str) {
+    let Ok(value) = serde_json::from_str::<Value>(text) else {
+        return;
+    };
+    endpoints().lock().unwrap().insert(endpoint.to_string(), Endpoint { shape: shape(&value), broken: false });
+}
+
+/// `text` from `endpoint` didn't parse. If it used to, keep the payload and queue a notice, once
+/// until the endpoint parses again
+pub fn failed(endpoint: &str, error: &str, text: &str) {
+    let diff = {
+        let mut endpoints = endpoints().lock().unwrap();
+        let Some(state) = endpoints.get_mut(endpoint) else {
+            return;
+        };
+        if state.broken {
+            return;
+        }
+        state.broken = true;
+        serde_json::from_str::<Value>(text).map(|value| diff(&state.shape, &shape(&value))).unwrap_or_default()
+    };
+
+    let dump = dump_dir().and_then(|dir| {
+        let path = dir.join(format!("{}.json", payload_fingerprint(text)));
+        match std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, text)) {
+            Ok(()) => Some(path),
+            Err(why) => {
+                error!("Error saving changed payload to {}: {:?}", path.display(), why);
+                None
+            }
+        }
+    });
+    warn!("Schema change at {}: {} ({} paths differ)", endpoint, error, diff.len());
+    PENDING.lock().unwrap().push(SchemaChange { endpoint: endpoint.to_string(), error: error.to_string(), dump, diff });
+}
+
+/// The changes noticed since the last call
+pub fn take() -> Vec<SchemaChange> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
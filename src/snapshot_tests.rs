@@ -0,0 +1,197 @@
+//! Snapshot tests for what users see: every renderer's full and compact report and alerts for
+//! one fixed fixture, in both locales, compared with the files under src/snapshots/. A
+//! formatting change then shows up as a diff in review. After an intended change, rerun with
+//! UPDATE_SNAPSHOTS=1 to rewrite them; otherwise a mismatch fails and leaves the new output
+//! beside the old one as `<name>.snap.new`.
+
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::accessible::AccessibleRenderer;
+use crate::alerts::{FaultChange, IndicatorChange};
+use crate::analysis::CombinedPowerData;
+use crate::embed::EmbedRenderer;
+use crate::locale::Locale;
+use crate::render::{DiscordTextRenderer, PlainRenderer, Renderer, ReportFormat};
+use crate::stress::{StressIndex, StressWeights};
+use crate::taipower_api::{analyze_power_payload, parse_load_payload, ReserveIndicator};
+use crate::trend::Trend;
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots");
+    let path = dir.join(format!("{}.snap", name));
+    let pending = dir.join(format!("{}.snap.new", name));
+    let expected = fs::read_to_string(&path).ok();
+    if expected.as_deref() == Some(actual) {
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+    if env::var("UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1") {
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, actual).unwrap();
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&pending, actual).unwrap();
+    // Where to start reading the diff; past the shorter one's end if one is a prefix of the other
+    let first_difference = expected.as_deref().map(|expected| {
+        let line = expected.lines().zip(actual.lines()).take_while(|(old, new)| old == new).count();
+        format!(" at line {}", line + 1)
+    });
+    panic!(
+        "snapshot {} {}{}; review {} and rerun with UPDATE_SNAPSHOTS=1 to accept it",
+        name,
+        if expected.is_some() { "changed" } else { "is new" },
+        first_difference.unwrap_or_default(),
+        pending.display()
+    );
+}
+
+fn unit(unit_type: &str, name: &str, capacity: f64, generation: f64, remark: &str) -> serde_json::Value {
+    json!({
+        "機組類型": unit_type,
+        "機組名稱": name,
+        "裝置容量(MW)": format!("{:.1}", capacity),
+        "淨發電量(MW)": format!("{:.1}", generation),
+        "淨發電量/裝置容量比(%)": format!("{:.3}%", generation / capacity * 100.0),
+        "備註": remark,
+    })
+}
+
+/// A tight summer afternoon: orange forecast, one coal unit tripped, another in overhaul
+fn fixture() -> CombinedPowerData {
+    let generation = json!({
+        "DateTime": "2024-07-15 14:30",
+        "aaData": [
+            unit("核能", "核三#2", 951.0, 936.4, ""),
+            unit("燃煤", "台中#1", 550.0, 0.0, "故障"),
+            unit("燃煤", "台中#2", 550.0, 512.3, ""),
+            unit("燃煤", "興達#3", 550.0, 0.0, "歲修"),
+            unit("燃煤", "小計", 1650.0, 512.3, ""),
+            unit("民營電廠-燃煤", "麥寮#1", 600.0, 577.1, ""),
+            unit("燃氣", "大潭CC#7", 1100.0, 1043.8, ""),
+            unit("燃氣", "通霄CC#1", 892.0, 861.5, "環保限制"),
+            unit("太陽能", "太陽能購電", 12000.0, 7412.6, ""),
+            unit("風力", "離岸風力購電", 2900.0, 388.2, ""),
+            unit("水力", "明潭#1", 267.0, 203.9, ""),
+            unit("儲能", "儲能購電", 700.0, 121.4, ""),
+        ],
+    });
+    let load = json!({
+        "success": "true",
+        "result": { "resource_id": "fixture" },
+        "records": [
+            {
+                "curr_load": "3812.5",
+                "curr_util_rate": "93",
+                "fore_maxi_sply_capacity": "4107.0",
+                "fore_peak_dema_load": "3880.0",
+                "fore_peak_resv_capacity": "227.0",
+                "fore_peak_resv_rate": "5.85",
+                "fore_peak_resv_indicator": "O",
+                "fore_peak_hour_range": "14:00~15:00",
+                "publish_time": "2024-07-15 14:30:00",
+            },
+            {
+                "yday_date": "2024-07-14",
+                "yday_maxi_sply_capacity": "4011.0",
+                "yday_peak_dema_load": "3720.0",
+                "yday_peak_resv_capacity": "291.0",
+                "yday_peak_resv_rate": "7.82",
+                "yday_peak_resv_indicator": "Y",
+                "real_hr_maxi_sply_capacity": "4021.0",
+                "real_hr_peak_time": "14:00",
+            },
+        ],
+    });
+    let power_analysis = analyze_power_payload(&generation.to_string()).unwrap().unwrap();
+    let load_data = parse_load_payload(&load.to_string()).unwrap();
+    let stress = StressIndex::compute(&power_analysis, Some(&load_data), None, &StressWeights::default());
+    CombinedPowerData {
+        power_analysis,
+        load_data: Some(load_data),
+        regions: Vec::new(),
+        temperature: Some(34.6),
+        own_forecast: None,
+        outages: Vec::new(),
+        demand_response_mw: Some(412.0),
+        stress,
+        trend: Some(Trend::Tightening),
+    }
+}
+
+fn indicator_change() -> IndicatorChange {
+    IndicatorChange { from: ReserveIndicator::Yellow, to: ReserveIndicator::Orange, reserve_rate: 5.85, reserve_rate_change: -1.42 }
+}
+
+fn fault_change() -> FaultChange {
+    FaultChange { faulted: vec![("台中#1".to_string(), 550.0)], recovered: vec![("林口#2".to_string(), 800.0)] }
+}
+
+fn locales() -> [(Locale, &'static str); 2] {
+    [(Locale::ZhTw, "zh"), (Locale::En, "en")]
+}
+
+fn pretty(value: &impl serde::Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap()
+}
+
+#[test]
+fn text_reports() {
+    let data = fixture();
+    for (locale, code) in locales() {
+        let text = DiscordTextRenderer { locale, ..Default::default() };
+        assert_snapshot(&format!("text_report_{}", code), &text.report(&data));
+        assert_snapshot(&format!("text_compact_{}", code), &text.compact(&data));
+    }
+}
+
+#[test]
+fn accessible_reports() {
+    let data = fixture();
+    for (locale, code) in locales() {
+        let accessible = AccessibleRenderer::from(&DiscordTextRenderer { locale, ..Default::default() });
+        assert_snapshot(&format!("accessible_report_{}", code), &accessible.report(&data));
+        assert_snapshot(&format!("accessible_compact_{}", code), &accessible.compact(&data));
+    }
+}
+
+#[test]
+fn plain_and_embed_reports() {
+    let data = fixture();
+    assert_snapshot("plain_report", &PlainRenderer.report(&data));
+    assert_snapshot("plain_compact", &PlainRenderer.compact(&data));
+    let embed = EmbedRenderer { numbers: Default::default(), sections: Default::default() };
+    assert_snapshot("embed_report", &pretty(&embed.report(&data)));
+    assert_snapshot("embed_compact", &pretty(&embed.compact(&data)));
+}
+
+#[test]
+fn alerts() {
+    let (indicator, fault) = (indicator_change(), fault_change());
+    for (locale, code) in locales() {
+        let text = DiscordTextRenderer { locale, ..Default::default() };
+        assert_snapshot(&format!("text_indicator_change_{}", code), &text.indicator_change(&indicator));
+        assert_snapshot(&format!("text_fault_change_{}", code), &text.fault_change(&fault));
+        let accessible = AccessibleRenderer::from(&text);
+        assert_snapshot(&format!("accessible_indicator_change_{}", code), &accessible.indicator_change(&indicator));
+        assert_snapshot(&format!("accessible_fault_change_{}", code), &accessible.fault_change(&fault));
+    }
+    assert_snapshot("plain_indicator_change", &PlainRenderer.indicator_change(&indicator));
+    assert_snapshot("plain_fault_change", &PlainRenderer.fault_change(&fault));
+    let embed = EmbedRenderer { numbers: Default::default(), sections: Default::default() };
+    assert_snapshot("embed_indicator_change", &pretty(&embed.indicator_change(&indicator)));
+    assert_snapshot("embed_fault_change", &pretty(&embed.fault_change(&fault)));
+}
+
+#[test]
+fn reserve_alert_messages() {
+    let text = DiscordTextRenderer::default();
+    for format in [ReportFormat::Text, ReportFormat::Embed] {
+        let message = format.reserve_alert_message(&indicator_change(), &text, Some(123456789012345678));
+        assert_snapshot(&format!("reserve_alert_{}", format.code()), &pretty(&message));
+    }
+}
@@ -0,0 +1,718 @@
+//! Soak test (`cargo run --release --features soak --bin soak`): thousands of update cycles
+//! against a mock Taipower server, on a simulated clock stepped by the scheduler's cron
+//! schedule, through the bot's own update cycle: the same fetches, sanity checks, alert state,
+//! history and renderers. Memory, runtime tasks and open file descriptors must stay flat once warmed up, and every
+//! alert must agree with a model of what the mock served.
+//!
+//! The mock serves a synthetic grid: a daily load and solar curve, a reserve rate swinging
+//! through the indicator colours, units dropping in and out of 故障, and now and then an HTTP
+//! 500, a garbage payload, a five-cycle outage or a publish_time that stops advancing.
+//! Discord delivery isn't exercised: messages are rendered and dropped.
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Timelike};
+use serde_json::json;
+use serenity::async_trait;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::f64::consts::PI;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+use tracing::{error, warn};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::accessible::AccessibleRenderer;
+use crate::alerts::{self, FaultChange, FreezeEvent, IndicatorChange, STRESS_REARM_MARGIN};
+use crate::analysis::CombinedPowerData;
+use crate::chart::{self, Series};
+use crate::clock::{taipei_datetime, Clock, ManualClock};
+use crate::config::Config;
+use crate::cycle::{LiveFeeds, Outlet, Updater};
+use crate::embed::EmbedRenderer;
+use crate::history::{History, UnitHistoryPolicy};
+use crate::locale::NumberFormat;
+use crate::mentions::{self, LadderMention};
+use crate::records::RecordBreak;
+use crate::render::{DiscordTextRenderer, PlainRenderer, Renderer};
+use crate::scheduler::{self, Scheduler};
+use crate::stress::{self, StressIndex};
+use crate::taipower_api::{LoadData, ReserveIndicator};
+use crate::trend::Trend;
+use crate::{digest, records};
+
+const GENERATION_PATH: &str = "/data/opendata/apply/file/d006001/001.json";
+const LOAD_PATH: &str = "/data/opendata/apply/file/d006020/001.json";
+const REGIONAL_PATH: &str = "/d006/loadGraph/loadGraph/data/genloadareaperc.json";
+
+/// A Monday, so the simulated weeks start with workdays
+const START: &str = "2026-01-05 00:00:00";
+const SCHEDULE: &str = "*/10 7-22 * * *; */30 0-6,23 * * *";
+const FREEZE_CYCLES: u32 = 3;
+const FAILURE_THRESHOLD: u32 = 3;
+const STRESS_THRESHOLD: f64 = 50.0;
+const LADDER_HYSTERESIS: f64 = 1.0;
+const LADDER_GUILD: u64 = 1;
+/// Cycles between rendered history charts
+const CHART_EVERY: u32 = 36;
+
+pub struct SoakOptions {
+    pub cycles: u32,
+    /// Cycles run before the memory, task and descriptor baselines are taken
+    pub warmup: u32,
+    /// Cycles between resource samples
+    pub sample_every: u32,
+    pub max_rss_growth_kb: u64,
+    pub max_task_growth: usize,
+}
+
+/// Settings the harness relies on; the binary sets them before any other thread starts
+pub fn environment(base_url: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("TAIPOWER_BASE_URL", base_url.to_string()),
+        ("HTTP_RETRY_ATTEMPTS", "2".to_string()),
+        ("HTTP_RETRY_BASE_MS", "0".to_string()),
+        ("PAYLOAD_ARCHIVE_DIR", String::new()),
+        ("ERROR_REPORT_AFTER_FAILURES", FAILURE_THRESHOLD.to_string()),
+        ("FREEZE_ALERT_CYCLES", FREEZE_CYCLES.to_string()),
+        ("ALERT_COOLDOWN_MINUTES", "60".to_string()),
+        ("STRESS_ALERT_THRESHOLD", STRESS_THRESHOLD.to_string()),
+        ("FAULT_ALERTS", "on".to_string()),
+        ("RECORD_ALERTS", "on".to_string()),
+        ("DAILY_DIGEST", "on".to_string()),
+        ("DIGEST_TIME", String::new()),
+        // Nothing outside the mock server
+        ("OWN_FORECAST", "off".to_string()),
+        ("DEMAND_RESPONSE_URL", String::new()),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GenerationFault {
+    /// HTTP 500 from every generation endpoint
+    Down,
+    /// HTTP 200 with an error page instead of JSON
+    Garbage,
+}
+
+/// Cycles 300–305 of every 600 serve a publish_time that doesn't advance
+fn frozen(n: u32) -> bool {
+    (300..306).contains(&(n % 600))
+}
+
+/// No other faults just around a freeze, so every freeze runs its full length
+fn quiet(n: u32) -> bool {
+    (299..=306).contains(&(n % 600))
+}
+
+fn generation_fault(n: u32) -> Option<GenerationFault> {
+    if quiet(n) {
+        None
+    } else if (500..505).contains(&(n % 1000)) {
+        Some(GenerationFault::Down)
+    } else if n % 89 == 41 {
+        Some(GenerationFault::Garbage)
+    } else {
+        None
+    }
+}
+
+fn load_down(n: u32) -> bool {
+    !quiet(n) && n % 97 == 13
+}
+
+fn regional_garbage(n: u32) -> bool {
+    n % 53 == 7
+}
+
+fn reserve_rate(n: u32) -> f64 {
+    9.0 + 7.0 * (n as f64 / 29.0).sin()
+}
+
+fn indicator_code(rate: f64) -> &'static str {
+    match rate {
+        r if r >= 10.0 => "G",
+        r if r >= 6.0 => "Y",
+        r if r >= 3.0 => "O",
+        _ => "R",
+    }
+}
+
+fn hours(time: NaiveDateTime) -> f64 {
+    time.hour() as f64 + time.minute() as f64 / 60.0
+}
+
+/// 萬瓩, peaking mid-afternoon
+fn current_load(time: NaiveDateTime) -> f64 {
+    2500.0 + 700.0 * (PI * (hours(time) - 7.0) / 14.0).sin().max(0.0)
+}
+
+struct Unit {
+    kind: &'static str,
+    name: String,
+    capacity: f64,
+    output: f64,
+    faulted: bool,
+}
+
+/// One thermal unit at a time is in 故障 for 40 cycles, with a few fault-free stretches between
+fn faulted(n: u32, unit: u32) -> bool {
+    (n / 40 + unit * 7).is_multiple_of(29)
+}
+
+fn units(n: u32, time: NaiveDateTime) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let thermal = (1..=8)
+        .map(|i| ("燃煤", format!("台中#{}", i), 550.0, 480.0))
+        .chain((1..=14).map(|i| ("燃氣", format!("大潭#{}", i), 800.0, 750.0)))
+        .chain([("核能", "核三#1".to_string(), 950.0, 900.0)]);
+    for (i, (kind, name, capacity, output)) in thermal.enumerate() {
+        let faulted = faulted(n, i as u32);
+        units.push(Unit { kind, name, capacity, output: if faulted { 0.0 } else { output }, faulted });
+    }
+
+    // Solar capacity creeps up so renewable records keep falling
+    let solar_capacity = 12000.0 + n as f64 * 0.5;
+    let daylight = (PI * (hours(time) - 6.0) / 12.0).sin().max(0.0);
+    units.push(Unit { kind: "太陽能", name: "彰濱光電".to_string(), capacity: solar_capacity, output: 50.0 + 0.6 * solar_capacity * daylight, faulted: false });
+    units.push(Unit { kind: "風力", name: "離岸風電".to_string(), capacity: 2000.0, output: 900.0 + 700.0 * (n as f64 / 17.0).sin(), faulted: false });
+    units.push(Unit { kind: "水力", name: "明潭#1".to_string(), capacity: 2000.0, output: 600.0, faulted: false });
+    units
+}
+
+fn generation_payload(time: NaiveDateTime, units: &[Unit]) -> String {
+    let rows: Vec<_> = units
+        .iter()
+        .map(|u| {
+            json!({
+                "機組類型": u.kind,
+                "機組名稱": u.name,
+                "裝置容量(MW)": format!("{:.1}", u.capacity),
+                "淨發電量(MW)": format!("{:.1}", u.output),
+                "備註": if u.faulted { "故障" } else { "" },
+            })
+        })
+        .collect();
+    json!({ "DateTime": time.format("%Y-%m-%d %H:%M:%S").to_string(), "aaData": rows }).to_string()
+}
+
+fn load_payload(time: NaiveDateTime, publish_time: NaiveDateTime, rate: f64) -> String {
+    let load = current_load(time);
+    let supply = 3200.0 * (1.0 + rate / 100.0);
+    json!({
+        "success": "true",
+        "result": { "resource_id": "soak" },
+        "records": [
+            {
+                "curr_load": format!("{:.1}", load),
+                "curr_util_rate": format!("{:.1}", load / supply * 100.0),
+                "publish_time": publish_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            },
+            {
+                "fore_maxi_sply_capacity": format!("{:.1}", supply),
+                "fore_peak_dema_load": "3200.0",
+                "fore_peak_resv_capacity": format!("{:.1}", supply - 3200.0),
+                "fore_peak_resv_rate": format!("{:.2}", rate),
+                "fore_peak_resv_indicator": indicator_code(rate),
+                "fore_peak_hour_range": "13:00~14:00",
+            },
+            {
+                "yday_date": "",
+                "yday_maxi_sply_capacity": "3500.0",
+                "yday_peak_dema_load": "3150.0",
+                "yday_peak_resv_capacity": "350.0",
+                "yday_peak_resv_rate": "11.11",
+                "yday_peak_resv_indicator": "G",
+                "real_hr_maxi_sply_capacity": "3300.0",
+                "real_hr_peak_time": "14:00",
+            }
+        ]
+    })
+    .to_string()
+}
+
+fn regional_payload() -> String {
+    json!({
+        "data": [
+            { "area": "北部", "genPerc": "30.5", "loadPerc": "38.2" },
+            { "area": "中部", "genPerc": "33.1", "loadPerc": "27.4" },
+            { "area": "南部", "genPerc": "34.2", "loadPerc": "32.1" },
+            { "area": "東部", "genPerc": "2.2", "loadPerc": "2.3" }
+        ]
+    })
+    .to_string()
+}
+
+#[derive(Clone)]
+enum Reply {
+    Json(String),
+    Status(u16),
+}
+
+#[derive(Clone)]
+struct Served {
+    generation: Reply,
+    load: Reply,
+    regional: Reply,
+}
+
+/// Answers one feed's path with whatever the current cycle serves
+struct Feed {
+    served: Arc<Mutex<Served>>,
+    pick: Pick,
+}
+
+type Pick = fn(&Served) -> Reply;
+
+impl Respond for Feed {
+    fn respond(&self, _request: &Request) -> ResponseTemplate {
+        match (self.pick)(&self.served.lock().unwrap()) {
+            Reply::Json(body) => ResponseTemplate::new(200).set_body_raw(body, "application/json"),
+            Reply::Status(status) => ResponseTemplate::new(status),
+        }
+    }
+}
+
+/// Routes each feed's path to the current cycle's reply. wiremock keeps every request a mock
+/// matched even with recording disabled, so the mocks are remounted each cycle to drop them
+async fn mount_feeds(server: &MockServer, served: &Arc<Mutex<Served>>) {
+    server.reset().await;
+    let feeds: [(&str, Pick); 3] = [
+        (GENERATION_PATH, |s| s.generation.clone()),
+        (LOAD_PATH, |s| s.load.clone()),
+        (REGIONAL_PATH, |s| s.regional.clone()),
+    ];
+    for (feed_path, pick) in feeds {
+        Mock::given(method("GET"))
+            .and(path(feed_path))
+            .respond_with(Feed { served: served.clone(), pick })
+            .mount(server)
+            .await;
+    }
+}
+
+/// What the harness expects the alert state to do, from what the mock served
+#[derive(Default)]
+struct Model {
+    faults: Option<BTreeSet<String>>,
+    indicator: Option<ReserveIndicator>,
+    publish_time: Option<NaiveDateTime>,
+    stale_streak: u32,
+    generation_streak: u32,
+    /// The index fell below the re-arm level since the last stress alert
+    stress_rearmed: bool,
+    record: Option<f64>,
+    announced: HashSet<(NaiveDate, &'static str)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    cycle: u32,
+    rss_kb: Option<u64>,
+    tasks: usize,
+    fds: Option<usize>,
+}
+
+impl Sample {
+    async fn take(cycle: u32) -> Self {
+        // Let connections from the last fetch finish closing
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let rss_kb = std::fs::read_to_string("/proc/self/status").ok().and_then(|status| {
+            let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+            line.split_whitespace().nth(1)?.parse().ok()
+        });
+        let fds = std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count());
+        let tasks = tokio::runtime::Handle::current().metrics().num_alive_tasks();
+        Sample { cycle, rss_kb, tasks, fds }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "cycle {}: rss {} kB, {} task(s), {} fd(s)",
+            self.cycle,
+            self.rss_kb.map_or("?".to_string(), |kb| kb.to_string()),
+            self.tasks,
+            self.fds.map_or("?".to_string(), |fds| fds.to_string())
+        )
+    }
+}
+
+pub struct SoakReport {
+    pub cycles: u32,
+    pub published: u32,
+    /// Alerts and injected faults seen, by kind
+    pub events: Vec<(&'static str, u32)>,
+    pub violations: Vec<String>,
+}
+
+struct Checks {
+    violations: Vec<String>,
+}
+
+impl Checks {
+    fn expect(&mut self, cycle: u32, ok: bool, what: impl FnOnce() -> String) {
+        if !ok {
+            let violation = format!("cycle {}: {}", cycle, what());
+            warn!("Soak violation: {}", violation);
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// What one cycle announced
+#[derive(Default)]
+struct Observed {
+    data: Option<CombinedPowerData>,
+    outage: bool,
+    freeze: Option<FreezeEvent>,
+    indicator: Option<(IndicatorChange, bool)>,
+    ladder_pinged: bool,
+    fault: Option<FaultChange>,
+    stress_alert: bool,
+    record: Option<RecordBreak>,
+    stale_data: bool,
+    admin_notices: u32,
+    /// Database errors inside the outlet
+    errors: Vec<String>,
+}
+
+/// Renders every announcement and drops it, keeping what was announced for the checks
+struct SoakOutlet {
+    history: Arc<History>,
+    text: DiscordTextRenderer,
+    embed: EmbedRenderer,
+    accessible: AccessibleRenderer,
+    observed: Observed,
+}
+
+#[async_trait]
+impl Outlet for SoakOutlet {
+    async fn admin(&mut self, _notice: &str) {
+        self.observed.admin_notices += 1;
+    }
+
+    async fn digest(&mut self, date: NaiveDate) {
+        let _ = digest::content(&self.history, date, self.text.numbers);
+    }
+
+    async fn outage(&mut self) -> bool {
+        self.observed.outage = true;
+        false
+    }
+
+    async fn freeze(&mut self, event: &FreezeEvent) {
+        self.observed.freeze = Some(event.clone());
+    }
+
+    async fn observed(&mut self, data: &CombinedPowerData, _previous_load: Option<&LoadData>) {
+        self.observed.data = Some(data.clone());
+    }
+
+    async fn indicator_change(&mut self, change: &IndicatorChange, ping: bool) {
+        let _ = self.text.indicator_change(change);
+        self.observed.indicator = Some((change.clone(), ping));
+    }
+
+    async fn load_update(&mut self, load: &LoadData, _previous: Option<&LoadData>, _trend: Option<Trend>) {
+        let rate = load.forecast_peak_reserve_rate;
+        let crossed = match mentions::advance_ladder(&self.history, LADDER_GUILD, rate, LADDER_HYSTERESIS) {
+            Ok(crossed) => crossed,
+            Err(why) => return self.observed.errors.push(format!("updating the ping ladder: {}", why)),
+        };
+        if crossed.is_empty() {
+            return;
+        }
+        self.observed.ladder_pinged = true;
+        let _ = mentions::ladder_message(rate, &crossed);
+        for rung in crossed {
+            if let Err(why) = self.history.set_ladder_active(LADDER_GUILD, rung.below, true) {
+                self.observed.errors.push(format!("marking ladder rung {} sent: {}", rung.below, why));
+            }
+        }
+    }
+
+    async fn fault_change(&mut self, change: &FaultChange) {
+        let _ = self.text.fault_change(change);
+        self.observed.fault = Some(change.clone());
+    }
+
+    async fn stress_alert(&mut self, stress: &StressIndex) {
+        let _ = stress::alert_message(stress);
+        self.observed.stress_alert = true;
+    }
+
+    async fn stale_data(&mut self, data_time: DateTime<FixedOffset>, age: Duration) {
+        let _ = alerts::stale_data_warning(data_time, age);
+        self.observed.stale_data = true;
+    }
+
+    async fn record(&mut self, record: &RecordBreak) {
+        let _ = records::announcement(record);
+        self.observed.record = Some(*record);
+    }
+
+    async fn report(&mut self, data: &CombinedPowerData, _indicator_change: Option<&IndicatorChange>) {
+        let _ = (
+            self.text.report(data),
+            self.text.compact(data),
+            PlainRenderer.report(data),
+            self.embed.report_with_badge(data),
+            self.accessible.report(data),
+        );
+    }
+}
+
+pub async fn run(options: &SoakOptions, listener: TcpListener) -> Result<SoakReport, Box<dyn std::error::Error + Send + Sync>> {
+    let start = NaiveDateTime::parse_from_str(START, "%Y-%m-%d %H:%M:%S")?;
+    let initial = Served { generation: Reply::Status(503), load: Reply::Status(503), regional: Reply::Status(503) };
+    let served = Arc::new(Mutex::new(initial));
+    let server = MockServer::builder().listener(listener).disable_request_recording().start().await;
+
+    let clock = Arc::new(ManualClock::new(taipei_datetime(start).ok_or("invalid start time")?));
+    let history_path = std::env::temp_dir().join(format!("taipower-soak-{}.db", std::process::id()));
+    let history = Arc::new(History::open_with_clock(&history_path, clock.clone())?);
+    let config = Config {
+        unit_history: UnitHistoryPolicy { enabled: true, prefixes: Vec::new(), min_capacity: 0.0, retention_days: 2 },
+        ..Config::from_env().map_err(|errors| errors.join("; "))?
+    };
+    let schedule = Scheduler::new(config.interval, scheduler::parse_schedules(SCHEDULE)?, clock.clone());
+    for (below, mention) in [(10.0, LadderMention::Role(1)), (6.0, LadderMention::Here), (3.0, LadderMention::Everyone)] {
+        history.set_ladder_rung(LADDER_GUILD, below, mention)?;
+    }
+
+    let sanity_bounds = config.sanity_bounds.clone();
+    let mut updater = Updater::new(history.clone(), clock.clone(), Arc::new(RwLock::new(config)));
+    let mut feeds = LiveFeeds { maintenance: None };
+    let mut outlet = SoakOutlet {
+        history: history.clone(),
+        text: DiscordTextRenderer::default(),
+        embed: EmbedRenderer::default(),
+        accessible: AccessibleRenderer::default(),
+        observed: Observed::default(),
+    };
+    let mut served_publish = start;
+
+    let mut model = Model { stress_rearmed: true, ..Model::default() };
+    let mut checks = Checks { violations: Vec::new() };
+    let mut events: HashMap<&'static str, u32> = HashMap::new();
+    let mut published = 0;
+    let mut baseline: Option<Sample> = None;
+    let began = Instant::now();
+
+    for n in 0..options.cycles {
+        let now = clock.now().naive_local();
+        let next = schedule.next_run(now).unwrap_or(now + Duration::minutes(10));
+        clock.set(taipei_datetime(next).ok_or("invalid cycle time")?);
+        let time = next;
+
+        let grid = units(n, time);
+        let rate = reserve_rate(n);
+        if !frozen(n) {
+            served_publish = time;
+        }
+        let fault = generation_fault(n);
+        mount_feeds(&server, &served).await;
+        *served.lock().unwrap() = Served {
+            generation: match fault {
+                None => Reply::Json(generation_payload(time, &grid)),
+                Some(GenerationFault::Down) => Reply::Status(500),
+                Some(GenerationFault::Garbage) => Reply::Json("<html>系統維護中</html>".to_string()),
+            },
+            load: if load_down(n) { Reply::Status(500) } else { Reply::Json(load_payload(time, served_publish, rate)) },
+            regional: if regional_garbage(n) { Reply::Json("{\"data\": \"-\"}".to_string()) } else { Reply::Json(regional_payload()) },
+        };
+
+        outlet.observed = Observed::default();
+        updater.cycle(&mut feeds, &mut outlet).await;
+        let observed = std::mem::take(&mut outlet.observed);
+        for why in observed.errors {
+            checks.expect(n, false, || why);
+        }
+        if observed.admin_notices > 0 {
+            *events.entry("admin notices").or_default() += observed.admin_notices;
+        }
+        if observed.stale_data {
+            *events.entry("stale data warnings").or_default() += 1;
+        }
+
+        let Some(data) = observed.data else {
+            checks.expect(n, fault.is_some(), || "nothing published without an injected generation fault".to_string());
+            *events.entry("generation failures").or_default() += 1;
+            model.generation_streak += 1;
+            checks.expect(n, observed.outage == (model.generation_streak == FAILURE_THRESHOLD), || {
+                format!("outage reported: {}, after {} failure(s)", observed.outage, model.generation_streak)
+            });
+            if observed.outage {
+                *events.entry("outages reported").or_default() += 1;
+            }
+            continue;
+        };
+        checks.expect(n, fault.is_none(), || format!("published despite {:?}", fault));
+        checks.expect(n, !observed.outage, || "outage reported on a published cycle".to_string());
+        model.generation_streak = 0;
+        published += 1;
+
+        let violations: Vec<_> = sanity_bounds
+            .check_power(&data.power_analysis)
+            .into_iter()
+            .chain(data.load_data.as_ref().map(|load| sanity_bounds.check_load(load)).unwrap_or_default())
+            .collect();
+        checks.expect(n, violations.is_empty(), || {
+            format!("published data failed sanity checks: {}", violations.iter().map(|v| v.describe()).collect::<Vec<_>>().join("; "))
+        });
+
+        // The load feed is only missing while it's down; a fetch that succeeds passes the sanity checks
+        checks.expect(n, data.load_data.is_some() != load_down(n), || {
+            format!("load {} with the feed {}", if data.load_data.is_some() { "published" } else { "missing" }, if load_down(n) { "down" } else { "up" })
+        });
+        if load_down(n) {
+            *events.entry("load failures").or_default() += 1;
+        }
+        if let Some(load) = &data.load_data {
+            checks.expect(n, (load.forecast_peak_reserve_rate - rate).abs() < 0.01, || {
+                format!("reserve rate {} parsed as {}", rate, load.forecast_peak_reserve_rate)
+            });
+            let publish_time = load.publish_time.map(|t| t.naive_local());
+            checks.expect(n, publish_time == Some(served_publish), || format!("publish_time {:?} parsed as {:?}", served_publish, publish_time));
+
+            let event = &observed.freeze;
+            let stale = model.publish_time.is_some_and(|last| served_publish <= last);
+            if stale {
+                model.stale_streak += 1;
+                let ok = match model.stale_streak == FREEZE_CYCLES {
+                    true => matches!(event, Some(FreezeEvent::Frozen { .. })),
+                    false => event.is_none(),
+                };
+                checks.expect(n, ok, || format!("freeze watchdog said {:?} after {} stale fetch(es)", event, model.stale_streak));
+            } else {
+                let ok = match model.stale_streak >= FREEZE_CYCLES {
+                    true => matches!(event, Some(FreezeEvent::Recovered { .. })),
+                    false => event.is_none(),
+                };
+                checks.expect(n, ok, || format!("freeze watchdog said {:?} on a fresh fetch after {} stale", event, model.stale_streak));
+                model.stale_streak = 0;
+                model.publish_time = Some(served_publish);
+            }
+            if matches!(event, Some(FreezeEvent::Frozen { .. })) {
+                *events.entry("freezes").or_default() += 1;
+            }
+
+            // Regional shares are scaled by the load, so they're only there with it
+            checks.expect(n, data.regions.is_empty() == regional_garbage(n), || {
+                format!("{} region(s) published with the regional feed {}", data.regions.len(), if regional_garbage(n) { "garbled" } else { "up" })
+            });
+            if regional_garbage(n) {
+                *events.entry("regional failures").or_default() += 1;
+            }
+
+            let indicator = load.forecast_peak_reserve_indicator;
+            let expected = model.indicator.is_some_and(|previous| previous != indicator);
+            let change = &observed.indicator;
+            checks.expect(n, change.is_some() == expected, || format!("indicator {:?} → {:?} reported as {:?}", model.indicator, indicator, change));
+            if let Some((change, ping)) = change {
+                *events.entry("indicator changes").or_default() += 1;
+                if *ping {
+                    checks.expect(n, change.to.is_critical() && change.to > change.from, || format!("pinged for {:?} → {:?}", change.from, change.to));
+                    *events.entry("reserve pings").or_default() += 1;
+                }
+            }
+            model.indicator = Some(indicator);
+
+            if observed.ladder_pinged {
+                *events.entry("ladder pings").or_default() += 1;
+            }
+            for rung in history.ping_ladder(LADDER_GUILD)? {
+                let rate = load.forecast_peak_reserve_rate;
+                let consistent = if rate < rung.below { rung.active } else { rate < rung.below + LADDER_HYSTERESIS || !rung.active };
+                checks.expect(n, consistent, || format!("ladder rung {} active={} at {:.2}%", rung.below, rung.active, rate));
+            }
+        }
+
+        // Records only ever rise, and each kind is announced at most once a day
+        let now = clock.now().naive_local();
+        let stored = history.record_value(records::ALL_TIME)?.map(|(value, _, _)| value);
+        checks.expect(n, stored >= model.record, || format!("renewable record went from {:?} to {:?}", model.record, stored));
+        model.record = stored;
+        if let Some(record) = &observed.record {
+            let kind = match record {
+                RecordBreak::AllTime { value, previous, .. } => {
+                    checks.expect(n, value > previous, || format!("record {} announced below the previous {}", value, previous));
+                    "all-time records"
+                }
+                RecordBreak::Daily { .. } => "daily records",
+            };
+            checks.expect(n, model.announced.insert((now.date(), kind)), || format!("{} announced twice on {}", kind, now.date()));
+            *events.entry(kind).or_default() += 1;
+        }
+
+        let faults: BTreeSet<String> = grid.iter().filter(|u| u.faulted).map(|u| u.name.clone()).collect();
+        let reported = observed.fault.as_ref().map(|c| {
+            let names = |units: &[(String, f64)]| units.iter().map(|(name, _)| name.clone()).collect::<BTreeSet<_>>();
+            (names(&c.faulted), names(&c.recovered))
+        });
+        let expected = model.faults.as_ref().map(|previous| (&faults - previous, previous - &faults)).filter(|(f, r)| !f.is_empty() || !r.is_empty());
+        checks.expect(n, reported == expected, || format!("fault changes {:?}, expected {:?}", reported, expected));
+        if observed.fault.is_some() {
+            *events.entry("fault changes").or_default() += 1;
+        }
+        model.faults = Some(faults);
+
+        if let Some(index) = &data.stress {
+            if observed.stress_alert {
+                checks.expect(n, index.value >= STRESS_THRESHOLD && model.stress_rearmed, || {
+                    format!("stress alert at {:.1} (re-armed: {})", index.value, model.stress_rearmed)
+                });
+                model.stress_rearmed = false;
+                *events.entry("stress alerts").or_default() += 1;
+            } else if index.value < STRESS_THRESHOLD - STRESS_REARM_MARGIN {
+                model.stress_rearmed = true;
+            }
+        }
+
+        if n.is_multiple_of(CHART_EVERY) {
+            let points = history.snapshot_series("current_load", now - Duration::hours(24))?;
+            if points.len() >= 2 {
+                chart::line_chart("soak", "萬瓩", &[Series { label: "負載".to_string(), points }], NumberFormat::default())?;
+            }
+        }
+
+        if n + 1 == options.warmup || (n + 1).is_multiple_of(options.sample_every) {
+            let sample = Sample::take(n + 1).await;
+            println!("{} ({} published, {:.0}s)", sample.describe(), published, began.elapsed().as_secs_f64());
+            if n + 1 == options.warmup {
+                baseline = Some(sample);
+            }
+        }
+    }
+
+    let snapshots = history.snapshot_series("total_generation", start - Duration::days(1))?.len();
+    checks.expect(options.cycles, snapshots == published as usize, || format!("{} snapshot(s) stored for {} published cycle(s)", snapshots, published));
+
+    let last = Sample::take(options.cycles).await;
+    println!("Final {}", last.describe());
+    match baseline {
+        Some(baseline) => {
+            if let (Some(before), Some(after)) = (baseline.rss_kb, last.rss_kb) {
+                checks.expect(options.cycles, after <= before + options.max_rss_growth_kb, || {
+                    format!("RSS grew {} kB after warmup ({} → {} kB)", after.saturating_sub(before), before, after)
+                });
+            }
+            checks.expect(options.cycles, last.tasks <= baseline.tasks + options.max_task_growth, || {
+                format!("alive tasks grew from {} to {}", baseline.tasks, last.tasks)
+            });
+            if let (Some(before), Some(after)) = (baseline.fds, last.fds) {
+                checks.expect(options.cycles, after <= before + options.max_task_growth, || format!("open descriptors grew from {} to {}", before, after));
+            }
+        }
+        None => println!("No resource baseline: fewer cycles than the warmup"),
+    }
+
+    // The updater and outlet hold the database open too
+    drop((updater, outlet, history));
+    if let Err(why) = std::fs::remove_file(&history_path) {
+        error!("Error removing {}: {:?}", history_path.display(), why);
+    }
+
+    let mut events: Vec<_> = events.into_iter().collect();
+    events.sort();
+    Ok(SoakReport { cycles: options.cycles, published, events, violations: checks.violations })
+}
@@ -0,0 +1,265 @@
+//! Grid stress index: one 0–100 number for how tight things are, from components each scaled to
+//! 0–100 stress:
+//!
+//! - reserve: forecast peak reserve rate, none at 15% or more, full at 3% or less
+//! - ramp: how fast load is rising between two readings, none when flat or falling, full at
+//!   400 萬瓩 an hour
+//! - fault: installed capacity of units in 故障, none at zero, full at 3000 MW
+//!
+//! Taipower's open data has no grid frequency, so frequency deviation can't be part of it.
+//! STRESS_WEIGHTS sets the components' relative weights (default `reserve=5,ramp=2,fault=3`);
+//! a component without data (no load feed, no previous reading) is left out and the rest reweighted.
+
+use tracing::warn;
+
+use crate::analysis::{classify_remark, PowerAnalysis, RemarkClass};
+use crate::taipower_api::LoadData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StressComponent {
+    Reserve,
+    Ramp,
+    Fault,
+}
+
+impl StressComponent {
+    const ALL: [StressComponent; 3] = [StressComponent::Reserve, StressComponent::Ramp, StressComponent::Fault];
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reserve" => Some(StressComponent::Reserve),
+            "ramp" => Some(StressComponent::Ramp),
+            "fault" => Some(StressComponent::Fault),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StressComponent::Reserve => "備轉容量率",
+            StressComponent::Ramp => "負載爬升",
+            StressComponent::Fault => "故障機組",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StressWeights {
+    reserve: f64,
+    ramp: f64,
+    fault: f64,
+}
+
+impl Default for StressWeights {
+    fn default() -> Self {
+        StressWeights { reserve: 5.0, ramp: 2.0, fault: 3.0 }
+    }
+}
+
+impl StressWeights {
+    /// "reserve=5,ramp=2,fault=3"; components left out keep their default weight
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut weights = StressWeights::default();
+        for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, weight) = pair.split_once('=')?;
+            let weight: f64 = weight.trim().parse().ok().filter(|w: &f64| w.is_finite() && *w >= 0.0)?;
+            match StressComponent::parse(name)? {
+                StressComponent::Reserve => weights.reserve = weight,
+                StressComponent::Ramp => weights.ramp = weight,
+                StressComponent::Fault => weights.fault = weight,
+            }
+        }
+        Some(weights)
+    }
+
+    /// Read every cycle so `/config reload` applies it
+    pub fn from_env() -> Self {
+        match crate::config::var("STRESS_WEIGHTS") {
+            Ok(value) if !value.trim().is_empty() => StressWeights::parse(&value).unwrap_or_else(|| {
+                warn!("Ignoring invalid STRESS_WEIGHTS {:?}", value);
+                StressWeights::default()
+            }),
+            _ => StressWeights::default(),
+        }
+    }
+
+    fn weight(&self, component: StressComponent) -> f64 {
+        match component {
+            StressComponent::Reserve => self.reserve,
+            StressComponent::Ramp => self.ramp,
+            StressComponent::Fault => self.fault,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressIndex {
+    /// 0 (relaxed) to 100 (as tight as it gets)
+    pub value: f64,
+    /// Each component's own 0–100 stress, for those that had data
+    pub components: Vec<(StressComponent, f64)>,
+}
+
+/// `value` mapped from `relaxed`..`stressed` (either order) onto 0–100
+fn scale(value: f64, relaxed: f64, stressed: f64) -> f64 {
+    ((value - relaxed) / (stressed - relaxed) * 100.0).clamp(0.0, 100.0)
+}
+
+/// Load change in 萬瓩 an hour between two readings, when both say when they were published
+pub fn load_ramp(current: &LoadData, previous: &LoadData) -> Option<f64> {
+    let minutes = (current.publish_time? - previous.publish_time?).num_seconds() as f64 / 60.0;
+    (minutes > 0.0).then(|| (current.current_load - previous.current_load) / minutes * 60.0)
+}
+
+/// MW of installed capacity in units reported as faulted
+pub fn fault_mw(analysis: &PowerAnalysis) -> f64 {
+    analysis
+        .units
+        .iter()
+        .filter(|u| !u.unit_name.contains("小計") && classify_remark(&u.remark) == RemarkClass::Fault)
+        // Not `sum()`, which starts from -0.0 and would show as "-0" with no faults
+        .fold(0.0, |total, u| total + u.capacity)
+}
+
+impl StressIndex {
+    pub fn compute(analysis: &PowerAnalysis, load_data: Option<&LoadData>, previous_load: Option<&LoadData>, weights: &StressWeights) -> Option<Self> {
+        let components: Vec<(StressComponent, f64)> = StressComponent::ALL
+            .into_iter()
+            .filter_map(|component| {
+                let stress = match component {
+                    StressComponent::Reserve => scale(load_data?.forecast_peak_reserve_rate, 15.0, 3.0),
+                    StressComponent::Ramp => scale(load_ramp(load_data?, previous_load?)?, 0.0, 400.0),
+                    StressComponent::Fault => scale(fault_mw(analysis), 0.0, 3000.0),
+                };
+                Some((component, stress))
+            })
+            .collect();
+        let total_weight: f64 = components.iter().map(|(c, _)| weights.weight(*c)).sum();
+        if total_weight <= 0.0 {
+            return None;
+        }
+        let value = components.iter().map(|(c, stress)| weights.weight(*c) * stress).sum::<f64>() / total_weight;
+        Some(StressIndex { value, components })
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self.value {
+            v if v >= 80.0 => "🔴",
+            v if v >= 60.0 => "🟠",
+            v if v >= 40.0 => "🟡",
+            _ => "🟢",
+        }
+    }
+
+    /// A ten-segment bar, e.g. ▰▰▰▱▱▱▱▱▱▱
+    pub fn gauge(&self) -> String {
+        let filled = (self.value / 10.0).round().clamp(0.0, 10.0) as usize;
+        format!("{}{}", "▰".repeat(filled), "▱".repeat(10 - filled))
+    }
+
+    /// "🟡 ▰▰▰▰▱▱▱▱▱▱ 42/100"
+    pub fn display(&self) -> String {
+        format!("{} {} {:.0}/100", self.emoji(), self.gauge(), self.value)
+    }
+
+    /// "備轉容量率 30 · 負載爬升 12 · 故障機組 0"
+    pub fn breakdown(&self) -> String {
+        self.components
+            .iter()
+            .map(|(component, stress)| format!("{} {:.0}", component.label(), stress))
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
+}
+
+/// Posted when the index reaches STRESS_ALERT_THRESHOLD
+pub fn alert_message(index: &StressIndex) -> String {
+    format!("🌡️ **電網壓力指數升高**: {}\n-# {}", index.display(), index.breakdown())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::analyze_power_data;
+    use crate::clock::parse_taipei_datetime;
+    use crate::taipower_api::{PowerUnit, ReserveIndicator};
+
+    fn load(current_load: f64, forecast_peak_reserve_rate: f64, published: &str) -> LoadData {
+        LoadData {
+            current_load,
+            current_util_rate: 80.0,
+            forecast_max_supply_capacity: 0.0,
+            forecast_peak_demand_load: 0.0,
+            forecast_peak_reserve_capacity: 0.0,
+            forecast_peak_reserve_rate,
+            forecast_peak_reserve_indicator: ReserveIndicator::Unknown,
+            forecast_peak_hour_range: None,
+            publish_time: parse_taipei_datetime(published),
+            yesterday_max_supply_capacity: 0.0,
+            yesterday_peak_demand_load: 0.0,
+            yesterday_peak_reserve_capacity: 0.0,
+            yesterday_peak_reserve_rate: 0.0,
+            yesterday_peak_reserve_indicator: ReserveIndicator::Unknown,
+            real_hour_max_supply_capacity: 0.0,
+            real_hour_peak_time: None,
+        }
+    }
+
+    fn unit(unit_name: &str, capacity: f64, remark: &str) -> PowerUnit {
+        PowerUnit {
+            unit_type: "燃氣".to_string(),
+            unit_name: unit_name.to_string(),
+            capacity,
+            generation: 0.0,
+            ratio: None,
+            remark: remark.to_string(),
+        }
+    }
+
+    fn analysis(units: Vec<PowerUnit>) -> PowerAnalysis {
+        let mut analysis = analyze_power_data(Vec::new(), None).unwrap();
+        analysis.units = units;
+        analysis
+    }
+
+    #[test]
+    fn weights_parse_with_defaults_for_the_rest() {
+        assert_eq!(StressWeights::parse("ramp=0"), Some(StressWeights { reserve: 5.0, ramp: 0.0, fault: 3.0 }));
+        assert_eq!(StressWeights::parse(" Reserve = 1 , fault=1 "), Some(StressWeights { reserve: 1.0, ramp: 2.0, fault: 1.0 }));
+        assert_eq!(StressWeights::parse("reserve=-1"), None);
+        assert_eq!(StressWeights::parse("frequency=2"), None);
+        assert_eq!(StressWeights::parse("reserve"), None);
+    }
+
+    #[test]
+    fn components_without_data_are_left_out() {
+        // 1500 MW faulted is half stress; the subtotal row repeats it and isn't counted again
+        let faulted = analysis(vec![unit("大潭#1", 1500.0, "故障"), unit("小計", 1500.0, "故障"), unit("大潭#2", 1500.0, "")]);
+        assert_eq!(fault_mw(&faulted), 1500.0);
+
+        let fault_only = StressIndex::compute(&faulted, None, None, &StressWeights::default()).unwrap();
+        assert_eq!(fault_only.components, [(StressComponent::Fault, 50.0)]);
+        assert_eq!(fault_only.value, 50.0);
+
+        // Reserve at 9% is half stress too; no previous reading leaves the ramp out
+        let current = load(3500.0, 9.0, "2024-07-15 14:30");
+        let index = StressIndex::compute(&faulted, Some(&current), None, &StressWeights::default()).unwrap();
+        assert_eq!(index.components, [(StressComponent::Reserve, 50.0), (StressComponent::Fault, 50.0)]);
+        assert_eq!(index.value, 50.0);
+
+        let no_weight = StressWeights { reserve: 0.0, ramp: 0.0, fault: 0.0 };
+        assert_eq!(StressIndex::compute(&faulted, Some(&current), None, &no_weight), None);
+    }
+
+    #[test]
+    fn weighted_index_and_display() {
+        // +100 萬瓩 in half an hour is a 200 an hour ramp, half stress; reserve at 3% is full
+        let previous = load(3400.0, 3.0, "2024-07-15 14:00");
+        let current = load(3500.0, 3.0, "2024-07-15 14:30");
+        assert_eq!(load_ramp(&current, &previous), Some(200.0));
+        let index = StressIndex::compute(&analysis(Vec::new()), Some(&current), Some(&previous), &StressWeights::default()).unwrap();
+        assert_eq!(index.value, 60.0);
+        assert_eq!(index.display(), "🟠 ▰▰▰▰▰▰▱▱▱▱ 60/100");
+        assert_eq!(index.breakdown(), "備轉容量率 100 · 負載爬升 50 · 故障機組 0");
+    }
+}
@@ -0,0 +1,81 @@
+//! sd_notify(3) for running under systemd with `Type=notify`: READY=1 once connected to
+//! Discord, WATCHDOG=1 after every cycle that fetched fresh generation data, STOPPING=1 on
+//! shutdown. With `WatchdogSec=` set, systemd restarts the bot when the update loop wedges, e.g.
+//!
+//! ```ini
+//! [Service]
+//! Type=notify
+//! NotifyAccess=main
+//! WatchdogSec=30min
+//! Restart=on-failure
+//! ```
+//!
+//! WatchdogSec must cover a few update intervals, or a short Taipower outage counts as a wedge.
+//! Everything is a no-op when NOTIFY_SOCKET isn't set.
+
+use std::env;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::scheduler;
+
+pub fn ready() {
+    notify("READY=1");
+}
+
+pub fn watchdog() {
+    notify("WATCHDOG=1");
+}
+
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// The watchdog timeout systemd asked for, if it's meant for this process
+fn watchdog_timeout() -> Option<Duration> {
+    let usec = env::var("WATCHDOG_USEC").ok()?.trim().parse::<u64>().ok()?;
+    let pid_matches = env::var("WATCHDOG_PID").ok().is_none_or(|pid| pid.trim() == std::process::id().to_string());
+    pid_matches.then(|| Duration::from_micros(usec))
+}
+
+/// Say so at startup if the watchdog would fire between two healthy cycles
+pub fn check_watchdog() {
+    if let Some(timeout) = watchdog_timeout() {
+        let interval = scheduler::interval_from_env();
+        if timeout <= interval {
+            info!(
+                "Warning: WatchdogSec ({}s) is not longer than the update interval ({}s); systemd will restart the bot between cycles",
+                timeout.as_secs(),
+                interval.as_secs()
+            );
+        } else {
+            info!("systemd watchdog enabled ({}s)", timeout.as_secs());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        let path = path.to_string_lossy();
+        // A leading @ is a Linux abstract socket
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.strip_prefix('@') {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+            return socket.send_to_addr(state.as_bytes(), &addr);
+        }
+        socket.send_to(state.as_bytes(), path.as_ref())
+    });
+    if let Err(why) = result {
+        error!("Error notifying systemd ({}): {:?}", state, why);
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_state: &str) {}
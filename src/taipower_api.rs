@@ -0,0 +1,581 @@
+//! Taipower's open-data feeds: the generation (機組) and load (負載) payloads as published, the
+//! fetches for them and the parsers that turn them into `PowerAnalysis` and `LoadData`.
+
+use chrono::{DateTime, FixedOffset, NaiveTime};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::analysis::{analyze_power_data, analyze_power_data_from_alternative, analyze_power_data_from_standard, PowerAnalysis};
+use crate::clock::parse_taipei_datetime;
+use crate::latency::{self, Phase};
+use crate::{de, http, payload_archive, regional, reporting, schema_watch};
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PowerData {
+    #[serde(rename = "DateTime")]
+    pub date_time: String,
+    #[serde(rename = "aaData")]
+    pub aa_data: Vec<PowerUnit>,
+}
+
+// Alternative structure for different API endpoints
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlternativePowerData {
+    #[serde(rename = "datas")]
+    pub datas: Vec<PowerUnit>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)] // Mirrors the upstream schema
+pub struct PowerUnit {
+    #[serde(rename = "機組類型")]
+    pub unit_type: String,
+    #[serde(rename = "機組名稱")]
+    pub unit_name: String,
+    #[serde(rename = "裝置容量(MW)", deserialize_with = "de::mw_value")]
+    pub capacity: f64,
+    #[serde(rename = "淨發電量(MW)", deserialize_with = "de::mw_value")]
+    pub generation: f64,
+    #[serde(rename = "淨發電量/裝置容量比(%)", default, deserialize_with = "de::optional_number")]
+    pub ratio: Option<f64>,
+    #[serde(rename = "備註")]
+    pub remark: String,
+}
+
+// New structures for load data API
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)] // Mirrors the upstream schema
+struct LoadDataResponse {
+    success: String,
+    result: LoadResult,
+    records: Vec<LoadRecord>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+struct LoadResult {
+    resource_id: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+struct LoadRecord {
+    #[serde(rename = "curr_load", default, deserialize_with = "de::optional_number")]
+    current_load: Option<f64>,
+    #[serde(rename = "curr_util_rate", default, deserialize_with = "de::optional_number")]
+    current_util_rate: Option<f64>,
+    #[serde(rename = "fore_maxi_sply_capacity", default, deserialize_with = "de::optional_number")]
+    forecast_max_supply_capacity: Option<f64>,
+    #[serde(rename = "fore_peak_dema_load", default, deserialize_with = "de::optional_number")]
+    forecast_peak_demand_load: Option<f64>,
+    #[serde(rename = "fore_peak_resv_capacity", default, deserialize_with = "de::optional_number")]
+    forecast_peak_reserve_capacity: Option<f64>,
+    #[serde(rename = "fore_peak_resv_rate", default, deserialize_with = "de::optional_number")]
+    forecast_peak_reserve_rate: Option<f64>,
+    #[serde(rename = "fore_peak_resv_indicator")]
+    forecast_peak_reserve_indicator: Option<String>,
+    #[serde(rename = "fore_peak_hour_range")]
+    forecast_peak_hour_range: Option<String>,
+    #[serde(rename = "publish_time")]
+    publish_time: Option<String>,
+    #[serde(rename = "yday_date")]
+    yesterday_date: Option<String>,
+    #[serde(rename = "yday_maxi_sply_capacity", default, deserialize_with = "de::optional_number")]
+    yesterday_max_supply_capacity: Option<f64>,
+    #[serde(rename = "yday_peak_dema_load", default, deserialize_with = "de::optional_number")]
+    yesterday_peak_demand_load: Option<f64>,
+    #[serde(rename = "yday_peak_resv_capacity", default, deserialize_with = "de::optional_number")]
+    yesterday_peak_reserve_capacity: Option<f64>,
+    #[serde(rename = "yday_peak_resv_rate", default, deserialize_with = "de::optional_number")]
+    yesterday_peak_reserve_rate: Option<f64>,
+    #[serde(rename = "yday_peak_resv_indicator")]
+    yesterday_peak_reserve_indicator: Option<String>,
+    #[serde(rename = "real_hr_maxi_sply_capacity", default, deserialize_with = "de::optional_number")]
+    real_hour_max_supply_capacity: Option<f64>,
+    #[serde(rename = "real_hr_peak_time")]
+    real_hour_peak_time: Option<String>,
+}
+
+/// Taipower's reserve margin light (供電燈號), ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReserveIndicator {
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Black,
+    Unknown,
+}
+
+impl ReserveIndicator {
+    pub fn from_code(code: &str) -> Self {
+        match code.trim() {
+            "G" => ReserveIndicator::Green,
+            "Y" => ReserveIndicator::Yellow,
+            "O" => ReserveIndicator::Orange,
+            "R" => ReserveIndicator::Red,
+            "B" => ReserveIndicator::Black,
+            _ => ReserveIndicator::Unknown,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReserveIndicator::Green => "G",
+            ReserveIndicator::Yellow => "Y",
+            ReserveIndicator::Orange => "O",
+            ReserveIndicator::Red => "R",
+            ReserveIndicator::Black => "B",
+            ReserveIndicator::Unknown => "",
+        }
+    }
+
+    /// Orange or worse: supply is tight enough to be newsworthy
+    pub fn is_critical(&self) -> bool {
+        matches!(self, ReserveIndicator::Orange | ReserveIndicator::Red | ReserveIndicator::Black)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadData {
+    pub current_load: f64,
+    pub current_util_rate: f64,
+    pub forecast_max_supply_capacity: f64,
+    pub forecast_peak_demand_load: f64,
+    pub forecast_peak_reserve_capacity: f64,
+    pub forecast_peak_reserve_rate: f64,
+    pub forecast_peak_reserve_indicator: ReserveIndicator,
+    pub forecast_peak_hour_range: Option<(NaiveTime, NaiveTime)>,
+    pub publish_time: Option<DateTime<FixedOffset>>,
+    pub yesterday_max_supply_capacity: f64,
+    pub yesterday_peak_demand_load: f64,
+    pub yesterday_peak_reserve_capacity: f64,
+    pub yesterday_peak_reserve_rate: f64,
+    pub yesterday_peak_reserve_indicator: ReserveIndicator,
+    pub real_hour_max_supply_capacity: f64,
+    pub real_hour_peak_time: Option<NaiveTime>,
+}
+
+/// At most `max_chars` characters of `text`, for logging; never splits a multibyte character
+fn preview(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Why getting data from Taipower (or posting it) failed. Only `Http` is worth retrying, and not
+/// when the server refused the request itself; the others say something about the data and are
+/// reported differently
+#[derive(Debug, thiserror::Error)]
+pub enum TaipowerError {
+    /// Unreachable, timed out or an error status
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The response isn't JSON at all (e.g. an error page)
+    #[error("decode error: {0}")]
+    Decode(#[from] serde_json::Error),
+    /// JSON, but not in a layout we know or with a field that no longer parses
+    #[error("payload format changed: {0}")]
+    SchemaChanged(String),
+    /// Data arrived, but Taipower hasn't updated it for a while
+    #[error("stale data: last updated {} ({} minutes ago)", data_time.format("%Y-%m-%d %H:%M"), age.num_minutes())]
+    StaleData { data_time: DateTime<FixedOffset>, age: chrono::Duration },
+    /// Boxed: serenity's error is several times the size of the others
+    #[error("Discord error: {0}")]
+    Discord(Box<serenity::Error>),
+}
+
+impl From<serenity::Error> for TaipowerError {
+    fn from(error: serenity::Error) -> Self {
+        TaipowerError::Discord(Box::new(error))
+    }
+}
+
+impl TaipowerError {
+    /// Worth trying again in a moment
+    pub fn is_transient(&self) -> bool {
+        match self {
+            TaipowerError::Http(e) => transient_status(e.status()),
+            _ => false,
+        }
+    }
+
+    /// The payload arrived but couldn't be read: upstream changed something, not a blip
+    pub fn is_parse_failure(&self) -> bool {
+        matches!(self, TaipowerError::Decode(_) | TaipowerError::SchemaChanged(_))
+    }
+}
+
+/// Connection errors (no status) and server errors may clear up; a 4xx won't, except a request
+/// timeout or being rate limited
+fn transient_status(status: Option<reqwest::StatusCode>) -> bool {
+    status.is_none_or(|status| !status.is_client_error() || matches!(status.as_u16(), 408 | 429))
+}
+
+pub async fn fetch_load_data() -> Result<LoadData, TaipowerError> {
+    http::with_retry("load data", fetch_load_data_once).await
+}
+
+async fn fetch_load_data_once() -> Result<LoadData, TaipowerError> {
+    let url = http::endpoint("https://service.taipower.com.tw/data/opendata/apply/file/d006020/001.json");
+    
+    let client = http::client();
+    
+    debug!(endpoint = %url, "Fetching load data");
+    let started = Instant::now();
+    
+    let response = client.get(&url).send().await?.error_for_status()?;
+    
+    let text = response.text().await?;
+    info!(endpoint = %url, bytes = text.len(), elapsed_ms = started.elapsed().as_millis() as u64, "Fetched load data");
+    
+    let data = latency::measure(Phase::Parse, || parse_load_payload(&text)).inspect_err(|e| reporting::report_parse_error(&url, &e.to_string(), &text))?;
+    payload_archive::save(payload_archive::LOAD, &text);
+    schema_watch::parsed(&url, &text);
+    Ok(data)
+}
+
+pub fn parse_load_payload(text: &str) -> Result<LoadData, TaipowerError> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let load_response: LoadDataResponse = deserialize_with_path(value)?;
+    
+    // Process records to extract load data
+    let mut current_load = 0.0;
+    let mut current_util_rate = 0.0;
+    let mut forecast_max_supply_capacity = 0.0;
+    let mut forecast_peak_demand_load = 0.0;
+    let mut forecast_peak_reserve_capacity = 0.0;
+    let mut forecast_peak_reserve_rate = 0.0;
+    let mut forecast_peak_reserve_indicator = ReserveIndicator::Unknown;
+    let mut forecast_peak_hour_range = None;
+    let mut publish_time = None;
+    let mut yesterday_max_supply_capacity = 0.0;
+    let mut yesterday_peak_demand_load = 0.0;
+    let mut yesterday_peak_reserve_capacity = 0.0;
+    let mut yesterday_peak_reserve_rate = 0.0;
+    let mut yesterday_peak_reserve_indicator = ReserveIndicator::Unknown;
+    let mut real_hour_max_supply_capacity = 0.0;
+    let mut real_hour_peak_time = None;
+    
+    for record in load_response.records {
+        if let Some(load) = record.current_load {
+            current_load = load;
+        }
+        if let Some(rate) = record.current_util_rate {
+            current_util_rate = rate;
+        }
+        if let Some(capacity) = record.forecast_max_supply_capacity {
+            forecast_max_supply_capacity = capacity;
+        }
+        if let Some(demand) = record.forecast_peak_demand_load {
+            forecast_peak_demand_load = demand;
+        }
+        if let Some(reserve) = record.forecast_peak_reserve_capacity {
+            forecast_peak_reserve_capacity = reserve;
+        }
+        if let Some(rate) = record.forecast_peak_reserve_rate {
+            forecast_peak_reserve_rate = rate;
+        }
+        if let Some(indicator) = record.forecast_peak_reserve_indicator {
+            forecast_peak_reserve_indicator = ReserveIndicator::from_code(&indicator);
+        }
+        if let Some(hour_range) = record.forecast_peak_hour_range {
+            forecast_peak_hour_range = parse_hour_range(&hour_range);
+        }
+        if let Some(time) = record.publish_time {
+            publish_time = parse_taipei_datetime(&time);
+        }
+        if let Some(capacity) = record.yesterday_max_supply_capacity {
+            yesterday_max_supply_capacity = capacity;
+        }
+        if let Some(demand) = record.yesterday_peak_demand_load {
+            yesterday_peak_demand_load = demand;
+        }
+        if let Some(reserve) = record.yesterday_peak_reserve_capacity {
+            yesterday_peak_reserve_capacity = reserve;
+        }
+        if let Some(rate) = record.yesterday_peak_reserve_rate {
+            yesterday_peak_reserve_rate = rate;
+        }
+        if let Some(indicator) = record.yesterday_peak_reserve_indicator {
+            yesterday_peak_reserve_indicator = ReserveIndicator::from_code(&indicator);
+        }
+        if let Some(capacity) = record.real_hour_max_supply_capacity {
+            real_hour_max_supply_capacity = capacity;
+        }
+        if let Some(time) = record.real_hour_peak_time {
+            real_hour_peak_time = parse_time_of_day(&time);
+        }
+    }
+    
+    Ok(LoadData {
+        current_load,
+        current_util_rate,
+        forecast_max_supply_capacity,
+        forecast_peak_demand_load,
+        forecast_peak_reserve_capacity,
+        forecast_peak_reserve_rate,
+        forecast_peak_reserve_indicator,
+        forecast_peak_hour_range,
+        publish_time,
+        yesterday_max_supply_capacity,
+        yesterday_peak_demand_load,
+        yesterday_peak_reserve_capacity,
+        yesterday_peak_reserve_rate,
+        yesterday_peak_reserve_indicator,
+        real_hour_max_supply_capacity,
+        real_hour_peak_time,
+    })
+}
+
+pub async fn fetch_and_analyze_power_data() -> Result<PowerAnalysis, TaipowerError> {
+    http::with_retry("generation data", fetch_and_analyze_power_data_once).await
+}
+
+async fn fetch_and_analyze_power_data_once() -> Result<PowerAnalysis, TaipowerError> {
+    // Try multiple endpoints
+    let urls = [
+        "https://www.taipower.com.tw/d006/loadGraph/loadGraph/data/genloadareaperc.json",
+        "https://service.taipower.com.tw/data/opendata/apply/file/d006001/001.json",
+        "https://www.taipower.com.tw/d006/loadGraph/loadGraph/data/genary.json"
+    ];
+    
+    let client = http::client();
+    let mut parse_error = None;
+    let mut http_error = None;
+    
+    for (i, url) in urls.iter().enumerate() {
+        let endpoint = http::endpoint(url);
+        debug!(endpoint = %endpoint, "Trying generation URL {}", i + 1);
+        let started = Instant::now();
+        
+        match client.get(&endpoint).send().await.and_then(|response| response.error_for_status()) {
+            Ok(response) => {
+                match response.text().await {
+                    Ok(text) => {
+                        info!(endpoint = %endpoint, bytes = text.len(), elapsed_ms = started.elapsed().as_millis() as u64, "Fetched generation data");
+                        debug!(endpoint = %endpoint, "First 200 chars: {}", preview(&text, 200));
+                        
+                        match latency::measure(Phase::Parse, || analyze_power_payload(&text)) {
+                            Some(Ok(mut analysis)) => {
+                                payload_archive::save(payload_archive::GENERATION, &text);
+                                schema_watch::parsed(&endpoint, &text);
+                                analysis.source = Some(url);
+                                return Ok(analysis);
+                            }
+                            Some(Err(e)) => {
+                                reporting::report_parse_error(&endpoint, &e.to_string(), &text);
+                                parse_error = Some(e);
+                            }
+                            None => {
+                                warn!(endpoint = %endpoint, "Failed to parse JSON from URL {}", i + 1);
+                                let e = TaipowerError::SchemaChanged(format!("{}: unrecognised payload", url));
+                                reporting::report_parse_error(&endpoint, &e.to_string(), &text);
+                                parse_error = Some(e);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!(endpoint = %endpoint, "Failed to get text from URL {}: {}", i + 1, e);
+                        http_error = Some(e);
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(endpoint = %endpoint, elapsed_ms = started.elapsed().as_millis() as u64, "Failed to fetch URL {}: {}", i + 1, e);
+                http_error = Some(e);
+            }
+        }
+    }
+    
+    // "Upstream sent garbage" matters more than "one endpoint was unreachable"
+    Err(parse_error.or(http_error.map(TaipowerError::from)).expect("every endpoint either failed or returned"))
+}
+
+/// Recognise which generation payload layout `text` uses and analyse it.
+/// `None` if it matches none of them; field-level errors are reported with their JSON path.
+pub fn analyze_power_payload(text: &str) -> Option<Result<PowerAnalysis, TaipowerError>> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    
+    let positional = value.get("aaData").and_then(|rows| rows.as_array()).and_then(|rows| rows.first()).is_some_and(|row| row.is_array());
+    let result = if positional {
+        // genary.json: rows as arrays of strings
+        parse_genary(&value).and_then(analyze_power_data_from_standard)
+    } else if value.get("aaData").is_some() {
+        // Original format
+        deserialize_with_path::<PowerData>(value).and_then(analyze_power_data_from_standard)
+    } else if value.get("datas").is_some() {
+        // Alternative format
+        deserialize_with_path::<AlternativePowerData>(value).and_then(analyze_power_data_from_alternative)
+    } else if value.is_array() {
+        // Bare data array
+        deserialize_with_path::<Vec<PowerUnit>>(value).and_then(|units| analyze_power_data(units, None))
+    } else {
+        return None;
+    };
+    
+    Some(result)
+}
+
+/// genary.json's layout: `aaData` rows are positional string arrays, `[type, "", name, capacity,
+/// generation, ratio, remark]` (older files lack the blank second column), the type is wrapped in
+/// HTML such as `<A NAME='nuclear'></A><b>核能(Nuclear)</b>`, and the time sits under an empty key
+fn parse_genary(value: &serde_json::Value) -> Result<PowerData, TaipowerError> {
+    let date_time = ["", "DateTime"]
+        .iter()
+        .find_map(|key| value.get(*key).and_then(|v| v.as_str()))
+        .unwrap_or_default()
+        .to_string();
+    let rows = value["aaData"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let mut aa_data = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let schema = |why: String| TaipowerError::SchemaChanged(format!("aaData[{}]{}", i, why));
+        let cells: Vec<&str> = row
+            .as_array()
+            .ok_or_else(|| schema(": expected an array".to_string()))?
+            .iter()
+            .map(|cell| cell.as_str().unwrap_or_default())
+            .collect();
+        let offset = match cells.len() {
+            6 => 0,
+            n if n >= 7 => 1,
+            n => return Err(schema(format!(": expected 6 or more columns, got {}", n))),
+        };
+        let cell = |column: usize| cells[column + offset];
+        let number = |column: usize| de::parse_mw_value(cell(column)).map_err(|why| schema(format!("[{}]: {}", column + offset, why)));
+        aa_data.push(PowerUnit {
+            unit_type: genary_energy_type(cells[0]),
+            unit_name: strip_html(cell(1)).trim().to_string(),
+            capacity: number(2)?,
+            generation: number(3)?,
+            ratio: de::parse_optional_number(cell(4)).map_err(|why| schema(format!("[{}]: {}", 4 + offset, why)))?,
+            remark: strip_html(cell(5)).trim().to_string(),
+        });
+    }
+    Ok(PowerData { date_time, aa_data })
+}
+
+/// Text with `<...>` tags removed
+fn strip_html(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut in_tag = false;
+    for c in value.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&amp;", "&").replace("&nbsp;", " ")
+}
+
+/// "<b>核能(Nuclear)</b>" → "核能", the name the other feeds use
+fn genary_energy_type(value: &str) -> String {
+    let text = strip_html(value);
+    let text = text.trim();
+    match text.rfind('(') {
+        Some(start) if text.ends_with(')') && text[start..].is_ascii() => text[..start].trim().to_string(),
+        _ => text.to_string(),
+    }
+}
+
+/// Deserialize, naming the offending field (e.g. `aaData[12].淨發電量(MW)`) on failure
+pub fn deserialize_with_path<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T, TaipowerError> {
+    serde_path_to_error::deserialize(value)
+        .map_err(|e| TaipowerError::SchemaChanged(format!("{}: {}", e.path(), e.inner())))
+}
+
+fn parse_hour_range(value: &str) -> Option<(NaiveTime, NaiveTime)> {
+    // e.g. "14:00~15:00" or "14:00-15:00"
+    let (start, end) = value.split_once(['~', '-'])?;
+    Some((parse_time_of_day(start)?, parse_time_of_day(end)?))
+}
+
+fn parse_time_of_day(value: &str) -> Option<NaiveTime> {
+    let value = value.trim();
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .ok()
+        .or_else(|| parse_taipei_datetime(value).map(|dt| dt.time()))
+}
+
+/// Parsed contents of local generation, load and regional payload files
+#[derive(Default)]
+pub struct PayloadFiles {
+    pub power_analysis: Option<PowerAnalysis>,
+    pub load_data: Option<LoadData>,
+    pub regional_shares: Option<Vec<regional::RegionalShare>>,
+}
+
+pub fn read_payload_files(paths: &[PathBuf]) -> Result<PayloadFiles, Box<dyn std::error::Error + Send + Sync>> {
+    let mut files = PayloadFiles::default();
+    
+    for path in paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        
+        if let Some(result) = analyze_power_payload(&text) {
+            files.power_analysis = Some(result.map_err(|e| format!("{}: {}", path.display(), e))?);
+            continue;
+        }
+        
+        if let Ok(shares) = regional::parse_regional_payload(&text) {
+            files.regional_shares = Some(shares);
+            continue;
+        }
+        
+        match parse_load_payload(&text) {
+            Ok(data) => files.load_data = Some(data),
+            Err(e) => return Err(format!("{}: not a recognised generation or load payload ({})", path.display(), e).into()),
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn genary_rows_parse_positionally(units in proptest::collection::vec((0.0f64..2000.0, 0.0f64..2000.0), 1..20)) {
+            let rows: Vec<serde_json::Value> = units
+                .iter()
+                .enumerate()
+                .map(|(i, (capacity, generation))| {
+                    serde_json::json!(["<A NAME='coal'></A><b>燃煤(Coal)</b>", "", format!("台中#{}", i), format!("{:.1}", capacity), format!("{:.1}", generation), "50.0%", "", ""])
+                })
+                .collect();
+            let payload = serde_json::json!({ "": "2024-07-01 14:00", "aaData": rows }).to_string();
+            let analysis = analyze_power_payload(&payload).unwrap().unwrap();
+            let expected: f64 = units.iter().map(|(_, generation)| format!("{:.1}", generation).parse::<f64>().unwrap()).sum();
+            prop_assert!((analysis.total_generation - expected).abs() < 1e-6);
+            prop_assert!(analysis.generation_by_type.contains_key("燃煤"));
+        }
+
+        #[test]
+        fn preview_respects_char_boundaries(text in "\\PC*", max_chars in 0usize..300) {
+            let cut = preview(&text, max_chars);
+            prop_assert!(text.starts_with(cut));
+            prop_assert!(cut.chars().count() <= max_chars);
+            prop_assert_eq!(cut.chars().count(), text.chars().count().min(max_chars));
+        }
+    }
+
+    #[test]
+    fn only_timeouts_and_rate_limits_among_client_errors_are_retried() {
+        let status = |code| Some(reqwest::StatusCode::from_u16(code).unwrap());
+        assert!(transient_status(None));
+        for code in [408, 429, 500, 502, 503] {
+            assert!(transient_status(status(code)), "{} not retried", code);
+        }
+        for code in [400, 401, 403, 404, 410] {
+            assert!(!transient_status(status(code)), "{} retried", code);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Which way the grid is heading, from least-squares slopes over the last hour of samples.
+//! Shown in the compact report and the bot's presence.
+
+use chrono::{Duration, NaiveDateTime};
+use tracing::error;
+
+use crate::history::{History, SnapshotRow};
+use crate::locale::Labels;
+
+const WINDOW_MINUTES: i64 = 60;
+/// Load change (萬瓩 an hour) that counts as climbing or easing; mornings ramp several times this
+const LOAD_SLOPE: f64 = 100.0;
+/// Fall of the reserve rate (percentage points an hour) that counts as tightening
+const RESERVE_SLOPE: f64 = 0.5;
+/// Fewer samples than this don't make a slope
+const MIN_SAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    /// The reserve rate is falling; takes precedence over the load's direction
+    Tightening,
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    pub fn label(&self, l: &Labels) -> &'static str {
+        match self {
+            Trend::Tightening => l.trend_tightening,
+            Trend::Rising => l.trend_rising,
+            Trend::Falling => l.trend_falling,
+            Trend::Steady => l.trend_steady,
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            Trend::Tightening => "⚠️",
+            Trend::Rising => "📈",
+            Trend::Falling => "📉",
+            Trend::Steady => "➖",
+        }
+    }
+}
+
+/// Change per hour of the least-squares line through `points`
+fn slope(points: &[(NaiveDateTime, f64)]) -> Option<f64> {
+    if points.len() < MIN_SAMPLES {
+        return None;
+    }
+    let origin = points[0].0;
+    let xs: Vec<f64> = points.iter().map(|(t, _)| (*t - origin).num_seconds() as f64 / 3600.0).collect();
+    let n = points.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = xs.iter().zip(points).map(|(x, (_, y))| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+/// The trend of `samples` (oldest first); None without enough load readings
+pub fn classify(samples: &[SnapshotRow]) -> Option<Trend> {
+    let load: Vec<(NaiveDateTime, f64)> = samples.iter().filter_map(|s| Some((s.recorded_at, s.current_load.filter(|l| *l > 0.0)?))).collect();
+    let reserve: Vec<(NaiveDateTime, f64)> = samples.iter().filter_map(|s| Some((s.recorded_at, s.reserve_rate.filter(|r| *r > 0.0)?))).collect();
+    let load_slope = slope(&load)?;
+    if slope(&reserve).is_some_and(|s| s <= -RESERVE_SLOPE) {
+        return Some(Trend::Tightening);
+    }
+    Some(if load_slope >= LOAD_SLOPE {
+        Trend::Rising
+    } else if load_slope <= -LOAD_SLOPE {
+        Trend::Falling
+    } else {
+        Trend::Steady
+    })
+}
+
+/// The trend over the hour up to `now`, from the history store
+pub fn current(history: &History, now: NaiveDateTime) -> Option<Trend> {
+    match history.snapshots_between(now - Duration::minutes(WINDOW_MINUTES), now) {
+        Ok(samples) => classify(&samples),
+        Err(why) => {
+            error!("Error reading history for the trend: {:?}", why);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// A sample every 10 minutes from 17:00, with the load and reserve rate at each
+    fn samples(readings: &[(Option<f64>, Option<f64>)]) -> Vec<SnapshotRow> {
+        let start = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap().and_hms_opt(17, 0, 0).unwrap();
+        readings
+            .iter()
+            .enumerate()
+            .map(|(i, (load, reserve))| SnapshotRow {
+                recorded_at: start + Duration::minutes(10 * i as i64),
+                total_generation: 0.0,
+                renewable_ratio: 0.0,
+                fault_count: 0,
+                maintenance_count: 0,
+                current_load: *load,
+                current_util_rate: None,
+                reserve_rate: *reserve,
+                indicator: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_by_reserve_then_load_slope() {
+        // Load +30 萬瓩 every 10 minutes is 180 an hour
+        let rising = samples(&[(Some(3400.0), Some(10.0)), (Some(3430.0), Some(10.0)), (Some(3460.0), Some(10.0))]);
+        assert_eq!(classify(&rising), Some(Trend::Rising));
+        let falling = samples(&[(Some(3460.0), Some(10.0)), (Some(3430.0), Some(10.0)), (Some(3400.0), Some(10.0))]);
+        assert_eq!(classify(&falling), Some(Trend::Falling));
+        let steady = samples(&[(Some(3400.0), Some(10.0)), (Some(3405.0), Some(10.0)), (Some(3402.0), Some(10.0))]);
+        assert_eq!(classify(&steady), Some(Trend::Steady));
+        // Reserve -0.2pp every 10 minutes outranks the falling load
+        let tightening = samples(&[(Some(3460.0), Some(10.0)), (Some(3430.0), Some(9.8)), (Some(3400.0), Some(9.6))]);
+        assert_eq!(classify(&tightening), Some(Trend::Tightening));
+    }
+
+    #[test]
+    fn needs_enough_load_readings() {
+        assert_eq!(classify(&samples(&[(Some(3400.0), Some(10.0)), (Some(3460.0), Some(9.0))])), None);
+        // Zero and missing loads are dropped rather than read as a collapse
+        let gaps = samples(&[(Some(3400.0), Some(10.0)), (Some(0.0), Some(9.0)), (None, Some(8.0)), (Some(3460.0), Some(7.0))]);
+        assert_eq!(classify(&gaps), None);
+    }
+}
@@ -0,0 +1,223 @@
+
+use tracing::warn;
+
+use crate::config;
+use crate::digest::clear_sky_fraction;
+use crate::analysis::PowerAnalysis;
+use crate::taipower_api::LoadData;
+
+const SOLAR: &str = "太陽能";
+
+/// Clear-sky output (fraction of capacity) above which the sun is well up (about 8° elevation);
+/// before and after that, solar at zero is just night and isn't checked
+const DAYLIGHT_CLEAR_SKY_FRACTION: f64 = 0.1;
+
+/// Metrics with a plausible physical range; anything outside is treated as a feed glitch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    CurrentLoad,
+    CurrentUtilRate,
+    ForecastPeakReserveRate,
+    TotalGeneration,
+    RenewableRatio,
+    /// Only checked in daylight
+    SolarOutput,
+}
+
+impl Metric {
+    /// Suffix used for the SANITY_<KEY>_MIN / SANITY_<KEY>_MAX environment variables
+    pub fn key(&self) -> &'static str {
+        match self {
+            Metric::CurrentLoad => "CURRENT_LOAD",
+            Metric::CurrentUtilRate => "CURRENT_UTIL_RATE",
+            Metric::ForecastPeakReserveRate => "FORECAST_PEAK_RESERVE_RATE",
+            Metric::TotalGeneration => "TOTAL_GENERATION",
+            Metric::RenewableRatio => "RENEWABLE_RATIO",
+            Metric::SolarOutput => "SOLAR_OUTPUT",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Metric::CurrentLoad => "目前用電量 (萬瓩)",
+            Metric::CurrentUtilRate => "目前使用率 (%)",
+            Metric::ForecastPeakReserveRate => "預估尖峰備轉容量率 (%)",
+            Metric::TotalGeneration => "總發電量 (MW)",
+            Metric::RenewableRatio => "再生能源占比 (%)",
+            Metric::SolarOutput => "日間太陽能出力 (MW)",
+        }
+    }
+
+    /// Whether a violation drops the whole cycle. A suspect solar figure is logged and reported
+    /// to admins, but the rest of the feed is still good
+    pub fn blocks_publishing(&self) -> bool {
+        !matches!(self, Metric::SolarOutput)
+    }
+
+    fn default_bounds(&self) -> Bounds {
+        match self {
+            // Even holiday nights stay well above this; lower values are feed glitches
+            Metric::CurrentLoad => Bounds { min: Some(1800.0), max: Some(5000.0) },
+            Metric::CurrentUtilRate => Bounds { min: Some(0.0), max: Some(100.0) },
+            Metric::ForecastPeakReserveRate => Bounds { min: Some(0.0), max: Some(60.0) },
+            Metric::TotalGeneration => Bounds { min: Some(15000.0), max: Some(50000.0) },
+            Metric::RenewableRatio => Bounds { min: Some(0.0), max: Some(100.0) },
+            // Even overcast daylight produces something; zero means the solar rows went missing
+            Metric::SolarOutput => Bounds { min: Some(1.0), max: None },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Bounds {
+    fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Violation {
+    pub metric: Metric,
+    pub value: f64,
+    pub bounds: Bounds,
+}
+
+impl Violation {
+    pub fn describe(&self) -> String {
+        match (self.bounds.min, self.bounds.max) {
+            (Some(min), _) if self.value < min => {
+                format!("{}: {:.1} 低於下限 {:.1}", self.metric.label(), self.value, min)
+            }
+            (_, Some(max)) if self.value > max => {
+                format!("{}: {:.1} 高於上限 {:.1}", self.metric.label(), self.value, max)
+            }
+            _ => format!("{}: {:.1}", self.metric.label(), self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanityBounds {
+    bounds: Vec<(Metric, Bounds)>,
+}
+
+impl SanityBounds {
+    /// Defaults, overridable with SANITY_<METRIC>_MIN / SANITY_<METRIC>_MAX (use "none" to disable a side)
+    pub fn from_env() -> Self {
+        let metrics = [
+            Metric::CurrentLoad,
+            Metric::CurrentUtilRate,
+            Metric::ForecastPeakReserveRate,
+            Metric::TotalGeneration,
+            Metric::RenewableRatio,
+            Metric::SolarOutput,
+        ];
+
+        let bounds = metrics
+            .into_iter()
+            .map(|metric| {
+                let defaults = metric.default_bounds();
+                let bounds = Bounds {
+                    min: env_bound(&format!("SANITY_{}_MIN", metric.key()), defaults.min),
+                    max: env_bound(&format!("SANITY_{}_MAX", metric.key()), defaults.max),
+                };
+                (metric, bounds)
+            })
+            .collect();
+
+        SanityBounds { bounds }
+    }
+
+    fn check(&self, metric: Metric, value: f64, violations: &mut Vec<Violation>) {
+        if let Some((_, bounds)) = self.bounds.iter().find(|(m, _)| *m == metric)
+            && !bounds.contains(value)
+        {
+            violations.push(Violation { metric, value, bounds: *bounds });
+        }
+    }
+
+    pub fn check_load(&self, load_data: &LoadData) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(Metric::CurrentLoad, load_data.current_load, &mut violations);
+        self.check(Metric::CurrentUtilRate, load_data.current_util_rate, &mut violations);
+        self.check(Metric::ForecastPeakReserveRate, load_data.forecast_peak_reserve_rate, &mut violations);
+        violations
+    }
+
+    pub fn check_power(&self, analysis: &PowerAnalysis) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(Metric::TotalGeneration, analysis.total_generation, &mut violations);
+        self.check(Metric::RenewableRatio, analysis.renewable_ratio, &mut violations);
+        if analysis.capacity_by_type.get(SOLAR).is_some_and(|capacity| *capacity > 0.0)
+            && clear_sky_fraction(analysis.update_time.naive_local()) >= DAYLIGHT_CLEAR_SKY_FRACTION
+        {
+            let solar = analysis.generation_by_type.get(SOLAR).copied().unwrap_or(0.0);
+            self.check(Metric::SolarOutput, solar, &mut violations);
+        }
+        violations
+    }
+}
+
+fn env_bound(name: &str, default: Option<f64>) -> Option<f64> {
+    match config::var(name) {
+        Ok(value) if value.trim().eq_ignore_ascii_case("none") => None,
+        Ok(value) => match value.trim().parse() {
+            Ok(bound) => Some(bound),
+            Err(_) => {
+                warn!("Ignoring invalid {}: {}", name, value);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::analyze_power_data;
+    use crate::clock::parse_taipei_datetime;
+
+    fn defaults() -> SanityBounds {
+        let metrics = [Metric::TotalGeneration, Metric::RenewableRatio, Metric::SolarOutput];
+        SanityBounds { bounds: metrics.into_iter().map(|metric| (metric, metric.default_bounds())).collect() }
+    }
+
+    /// A feed read at `time` with 30000 MW generated, `solar` MW of it from 12000 MW of panels
+    fn analysis(time: &str, solar: f64) -> PowerAnalysis {
+        let mut analysis = analyze_power_data(Vec::new(), parse_taipei_datetime(time)).unwrap();
+        analysis.total_generation = 30000.0;
+        analysis.renewable_ratio = 20.0;
+        analysis.capacity_by_type.insert(SOLAR.to_string(), 12000.0);
+        analysis.generation_by_type.insert(SOLAR.to_string(), solar);
+        analysis
+    }
+
+    #[test]
+    fn zero_solar_is_only_suspect_in_daylight() {
+        let bounds = defaults();
+        assert!(bounds.check_power(&analysis("2024-07-15 12:00", 6000.0)).is_empty());
+        assert!(bounds.check_power(&analysis("2024-07-15 22:00", 0.0)).is_empty());
+
+        let violations = bounds.check_power(&analysis("2024-07-15 12:00", 0.0));
+        assert_eq!(violations.iter().map(|v| v.metric).collect::<Vec<_>>(), [Metric::SolarOutput]);
+        assert!(!violations[0].metric.blocks_publishing());
+    }
+
+    #[test]
+    fn out_of_range_totals_block_publishing() {
+        let mut glitch = analysis("2024-07-15 22:00", 0.0);
+        glitch.total_generation = 0.0;
+        glitch.renewable_ratio = 120.0;
+        let violations = defaults().check_power(&glitch);
+        assert_eq!(violations.iter().map(|v| v.metric).collect::<Vec<_>>(), [Metric::TotalGeneration, Metric::RenewableRatio]);
+        assert!(violations.iter().all(|v| v.metric.blocks_publishing()));
+        assert_eq!(violations[0].describe(), "總發電量 (MW): 0.0 低於下限 15000.0");
+        assert_eq!(violations[1].describe(), "再生能源占比 (%): 120.0 高於上限 100.0");
+    }
+}
@@ -0,0 +1,70 @@
+//! Hourly temperature from Open-Meteo (no API key needed), used as a demand forecast feature.
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::config;
+
+#[derive(Debug, Deserialize)]
+struct ForecastResponse {
+    hourly: Hourly,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hourly {
+    time: Vec<String>,
+    temperature_2m: Vec<Option<f64>>,
+}
+
+/// Hourly temperatures (°C, Taipei local time) for today and tomorrow
+pub struct HourlyTemperatures {
+    pub hours: Vec<(NaiveDateTime, f64)>,
+}
+
+impl HourlyTemperatures {
+    /// Temperature for the hour containing `time`
+    pub fn at(&self, time: NaiveDateTime) -> Option<f64> {
+        let hour = time.format("%Y-%m-%d %H").to_string();
+        self.hours
+            .iter()
+            .find(|(t, _)| t.format("%Y-%m-%d %H").to_string() == hour)
+            .map(|(_, temperature)| *temperature)
+    }
+}
+
+/// Defaults to Taichung, roughly the load-weighted middle of the island
+fn location() -> (f64, f64) {
+    let read = |key: &str, default: f64| config::var(key).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default);
+    (read("WEATHER_LATITUDE", 24.15), read("WEATHER_LONGITUDE", 120.67))
+}
+
+pub async fn fetch_hourly_temperatures() -> Result<HourlyTemperatures, Box<dyn std::error::Error + Send + Sync>> {
+    let (latitude, longitude) = location();
+    let url = format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m&timezone=Asia%2FTaipei&forecast_days=2",
+        latitude, longitude
+    );
+
+    let client = crate::http::client();
+    debug!(endpoint = %url, "Fetching weather");
+
+    let response = client.get(&url).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()).into());
+    }
+
+    let forecast: ForecastResponse = response.json().await?;
+    let hours = forecast
+        .hourly
+        .time
+        .iter()
+        .zip(forecast.hourly.temperature_2m)
+        .filter_map(|(time, temperature)| {
+            let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok()?;
+            Some((time, temperature?))
+        })
+        .collect();
+
+    Ok(HourlyTemperatures { hours })
+}
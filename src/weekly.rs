@@ -0,0 +1,151 @@
+//! Monday-morning report comparing the week that just ended with the one before, from the
+//! history store.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serenity::builder::{CreateAllowedMentions, CreateAttachment, CreateMessage};
+use std::collections::HashSet;
+use tracing::error;
+
+use crate::chart::{self, Series};
+use crate::config;
+use crate::history::{History, SnapshotRow};
+use crate::locale::NumberFormat;
+use crate::render::format_pp_change;
+use crate::taipower_api::ReserveIndicator;
+
+pub fn enabled() -> bool {
+    !matches!(config::var("WEEKLY_REPORT").as_deref().map(str::trim), Ok("off") | Ok("false") | Ok("0"))
+}
+
+/// WEEKLY_REPORT_HOUR: the report goes out with the first cycle on Monday from this hour (Taipei)
+fn hour_from_env() -> u32 {
+    config::var("WEEKLY_REPORT_HOUR").ok().and_then(|v| v.trim().parse().ok()).filter(|h| *h < 24).unwrap_or(8)
+}
+
+fn monday_of(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Notices the first check past Monday's report hour, once a week
+pub struct WeeklyTrigger {
+    hour: u32,
+    /// Monday of the last week reported (or skipped because the bot started after its hour)
+    last: NaiveDate,
+}
+
+impl WeeklyTrigger {
+    pub fn new(now: NaiveDateTime) -> Self {
+        let hour = hour_from_env();
+        let monday = monday_of(now.date());
+        // Starting after this week's report time shouldn't post it late (or again after a restart)
+        let last = if now.date() > monday || now.hour() >= hour { monday } else { monday - Duration::days(7) };
+        WeeklyTrigger { hour, last }
+    }
+
+    /// The Monday starting the new week, once it is due
+    pub fn check(&mut self, now: NaiveDateTime) -> Option<NaiveDate> {
+        let monday = monday_of(now.date());
+        (monday > self.last && now.hour() >= self.hour).then(|| {
+            self.last = monday;
+            monday
+        })
+    }
+}
+
+struct WeekStats {
+    average_load: f64,
+    peak_load: f64,
+    peak_at: NaiveDateTime,
+    renewable_ratio: f64,
+    /// Hours with at least one orange-or-worse sample
+    critical_hours: usize,
+}
+
+fn week_stats(samples: &[SnapshotRow]) -> Option<WeekStats> {
+    let loads: Vec<(NaiveDateTime, f64)> = samples.iter().filter_map(|s| s.current_load.filter(|l| *l > 0.0).map(|l| (s.recorded_at, l))).collect();
+    let (peak_at, peak_load) = loads.iter().copied().max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let critical_hours: HashSet<(NaiveDate, u32)> = samples
+        .iter()
+        .filter(|s| s.indicator.as_deref().is_some_and(|code| ReserveIndicator::from_code(code).is_critical()))
+        .map(|s| (s.recorded_at.date(), s.recorded_at.hour()))
+        .collect();
+    Some(WeekStats {
+        average_load: loads.iter().map(|(_, l)| l).sum::<f64>() / loads.len() as f64,
+        peak_load,
+        peak_at,
+        renewable_ratio: samples.iter().map(|s| s.renewable_ratio).sum::<f64>() / samples.len() as f64,
+        critical_hours: critical_hours.len(),
+    })
+}
+
+/// "+3.2%" change from `previous` to `current`
+fn relative_change(current: f64, previous: f64, numbers: NumberFormat) -> String {
+    if previous <= 0.0 {
+        return String::new();
+    }
+    let change = (current - previous) / previous * 100.0;
+    format!(" ({}{})", if change >= 0.0 { "+" } else { "" }, numbers.percent(change, 1))
+}
+
+/// The report for the week before `monday`, or None without samples from that week
+pub fn build(history: &History, monday: NaiveDate, numbers: NumberFormat) -> Option<CreateMessage> {
+    let start = monday - Duration::days(7);
+    let read = |from: NaiveDate| {
+        history
+            .snapshots_between(from.and_hms_opt(0, 0, 0).unwrap(), (from + Duration::days(7)).and_hms_opt(0, 0, 0).unwrap() - Duration::seconds(1))
+            .inspect_err(|e| error!("Error reading history for weekly report: {:?}", e))
+            .unwrap_or_default()
+    };
+    let this_week = read(start);
+    let last_week = read(start - Duration::days(7));
+    let current = week_stats(&this_week)?;
+    let previous = week_stats(&last_week);
+
+    let mut lines = vec![format!(
+        "📆 **每週電力趨勢** {} ~ {}",
+        start.format("%m/%d"),
+        (monday - Duration::days(1)).format("%m/%d")
+    )];
+    lines.push(String::new());
+    lines.push(format!(
+        "📊 **平均用電**: {}{}",
+        numbers.wan_kw(current.average_load),
+        previous.as_ref().map(|p| relative_change(current.average_load, p.average_load, numbers)).unwrap_or_default()
+    ));
+    lines.push(format!(
+        "⬆️ **尖峰用電**: {} ({}){}",
+        numbers.wan_kw(current.peak_load),
+        current.peak_at.format("%m/%d %H:%M"),
+        previous.as_ref().map(|p| relative_change(current.peak_load, p.peak_load, numbers)).unwrap_or_default()
+    ));
+    lines.push(format!(
+        "🌱 **平均再生能源占比**: {}{}",
+        numbers.percent(current.renewable_ratio, 1),
+        previous.as_ref().map(|p| format!(" ({})", format_pp_change(current.renewable_ratio - p.renewable_ratio))).unwrap_or_default()
+    ));
+    lines.push(format!(
+        "🟠 **橘燈以上時數**: {} 小時{}",
+        current.critical_hours,
+        previous.as_ref().map(|p| format!(" (上週 {} 小時)", p.critical_hours)).unwrap_or_default()
+    ));
+    if previous.is_none() {
+        lines.push("-# 上週沒有紀錄，無法比較".to_string());
+    }
+
+    let mut message = CreateMessage::new().allowed_mentions(CreateAllowedMentions::new());
+    // Last week's load shifted onto this week's days, so the two lines overlay
+    let load_points = |samples: &[SnapshotRow], shift: Duration| -> Vec<(NaiveDateTime, f64)> {
+        samples.iter().filter_map(|s| s.current_load.filter(|l| *l > 0.0).map(|l| (s.recorded_at + shift, l))).collect()
+    };
+    let mut series = vec![Series { label: "本週".to_string(), points: load_points(&this_week, Duration::zero()) }];
+    if last_week.len() >= 2 {
+        series.push(Series { label: "上週".to_string(), points: load_points(&last_week, Duration::days(7)) });
+    }
+    if series[0].points.len() >= 2 {
+        match chart::line_chart("用電負載: 本週 vs 上週", "萬瓩", &series, numbers) {
+            Ok(png) => message = message.add_file(CreateAttachment::bytes(png, "weekly.png")),
+            Err(e) => error!("Error rendering weekly chart: {:?}", e),
+        }
+    }
+    Some(message.content(lines.join("\n")))
+}